@@ -1,46 +1,284 @@
-use std::time::Duration;
+use std::{
+    collections::VecDeque,
+    ffi::CString,
+    path::Path,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
-use windows::Win32::Foundation::HWND;
+use windows::Win32::{
+    Foundation::{COLORREF, HWND},
+    UI::WindowsAndMessaging::{
+        SetLayeredWindowAttributes, LWA_ALPHA, SW_HIDE, SW_SHOWNOACTIVATE, WS_EX_LAYERED,
+        WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TRANSPARENT,
+    },
+};
+use windows_core::{s, PCSTR};
 
 use crate::{
-    action, checkbox,
+    action,
+    caption::{
+        self, history::History, minutes::Minutes, CaptionSegment, EmphasisStyle, SegmentRole,
+        SpeechStats, TimedText, WrapRules,
+    },
+    checkbox,
     config::*,
     graphics::Renderer,
     gui::{
-        app::{App as GuiApp, MenuItem},
-        utils::Hwnd as _,
+        app::{App as GuiApp, MenuItem, Slider},
+        clipboard,
+        ducking::DuckingWatcher,
+        gamepad::{self, Gamepad},
+        history::HistoryWindow,
+        hotkey::Hotkey,
+        midi::{self, MidiInput},
+        note::NoteDialog,
+        tray::TrayIcon,
+        utils::{self, Hwnd as _, Rect as _},
+    },
+    profile::{Profile, ProfileBase, ProfileSwitcher},
+    radio,
+    schedule::Schedule,
+    script::{CaptionScript, ScriptAction},
+    separator,
+    sink::{
+        self, CaptionLog, Formatted, Formatter, ObsFile, Redactor, Sink, Sinks, SubtitleExport,
+        SubtitleStyle, Webhook,
+    },
+    soak::SoakMonitor,
+    speech_to_text::{
+        self, AudioDevice, MeetingEvent, MeetingOptions, MeetingTranscriber, Speaker, SpeechToText,
+        TranscriptEvent, VadSensitivity,
     },
-    radio, separator,
-    speech_to_text::SpeechToText,
     submenu,
+    theme::{Theme, THEME_EXTENSION},
+    watch::{FolderWatcher, WatchOptions},
 };
 
 const TIMER_ID: usize = 0x01;
+const SPEECH_STATS_WINDOW: Duration = Duration::from_secs(60);
+const THEMES_DIR: &str = "themes";
+const MINUTES_MARKDOWN: &str = "md";
+const MINUTES_DOCX: &str = "docx";
+const PRIVACY_BADGE: &str = "Private";
+const DEFAULT_CAPTION_LOG: &str = "captions.log";
+// Finished lines of a meeting kept on screen above the ones in progress.
+const MEETING_LINES: usize = 4;
+const HOTKEY_BOOKMARK: i32 = 1;
+const HOTKEY_PAUSE: i32 = 2;
+const HOTKEY_CLEAR: i32 = 3;
+const HOTKEY_CLICK_THROUGH: i32 = 4;
+const HOTKEY_TOGGLE: i32 = 5;
 
 pub struct App {
     config: Config,
     hwnd: HWND,
     s2t: SpeechToText,
     renderer: Renderer,
+    sinks: Sinks,
+    fullscreen_paused: bool,
+    live_captions_visible: bool,
+    schedule: Schedule,
+    off_schedule: bool,
+    user_paused: bool,
+    gamepad: Option<Gamepad>,
+    midi: Option<MidiInput>,
+    ducking: Option<DuckingWatcher>,
+    // The app of the call ducking other audio, shown in the badge.
+    ducked_app: Option<String>,
+    hidden: bool,
+    click_through: bool,
+    script: Option<CaptionScript>,
+    tray: Option<TrayIcon>,
+    stats: Option<SpeechStats>,
+    watcher: Option<FolderWatcher>,
+    soak: SoakMonitor,
+    themes: Vec<(CString, Theme)>,
+    audio_devices: Vec<(CString, AudioDevice)>,
+    history: History,
+    history_window: Option<HistoryWindow>,
+    hotkeys: Vec<Hotkey>,
+    note_dialog: Option<NoteDialog>,
+    /// When the bookmark waiting for its note was taken.
+    pending_bookmark: Option<Instant>,
+    redactor: Option<Redactor>,
+    profiles: ProfileSwitcher,
+    /// The settings before the active profile and right after it took effect.
+    profile_base: Option<(ProfileBase, ProfileBase)>,
+    foreground: utils::ForegroundProcess,
+    own_process: Option<String>,
+    meeting: Option<MeetingTranscriber>,
+    meeting_lines: VecDeque<(Speaker, String)>,
+    meeting_partials: [String; 2],
+    meeting_status: String,
 }
 
 impl App {
     fn clear(&mut self) {
         self.s2t.clear();
-        self.renderer.set_text("");
+        self.renderer.set_segments(&[]);
+    }
+
+    fn show_history(&mut self) {
+        match &self.history_window {
+            Some(window) if window.is_open() => window.activate(),
+            _ => self.history_window = HistoryWindow::show(self.hwnd, self.history.entries()).ok(),
+        }
+    }
+
+    fn copy(&mut self, all: bool) {
+        let text = if all {
+            self.history
+                .entries()
+                .iter()
+                .filter(|entry| !entry.bookmark && !entry.filtered)
+                .map(|entry| entry.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\r\n")
+        } else {
+            self.renderer.caption_text()
+        };
+        let text = self.redact(&text);
+
+        if let Err(e) = clipboard::set_text(self.hwnd, &text) {
+            self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't copy to the clipboard, {e}"
+            ))]);
+        }
+    }
+
+    /// Saves the history as meeting minutes, in Markdown or as a Word document.
+    fn export_minutes(&mut self, extension: &str) {
+        let date = utils::local_date_time();
+        let name = format!("minutes-{}", date.replace([' ', ':'], "-"));
+        let dir = self.config.data_path("");
+        let Some(path) = utils::save_file_dialog(self.hwnd, extension, &dir, &name) else {
+            return;
+        };
+
+        let entries: Vec<_> = match &self.redactor {
+            Some(redactor) => self
+                .history
+                .entries()
+                .iter()
+                .map(|entry| redactor.redact_entry(entry))
+                .collect(),
+            None => self.history.entries().iter().cloned().collect(),
+        };
+        let minutes = Minutes::new(
+            &format!("Minutes {date}"),
+            &entries,
+            self.config.minutes_pause,
+        );
+        let result = match extension {
+            MINUTES_DOCX => std::fs::write(&path, minutes.to_docx()),
+            _ => std::fs::write(&path, minutes.to_markdown()),
+        };
+        if let Err(e) = result {
+            self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't export the minutes, {e}"
+            ))]);
+        }
+    }
+
+    fn redact(&self, text: &str) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.redact(text),
+            None => text.to_string(),
+        }
+    }
+
+    /// Saves a plain copy of an encrypted caption log, using the configured passphrase.
+    fn decrypt_caption_log(&mut self) {
+        if self.config.caption_log_passphrase.is_empty() {
+            self.renderer.set_segments(&[CaptionSegment::status(
+                "Set caption-log-passphrase in livesub.ini to decrypt caption logs; it is saved encrypted",
+            )]);
+            return;
+        }
+
+        let log = self.config.data_path(&self.config.caption_log);
+        let extension = log
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("txt")
+            .to_string();
+        let dir = log.parent().unwrap_or(Path::new("")).to_path_buf();
+        let Some(path) = utils::open_file_dialog(self.hwnd, &extension, &dir) else {
+            return;
+        };
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(stem) => format!("{stem}-decrypted"),
+            None => String::new(),
+        };
+        let Some(target) = utils::save_file_dialog(self.hwnd, &extension, &dir, &name) else {
+            return;
+        };
+
+        let result = sink::decrypt_caption_log(&path, &self.config.caption_log_passphrase)
+            .and_then(|text| Ok(std::fs::write(target, text)?));
+        if let Err(e) = result {
+            self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't decrypt the caption log, {e}"
+            ))]);
+        }
+    }
+
+    /// Marks the current time, asking for a note first if enabled.
+    fn bookmark(&mut self) {
+        if let Some(dialog) = self.note_dialog.as_ref().filter(|dialog| dialog.is_open()) {
+            dialog.activate();
+            return;
+        }
+
+        let now = Instant::now();
+        if !self.config.bookmark_note {
+            self.add_bookmark(now, "");
+            return;
+        }
+
+        self.pending_bookmark = Some(now);
+        match NoteDialog::show(self.hwnd, s!("Bookmark"), "Note (optional)") {
+            Ok(dialog) => self.note_dialog = Some(dialog),
+            Err(_) => self.on_note(""),
+        }
+    }
+
+    fn add_bookmark(&mut self, time: Instant, note: &str) {
+        let entry = self.history.bookmark(time, note);
+        if let Some(window) = &self.history_window {
+            window.append(entry);
+        }
+        self.sinks.bookmark(time, note);
     }
 
     fn set_model(&mut self, repo_id: &str) {
         self.config.model = repo_id.into();
-        self.s2t.set_model(repo_id);
+        self.s2t.set_model_options(self.config.model_options());
+        match self.meeting {
+            Some(_) => self.set_meeting_mode(true),
+            None => self.s2t.set_model(repo_id),
+        }
+    }
+
+    fn set_model_option(&mut self, id: u32) {
+        let index = (id - CMD_MODEL_OPTION - 1) as usize;
+        let settings = speech_to_text::model_settings(&self.config.model);
+        let Some(setting) = settings.get(index / MODEL_OPTION_CHOICES) else {
+            return;
+        };
+        let Some((value, _)) = setting.choices.get(index % MODEL_OPTION_CHOICES) else {
+            return;
+        };
+
+        self.config.set_model_option(setting.key, value);
+        self.s2t.set_model_options(self.config.model_options());
     }
 
     fn set_latency(&mut self, latency: Duration) {
         self.config.latency = latency;
         self.s2t.set_latency(self.config.latency);
-        self.hwnd
-            .set_timer(TIMER_ID, latency.as_millis() as u32 / 2);
+        self.hwnd.set_timer(TIMER_ID, timer_interval(latency));
     }
 
     fn set_opacity(&mut self, opacity: f32) {
@@ -73,6 +311,845 @@ impl App {
         self.renderer.set_outline(outline);
     }
 
+    fn set_audio_source(&mut self, id: u32) {
+        let source = match (id - CMD_AUDIO_SOURCE) as usize {
+            0 => String::new(),
+            i => match self.audio_devices.get(i - 1) {
+                Some((_, device)) => device.id.clone(),
+                None => return,
+            },
+        };
+        self.s2t.set_audio_source(&source);
+        self.config.audio_source = source;
+    }
+
+    fn set_language(&mut self, id: u32) {
+        let language = match (id - CMD_LANGUAGE) as usize {
+            0 => "",
+            i => match speech_to_text::LANGUAGES.get(i - 1) {
+                Some((code, _)) => code,
+                None => return,
+            },
+        };
+        self.config.language = language.into();
+        self.s2t
+            .set_task(&self.config.language, self.config.translate);
+    }
+
+    fn set_translate(&mut self, enable: bool) {
+        self.config.translate = enable;
+        self.s2t.set_task(&self.config.language, enable);
+    }
+
+    /// The default output device, then the devices found when the menu opened:
+    /// outputs are captured by loopback, inputs directly.
+    fn audio_source_items(&self) -> Vec<MenuItem> {
+        let mut items = vec![radio!(
+            CMD_AUDIO_SOURCE,
+            "Default Output Device",
+            self.config.audio_source.is_empty(),
+            Some(s!("Follows the device Windows plays to")),
+        )];
+        let mut input = None;
+        for (i, (name, device)) in self.audio_devices.iter().take(0xfe).enumerate() {
+            if input != Some(device.input) {
+                items.push(separator!());
+                input = Some(device.input);
+            }
+            items.push(MenuItem::Radio {
+                id: CMD_AUDIO_SOURCE + i as u32 + 1,
+                text: PCSTR(name.as_ptr() as _),
+                checked: device.id == self.config.audio_source,
+                description: Some(if device.input {
+                    s!("Input")
+                } else {
+                    s!("Output")
+                }),
+            });
+        }
+
+        items
+    }
+
+    fn set_theme(&mut self, id: u32) {
+        let index = (id - CMD_THEME - 1) as usize;
+        if let Some((_, theme)) = self.themes.get(index).cloned() {
+            theme.apply_to(&mut self.config);
+            self.update_appearance();
+        }
+    }
+
+    fn import_theme(&mut self) {
+        let dir = self.config.data_path(THEMES_DIR);
+        let Some(path) = utils::open_file_dialog(self.hwnd, THEME_EXTENSION, &dir) else {
+            return;
+        };
+
+        let result = Theme::load(&path).and_then(|theme| {
+            // Copied into the themes folder so it stays listed.
+            if let Some(name) = path.file_name() {
+                let installed = dir.join(name);
+                if std::fs::canonicalize(&installed).ok() != std::fs::canonicalize(&path).ok() {
+                    std::fs::create_dir_all(&dir)?;
+                    std::fs::copy(&path, installed)?;
+                }
+            }
+            Ok(theme)
+        });
+
+        match result {
+            Ok(theme) => {
+                self.load_themes();
+                theme.apply_to(&mut self.config);
+                self.update_appearance();
+            }
+            Err(e) => self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't import the theme, {e}"
+            ))]),
+        }
+    }
+
+    fn export_theme(&mut self) {
+        let dir = self.config.data_path(THEMES_DIR);
+        _ = std::fs::create_dir_all(&dir);
+        let Some(path) = utils::save_file_dialog(self.hwnd, THEME_EXTENSION, &dir, "") else {
+            return;
+        };
+
+        match Theme::from_config(&self.config).save(&path) {
+            Ok(()) => self.load_themes(),
+            Err(e) => self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't export the theme, {e}"
+            ))]),
+        }
+    }
+
+    fn load_themes(&mut self) {
+        self.themes = Theme::builtin()
+            .into_iter()
+            .chain(Theme::installed(&self.config.data_path(THEMES_DIR)))
+            .filter_map(|(name, theme)| Some((CString::new(name).ok()?, theme)))
+            .collect();
+    }
+
+    /// Built-in and installed themes, the one matching the current appearance checked,
+    /// then import and export.
+    fn theme_items(&self) -> Vec<MenuItem> {
+        let current = Theme::from_config(&self.config);
+        let builtin = Theme::builtin().len();
+        let mut items = vec![];
+
+        for (i, (name, theme)) in self.themes.iter().take(0xff).enumerate() {
+            if i == builtin {
+                items.push(separator!());
+            }
+            items.push(MenuItem::Radio {
+                id: CMD_THEME + i as u32 + 1,
+                text: PCSTR(name.as_ptr() as _),
+                checked: *theme == current,
+                description: None,
+            });
+        }
+        items.push(separator!());
+        items.push(action!(CMD_THEME_IMPORT, "Import Theme..."));
+        items.push(action!(CMD_THEME_EXPORT, "Export Current Appearance..."));
+
+        items
+    }
+
+    /// Pushes every appearance setting to the renderer, after a theme replaced them all.
+    fn update_appearance(&mut self) {
+        let config = &self.config;
+        self.renderer.set_font_name(&config.font_name);
+        self.renderer.set_font_size(config.font_size);
+        self.renderer.set_bold(config.bold);
+        self.renderer.set_italic(config.italic);
+        self.renderer.set_outline(config.outline);
+        self.renderer.set_opacity(config.opacity);
+        update_colors(&mut self.renderer, config);
+        self.renderer.set_padding(config.padding);
+        self.renderer.set_alignment(&config.alignment);
+    }
+
+    fn set_emphasis_color(&mut self, enable: bool) {
+        self.config.emphasis_color = enable;
+        self.renderer.set_emphasis(emphasis_style(&self.config));
+    }
+
+    fn set_emphasis_bold(&mut self, enable: bool) {
+        self.config.emphasis_bold = enable;
+        self.renderer.set_emphasis(emphasis_style(&self.config));
+    }
+
+    fn set_granularity(&mut self, granularity: &str) {
+        self.config.granularity = granularity.into();
+        self.s2t.set_granularity(granularity);
+    }
+
+    fn set_max_lines(&mut self, max_lines: u32) {
+        self.config.max_lines = max_lines;
+        self.renderer.set_max_lines(max_lines as _);
+    }
+
+    fn set_line_wrap(&mut self, line_wrap: &str) {
+        self.config.line_wrap = line_wrap.into();
+        self.renderer.set_line_wrap(line_wrap);
+    }
+
+    fn set_overflow(&mut self, overflow: &str) {
+        self.config.overflow = overflow.into();
+        self.renderer.set_overflow(overflow);
+    }
+
+    fn set_transliteration(&mut self, transliteration: &str) {
+        self.config.transliteration = transliteration.into();
+        self.s2t.set_transliteration(transliteration);
+    }
+
+    fn set_simplify(&mut self, enable: bool) {
+        self.config.simplify = enable;
+        self.s2t.set_simplify(enable);
+    }
+
+    fn set_auto_casing(&mut self, enable: bool) {
+        self.config.auto_casing = enable;
+        let names = match enable {
+            true => self.hotwords(),
+            false => vec![],
+        };
+        self.s2t.set_casing(enable, &names);
+    }
+
+    fn set_punctuation(&mut self, enable: bool) {
+        self.config.punctuation = enable;
+        let dir = self.config.data_path(&self.config.punctuation_model);
+        self.s2t.set_punctuation(enable, &dir);
+    }
+
+    fn set_hotword_biasing(&mut self, enable: bool) {
+        self.config.hotword_biasing = enable;
+        let words = match enable {
+            true => self.hotwords(),
+            false => vec![],
+        };
+        self.s2t.set_hotwords(words);
+    }
+
+    /// The hotwords, or none with the reason shown if the file can't be read.
+    fn hotwords(&mut self) -> Vec<String> {
+        self.config.hotwords().unwrap_or_else(|e| {
+            self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't load the hotwords, {e}"
+            ))]);
+            vec![]
+        })
+    }
+
+    /// Picks up edits to the replacements file.
+    fn reload_replacements(&mut self) {
+        let rules = self.config.replacement_rules().unwrap_or_else(|e| {
+            self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't load the replacements, {e}"
+            ))]);
+            vec![]
+        });
+        self.s2t.set_replacements(&rules);
+    }
+
+    fn set_speech_stats(&mut self, enable: bool) {
+        self.config.speech_stats = enable;
+        self.stats = enable.then(|| SpeechStats::new(SPEECH_STATS_WINDOW));
+        self.renderer.set_stats(None);
+    }
+
+    /// Keeps captions off disk and the network: no sink gets them, the watch folder and
+    /// the soak log pause, and a badge shows it is on.
+    fn set_privacy_mode(&mut self, enable: bool) {
+        self.config.privacy_mode = enable;
+        self.sinks.set_private(enable);
+        // Started in privacy mode, the outputs haven't been opened yet.
+        if !enable && self.sinks.is_empty() {
+            let mut warnings = vec![];
+            self.sinks = create_sinks(&self.config, &mut warnings);
+            self.sinks.set_redactor(self.redactor.clone());
+            if !warnings.is_empty() {
+                self.renderer
+                    .set_segments(&[CaptionSegment::status(warnings.join("\r\n"))]);
+            }
+        } else if !enable && !self.sinks.contains("caption-log") {
+            // The log may have been turned on meanwhile.
+            self.open_caption_log();
+        }
+        self.soak.set_log_paused(enable);
+        if let Some(watcher) = &self.watcher {
+            watcher.set_paused(enable);
+        }
+        self.update_badge();
+    }
+
+    /// Shows privacy mode and the app of a call that ducks other audio, whichever are on.
+    fn update_badge(&mut self) {
+        let mut badge = vec![];
+        if self.config.privacy_mode {
+            badge.push(PRIVACY_BADGE.to_string());
+        }
+        if let Some(app) = &self.ducked_app {
+            badge.push(format!("[{app}]"));
+        }
+        self.renderer
+            .set_badge((!badge.is_empty()).then(|| badge.join("  ")).as_deref());
+    }
+
+    fn check_ducking(&mut self) {
+        let app = self
+            .ducking
+            .as_mut()
+            .and_then(DuckingWatcher::app)
+            .map(str::to_string);
+        if app != self.ducked_app {
+            self.ducked_app = app;
+            self.update_badge();
+        }
+    }
+
+    fn set_caption_log(&mut self, enable: bool) {
+        self.config.caption_log_enabled = enable;
+        if enable && self.config.caption_log.is_empty() {
+            self.config.caption_log = DEFAULT_CAPTION_LOG.to_string();
+        }
+        // Nothing is created on disk in privacy mode; the log is opened when it ends.
+        if self.config.privacy_mode {
+            if !enable {
+                self.sinks.set("caption-log", None);
+            }
+            return;
+        }
+        self.open_caption_log();
+    }
+
+    fn open_caption_log(&mut self) {
+        match caption_log_sink(&self.config) {
+            Ok(sink) => self.sinks.set("caption-log", sink),
+            Err(e) => self.renderer.set_segments(&[CaptionSegment::status(format!(
+                "Can't open the caption log, {e}"
+            ))]),
+        }
+    }
+
+    fn set_meeting_mode(&mut self, enable: bool) {
+        self.config.meeting_mode = enable;
+        let model_dir = self.config.model_dir();
+        let latency = self.config.latency.max(DELAY_LOW);
+        let hotwords = match self.config.hotword_biasing {
+            true => self.hotwords(),
+            false => vec![],
+        };
+        let options = MeetingOptions {
+            language: self.config.language.clone(),
+            translate: self.config.translate,
+            hotwords,
+        };
+        self.meeting = match enable
+            .then(|| MeetingTranscriber::new(&self.config.model, &model_dir, latency, options))
+            .transpose()
+        {
+            Ok(meeting) => meeting,
+            Err(e) => {
+                self.renderer.set_segments(&[CaptionSegment::status(format!(
+                    "Can't start meeting mode, {e}"
+                ))]);
+                None
+            }
+        };
+        self.meeting_lines.clear();
+        self.meeting_partials = Default::default();
+        self.meeting_status.clear();
+        self.update_paused();
+        // The main model is freed rather than kept idle, so only one copy is ever loaded.
+        match self.meeting {
+            Some(_) => self.s2t.unload(),
+            None if enable => {}
+            None => {
+                self.s2t.set_model(&self.config.model);
+                self.renderer.set_segments(&[]);
+            }
+        }
+    }
+
+    /// Merges both sides of the meeting into one transcript in the order lines finish.
+    fn poll_meeting(&mut self) {
+        let Some(meeting) = &self.meeting else {
+            return;
+        };
+        let events = meeting.poll();
+        if events.is_empty() {
+            return;
+        }
+
+        for event in events {
+            match event {
+                MeetingEvent::Status(status) => self.meeting_status = status,
+                MeetingEvent::Text(speaker, TranscriptEvent::Partial(text)) => {
+                    self.meeting_partials[speaker as usize] = text;
+                }
+                MeetingEvent::Text(speaker, TranscriptEvent::Final(text)) => {
+                    self.meeting_partials[speaker as usize].clear();
+                    let text = self.s2t.process_confirmed(text.trim());
+                    let text = text.trim();
+                    if text.is_empty() {
+                        continue;
+                    }
+
+                    let line = format!("{}: {text}", speaker.label());
+                    self.emit_confirmed(line, &[], Instant::now());
+
+                    self.meeting_lines.push_back((speaker, text.to_string()));
+                    if self.meeting_lines.len() > MEETING_LINES {
+                        self.meeting_lines.pop_front();
+                    }
+                }
+            }
+        }
+
+        let lines: Vec<_> = self
+            .meeting_lines
+            .iter()
+            .map(|(speaker, text)| (*speaker, text.trim().to_string(), false))
+            .chain([Speaker::Me, Speaker::Them].into_iter().map(|speaker| {
+                let text = self.meeting_partials[speaker as usize].trim().to_string();
+                (speaker, text, true)
+            }))
+            .collect();
+        let mut segments: Vec<_> = lines
+            .into_iter()
+            .map(|(speaker, text, partial)| (speaker, self.s2t.process_display(&text, partial)))
+            .filter(|(_, text)| !text.trim().is_empty())
+            .enumerate()
+            .map(|(i, (speaker, text))| {
+                let text = format!(
+                    "{}{}: {text}",
+                    if i == 0 { "" } else { "\n" },
+                    speaker.label()
+                );
+                CaptionSegment::new(text, SegmentRole::Speaker(speaker as u32))
+            })
+            .collect();
+        if !self.meeting_status.is_empty() {
+            segments.push(CaptionSegment::status(self.meeting_status.clone()));
+        }
+
+        self.show_segments(&segments);
+    }
+
+    /// Passes a confirmed caption through the stats and the script on to the history and sinks.
+    fn emit_confirmed(&mut self, text: String, timings: &[TimedText], captured: Instant) {
+        if let Some(stats) = &mut self.stats {
+            stats.add(&text);
+            self.renderer.set_stats(Some(&stats.summary()));
+        }
+
+        let action = match &mut self.script {
+            Some(script) => script.on_confirmed(&text),
+            None => ScriptAction::Emit {
+                text: text.clone(),
+                sinks: None,
+            },
+        };
+
+        if let ScriptAction::Emit {
+            text: emitted,
+            sinks,
+        } = action
+        {
+            // Tells voice chat apart from what else is playing while a call ducks it.
+            let emitted = match self.ducking.as_mut().and_then(DuckingWatcher::app) {
+                Some(app) => format!("[{app}] {emitted}"),
+                None => emitted,
+            };
+            // The spans carry the text as rewritten by the script and tagged.
+            let timings = &caption::retime(timings, &emitted);
+            let entry = self.history.push(&emitted, timings);
+            if let Some(window) = &self.history_window {
+                window.append(entry);
+            }
+            match sinks {
+                None => self.sinks.confirmed(&emitted, timings, captured),
+                Some(names) => self.sinks.confirmed_to(&names, &emitted, timings, captured),
+            }
+        }
+    }
+
+    fn show_segments(&mut self, segments: &[CaptionSegment]) {
+        self.sinks.update(segments);
+        match self.redactor.as_ref().filter(|_| self.config.redact_screen) {
+            Some(redactor) => self
+                .renderer
+                .set_segments(&redactor.redact_segments(segments)),
+            None => self.renderer.set_segments(segments),
+        }
+    }
+
+    fn set_ducking_tag(&mut self, enable: bool) {
+        self.config.ducking_tag = enable;
+        self.ducking = match enable.then(DuckingWatcher::new).transpose() {
+            Ok(ducking) => ducking,
+            Err(e) => {
+                self.renderer.set_segments(&[CaptionSegment::status(format!(
+                    "Can't watch for calls, {e}"
+                ))]);
+                None
+            }
+        };
+        self.check_ducking();
+    }
+
+    fn set_guardrails(&mut self, enable: bool) {
+        self.config.guardrails = enable;
+        self.s2t.set_guardrails(enable);
+    }
+
+    fn set_silence_trim(&mut self, max_silence: Duration) {
+        self.config.silence_trim = max_silence;
+        self.s2t.set_silence_trim(max_silence);
+    }
+
+    fn set_vad_sensitivity(&mut self, sensitivity: &str) {
+        self.config.vad_sensitivity = sensitivity.to_string();
+        self.s2t.set_vad_sensitivity(vad_sensitivity(&self.config));
+    }
+
+    fn set_audio_chain_bypass(&mut self, bypass: bool) {
+        self.config.audio_chain_bypass = bypass;
+        self.s2t.set_audio_chain(match bypass {
+            true => "",
+            false => &self.config.audio_chain,
+        });
+    }
+
+    fn set_adaptive_vad(&mut self, enable: bool) {
+        self.config.adaptive_vad = enable;
+        self.s2t.set_adaptive_vad(enable);
+    }
+
+    fn set_pre_roll(&mut self, pre_roll: Duration) {
+        self.config.pre_roll = pre_roll;
+        self.s2t.set_pre_roll(pre_roll);
+    }
+
+    fn set_monitor_delay(&mut self, delay: Duration) {
+        self.config.monitor_delay = delay;
+        self.s2t
+            .set_monitor(&self.config.monitor_device, self.config.monitor_delay);
+    }
+
+    fn set_fullscreen_pause(&mut self, enable: bool) {
+        self.config.fullscreen_pause = enable;
+        self.check_fullscreen();
+    }
+
+    fn check_fullscreen(&mut self) {
+        let paused = self.config.fullscreen_pause
+            && utils::is_fullscreen_exclusive()
+            && (self.config.fullscreen_process.is_empty()
+                || self.foreground.name().is_some_and(|name| {
+                    name.eq_ignore_ascii_case(&self.config.fullscreen_process)
+                }));
+
+        if paused != self.fullscreen_paused {
+            self.fullscreen_paused = paused;
+            self.update_paused();
+            self.update_visibility();
+        }
+    }
+
+    fn set_profile_switch(&mut self, enable: bool) {
+        self.config.profile_switch = enable;
+        if !enable {
+            self.profiles = ProfileSwitcher::new(self.config.profiles());
+            self.apply_profile(None);
+        }
+    }
+
+    fn check_profile(&mut self) {
+        if !self.config.profile_switch || self.profiles.is_empty() {
+            return;
+        }
+
+        // The app's own windows, such as its menu, keep the current profile.
+        let process = self
+            .foreground
+            .name()
+            .filter(|name| Some(name) != self.own_process.as_ref());
+        if let Some(profile) = self.profiles.update(process.as_deref(), Instant::now()) {
+            let profile = profile.cloned();
+            self.apply_profile(profile.as_ref());
+        }
+    }
+
+    fn apply_profile(&mut self, profile: Option<&Profile>) {
+        if let Some((base, applied)) = self.profile_base.take() {
+            base.restore(&applied, &mut self.config);
+        }
+
+        if let Some(profile) = profile {
+            let base = ProfileBase::from_config(&self.config);
+            if let Some(language) = &profile.language {
+                self.config.language.clone_from(language);
+            }
+            if let Some(translate) = profile.translate {
+                self.config.translate = translate;
+            }
+            let theme = profile.theme.as_ref().and_then(|name| {
+                self.themes
+                    .iter()
+                    .find(|(theme, _)| theme.to_string_lossy().eq_ignore_ascii_case(name))
+            });
+            if let Some((_, theme)) = theme {
+                theme.apply_to(&mut self.config);
+            }
+            self.profile_base = Some((base, ProfileBase::from_config(&self.config)));
+        }
+
+        self.s2t
+            .set_task(&self.config.language, self.config.translate);
+        self.update_appearance();
+    }
+
+    fn set_live_captions_hide(&mut self, enable: bool) {
+        self.config.live_captions_hide = enable;
+        self.check_live_captions();
+    }
+
+    /// Windows Live Captions transcribes the same audio, so showing both only duplicates captions.
+    fn check_live_captions(&mut self) {
+        let visible = self.config.live_captions_hide && utils::is_live_captions_visible();
+
+        if visible != self.live_captions_visible {
+            self.live_captions_visible = visible;
+            self.update_visibility();
+        }
+    }
+
+    /// Capture, and with it caption logging, only runs during scheduled sessions.
+    fn check_schedule(&mut self) {
+        let off_schedule = !self.schedule.is_empty() && {
+            let (day, minute) = utils::local_time();
+            !self.schedule.is_active(day, minute)
+        };
+
+        if off_schedule != self.off_schedule {
+            self.off_schedule = off_schedule;
+            self.update_paused();
+            if off_schedule {
+                self.renderer.set_segments(&[CaptionSegment::status(
+                    "Waiting for the next scheduled session",
+                )]);
+            } else {
+                self.renderer.set_segments(&[]);
+            }
+        }
+    }
+
+    fn set_gamepad(&mut self, enable: bool) {
+        self.config.gamepad = enable;
+        self.gamepad = enable.then(Gamepad::new);
+    }
+
+    fn set_midi(&mut self, enable: bool) {
+        self.config.midi = enable;
+        self.midi = match enable.then(MidiInput::new).transpose() {
+            Ok(midi) => midi,
+            Err(e) => {
+                self.renderer
+                    .set_segments(&[CaptionSegment::status(format!("Can't listen to MIDI, {e}"))]);
+                None
+            }
+        };
+    }
+
+    fn set_no_activate(&mut self, enable: bool) {
+        self.config.no_activate = enable;
+
+        let ex_style = self.hwnd.ex_style();
+        self.hwnd.set_ex_style(if enable {
+            ex_style | WS_EX_NOACTIVATE
+        } else {
+            ex_style & !WS_EX_NOACTIVATE
+        });
+    }
+
+    fn set_show_in_taskbar(&mut self, enable: bool) {
+        self.config.show_in_taskbar = enable;
+
+        // Without a tray icon the taskbar is the only way back to a hidden window.
+        let mut taskbar = enable;
+        self.tray = None;
+        if !enable {
+            match TrayIcon::new(self.hwnd) {
+                Ok(tray) => self.tray = Some(tray),
+                Err(e) => {
+                    taskbar = true;
+                    self.renderer.set_segments(&[CaptionSegment::status(format!(
+                        "Can't add the tray icon, staying in the taskbar, {e}"
+                    ))]);
+                }
+            }
+        }
+
+        // The taskbar only picks up WS_EX_TOOLWINDOW changes while the window is hidden.
+        let ex_style = self.hwnd.ex_style();
+        self.hwnd.show(SW_HIDE);
+        self.hwnd.set_ex_style(if taskbar {
+            ex_style & !WS_EX_TOOLWINDOW
+        } else {
+            ex_style | WS_EX_TOOLWINDOW
+        });
+        self.update_visibility();
+    }
+
+    fn poll_gamepad(&mut self) {
+        let Some(gamepad) = &mut self.gamepad else {
+            return;
+        };
+
+        let [clear, toggle] = gamepad.poll(&[
+            gamepad::parse_chord(&self.config.gamepad_clear),
+            gamepad::parse_chord(&self.config.gamepad_toggle),
+        ]);
+
+        if clear {
+            self.clear();
+        }
+        if toggle {
+            self.toggle_hidden();
+        }
+    }
+
+    fn poll_midi(&mut self) {
+        let Some(midi) = &self.midi else {
+            return;
+        };
+
+        let config = &self.config;
+        let bindings = [
+            &config.midi_clear,
+            &config.midi_pause,
+            &config.midi_model,
+            &config.midi_profile,
+            &config.midi_toggle,
+        ]
+        .map(|binding| midi::parse_binding(binding));
+
+        for event in midi.poll() {
+            match bindings.iter().position(|&binding| binding == Some(event)) {
+                Some(0) => self.clear(),
+                Some(1) => self.toggle_pause(),
+                Some(2) => self.cycle_model(),
+                Some(3) => self.cycle_profile(),
+                Some(4) => self.toggle_hidden(),
+                _ => {}
+            }
+        }
+    }
+
+    fn cycle_model(&mut self) {
+        let models = speech_to_text::model_repo_ids();
+        let next = models
+            .iter()
+            .position(|&model| model == self.config.model)
+            .map_or(0, |i| (i + 1) % models.len());
+        self.set_model(models[next]);
+        self.renderer
+            .set_segments(&[CaptionSegment::status(format!("Model: {}", models[next]))]);
+    }
+
+    fn cycle_profile(&mut self) {
+        let profile = self.profiles.cycle().cloned();
+        self.apply_profile(profile.as_ref());
+        let name = profile.as_ref().map_or("Default", |profile| &profile.name);
+        self.renderer
+            .set_segments(&[CaptionSegment::status(format!("Profile: {name}"))]);
+    }
+
+    fn toggle_hidden(&mut self) {
+        self.hidden = !self.hidden;
+        self.update_visibility();
+    }
+
+    fn toggle_pause(&mut self) {
+        self.user_paused = !self.user_paused;
+        self.update_paused();
+        if self.user_paused {
+            self.renderer
+                .set_segments(&[CaptionSegment::status("Paused")]);
+        } else {
+            self.renderer.set_segments(&[]);
+        }
+    }
+
+    /// The meeting transcriber captures on its own, so the main engine rests meanwhile.
+    fn update_paused(&self) {
+        self.s2t.set_paused(
+            self.fullscreen_paused
+                || self.off_schedule
+                || self.user_paused
+                || self.meeting.is_some(),
+        );
+    }
+
+    /// Lets clicks through to the windows below. Input only reaches a layered window's
+    /// underlying windows, so the window is made layered, fully opaque, for as long as it lasts.
+    fn toggle_click_through(&mut self) {
+        self.click_through = !self.click_through;
+
+        let ex_style = self.hwnd.ex_style();
+        if self.click_through {
+            self.hwnd
+                .set_ex_style(ex_style | WS_EX_LAYERED | WS_EX_TRANSPARENT);
+            unsafe { _ = SetLayeredWindowAttributes(self.hwnd, COLORREF(0), 255, LWA_ALPHA) };
+        } else {
+            self.hwnd
+                .set_ex_style(ex_style & !(WS_EX_LAYERED | WS_EX_TRANSPARENT));
+        }
+    }
+
+    /// Registers the global shortcuts, reporting the ones that are invalid or taken.
+    fn register_hotkeys(&mut self) {
+        self.hotkeys.clear();
+
+        let bindings = [
+            (HOTKEY_BOOKMARK, "Bookmark", &self.config.bookmark_hotkey),
+            (HOTKEY_PAUSE, "Pause", &self.config.pause_hotkey),
+            (HOTKEY_CLEAR, "Clear", &self.config.clear_hotkey),
+            (
+                HOTKEY_CLICK_THROUGH,
+                "Click-through",
+                &self.config.click_through_hotkey,
+            ),
+            (HOTKEY_TOGGLE, "Show/hide", &self.config.toggle_hotkey),
+        ];
+
+        let mut errors = vec![];
+        for (id, name, hotkey) in bindings {
+            match Hotkey::register(self.hwnd, id, hotkey) {
+                Ok(Some(hotkey)) => self.hotkeys.push(hotkey),
+                Ok(None) => {}
+                Err(e) => errors.push(format!("{name} hotkey: {e}")),
+            }
+        }
+
+        if !errors.is_empty() {
+            self.renderer
+                .set_segments(&[CaptionSegment::status(errors.join("\r\n"))]);
+        }
+    }
+
+    fn update_visibility(&self) {
+        let visible = !self.fullscreen_paused && !self.live_captions_visible && !self.hidden;
+        self.hwnd
+            .show(if visible { SW_SHOWNOACTIVATE } else { SW_HIDE });
+    }
+
     fn quit(&mut self) {
         self.hwnd.destroy();
     }
@@ -80,9 +1157,11 @@ impl App {
 
 impl GuiApp for App {
     fn new(config: Config, hwnd: HWND) -> Result<Self> {
-        let s2t = SpeechToText::new(&config.model, config.latency)?;
+        // Problems that leave a feature off, shown once the window is up.
+        let mut warnings = vec![];
+        let s2t = create_speech_to_text(&config, &mut warnings)?;
 
-        let renderer = Renderer::new(
+        let mut renderer = Renderer::new(
             hwnd,
             &config.font_name,
             config.font_size,
@@ -91,28 +1170,157 @@ impl GuiApp for App {
             config.outline,
             config.opacity,
         )?;
+        renderer.set_line_max_chars(config.line_max_chars as _);
+        renderer.set_hyphenate(config.hyphenate);
+        renderer.set_line_wrap(&config.line_wrap);
+        renderer.set_max_lines(config.max_lines as _);
+        renderer.set_overflow(&config.overflow);
+        update_colors(&mut renderer, &config);
+        renderer.set_padding(config.padding);
+        renderer.set_alignment(&config.alignment);
+        renderer.set_emphasis(emphasis_style(&config));
 
-        _ = hwnd.set_timer(TIMER_ID, config.latency.as_millis() as u32 / 2);
+        _ = hwnd.set_timer(TIMER_ID, timer_interval(config.latency));
 
-        Ok(Self {
+        let (redactor, skipped) = create_redactor(&config);
+        warnings.extend(skipped.into_iter().map(|e| format!("Skipped {e}")));
+        let mut sinks = if config.privacy_mode {
+            Sinks::new()
+        } else {
+            create_sinks(&config, &mut warnings)
+        };
+        sinks.set_redactor(redactor.clone());
+        let watcher = if config.watch_folder.is_empty() {
+            None
+        } else {
+            Some(FolderWatcher::new(
+                config.data_path(&config.watch_folder),
+                WatchOptions {
+                    repo_id: config.model.clone(),
+                    model_dir: config.model_dir(),
+                    style: subtitle_style(&config),
+                    rules: wrap_rules(&config),
+                    max_duration: config.subtitle_max_duration,
+                    redactor: redactor.clone(),
+                },
+            )?)
+        };
+
+        let gamepad = config.gamepad.then(Gamepad::new);
+        let midi = config.midi.then(MidiInput::new).and_then(Result::ok);
+        let ducking = config
+            .ducking_tag
+            .then(DuckingWatcher::new)
+            .and_then(Result::ok);
+        let stats = config
+            .speech_stats
+            .then(|| SpeechStats::new(SPEECH_STATS_WINDOW));
+        let tray = if config.show_in_taskbar {
+            None
+        } else {
+            match TrayIcon::new(hwnd) {
+                Ok(tray) => Some(tray),
+                Err(e) => {
+                    // Not shown yet, so the taskbar takes the change as it is.
+                    hwnd.set_ex_style(hwnd.ex_style() & !WS_EX_TOOLWINDOW);
+                    warnings.push(format!(
+                        "Can't add the tray icon, showing in the taskbar, {e}"
+                    ));
+                    None
+                }
+            }
+        };
+        // Captions go on without the script rather than the app not starting.
+        let script = match load_script(&config) {
+            Ok(script) => script,
+            Err(e) => {
+                warnings.push(format!("Can't load the script, {e}"));
+                None
+            }
+        };
+
+        // A schedule that can't be read leaves captions on, saying why.
+        let schedule = Schedule::parse(&config.schedule).unwrap_or_else(|e| {
+            warnings.push(format!("Ignoring the schedule, {e}"));
+            Schedule::default()
+        });
+        let profiles = ProfileSwitcher::new(config.profiles());
+        // Started in privacy mode, the soak log stays closed for the session.
+        let soak = SoakMonitor::new(
+            (!config.soak_log.is_empty() && !config.privacy_mode)
+                .then(|| config.data_path(&config.soak_log)),
+        )?;
+
+        let mut app = Self {
             config,
             hwnd,
             s2t,
             renderer,
-        })
+            sinks,
+            fullscreen_paused: false,
+            live_captions_visible: false,
+            schedule,
+            off_schedule: false,
+            user_paused: false,
+            gamepad,
+            midi,
+            ducking,
+            ducked_app: None,
+            hidden: false,
+            click_through: false,
+            script,
+            tray,
+            stats,
+            watcher,
+            soak,
+            themes: vec![],
+            audio_devices: vec![],
+            history: History::new(),
+            history_window: None,
+            hotkeys: vec![],
+            note_dialog: None,
+            pending_bookmark: None,
+            redactor,
+            profiles,
+            profile_base: None,
+            foreground: Default::default(),
+            own_process: utils::process_name(std::process::id()),
+            meeting: None,
+            meeting_lines: VecDeque::new(),
+            meeting_partials: Default::default(),
+            meeting_status: String::new(),
+        };
+        app.load_themes();
+        app.register_hotkeys();
+        if app.config.privacy_mode {
+            app.set_privacy_mode(true);
+        }
+        if app.config.meeting_mode {
+            app.set_meeting_mode(true);
+        }
+        if !warnings.is_empty() {
+            app.renderer
+                .set_segments(&[CaptionSegment::status(warnings.join("\r\n"))]);
+        }
+
+        Ok(app)
     }
 
     fn on_close(&mut self) {
+        // Saved with the settings the user chose, not those of the app in front.
+        if let Some((base, applied)) = self.profile_base.take() {
+            base.restore(&applied, &mut self.config);
+        }
         self.config.save()
     }
 
     fn on_move(&mut self, _x: i32, _y: i32) {
-        self.config.window_rect = self.hwnd.rect();
+        self.config.set_window_rect(self.hwnd.rect());
     }
 
     fn on_sized(&mut self, cx: i32, cy: i32) {
         if cx > 0 && cy > 0 {
-            self.config.window_rect = self.hwnd.rect();
+            self.config.set_window_rect(self.hwnd.rect());
             _ = self.renderer.set_size(cx as _, cy as _);
         }
     }
@@ -122,8 +1330,51 @@ impl GuiApp for App {
     }
 
     fn on_timer(&mut self) {
-        if let Some(text) = self.s2t.text() {
-            self.renderer.set_text(&text);
+        self.check_fullscreen();
+        self.check_profile();
+        self.check_live_captions();
+        self.check_schedule();
+        self.soak.tick();
+        self.poll_gamepad();
+        self.poll_midi();
+        self.poll_meeting();
+        self.check_ducking();
+        if let Some(summary) = self.stats.as_mut().and_then(SpeechStats::tick) {
+            self.renderer.set_stats(Some(&summary));
+        }
+
+        for (text, timings, captured) in self.s2t.confirmed() {
+            self.emit_confirmed(text, &timings, captured);
+        }
+
+        // Kept in the history as the raw transcript, but not shown or sent to sinks.
+        for (language, text, timings) in self.s2t.filtered() {
+            let entry = self.history.push_filtered(&language, &text, &timings);
+            if let Some(window) = &self.history_window {
+                window.append(entry);
+            }
+        }
+
+        if let Some(progress) = self.watcher.as_ref().and_then(FolderWatcher::progress) {
+            self.renderer.set_progress(Some(&progress));
+        }
+
+        self.renderer.set_listening(self.s2t.awaiting_text());
+        if let Some(segments) = self.s2t.segments() {
+            self.show_segments(&segments);
+        } else {
+            self.renderer.animate();
+        }
+
+        let errors = self.sinks.take_errors();
+        if !errors.is_empty() {
+            self.renderer
+                .set_segments(&[CaptionSegment::status(errors.join("\r\n"))]);
+        }
+
+        if let Some(e) = self.script.as_mut().and_then(CaptionScript::take_error) {
+            self.renderer
+                .set_segments(&[CaptionSegment::status(format!("Script error, {e}"))]);
         }
     }
 
@@ -131,23 +1382,55 @@ impl GuiApp for App {
         self.renderer.set_dpi(dpi);
     }
 
+    fn on_display_changed(&mut self) {
+        let rect = self
+            .config
+            .window_rects
+            .get(&utils::monitor_topology())
+            .copied()
+            .unwrap_or_else(|| self.hwnd.rect());
+        let rect = utils::clamp_to_work_area(rect);
+
+        self.hwnd
+            .set_pos(rect.x(), rect.y(), rect.width(), rect.height());
+    }
+
+    fn on_hover(&mut self, hover: bool) {
+        self.renderer.set_grab_handle(hover);
+    }
+
+    fn on_tray_click(&mut self) {
+        self.toggle_hidden();
+    }
+
+    fn on_taskbar_created(&mut self) {
+        if let Some(tray) = &self.tray {
+            _ = tray.add();
+        }
+    }
+
+    fn on_menu_open(&mut self) {
+        self.audio_devices = speech_to_text::audio_devices()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|device| Some((CString::new(device.name.clone()).ok()?, device)))
+            .collect();
+    }
+
     fn on_menu(&mut self, id: u32, state: bool) {
         match id {
             CMD_CLEAR => self.clear(),
+            CMD_HISTORY => self.show_history(),
+            CMD_COPY => self.copy(false),
+            CMD_COPY_ALL => self.copy(true),
+            CMD_DECRYPT_LOG => self.decrypt_caption_log(),
+            CMD_MINUTES_MARKDOWN => self.export_minutes(MINUTES_MARKDOWN),
+            CMD_MINUTES_DOCX => self.export_minutes(MINUTES_DOCX),
+            CMD_BOOKMARK => self.bookmark(),
             CMD_MODEL_SMALL_EN => self.set_model(MODEL_SMALL_EN),
             CMD_MODEL_MEDIUM_EN => self.set_model(MODEL_MEDIUM_EN),
             CMD_MODEL_LARGE_V3 => self.set_model(MODEL_LARGE_V3),
             CMD_MODEL_LARGE_V3_TURBO => self.set_model(MODEL_LARGE_V3_TURBO),
-            CMD_DELAY_LOWEST => self.set_latency(DELAY_LOWEST),
-            CMD_DELAY_LOW => self.set_latency(DELAY_LOW),
-            CMD_DELAY_MEDIUM => self.set_latency(DELAY_MEDIUM),
-            CMD_DELAY_HIGH => self.set_latency(DELAY_HIGH),
-            CMD_DELAY_HIGHEST => self.set_latency(DELAY_HIGHEST),
-            CMD_TRANSPARENCY_0 => self.set_opacity(0.0),
-            CMD_TRANSPARENCY_25 => self.set_opacity(0.25),
-            CMD_TRANSPARENCY_50 => self.set_opacity(0.5),
-            CMD_TRANSPARENCY_75 => self.set_opacity(0.75),
-            CMD_TRANSPARENCY_100 => self.set_opacity(1.0),
             CMD_FONT_NAME_SEGOE_UI => self.set_font_name(FONT_NAME_SEGOE_UI),
             CMD_FONT_NAME_ARIAL => self.set_font_name(FONT_NAME_ARIAL),
             CMD_FONT_NAME_VERDANA => self.set_font_name(FONT_NAME_VERDANA),
@@ -159,9 +1442,70 @@ impl GuiApp for App {
             CMD_FONT_SIZE_MEDIUM => self.set_font_size(FONT_SIZE_MEDIUM),
             CMD_FONT_SIZE_LARGE => self.set_font_size(FONT_SIZE_LARGE),
             CMD_FONT_SIZE_VERY_LARGE => self.set_font_size(FONT_SIZE_VERY_LARGE),
+            CMD_FONT_SIZE_AUTO => self.set_font_size(FONT_SIZE_AUTO),
             CMD_FONT_STYLE_BOLD => self.set_font_style_bold(state),
             CMD_FONT_STYLE_ITALIC => self.set_font_style_italic(state),
             CMD_FONT_STYLE_OUTLINE => self.set_font_style_outline(state),
+            CMD_EMPHASIS_COLOR => self.set_emphasis_color(state),
+            CMD_EMPHASIS_BOLD => self.set_emphasis_bold(state),
+            CMD_TRANSLITERATION_NONE => self.set_transliteration(TRANSLITERATION_NONE),
+            CMD_TRANSLITERATION_ROMAJI => self.set_transliteration(TRANSLITERATION_ROMAJI),
+            CMD_TRANSLITERATION_KANA => self.set_transliteration(TRANSLITERATION_KANA),
+            CMD_SIMPLIFY => self.set_simplify(state),
+            CMD_AUTO_CASING => self.set_auto_casing(state),
+            CMD_PUNCTUATION => self.set_punctuation(state),
+            CMD_HOTWORD_BIASING => self.set_hotword_biasing(state),
+            CMD_RELOAD_REPLACEMENTS => self.reload_replacements(),
+            CMD_SPEECH_STATS => self.set_speech_stats(state),
+            CMD_GRANULARITY_TOKEN => self.set_granularity(GRANULARITY_TOKEN),
+            CMD_GRANULARITY_WORD => self.set_granularity(GRANULARITY_WORD),
+            CMD_GRANULARITY_PHRASE => self.set_granularity(GRANULARITY_PHRASE),
+            CMD_GRANULARITY_SENTENCE => self.set_granularity(GRANULARITY_SENTENCE),
+            CMD_OVERFLOW_CLIP => self.set_overflow(OVERFLOW_CLIP),
+            CMD_OVERFLOW_SHRINK => self.set_overflow(OVERFLOW_SHRINK),
+            CMD_OVERFLOW_SCROLL => self.set_overflow(OVERFLOW_SCROLL),
+            CMD_OVERFLOW_ELLIPSIS => self.set_overflow(OVERFLOW_ELLIPSIS),
+            CMD_MAX_LINES_ONE => self.set_max_lines(1),
+            CMD_MAX_LINES_TWO => self.set_max_lines(2),
+            CMD_MAX_LINES_THREE => self.set_max_lines(3),
+            CMD_MAX_LINES_UNLIMITED => self.set_max_lines(MAX_LINES_UNLIMITED),
+            CMD_LINE_WRAP_WORD => self.set_line_wrap(LINE_WRAP_WORD),
+            CMD_LINE_WRAP_WHOLE_WORD => self.set_line_wrap(LINE_WRAP_WHOLE_WORD),
+            CMD_LINE_WRAP_CHARACTER => self.set_line_wrap(LINE_WRAP_CHARACTER),
+            CMD_GUARDRAILS => self.set_guardrails(state),
+            CMD_SILENCE_TRIM_OFF => self.set_silence_trim(SILENCE_TRIM_OFF),
+            CMD_SILENCE_TRIM_SHORT => self.set_silence_trim(SILENCE_TRIM_SHORT),
+            CMD_SILENCE_TRIM_MEDIUM => self.set_silence_trim(SILENCE_TRIM_MEDIUM),
+            CMD_SILENCE_TRIM_LONG => self.set_silence_trim(SILENCE_TRIM_LONG),
+            CMD_ADAPTIVE_VAD => self.set_adaptive_vad(state),
+            CMD_VAD_SENSITIVITY_LOW => self.set_vad_sensitivity(VAD_SENSITIVITY_LOW),
+            CMD_VAD_SENSITIVITY_MEDIUM => self.set_vad_sensitivity(VAD_SENSITIVITY_MEDIUM),
+            CMD_VAD_SENSITIVITY_HIGH => self.set_vad_sensitivity(VAD_SENSITIVITY_HIGH),
+            CMD_VAD_SENSITIVITY_CUSTOM => self.set_vad_sensitivity(VAD_SENSITIVITY_CUSTOM),
+            CMD_AUDIO_CHAIN_BYPASS => self.set_audio_chain_bypass(state),
+            CMD_MONITOR_DELAY_NONE => self.set_monitor_delay(MONITOR_DELAY_NONE),
+            CMD_MONITOR_DELAY_SHORT => self.set_monitor_delay(MONITOR_DELAY_SHORT),
+            CMD_MONITOR_DELAY_MEDIUM => self.set_monitor_delay(MONITOR_DELAY_MEDIUM),
+            CMD_MONITOR_DELAY_LONG => self.set_monitor_delay(MONITOR_DELAY_LONG),
+            CMD_MONITOR_DELAY_VERY_LONG => self.set_monitor_delay(MONITOR_DELAY_VERY_LONG),
+            CMD_FULLSCREEN_PAUSE => self.set_fullscreen_pause(state),
+            CMD_PROFILE_SWITCH => self.set_profile_switch(state),
+            id if id & !0xff == CMD_MODEL_OPTION => self.set_model_option(id),
+            id if id & !0xff == CMD_THEME => self.set_theme(id),
+            id if id & !0xff == CMD_AUDIO_SOURCE => self.set_audio_source(id),
+            id if id & !0xff == CMD_LANGUAGE => self.set_language(id),
+            CMD_TRANSLATE => self.set_translate(state),
+            CMD_THEME_IMPORT => self.import_theme(),
+            CMD_THEME_EXPORT => self.export_theme(),
+            CMD_GAMEPAD => self.set_gamepad(state),
+            CMD_MIDI => self.set_midi(state),
+            CMD_LIVE_CAPTIONS_HIDE => self.set_live_captions_hide(state),
+            CMD_DUCKING_TAG => self.set_ducking_tag(state),
+            CMD_PRIVACY_MODE => self.set_privacy_mode(state),
+            CMD_CAPTION_LOG => self.set_caption_log(state),
+            CMD_MEETING_MODE => self.set_meeting_mode(state),
+            CMD_NO_ACTIVATE => self.set_no_activate(state),
+            CMD_SHOW_IN_TASKBAR => self.set_show_in_taskbar(state),
             CMD_QUIT => self.quit(),
             _ => {}
         }
@@ -170,8 +1514,29 @@ impl GuiApp for App {
     fn menu_items(&self) -> Vec<MenuItem> {
         let config = &self.config;
 
-        vec![
+        let mut items = vec![
             action!(CMD_CLEAR, "Clear"),
+            action!(CMD_HISTORY, "History..."),
+            submenu!(
+                "Export Minutes",
+                action!(CMD_MINUTES_MARKDOWN, "Markdown..."),
+                action!(CMD_MINUTES_DOCX, "Word Document..."),
+            ),
+            action!(CMD_BOOKMARK, "Add Bookmark"),
+            action!(CMD_COPY, "Copy Caption"),
+            action!(CMD_COPY_ALL, "Copy Transcript"),
+            action!(CMD_DECRYPT_LOG, "Decrypt Caption Log..."),
+            checkbox!(CMD_PRIVACY_MODE, "Privacy Mode", config.privacy_mode),
+            checkbox!(
+                CMD_CAPTION_LOG,
+                "Log Captions to File",
+                config.caption_log_enabled && !config.caption_log.is_empty()
+            ),
+            checkbox!(
+                CMD_MEETING_MODE,
+                "Meeting Mode (Me and Them)",
+                config.meeting_mode
+            ),
             separator!(),
             submenu!(
                 "Model",
@@ -179,43 +1544,137 @@ impl GuiApp for App {
                     CMD_MODEL_SMALL_EN,
                     "distil-small.en",
                     config.model == MODEL_SMALL_EN,
+                    speech_to_text::model_description(MODEL_SMALL_EN),
                 ),
                 radio!(
                     CMD_MODEL_MEDIUM_EN,
                     "distil-medium.en",
                     config.model == MODEL_MEDIUM_EN,
+                    speech_to_text::model_description(MODEL_MEDIUM_EN),
                 ),
                 radio!(
                     CMD_MODEL_LARGE_V3,
                     "distil-large-v3",
                     config.model == MODEL_LARGE_V3,
+                    speech_to_text::model_description(MODEL_LARGE_V3),
                 ),
                 radio!(
                     CMD_MODEL_LARGE_V3_TURBO,
                     "large-v3-turbo",
                     config.model == MODEL_LARGE_V3_TURBO,
+                    speech_to_text::model_description(MODEL_LARGE_V3_TURBO),
                 ),
             ),
+            MenuItem::SubMenu {
+                text: s!("Model Options"),
+                items: model_option_items(config),
+            },
+            MenuItem::SubMenu {
+                text: s!("Language"),
+                items: language_items(config),
+            },
+            MenuItem::Sliders {
+                text: s!("Latency, Opacity and Pre-roll..."),
+            },
+            checkbox!(CMD_GUARDRAILS, "Performance Guardrails", config.guardrails),
+            MenuItem::SubMenu {
+                text: s!("Audio Source"),
+                items: self.audio_source_items(),
+            },
+            checkbox!(
+                CMD_AUDIO_CHAIN_BYPASS,
+                "Bypass Audio Preprocessing",
+                config.audio_chain_bypass,
+            ),
             submenu!(
-                "Latency",
-                radio!(CMD_DELAY_LOWEST, "Lowest", config.latency == DELAY_LOWEST),
-                radio!(CMD_DELAY_LOW, "Low", config.latency == DELAY_LOW),
-                radio!(CMD_DELAY_MEDIUM, "Medium", config.latency == DELAY_MEDIUM),
-                radio!(CMD_DELAY_HIGH, "High", config.latency == DELAY_HIGH),
+                "Trim Silence",
                 radio!(
-                    CMD_DELAY_HIGHEST,
-                    "Highest",
-                    config.latency == DELAY_HIGHEST
+                    CMD_SILENCE_TRIM_OFF,
+                    "Off",
+                    config.silence_trim == SILENCE_TRIM_OFF
+                ),
+                radio!(
+                    CMD_SILENCE_TRIM_SHORT,
+                    "Longer than 0.5s",
+                    config.silence_trim == SILENCE_TRIM_SHORT,
+                ),
+                radio!(
+                    CMD_SILENCE_TRIM_MEDIUM,
+                    "Longer than 1s",
+                    config.silence_trim == SILENCE_TRIM_MEDIUM,
+                ),
+                radio!(
+                    CMD_SILENCE_TRIM_LONG,
+                    "Longer than 2s",
+                    config.silence_trim == SILENCE_TRIM_LONG,
+                ),
+                separator!(),
+                checkbox!(
+                    CMD_ADAPTIVE_VAD,
+                    "Adapt to Background Noise",
+                    config.adaptive_vad,
                 ),
             ),
             submenu!(
-                "Opacity",
-                radio!(CMD_TRANSPARENCY_0, "0%", config.opacity == 0.0),
-                radio!(CMD_TRANSPARENCY_25, "25%", config.opacity == 0.25),
-                radio!(CMD_TRANSPARENCY_50, "50%", config.opacity == 0.5),
-                radio!(CMD_TRANSPARENCY_75, "75%", config.opacity == 0.75),
-                radio!(CMD_TRANSPARENCY_100, "100%", config.opacity == 1.0),
+                "VAD Sensitivity",
+                radio!(
+                    CMD_VAD_SENSITIVITY_LOW,
+                    "Low (Noisy Room)",
+                    config.vad_sensitivity == VAD_SENSITIVITY_LOW,
+                ),
+                radio!(
+                    CMD_VAD_SENSITIVITY_MEDIUM,
+                    "Medium",
+                    config.vad_sensitivity == VAD_SENSITIVITY_MEDIUM,
+                ),
+                radio!(
+                    CMD_VAD_SENSITIVITY_HIGH,
+                    "High (Quiet Room)",
+                    config.vad_sensitivity == VAD_SENSITIVITY_HIGH,
+                ),
+                radio!(
+                    CMD_VAD_SENSITIVITY_CUSTOM,
+                    "Custom",
+                    config.vad_sensitivity == VAD_SENSITIVITY_CUSTOM,
+                ),
             ),
+        ];
+        // The delay only applies to audio played back to a monitor device.
+        if !config.monitor_device.is_empty() {
+            items.push(submenu!(
+                "Audio Monitor Delay",
+                radio!(
+                    CMD_MONITOR_DELAY_NONE,
+                    "None",
+                    config.monitor_delay == MONITOR_DELAY_NONE,
+                ),
+                radio!(
+                    CMD_MONITOR_DELAY_SHORT,
+                    "0.2s",
+                    config.monitor_delay == MONITOR_DELAY_SHORT,
+                ),
+                radio!(
+                    CMD_MONITOR_DELAY_MEDIUM,
+                    "0.5s",
+                    config.monitor_delay == MONITOR_DELAY_MEDIUM,
+                ),
+                radio!(
+                    CMD_MONITOR_DELAY_LONG,
+                    "1s",
+                    config.monitor_delay == MONITOR_DELAY_LONG,
+                ),
+                radio!(
+                    CMD_MONITOR_DELAY_VERY_LONG,
+                    "2s",
+                    config.monitor_delay == MONITOR_DELAY_VERY_LONG,
+                ),
+            ));
+        }
+        items.extend([
+            MenuItem::SubMenu {
+                text: s!("Themes"),
+                items: self.theme_items(),
+            },
             submenu!(
                 "Font",
                 radio!(
@@ -276,19 +1735,556 @@ impl GuiApp for App {
                     "Very Large",
                     config.font_size == FONT_SIZE_VERY_LARGE,
                 ),
+                separator!(),
+                radio!(
+                    CMD_FONT_SIZE_AUTO,
+                    "Auto",
+                    config.font_size == FONT_SIZE_AUTO
+                ),
             ),
             submenu!(
                 "Font Style",
                 checkbox!(CMD_FONT_STYLE_BOLD, "Bold", config.bold),
                 checkbox!(CMD_FONT_STYLE_ITALIC, "Italic", config.italic),
                 checkbox!(CMD_FONT_STYLE_OUTLINE, "Outline", config.outline),
+                separator!(),
+                checkbox!(
+                    CMD_EMPHASIS_COLOR,
+                    "Color Questions and Exclamations",
+                    config.emphasis_color,
+                ),
+                checkbox!(
+                    CMD_EMPHASIS_BOLD,
+                    "Bold Questions and Exclamations",
+                    config.emphasis_bold,
+                ),
+            ),
+            submenu!(
+                "Updates",
+                radio!(
+                    CMD_GRANULARITY_TOKEN,
+                    "Every Token",
+                    config.granularity == GRANULARITY_TOKEN,
+                ),
+                radio!(
+                    CMD_GRANULARITY_WORD,
+                    "Whole Words",
+                    config.granularity == GRANULARITY_WORD,
+                ),
+                radio!(
+                    CMD_GRANULARITY_PHRASE,
+                    "Whole Phrases",
+                    config.granularity == GRANULARITY_PHRASE,
+                ),
+                radio!(
+                    CMD_GRANULARITY_SENTENCE,
+                    "Whole Sentences",
+                    config.granularity == GRANULARITY_SENTENCE,
+                ),
+            ),
+            submenu!(
+                "Overflow",
+                radio!(CMD_OVERFLOW_CLIP, "Clip", config.overflow == OVERFLOW_CLIP),
+                radio!(
+                    CMD_OVERFLOW_SHRINK,
+                    "Shrink to Fit",
+                    config.overflow == OVERFLOW_SHRINK,
+                ),
+                radio!(
+                    CMD_OVERFLOW_SCROLL,
+                    "Scroll",
+                    config.overflow == OVERFLOW_SCROLL,
+                ),
+                radio!(
+                    CMD_OVERFLOW_ELLIPSIS,
+                    "Ellipsis",
+                    config.overflow == OVERFLOW_ELLIPSIS,
+                ),
+            ),
+            submenu!(
+                "Max Lines",
+                radio!(CMD_MAX_LINES_ONE, "1", config.max_lines == 1),
+                radio!(CMD_MAX_LINES_TWO, "2", config.max_lines == 2),
+                radio!(CMD_MAX_LINES_THREE, "3", config.max_lines == 3),
+                radio!(
+                    CMD_MAX_LINES_UNLIMITED,
+                    "Unlimited",
+                    config.max_lines == MAX_LINES_UNLIMITED,
+                ),
+            ),
+            submenu!(
+                "Line Wrapping",
+                radio!(
+                    CMD_LINE_WRAP_WORD,
+                    "By Word, Splitting Long Words",
+                    config.line_wrap == LINE_WRAP_WORD,
+                ),
+                radio!(
+                    CMD_LINE_WRAP_WHOLE_WORD,
+                    "Whole Words Only",
+                    config.line_wrap == LINE_WRAP_WHOLE_WORD,
+                ),
+                radio!(
+                    CMD_LINE_WRAP_CHARACTER,
+                    "Any Character",
+                    config.line_wrap == LINE_WRAP_CHARACTER,
+                ),
+            ),
+            submenu!(
+                "Transliteration",
+                radio!(
+                    CMD_TRANSLITERATION_NONE,
+                    "None",
+                    config.transliteration == TRANSLITERATION_NONE,
+                ),
+                radio!(
+                    CMD_TRANSLITERATION_ROMAJI,
+                    "Romaji (Kana Only)",
+                    config.transliteration == TRANSLITERATION_ROMAJI,
+                ),
+                radio!(
+                    CMD_TRANSLITERATION_KANA,
+                    "Katakana to Hiragana",
+                    config.transliteration == TRANSLITERATION_KANA,
+                ),
+            ),
+            checkbox!(CMD_SIMPLIFY, "Hide Fillers and Stutters", config.simplify),
+            checkbox!(CMD_AUTO_CASING, "Capitalize Sentences", config.auto_casing),
+            checkbox!(CMD_PUNCTUATION, "Restore Punctuation", config.punctuation),
+            checkbox!(
+                CMD_HOTWORD_BIASING,
+                "Favor Hotwords",
+                config.hotword_biasing
+            ),
+            action!(CMD_RELOAD_REPLACEMENTS, "Reload Replacements"),
+            checkbox!(CMD_SPEECH_STATS, "Speech Stats", config.speech_stats),
+            checkbox!(
+                CMD_FULLSCREEN_PAUSE,
+                "Pause in Fullscreen Games",
+                config.fullscreen_pause,
+            ),
+            checkbox!(
+                CMD_PROFILE_SWITCH,
+                "Switch Profiles by App",
+                config.profile_switch,
+            ),
+            checkbox!(
+                CMD_LIVE_CAPTIONS_HIDE,
+                "Hide While Live Captions Is Open",
+                config.live_captions_hide,
+            ),
+            checkbox!(
+                CMD_DUCKING_TAG,
+                "Tag Captions During Calls",
+                config.ducking_tag,
+            ),
+            checkbox!(CMD_GAMEPAD, "Gamepad Shortcuts", config.gamepad),
+            checkbox!(CMD_MIDI, "MIDI Shortcuts", config.midi),
+            checkbox!(CMD_NO_ACTIVATE, "Don't Take Focus", config.no_activate),
+            checkbox!(
+                CMD_SHOW_IN_TASKBAR,
+                "Show in Taskbar",
+                config.show_in_taskbar
             ),
             separator!(),
+            MenuItem::Status {
+                text: s!("Status..."),
+            },
             action!(CMD_QUIT, "Quit(&Q)"),
+        ]);
+        items
+    }
+
+    fn on_slider(&mut self, id: u32, value: i32) {
+        match id {
+            CMD_LATENCY => self.set_latency(Duration::from_millis(value as _)),
+            CMD_OPACITY => self.set_opacity(value as f32 / 100.0),
+            CMD_PRE_ROLL => self.set_pre_roll(Duration::from_millis(value as _)),
+            _ => {}
+        }
+    }
+
+    fn status_report(&self) -> String {
+        let status = self.s2t.status();
+        let model = if status.model.is_empty() {
+            "Not loaded".to_string()
+        } else {
+            status.model
+        };
+        let decoder = if status.cpu_decoder {
+            ", decoder on CPU"
+        } else {
+            ""
+        };
+
+        format!(
+            "livesub {}\n\
+             Model: {model}\n\
+             Parameters: {:.0}M\n\
+             VRAM used: {:.2} GB (weights{decoder})\n\
+             Real-time factor: {:.2}\n\
+             Audio device: {}\n\
+             Capture format: {}\n\
+             Audio clock: {:+.1} ms drift over {:.1} min, {} discontinuities\n\
+             Cut short by the token budget: {} times\n\
+             {}",
+            env!("CARGO_PKG_VERSION"),
+            status.parameters as f64 / 1e6,
+            status.vram_bytes as f64 / (1u64 << 30) as f64,
+            status.real_time_factor,
+            status.audio_device,
+            status.capture_format,
+            status.audio_clock.drift * 1000.0,
+            status.audio_clock.span.as_secs_f64() / 60.0,
+            status.audio_clock.discontinuities,
+            status.truncations,
+            self.soak.summary(),
+        )
+    }
+
+    fn on_hotkey(&mut self, id: i32) {
+        match id {
+            HOTKEY_BOOKMARK => self.bookmark(),
+            HOTKEY_PAUSE => self.toggle_pause(),
+            HOTKEY_CLEAR => self.clear(),
+            HOTKEY_CLICK_THROUGH => self.toggle_click_through(),
+            HOTKEY_TOGGLE => self.toggle_hidden(),
+            _ => {}
+        }
+    }
+
+    fn on_copy(&mut self, all: bool) {
+        self.copy(all);
+    }
+
+    fn on_note(&mut self, note: &str) {
+        if let Some(time) = self.pending_bookmark.take() {
+            self.add_bookmark(time, note);
+        }
+    }
+
+    fn sliders(&self) -> Vec<Slider> {
+        let presets = [
+            DELAY_ADAPTIVE,
+            DELAY_LOWEST,
+            DELAY_LOW,
+            DELAY_MEDIUM,
+            DELAY_HIGH,
+            DELAY_HIGHEST,
+        ];
+
+        vec![
+            Slider {
+                id: CMD_LATENCY,
+                min: DELAY_ADAPTIVE.as_millis() as _,
+                max: DELAY_HIGHEST.as_millis() as _,
+                value: self.config.latency.as_millis() as _,
+                snaps: presets.iter().map(|delay| delay.as_millis() as _).collect(),
+                clamp: clamp_latency,
+                live: false,
+                text: |value| {
+                    if value == DELAY_ADAPTIVE.as_millis() as i32 {
+                        "Latency: Adaptive to speech rate".to_string()
+                    } else {
+                        format!("Latency: {value} ms")
+                    }
+                },
+            },
+            Slider {
+                id: CMD_OPACITY,
+                min: 0,
+                max: 100,
+                value: (self.config.opacity * 100.0).round() as _,
+                snaps: vec![0, 25, 50, 75, 100],
+                clamp: |value| value,
+                live: true,
+                text: |value| format!("Opacity: {value}%"),
+            },
+            Slider {
+                id: CMD_PRE_ROLL,
+                min: 0,
+                max: PRE_ROLL_MAX.as_millis() as _,
+                value: self.config.pre_roll.as_millis() as _,
+                snaps: vec![0, 200, 300, 500],
+                clamp: |value| value,
+                live: false,
+                text: |value| format!("Pre-roll before trimmed silence: {value} ms"),
+            },
         ]
     }
 }
 
+/// Latencies between adaptive and the lowest fixed one go to whichever is nearer.
+fn clamp_latency(value: i32) -> i32 {
+    let lowest = DELAY_LOWEST.as_millis() as i32;
+    match value {
+        v if v < lowest / 2 => DELAY_ADAPTIVE.as_millis() as _,
+        v => v.max(lowest),
+    }
+}
+
+/// Starts the engine with everything in `config` that shapes the captions. A list that
+/// can't be read is left empty, with why in `warnings`.
+pub(crate) fn create_speech_to_text(
+    config: &Config,
+    warnings: &mut Vec<String>,
+) -> Result<SpeechToText> {
+    let mut s2t = SpeechToText::new(&config.model, config.latency, &config.model_dir())?;
+    s2t.set_simplify(config.simplify);
+    s2t.set_punctuation(
+        config.punctuation,
+        &config.data_path(&config.punctuation_model),
+    );
+    let hotwords = match config.auto_casing || config.hotword_biasing {
+        true => config.hotwords().unwrap_or_else(|e| {
+            warnings.push(format!("Can't load the hotwords, {e}"));
+            vec![]
+        }),
+        false => vec![],
+    };
+    s2t.set_casing(config.auto_casing, &hotwords);
+    if config.hotword_biasing {
+        s2t.set_hotwords(hotwords);
+    }
+    s2t.set_transliteration(&config.transliteration);
+    s2t.set_granularity(&config.granularity);
+    s2t.set_task(&config.language, config.translate);
+    let rules = config.replacement_rules().unwrap_or_else(|e| {
+        warnings.push(format!("Can't load the replacements, {e}"));
+        vec![]
+    });
+    s2t.set_replacements(&rules);
+    s2t.set_plugins(&config.plugin_paths());
+    s2t.set_pre_roll(config.pre_roll);
+    s2t.set_adaptive_vad(config.adaptive_vad);
+    s2t.set_vad_sensitivity(vad_sensitivity(config));
+    if !config.audio_chain_bypass {
+        s2t.set_audio_chain(&config.audio_chain);
+    }
+    s2t.set_silence_trim(config.silence_trim);
+    s2t.set_audio_source(&config.audio_source);
+    s2t.set_monitor(&config.monitor_device, config.monitor_delay);
+    s2t.set_encoder_stride(config.encoder_stride);
+    s2t.set_model_options(config.model_options());
+    s2t.set_guardrails(config.guardrails);
+    s2t.set_language_filter(config.filter_languages(), config.language_filter_ghost);
+
+    Ok(s2t)
+}
+
+pub(crate) fn load_script(config: &Config) -> Result<Option<CaptionScript>> {
+    if config.script.is_empty() {
+        return Ok(None);
+    }
+
+    CaptionScript::load(config.data_path(&config.script)).map(Some)
+}
+
+/// Masks personal data for everything that leaves the app, per the redaction rules,
+/// along with the rules that had to be skipped.
+pub(crate) fn create_redactor(config: &Config) -> (Option<Redactor>, Vec<String>) {
+    let mut skipped = vec![];
+    let patterns = config.redact_patterns().unwrap_or_else(|e| {
+        skipped.push(format!("redaction patterns, {e}"));
+        vec![]
+    });
+    let (redactor, invalid) = Redactor::new(&config.redact_rules(), &patterns);
+    skipped.extend(invalid);
+    (redactor, skipped)
+}
+
+/// The sinks from the config. One that can't be opened is left out, with why in `warnings`.
+pub(crate) fn create_sinks(config: &Config, warnings: &mut Vec<String>) -> Sinks {
+    let mut sinks = Sinks::new();
+    match caption_log_sink(config) {
+        Ok(sink) => sinks.set("caption-log", sink),
+        Err(e) => warnings.push(format!("Can't open the caption log, {e}")),
+    }
+    if !config.obs_file.is_empty() {
+        let path = config.data_path(&config.obs_file);
+        match ObsFile::new(
+            path,
+            config.obs_file_lines as _,
+            config.obs_file_line_width as _,
+        ) {
+            Ok(sink) => {
+                let formatter = Formatter::from_name(&config.obs_file_format);
+                sinks.set("obs-file", Some(Box::new(Formatted::new(formatter, sink))))
+            }
+            Err(e) => warnings.push(format!("Can't open the OBS file, {e}")),
+        }
+    }
+    if !config.subtitle_export.is_empty() {
+        let path = config.data_path(&config.subtitle_export);
+        match SubtitleExport::new(
+            path,
+            &subtitle_style(config),
+            wrap_rules(config),
+            config.subtitle_max_duration,
+        ) {
+            Ok(sink) => sinks.set("subtitle-export", Some(Box::new(sink))),
+            Err(e) => warnings.push(format!("Can't open the subtitle export, {e}")),
+        }
+    }
+    if !config.webhook_url.is_empty() {
+        match Webhook::new(
+            &config.webhook_url,
+            &config.webhook_template,
+            &config.webhook_content_type,
+        ) {
+            Ok(sink) => {
+                let formatter = Formatter::from_name(&config.webhook_format);
+                sinks.set("webhook", Some(Box::new(Formatted::new(formatter, sink))))
+            }
+            Err(e) => warnings.push(format!("Can't start the webhook, {e}")),
+        }
+    }
+
+    sinks
+}
+
+fn caption_log_sink(config: &Config) -> Result<Option<Box<dyn Sink>>> {
+    if !config.caption_log_enabled || config.caption_log.is_empty() {
+        return Ok(None);
+    }
+
+    let path = config.data_path(&config.caption_log);
+    let max_size = config.caption_log_max_kb as u64 * 1024;
+    let formatter = Formatter::from_name(&config.caption_log_format);
+    let log = CaptionLog::new(
+        path,
+        max_size,
+        &config.caption_log_passphrase,
+        config.caption_log_timestamps,
+    )?;
+
+    Ok(Some(Box::new(Formatted::new(formatter, log))))
+}
+
+fn subtitle_style(config: &Config) -> SubtitleStyle {
+    SubtitleStyle {
+        font_name: config.font_name.clone(),
+        font_size: config.font_size,
+        bold: config.bold,
+        italic: config.italic,
+        outline: config.outline,
+        opacity: config.opacity,
+    }
+}
+
+/// Low sensitivity takes louder audio as speech and ends it sooner, for noisy rooms;
+/// custom takes `vad-threshold` in dB and `vad-hangover` in milliseconds.
+fn vad_sensitivity(config: &Config) -> VadSensitivity {
+    let (threshold_db, hangover) = match config.vad_sensitivity.as_str() {
+        VAD_SENSITIVITY_LOW => (-38.0, Duration::from_millis(400)),
+        VAD_SENSITIVITY_HIGH => (-52.0, Duration::from_millis(900)),
+        VAD_SENSITIVITY_CUSTOM => (config.vad_threshold as f32, config.vad_hangover),
+        _ => return VadSensitivity::default(),
+    };
+
+    VadSensitivity {
+        threshold_db,
+        hangover,
+    }
+}
+
+fn wrap_rules(config: &Config) -> WrapRules {
+    WrapRules {
+        max_chars: config.line_max_chars as _,
+        max_lines: config.cue_max_lines as _,
+    }
+}
+
+fn update_colors(renderer: &mut Renderer, config: &Config) {
+    renderer.set_background_color(parse_color(&config.background_color).unwrap_or([0.0; 4]));
+    renderer.set_text_colors(
+        parse_color(&config.text_color).unwrap_or([1.0; 4]),
+        parse_color(&config.outline_color).unwrap_or([0.0, 0.0, 0.0, 1.0]),
+    );
+}
+
+fn emphasis_style(config: &Config) -> EmphasisStyle {
+    let color = |color: &str| parse_color(color).filter(|_| config.emphasis_color);
+
+    EmphasisStyle {
+        question: color(&config.question_color),
+        exclamation: color(&config.exclamation_color),
+        bold: config.emphasis_bold,
+    }
+}
+
+/// Captions are polled twice per decode; adaptive latency can decode as often as [`DELAY_LOW`].
+pub(crate) fn timer_interval(latency: Duration) -> u32 {
+    let latency = if latency == DELAY_ADAPTIVE {
+        DELAY_LOW
+    } else {
+        latency
+    };
+    latency.as_millis() as u32 / 2
+}
+
+/// Needs a multilingual model; English-only models ignore the choice.
+fn language_items(config: &Config) -> Vec<MenuItem> {
+    let mut items = vec![
+        radio!(CMD_LANGUAGE, "Auto Detect", config.language.is_empty()),
+        separator!(),
+    ];
+    items.extend(
+        speech_to_text::LANGUAGES
+            .iter()
+            .enumerate()
+            .map(|(i, (code, name))| MenuItem::Radio {
+                id: CMD_LANGUAGE + i as u32 + 1,
+                text: *name,
+                checked: config.language == *code,
+                description: None,
+            }),
+    );
+    items.push(separator!());
+    items.push(checkbox!(
+        CMD_TRANSLATE,
+        "Translate to English",
+        config.translate
+    ));
+
+    items
+}
+
+fn model_option_items(config: &Config) -> Vec<MenuItem> {
+    let settings = speech_to_text::model_settings(&config.model);
+
+    settings
+        .iter()
+        .enumerate()
+        .map(|(i, setting)| {
+            // Without a value of its own, the model follows the global encoder-stride.
+            let global = match setting.key {
+                speech_to_text::SETTING_ENCODER_STRIDE => Some(config.encoder_stride.to_string()),
+                _ => None,
+            };
+            let current = config
+                .model_option(setting.key)
+                .map(str::to_string)
+                .or(global)
+                .unwrap_or_else(|| setting.default.to_string());
+            MenuItem::SubMenu {
+                text: setting.label,
+                items: setting
+                    .choices
+                    .iter()
+                    .enumerate()
+                    .map(|(j, (value, label))| MenuItem::Radio {
+                        id: CMD_MODEL_OPTION + (i * MODEL_OPTION_CHOICES + j + 1) as u32,
+                        text: *label,
+                        checked: *value == current.as_str(),
+                        description: None,
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
 macro_rules! cmd {
     ($category:expr, $item:expr, $cmd:ident) => {
         const $cmd: u32 = (0x100 * $category) + $item;
@@ -296,20 +2292,23 @@ macro_rules! cmd {
 }
 
 cmd!(1, 1, CMD_CLEAR);
+cmd!(1, 2, CMD_HISTORY);
+cmd!(1, 3, CMD_BOOKMARK);
+cmd!(1, 4, CMD_MINUTES_MARKDOWN);
+cmd!(1, 5, CMD_MINUTES_DOCX);
+cmd!(1, 6, CMD_COPY);
+cmd!(1, 7, CMD_COPY_ALL);
+cmd!(1, 8, CMD_DECRYPT_LOG);
+cmd!(1, 9, CMD_PRIVACY_MODE);
+cmd!(1, 10, CMD_CAPTION_LOG);
+cmd!(1, 11, CMD_MEETING_MODE);
 cmd!(2, 1, CMD_MODEL_SMALL_EN);
 cmd!(2, 2, CMD_MODEL_MEDIUM_EN);
 cmd!(2, 3, CMD_MODEL_LARGE_V3);
 cmd!(2, 4, CMD_MODEL_LARGE_V3_TURBO);
-cmd!(3, 1, CMD_DELAY_LOWEST);
-cmd!(3, 2, CMD_DELAY_LOW);
-cmd!(3, 3, CMD_DELAY_MEDIUM);
-cmd!(3, 4, CMD_DELAY_HIGH);
-cmd!(3, 5, CMD_DELAY_HIGHEST);
-cmd!(4, 1, CMD_TRANSPARENCY_0);
-cmd!(4, 2, CMD_TRANSPARENCY_25);
-cmd!(4, 3, CMD_TRANSPARENCY_50);
-cmd!(4, 4, CMD_TRANSPARENCY_75);
-cmd!(4, 5, CMD_TRANSPARENCY_100);
+cmd!(3, 1, CMD_LATENCY);
+cmd!(3, 6, CMD_GUARDRAILS);
+cmd!(4, 1, CMD_OPACITY);
 cmd!(5, 1, CMD_FONT_NAME_SEGOE_UI);
 cmd!(5, 2, CMD_FONT_NAME_ARIAL);
 cmd!(5, 3, CMD_FONT_NAME_VERDANA);
@@ -321,7 +2320,73 @@ cmd!(6, 2, CMD_FONT_SIZE_SMALL);
 cmd!(6, 3, CMD_FONT_SIZE_MEDIUM);
 cmd!(6, 4, CMD_FONT_SIZE_LARGE);
 cmd!(6, 5, CMD_FONT_SIZE_VERY_LARGE);
+cmd!(6, 6, CMD_FONT_SIZE_AUTO);
 cmd!(7, 1, CMD_FONT_STYLE_BOLD);
 cmd!(7, 2, CMD_FONT_STYLE_ITALIC);
 cmd!(7, 3, CMD_FONT_STYLE_OUTLINE);
+cmd!(7, 4, CMD_EMPHASIS_COLOR);
+cmd!(7, 5, CMD_EMPHASIS_BOLD);
 cmd!(8, 1, CMD_QUIT);
+cmd!(11, 1, CMD_FULLSCREEN_PAUSE);
+cmd!(11, 2, CMD_GAMEPAD);
+cmd!(11, 3, CMD_NO_ACTIVATE);
+cmd!(11, 4, CMD_SHOW_IN_TASKBAR);
+cmd!(11, 5, CMD_SPEECH_STATS);
+cmd!(11, 6, CMD_LIVE_CAPTIONS_HIDE);
+cmd!(11, 7, CMD_DUCKING_TAG);
+cmd!(11, 8, CMD_PROFILE_SWITCH);
+cmd!(11, 9, CMD_MIDI);
+cmd!(9, 1, CMD_TRANSLITERATION_NONE);
+cmd!(9, 2, CMD_TRANSLITERATION_ROMAJI);
+cmd!(9, 3, CMD_TRANSLITERATION_KANA);
+cmd!(9, 4, CMD_SIMPLIFY);
+cmd!(9, 5, CMD_AUTO_CASING);
+cmd!(9, 6, CMD_PUNCTUATION);
+cmd!(9, 7, CMD_RELOAD_REPLACEMENTS);
+cmd!(9, 8, CMD_HOTWORD_BIASING);
+cmd!(10, 1, CMD_SILENCE_TRIM_OFF);
+cmd!(10, 2, CMD_SILENCE_TRIM_SHORT);
+cmd!(10, 3, CMD_SILENCE_TRIM_MEDIUM);
+cmd!(10, 4, CMD_SILENCE_TRIM_LONG);
+cmd!(10, 5, CMD_PRE_ROLL);
+cmd!(10, 6, CMD_ADAPTIVE_VAD);
+cmd!(10, 7, CMD_AUDIO_CHAIN_BYPASS);
+cmd!(10, 8, CMD_VAD_SENSITIVITY_LOW);
+cmd!(10, 9, CMD_VAD_SENSITIVITY_MEDIUM);
+cmd!(10, 10, CMD_VAD_SENSITIVITY_HIGH);
+cmd!(10, 11, CMD_VAD_SENSITIVITY_CUSTOM);
+cmd!(12, 1, CMD_OVERFLOW_CLIP);
+cmd!(12, 2, CMD_OVERFLOW_SHRINK);
+cmd!(12, 3, CMD_OVERFLOW_SCROLL);
+cmd!(12, 4, CMD_OVERFLOW_ELLIPSIS);
+cmd!(12, 5, CMD_MAX_LINES_ONE);
+cmd!(12, 6, CMD_MAX_LINES_TWO);
+cmd!(12, 7, CMD_MAX_LINES_THREE);
+cmd!(12, 8, CMD_MAX_LINES_UNLIMITED);
+cmd!(12, 9, CMD_LINE_WRAP_WORD);
+cmd!(12, 10, CMD_LINE_WRAP_WHOLE_WORD);
+cmd!(12, 11, CMD_LINE_WRAP_CHARACTER);
+cmd!(13, 0, CMD_MODEL_OPTION);
+cmd!(14, 1, CMD_MONITOR_DELAY_NONE);
+cmd!(14, 2, CMD_MONITOR_DELAY_SHORT);
+cmd!(14, 3, CMD_MONITOR_DELAY_MEDIUM);
+cmd!(14, 4, CMD_MONITOR_DELAY_LONG);
+cmd!(14, 5, CMD_MONITOR_DELAY_VERY_LONG);
+// Theme commands are CMD_THEME + index + 1, in the order of `App::themes`.
+cmd!(15, 0, CMD_THEME);
+cmd!(16, 1, CMD_THEME_IMPORT);
+cmd!(16, 2, CMD_THEME_EXPORT);
+cmd!(17, 1, CMD_GRANULARITY_TOKEN);
+cmd!(17, 2, CMD_GRANULARITY_WORD);
+cmd!(17, 3, CMD_GRANULARITY_PHRASE);
+cmd!(17, 4, CMD_GRANULARITY_SENTENCE);
+// Audio source commands are CMD_AUDIO_SOURCE for the default output device,
+// or CMD_AUDIO_SOURCE + index + 1 in the order of `App::audio_devices`.
+cmd!(18, 0, CMD_AUDIO_SOURCE);
+// Language commands are CMD_LANGUAGE for auto detection,
+// or CMD_LANGUAGE + index + 1 in the order of `speech_to_text::LANGUAGES`.
+cmd!(19, 0, CMD_LANGUAGE);
+cmd!(20, 1, CMD_TRANSLATE);
+
+// Model option commands are CMD_MODEL_OPTION + setting * MODEL_OPTION_CHOICES + choice + 1.
+const MODEL_OPTION_CHOICES: usize = 0x10;
@@ -0,0 +1,106 @@
+use std::time::Instant;
+
+pub mod emphasis;
+pub mod history;
+pub mod minutes;
+pub mod stats;
+pub mod wrap;
+
+pub use emphasis::EmphasisStyle;
+pub use stats::SpeechStats;
+pub use wrap::WrapRules;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentRole {
+    Confirmed,
+    Tentative,
+    Status,
+    Speaker(u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptionSegment {
+    pub text: String,
+    pub role: SegmentRole,
+    pub color: Option<[f32; 4]>,
+}
+
+/// A span of caption text with the time it was spoken.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedText {
+    pub start: Instant,
+    pub end: Instant,
+    pub text: String,
+}
+
+impl CaptionSegment {
+    pub fn new(text: impl Into<String>, role: SegmentRole) -> Self {
+        Self {
+            text: text.into(),
+            role,
+            color: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn confirmed(text: impl Into<String>) -> Self {
+        Self::new(text, SegmentRole::Confirmed)
+    }
+
+    pub fn tentative(text: impl Into<String>) -> Self {
+        Self::new(text, SegmentRole::Tentative)
+    }
+
+    pub fn status(text: impl Into<String>) -> Self {
+        Self::new(text, SegmentRole::Status)
+    }
+}
+
+/// Spreads `text` over the spans of `timings` in proportion to their length, for text
+/// rewritten after it was timed. Cuts fall on the next space, or between CJK characters.
+pub fn retime(timings: &[TimedText], text: &str) -> Vec<TimedText> {
+    let joined: String = timings.iter().map(|timing| timing.text.as_str()).collect();
+    if joined.trim() == text.trim() {
+        return timings.to_vec();
+    }
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let total = joined.chars().count().max(1);
+    let mut spans = vec![];
+    let mut offset = 0;
+    let mut cut = 0;
+
+    for (i, timing) in timings.iter().enumerate() {
+        offset += timing.text.chars().count();
+        let start = cut;
+        cut = match i + 1 == timings.len() {
+            true => chars.len(),
+            false => {
+                let mut cut = (chars.len() * offset / total).max(start);
+                while cut < chars.len()
+                    && !chars[cut].1.is_whitespace()
+                    && !(cut > 0 && wrap::is_cjk(chars[cut - 1].1))
+                {
+                    cut += 1;
+                }
+                cut
+            }
+        };
+
+        let byte = |pos: usize| chars.get(pos).map_or(text.len(), |&(byte, _)| byte);
+        let span = &text[byte(start)..byte(cut)];
+        if !span.trim().is_empty() {
+            spans.push(TimedText {
+                start: timing.start,
+                end: timing.end,
+                text: span.to_string(),
+            });
+        }
+    }
+
+    spans
+}
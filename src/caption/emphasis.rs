@@ -0,0 +1,77 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mood {
+    Question,
+    Exclamation,
+}
+
+/// How sentences ending in "?" or "!" stand out, as a cue for the tone of voice.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EmphasisStyle {
+    pub question: Option<[f32; 4]>,
+    pub exclamation: Option<[f32; 4]>,
+    pub bold: bool,
+}
+
+impl EmphasisStyle {
+    pub fn is_enabled(&self) -> bool {
+        self.question.is_some() || self.exclamation.is_some() || self.bold
+    }
+
+    pub fn color(&self, mood: Mood) -> Option<[f32; 4]> {
+        match mood {
+            Mood::Question => self.question,
+            Mood::Exclamation => self.exclamation,
+        }
+    }
+}
+
+/// Returns the byte ranges of questions and exclamations, sentences split as in
+/// [`split_sentences`](super::wrap::split_sentences). "?!" counts as a question.
+pub fn sentence_moods(text: &str) -> Vec<(Range<usize>, Mood)> {
+    let mut moods = vec![];
+    let mut start = None;
+    let mut mood = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if start.is_none() && !c.is_whitespace() {
+            start = Some(i);
+        }
+
+        let end = i + c.len_utf8();
+        let terminal = match c {
+            '?' | '？' => Some(Some(Mood::Question)),
+            '!' | '！' => Some(Some(Mood::Exclamation)),
+            '.' | '。' => Some(None),
+            _ => None,
+        };
+        let Some(terminal) = terminal else {
+            continue;
+        };
+
+        mood = match (mood, terminal) {
+            (Some(Mood::Question), _) => Some(Mood::Question),
+            (_, terminal) => terminal.or(mood),
+        };
+        let boundary = matches!(c, '。' | '！' | '？')
+            || chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        let continues = chars
+            .peek()
+            .is_some_and(|(_, next)| matches!(next, '?' | '!' | '？' | '！'));
+
+        if continues {
+            continue;
+        }
+        if boundary {
+            if let (Some(start), Some(mood)) = (start, mood) {
+                moods.push((start..end, mood));
+            }
+            start = None;
+        }
+        mood = None;
+    }
+
+    moods
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use super::TimedText;
+
+pub const MAX_ENTRIES: usize = 5000;
+
+/// Confirmed captions of the session with when they were spoken, relative to its start.
+pub struct History {
+    started: Instant,
+    entries: VecDeque<HistoryEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+    /// Spans of speech, from the model's timestamps; empty without them.
+    pub spans: Vec<(Duration, Duration)>,
+    /// A moment flagged by the user, with its note as the text.
+    pub bookmark: bool,
+    /// Kept from the sinks by the language filter, tagged with its language.
+    pub filtered: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn started(&self) -> Instant {
+        self.started
+    }
+
+    pub fn entries(&self) -> &VecDeque<HistoryEntry> {
+        &self.entries
+    }
+
+    /// Without timings the caption is placed at the current time.
+    pub fn push(&mut self, text: &str, timings: &[TimedText]) -> &HistoryEntry {
+        let offset = |time: Instant| time.saturating_duration_since(self.started);
+        let spans: Vec<_> = timings
+            .iter()
+            .map(|timed| (offset(timed.start), offset(timed.end)))
+            .collect();
+        let now = self.started.elapsed();
+        let start = spans.first().map_or(now, |span| span.0);
+        let end = spans.last().map_or(now, |span| span.1);
+
+        self.add(HistoryEntry {
+            start,
+            end,
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            spans,
+            bookmark: false,
+            filtered: false,
+        })
+    }
+
+    /// A caption the language filter held back, kept as the raw transcript.
+    pub fn push_filtered(
+        &mut self,
+        language: &str,
+        text: &str,
+        timings: &[TimedText],
+    ) -> &HistoryEntry {
+        self.push(&format!("[{language}] {text}"), timings);
+        let last = self.entries.len() - 1;
+        self.entries[last].filtered = true;
+        &self.entries[last]
+    }
+
+    pub fn bookmark(&mut self, time: Instant, note: &str) -> &HistoryEntry {
+        let offset = time.saturating_duration_since(self.started);
+        self.add(HistoryEntry {
+            start: offset,
+            end: offset,
+            text: note.trim().to_string(),
+            spans: vec![],
+            bookmark: true,
+            filtered: false,
+        })
+    }
+
+    fn add(&mut self, entry: HistoryEntry) -> &HistoryEntry {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+
+        &self.entries[self.entries.len() - 1]
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `h:mm:ss`.
+pub fn format_offset(offset: Duration) -> String {
+    let secs = offset.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
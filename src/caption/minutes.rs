@@ -0,0 +1,324 @@
+use std::time::Duration;
+
+use crate::sink::Formatter;
+
+use super::history::{format_offset, HistoryEntry};
+
+/// The history as a document, with captions grouped into paragraphs at long pauses.
+/// Captions held back by the language filter are left out.
+pub struct Minutes {
+    title: String,
+    items: Vec<Item>,
+}
+
+enum Item {
+    Paragraph { start: Duration, text: String },
+    Bookmark { at: Duration, note: String },
+}
+
+impl Minutes {
+    /// A pause of `topic_pause` or longer between captions starts a new paragraph.
+    pub fn new<'a>(
+        title: &str,
+        entries: impl IntoIterator<Item = &'a HistoryEntry>,
+        topic_pause: Duration,
+    ) -> Self {
+        let mut items = vec![];
+        let mut last_end = None;
+
+        for entry in entries {
+            if entry.bookmark {
+                items.push(Item::Bookmark {
+                    at: entry.start,
+                    note: entry.text.clone(),
+                });
+                continue;
+            }
+            if entry.text.is_empty() || entry.filtered {
+                continue;
+            }
+
+            let paused = last_end.is_none_or(|end| entry.start.saturating_sub(end) >= topic_pause);
+            match items.last_mut() {
+                Some(Item::Paragraph { text, .. }) if !paused => {
+                    text.push(' ');
+                    text.push_str(&entry.text);
+                }
+                _ => items.push(Item::Paragraph {
+                    start: entry.start,
+                    text: entry.text.clone(),
+                }),
+            }
+            last_end = Some(entry.end);
+        }
+
+        Self {
+            title: title.to_string(),
+            items,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# {}\n", escape_markdown(&self.title));
+
+        for item in &self.items {
+            markdown += &match item {
+                Item::Paragraph { start, text } => {
+                    format!(
+                        "\n**{}** {}\n",
+                        format_offset(*start),
+                        escape_markdown(text)
+                    )
+                }
+                Item::Bookmark { at, note } if note.is_empty() => {
+                    format!("\n> **Bookmark** {}\n", format_offset(*at))
+                }
+                Item::Bookmark { at, note } => format!(
+                    "\n> **Bookmark** {}: {}\n",
+                    format_offset(*at),
+                    escape_markdown(note)
+                ),
+            };
+        }
+
+        markdown
+    }
+
+    /// A Word document, the smallest package Word and LibreOffice open.
+    pub fn to_docx(&self) -> Vec<u8> {
+        let mut body = paragraph(&[run(&self.title, true)]);
+
+        for item in &self.items {
+            body += &match item {
+                Item::Paragraph { start, text } => paragraph(&[
+                    run(&format!("{} ", format_offset(*start)), true),
+                    run(text, false),
+                ]),
+                Item::Bookmark { at, note } => {
+                    let label = format!("Bookmark {}", format_offset(*at));
+                    let mut runs = vec![run(&label, true)];
+                    if !note.is_empty() {
+                        runs.push(run(&format!(": {note}"), false));
+                    }
+                    paragraph(&runs)
+                }
+            };
+        }
+
+        let document = format!(
+            "{XML_HEADER}<w:document xmlns:w=\"{WORDML}\"><w:body>{body}</w:body></w:document>"
+        );
+
+        zip(&[
+            ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+            ("_rels/.rels", RELS.as_bytes()),
+            ("word/document.xml", document.as_bytes()),
+        ])
+    }
+}
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>";
+const WORDML: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+<Override PartName=\"/word/document.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\
+</Types>";
+const RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" \
+Target=\"word/document.xml\"/>\
+</Relationships>";
+
+fn paragraph(runs: &[String]) -> String {
+    format!("<w:p>{}</w:p>", runs.concat())
+}
+
+fn run(text: &str, bold: bool) -> String {
+    let properties = if bold { "<w:rPr><w:b/></w:rPr>" } else { "" };
+    format!(
+        "<w:r>{properties}<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        escape_xml(text)
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_markdown(text: &str) -> String {
+    Formatter::Markdown.confirmed(text)
+}
+
+/// A ZIP archive of uncompressed files, which is all a DOCX package needs to be.
+fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut archive = vec![];
+    let mut directory = vec![];
+
+    for (name, data) in files {
+        let offset = archive.len() as u32;
+        let crc = crc32(data);
+        let header = |signature: u32| {
+            let mut header = signature.to_le_bytes().to_vec();
+            if signature == CENTRAL_HEADER {
+                header.extend(20u16.to_le_bytes()); // version made by
+            }
+            header.extend(20u16.to_le_bytes()); // version needed
+            header.extend(0u16.to_le_bytes()); // flags
+            header.extend(0u16.to_le_bytes()); // stored
+            header.extend(0u32.to_le_bytes()); // time and date
+            header.extend(crc.to_le_bytes());
+            header.extend((data.len() as u32).to_le_bytes());
+            header.extend((data.len() as u32).to_le_bytes());
+            header.extend((name.len() as u16).to_le_bytes());
+            header.extend(0u16.to_le_bytes()); // extra field
+            header
+        };
+
+        archive.extend(header(LOCAL_HEADER));
+        archive.extend(name.as_bytes());
+        archive.extend(*data);
+
+        directory.extend(header(CENTRAL_HEADER));
+        directory.extend(0u16.to_le_bytes()); // comment
+        directory.extend(0u16.to_le_bytes()); // disk
+        directory.extend(0u16.to_le_bytes()); // internal attributes
+        directory.extend(0u32.to_le_bytes()); // external attributes
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+
+    let directory_offset = archive.len() as u32;
+    archive.extend(&directory);
+    archive.extend(END_OF_DIRECTORY.to_le_bytes());
+    archive.extend(0u16.to_le_bytes()); // disk
+    archive.extend(0u16.to_le_bytes()); // disk with the directory
+    archive.extend((files.len() as u16).to_le_bytes());
+    archive.extend((files.len() as u16).to_le_bytes());
+    archive.extend((directory.len() as u32).to_le_bytes());
+    archive.extend(directory_offset.to_le_bytes());
+    archive.extend(0u16.to_le_bytes()); // comment
+
+    archive
+}
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(secs: u64, text: &str) -> HistoryEntry {
+        HistoryEntry {
+            start: Duration::from_secs(secs),
+            end: Duration::from_secs(secs + 2),
+            text: text.to_string(),
+            spans: vec![],
+            bookmark: false,
+            filtered: false,
+        }
+    }
+
+    fn u16_at(data: &[u8], pos: usize) -> usize {
+        u16::from_le_bytes([data[pos], data[pos + 1]]) as usize
+    }
+
+    fn u32_at(data: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+    }
+
+    /// Reads the files back through the central directory, checking each against its
+    /// local header and CRC, as an unzip tool would.
+    fn unzip(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(archive, end), END_OF_DIRECTORY);
+        let count = u16_at(archive, end + 10);
+        let directory_len = u32_at(archive, end + 12) as usize;
+        let mut pos = u32_at(archive, end + 16) as usize;
+        assert_eq!(pos + directory_len, end);
+
+        let mut files = vec![];
+        for _ in 0..count {
+            assert_eq!(u32_at(archive, pos), CENTRAL_HEADER);
+            let crc = u32_at(archive, pos + 16);
+            let size = u32_at(archive, pos + 24) as usize;
+            let name_len = u16_at(archive, pos + 28);
+            let offset = u32_at(archive, pos + 42) as usize;
+            let name = &archive[pos + 46..pos + 46 + name_len];
+            pos += 46 + name_len;
+
+            assert_eq!(u32_at(archive, offset), LOCAL_HEADER);
+            assert_eq!(u32_at(archive, offset + 14), crc);
+            assert_eq!(u32_at(archive, offset + 22) as usize, size);
+            assert_eq!(&archive[offset + 30..offset + 30 + name_len], name);
+            let data = &archive[offset + 30 + name_len..offset + 30 + name_len + size];
+            assert_eq!(crc32(data), crc);
+
+            files.push((String::from_utf8(name.to_vec()).unwrap(), data.to_vec()));
+        }
+        files
+    }
+
+    #[test]
+    fn crc32_matches_the_standard_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn docx_round_trips_through_its_zip_directory() {
+        let mut filtered = entry(4, "[fr] bonjour");
+        filtered.filtered = true;
+        let entries = [
+            entry(0, "Hello & welcome"),
+            filtered,
+            entry(60, "Next <topic>"),
+        ];
+        let minutes = Minutes::new("Standup", &entries, Duration::from_secs(30));
+
+        let files = unzip(&minutes.to_docx());
+        let names: Vec<_> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["[Content_Types].xml", "_rels/.rels", "word/document.xml"]
+        );
+        assert_eq!(files[0].1, CONTENT_TYPES.as_bytes());
+        assert_eq!(files[1].1, RELS.as_bytes());
+
+        let document = String::from_utf8(files[2].1.clone()).unwrap();
+        assert!(document.contains("Standup"));
+        assert!(document.contains("Hello &amp; welcome"));
+        assert!(document.contains("Next &lt;topic&gt;"));
+        assert!(!document.contains("bonjour"));
+    }
+}
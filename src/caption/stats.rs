@@ -0,0 +1,149 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use super::wrap::is_cjk;
+
+// Short spans would make the rate spike after the first caption.
+const MIN_SPAN: Duration = Duration::from_secs(10);
+// How often the summary is redone while no captions come in, as the rates fall.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Speaking rate and vocabulary uniqueness over the captions confirmed in the last `window`.
+/// Words are counted in spaced scripts and characters in Chinese and Japanese, which have
+/// no spaces to count words by.
+pub struct SpeechStats {
+    window: Duration,
+    started: Option<Instant>,
+    refreshed: Instant,
+    captions: VecDeque<Caption>,
+}
+
+struct Caption {
+    time: Instant,
+    words: Vec<String>,
+    cjk_chars: usize,
+}
+
+impl SpeechStats {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            started: None,
+            refreshed: Instant::now(),
+            captions: VecDeque::new(),
+        }
+    }
+
+    pub fn add(&mut self, text: &str) {
+        let now = Instant::now();
+        let (words, cjk_chars) = words(text);
+        if !words.is_empty() || cjk_chars > 0 {
+            self.started.get_or_insert(now);
+            self.captions.push_back(Caption {
+                time: now,
+                words,
+                cjk_chars,
+            });
+        }
+        self.expire(now);
+    }
+
+    /// The summary once a second, so the rates fall off while nothing is said.
+    pub fn tick(&mut self) -> Option<String> {
+        let now = Instant::now();
+        if now.duration_since(self.refreshed) < REFRESH_INTERVAL || self.started.is_none() {
+            return None;
+        }
+        self.expire(now);
+        Some(self.summary())
+    }
+
+    pub fn words_per_minute(&self) -> f32 {
+        self.words().count() as f32 / self.minutes()
+    }
+
+    pub fn cjk_chars_per_minute(&self) -> f32 {
+        let chars: usize = self.captions.iter().map(|caption| caption.cjk_chars).sum();
+        chars as f32 / self.minutes()
+    }
+
+    /// Ratio of distinct words to all words, from 0 to 1.
+    pub fn uniqueness(&self) -> f32 {
+        let total = self.words().count();
+        if total == 0 {
+            return 0.0;
+        }
+
+        let distinct: HashSet<&str> = self.words().collect();
+        distinct.len() as f32 / total as f32
+    }
+
+    pub fn summary(&mut self) -> String {
+        self.refreshed = Instant::now();
+
+        let has_words = self.words().next().is_some();
+        let has_cjk = self.captions.iter().any(|caption| caption.cjk_chars > 0);
+        let mut parts = vec![];
+        if has_words || !has_cjk {
+            parts.push(format!("{:.0} wpm", self.words_per_minute()));
+        }
+        if has_cjk {
+            parts.push(format!("{:.0} chars/min", self.cjk_chars_per_minute()));
+        }
+        if has_words {
+            parts.push(format!("{:.0}% unique", self.uniqueness() * 100.0));
+        }
+        parts.join(" · ")
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while let Some(caption) = self.captions.front() {
+            if now.duration_since(caption.time) <= self.window {
+                break;
+            }
+            self.captions.pop_front();
+        }
+    }
+
+    /// The span the rates are over: the window, or less if captions started more recently.
+    fn minutes(&self) -> f32 {
+        let span = self
+            .started
+            .map_or(Duration::ZERO, |started| started.elapsed())
+            .min(self.window)
+            .max(MIN_SPAN);
+        span.as_secs_f32() / 60.0
+    }
+
+    fn words(&self) -> impl Iterator<Item = &str> {
+        self.captions
+            .iter()
+            .flat_map(|caption| caption.words.iter().map(String::as_str))
+    }
+}
+
+/// Splits on whitespace into lowercase words, counting CJK characters apart.
+fn words(text: &str) -> (Vec<String>, usize) {
+    let mut words = vec![];
+    let mut cjk_chars = 0;
+
+    for token in text.split_whitespace() {
+        let mut word = String::new();
+        for c in token.chars() {
+            if is_cjk(c) {
+                if c.is_alphanumeric() {
+                    cjk_chars += 1;
+                }
+            } else if c.is_alphanumeric() || c == '\'' {
+                word.extend(c.to_lowercase());
+            }
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+
+    (words, cjk_chars)
+}
@@ -0,0 +1,285 @@
+use std::ops::Range;
+
+pub const SOFT_HYPHEN: char = '\u{00AD}';
+pub const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+const LONG_TOKEN_CHARS: usize = 16;
+const HYPHEN_SPACING: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WrapRules {
+    pub max_chars: usize,
+    pub max_lines: usize,
+}
+
+impl WrapRules {
+    pub fn cues(&self, text: &str) -> Vec<String> {
+        let mut cues = vec![];
+
+        for sentence in split_sentences(text) {
+            let lines = wrap_lines(sentence, self.max_chars);
+            for lines in lines.chunks(self.max_lines.max(1)) {
+                cues.push(lines.join("\n"));
+            }
+        }
+
+        cues
+    }
+}
+
+/// Returns the byte ranges to be replaced with a line break.
+/// Words and capitalized names are kept together; CJK text may break between characters
+/// except where kinsoku rules forbid it.
+pub fn line_breaks(text: &str, max_chars: usize) -> Vec<Range<usize>> {
+    let mut breaks = vec![];
+    if max_chars == 0 {
+        return breaks;
+    }
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut column = 0;
+    let mut candidate: Option<(Range<usize>, usize)> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (pos, c) = chars[i];
+
+        if c == '\n' {
+            column = 0;
+            candidate = None;
+            i += 1;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            let mut j = i;
+            while j < chars.len() && chars[j].1.is_whitespace() && chars[j].1 != '\n' {
+                j += 1;
+            }
+            let end = chars.get(j).map_or(text.len(), |(pos, _)| *pos);
+
+            if column > 0 && !is_name_joint(&text[..pos], &text[end..]) {
+                candidate = Some((pos..end, 0));
+            } else if let Some((_, since)) = &mut candidate {
+                *since += j - i;
+            }
+
+            column += j - i;
+            i = j;
+            continue;
+        }
+
+        if column > 0 && i > 0 && is_cjk_break(chars[i - 1].1, c) {
+            candidate = Some((pos..pos, 0));
+        }
+
+        column += 1;
+        if let Some((_, since)) = &mut candidate {
+            *since += 1;
+        }
+
+        if column > max_chars {
+            if let Some((range, since)) = candidate.take() {
+                breaks.push(range);
+                column = since;
+            }
+        }
+
+        i += 1;
+    }
+
+    breaks
+}
+
+pub fn wrap_lines(text: &str, max_chars: usize) -> Vec<String> {
+    let mut wrapped = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for range in line_breaks(text, max_chars) {
+        wrapped.push_str(&text[last..range.start]);
+        wrapped.push('\n');
+        last = range.end;
+    }
+    wrapped.push_str(&text[last..]);
+
+    wrapped
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let end = i + c.len_utf8();
+        let boundary = match c {
+            '。' | '！' | '？' => true,
+            '.' | '!' | '?' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+
+        if boundary {
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+
+    sentences
+}
+
+/// Returns byte positions where a soft break hint should be inserted into long tokens:
+/// zero-width spaces after URL separators, soft hyphens inside long words.
+pub fn soft_breaks(text: &str) -> Vec<(usize, char)> {
+    let mut hints = vec![];
+
+    for (start, token) in tokens(text) {
+        let chars: Vec<(usize, char)> = token.char_indices().collect();
+        if chars.len() < LONG_TOKEN_CHARS || chars.iter().any(|(_, c)| is_cjk(*c)) {
+            continue;
+        }
+
+        let has_separator = chars.iter().any(|(_, c)| is_url_separator(*c));
+        let mut run = 0;
+        for (i, &(pos, c)) in chars.iter().enumerate() {
+            let remaining = chars.len() - i;
+
+            if i > 0 && is_url_separator(chars[i - 1].1) && !is_url_separator(c) {
+                hints.push((start + pos, ZERO_WIDTH_SPACE));
+            } else if !has_separator && run >= HYPHEN_SPACING && remaining >= 3 {
+                hints.push((start + pos, SOFT_HYPHEN));
+                run = 0;
+            }
+
+            run += 1;
+        }
+    }
+
+    hints
+}
+
+fn is_url_separator(c: char) -> bool {
+    matches!(c, '/' | '.' | '?' | '&' | '=' | '-' | '_')
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(char::is_whitespace)
+        .filter(|token| !token.is_empty())
+        .map(move |token| (token.as_ptr() as usize - text.as_ptr() as usize, token))
+}
+
+/// Whether a word joiner is needed between `prev` and `next` to keep
+/// DirectWrite from breaking the line against kinsoku rules.
+pub fn needs_joiner(prev: char, next: char) -> bool {
+    (is_cjk(prev) || is_cjk(next)) && (is_no_trailing(prev) || is_no_leading(next))
+}
+
+fn is_cjk_break(prev: char, next: char) -> bool {
+    (is_cjk(prev) || is_cjk(next))
+        && !prev.is_whitespace()
+        && !is_no_trailing(prev)
+        && !is_no_leading(next)
+}
+
+// Characters that must not start a line.
+fn is_no_leading(c: char) -> bool {
+    matches!(
+        c,
+        '、' | '。'
+            | '，'
+            | '．'
+            | '・'
+            | '：'
+            | '；'
+            | '？'
+            | '！'
+            | 'ー'
+            | '々'
+            | '〜'
+            | '…'
+            | '‥'
+            | '」'
+            | '』'
+            | '）'
+            | '】'
+            | '〕'
+            | '〉'
+            | '》'
+            | '］'
+            | '｝'
+            | '〟'
+            | 'ぁ'
+            | 'ぃ'
+            | 'ぅ'
+            | 'ぇ'
+            | 'ぉ'
+            | 'っ'
+            | 'ゃ'
+            | 'ゅ'
+            | 'ょ'
+            | 'ゎ'
+            | 'ゕ'
+            | 'ゖ'
+            | 'ァ'
+            | 'ィ'
+            | 'ゥ'
+            | 'ェ'
+            | 'ォ'
+            | 'ッ'
+            | 'ャ'
+            | 'ュ'
+            | 'ョ'
+            | 'ヮ'
+            | 'ヵ'
+            | 'ヶ'
+            | ')'
+            | ']'
+            | '}'
+            | ','
+            | '.'
+            | ':'
+            | ';'
+            | '?'
+            | '!'
+    )
+}
+
+// Characters that must not end a line.
+fn is_no_trailing(c: char) -> bool {
+    matches!(
+        c,
+        '「' | '『' | '（' | '【' | '〔' | '〈' | '《' | '［' | '｛' | '〝' | '(' | '[' | '{'
+    )
+}
+
+fn is_name_joint(before: &str, after: &str) -> bool {
+    let prev = before.split_whitespace().next_back().unwrap_or_default();
+    let next = after.split_whitespace().next().unwrap_or_default();
+
+    starts_uppercase(prev) && starts_uppercase(next) && !prev.ends_with(['.', '!', '?', ','])
+}
+
+fn starts_uppercase(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_uppercase)
+}
+
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF00}'..='\u{FFEF}')
+}
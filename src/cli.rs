@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub data_dir: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    /// Runs without a window, sending captions only to the configured sinks.
+    pub headless: bool,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let mut args = Self::default();
+        let mut iter = std::env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            match name.as_str() {
+                "--data-dir" => args.data_dir = value.or_else(|| iter.next()).map(PathBuf::from),
+                "--config" => args.config = value.or_else(|| iter.next()).map(PathBuf::from),
+                "--headless" => args.headless = true,
+                _ => {}
+            }
+        }
+
+        args
+    }
+}
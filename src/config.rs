@@ -1,9 +1,27 @@
-use std::{fmt::Debug, str::FromStr, time::Duration};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
+use anyhow::{Context as _, Result};
 use ini::{Ini, SectionSetter};
 use windows::Win32::Foundation::RECT;
 
-use crate::gui::utils::Rect as _;
+use crate::{
+    cli::Args,
+    gui::utils::{self, Rect as _},
+    profile::Profile,
+    sink,
+    theme::Theme,
+};
+
+const DEFAULT_CONFIG_PATH: &str = "livesub.ini";
+const SECTION_WINDOW_RECTS: &str = "window-rects";
+const SECTION_MODEL_PREFIX: &str = "model:";
+const SECTION_PROFILE_PREFIX: &str = "profile:";
 
 pub const MODEL_SMALL_EN: &str = "distil-whisper/distil-small.en";
 pub const MODEL_MEDIUM_EN: &str = "distil-whisper/distil-medium.en";
@@ -14,6 +32,8 @@ pub const DELAY_LOW: Duration = Duration::from_millis(100);
 pub const DELAY_MEDIUM: Duration = Duration::from_millis(300);
 pub const DELAY_HIGH: Duration = Duration::from_millis(1000);
 pub const DELAY_HIGHEST: Duration = Duration::from_millis(3000);
+/// Decodes as often as the speech rate calls for, between [`DELAY_LOW`] and [`DELAY_HIGH`].
+pub const DELAY_ADAPTIVE: Duration = Duration::ZERO;
 pub const FONT_NAME_SEGOE_UI: &str = "Segoe UI";
 pub const FONT_NAME_ARIAL: &str = "Arial";
 pub const FONT_NAME_VERDANA: &str = "Verdana";
@@ -25,9 +45,50 @@ pub const FONT_SIZE_SMALL: u32 = 24;
 pub const FONT_SIZE_MEDIUM: u32 = 48;
 pub const FONT_SIZE_LARGE: u32 = 64;
 pub const FONT_SIZE_VERY_LARGE: u32 = 128;
+pub const FONT_SIZE_AUTO: u32 = 0;
+pub const ALIGNMENT_LEFT: &str = "left";
+pub const ALIGNMENT_CENTER: &str = "center";
+pub const ALIGNMENT_RIGHT: &str = "right";
+pub const SILENCE_TRIM_OFF: Duration = Duration::ZERO;
+pub const SILENCE_TRIM_SHORT: Duration = Duration::from_millis(500);
+pub const SILENCE_TRIM_MEDIUM: Duration = Duration::from_millis(1000);
+pub const SILENCE_TRIM_LONG: Duration = Duration::from_millis(2000);
+pub const PRE_ROLL_MAX: Duration = Duration::from_millis(1000);
+pub const VAD_SENSITIVITY_LOW: &str = "low";
+pub const VAD_SENSITIVITY_MEDIUM: &str = "medium";
+pub const VAD_SENSITIVITY_HIGH: &str = "high";
+pub const VAD_SENSITIVITY_CUSTOM: &str = "custom";
+pub const TRANSLITERATION_NONE: &str = "none";
+pub const TRANSLITERATION_ROMAJI: &str = "romaji";
+pub const TRANSLITERATION_KANA: &str = "kana";
+pub const FORMAT_PLAIN: &str = "plain";
+pub const FORMAT_MARKDOWN: &str = "markdown";
+pub const FORMAT_HTML: &str = "html";
+pub const GRANULARITY_TOKEN: &str = "token";
+pub const GRANULARITY_WORD: &str = "word";
+pub const GRANULARITY_PHRASE: &str = "phrase";
+pub const GRANULARITY_SENTENCE: &str = "sentence";
+pub const OVERFLOW_CLIP: &str = "clip";
+pub const OVERFLOW_SHRINK: &str = "shrink";
+pub const OVERFLOW_SCROLL: &str = "scroll";
+pub const OVERFLOW_ELLIPSIS: &str = "ellipsis";
+pub const MAX_LINES_UNLIMITED: u32 = 0;
+pub const LINE_WRAP_WORD: &str = "word";
+pub const LINE_WRAP_WHOLE_WORD: &str = "whole-word";
+pub const LINE_WRAP_CHARACTER: &str = "character";
+pub const MONITOR_DELAY_NONE: Duration = Duration::ZERO;
+pub const MONITOR_DELAY_SHORT: Duration = Duration::from_millis(200);
+pub const MONITOR_DELAY_MEDIUM: Duration = Duration::from_millis(500);
+pub const MONITOR_DELAY_LONG: Duration = Duration::from_millis(1000);
+pub const MONITOR_DELAY_VERY_LONG: Duration = Duration::from_millis(2000);
 
 #[derive(Clone, Debug, Default)]
 pub struct Config {
+    pub path: PathBuf,
+    /// `data-dir` as saved, if set.
+    pub data_dir: Option<PathBuf>,
+    /// `--data-dir`, which applies to this run without being saved.
+    pub data_dir_override: Option<PathBuf>,
     pub model: String,
     pub latency: Duration,
     pub opacity: f32,
@@ -36,51 +97,592 @@ pub struct Config {
     pub bold: bool,
     pub italic: bool,
     pub outline: bool,
+    pub text_color: String,
+    pub outline_color: String,
+    pub background_color: String,
+    pub padding: u32,
+    pub alignment: String,
+    pub emphasis_color: bool,
+    pub emphasis_bold: bool,
+    pub question_color: String,
+    pub exclamation_color: String,
+    pub transliteration: String,
+    pub language: String,
+    pub translate: bool,
+    pub language_filter: String,
+    pub language_filter_ghost: bool,
+    pub simplify: bool,
+    pub auto_casing: bool,
+    pub punctuation: bool,
+    pub punctuation_model: String,
+    pub hotwords: String,
+    pub hotword_biasing: bool,
+    pub replacements: String,
+    pub speech_stats: bool,
+    pub plugins: String,
+    pub script: String,
+    pub silence_trim: Duration,
+    pub minutes_pause: Duration,
+    pub pre_roll: Duration,
+    pub adaptive_vad: bool,
+    pub vad_sensitivity: String,
+    pub vad_threshold: i32,
+    pub vad_hangover: Duration,
+    pub audio_chain: String,
+    pub audio_chain_bypass: bool,
+    pub audio_source: String,
+    pub monitor_device: String,
+    pub monitor_delay: Duration,
+    pub encoder_stride: u32,
+    pub guardrails: bool,
+    pub fullscreen_pause: bool,
+    pub profile_switch: bool,
+    pub fullscreen_process: String,
+    pub live_captions_hide: bool,
+    pub ducking_tag: bool,
+    pub gamepad: bool,
+    pub no_activate: bool,
+    pub show_in_taskbar: bool,
+    pub gamepad_clear: String,
+    pub gamepad_toggle: String,
+    pub midi: bool,
+    pub midi_clear: String,
+    pub midi_pause: String,
+    pub midi_model: String,
+    pub midi_profile: String,
+    pub midi_toggle: String,
+    pub bookmark_hotkey: String,
+    pub pause_hotkey: String,
+    pub clear_hotkey: String,
+    pub click_through_hotkey: String,
+    pub toggle_hotkey: String,
+    pub bookmark_note: bool,
+    pub line_max_chars: u32,
+    pub cue_max_lines: u32,
+    pub hyphenate: bool,
+    pub max_lines: u32,
+    pub line_wrap: String,
+    pub overflow: String,
+    pub granularity: String,
+    pub privacy_mode: bool,
+    pub meeting_mode: bool,
+    pub redact: String,
+    pub redact_patterns: String,
+    pub redact_screen: bool,
+    pub caption_log: String,
+    pub caption_log_enabled: bool,
+    pub caption_log_timestamps: bool,
+    pub caption_log_max_kb: u32,
+    pub caption_log_format: String,
+    pub caption_log_passphrase: String,
+    pub obs_file: String,
+    pub obs_file_lines: u32,
+    pub obs_file_line_width: u32,
+    pub obs_file_format: String,
+    pub subtitle_export: String,
+    pub subtitle_max_duration: Duration,
+    pub webhook_url: String,
+    pub webhook_template: String,
+    pub webhook_content_type: String,
+    pub webhook_format: String,
+    pub watch_folder: String,
+    pub schedule: String,
+    pub soak_log: String,
+    pub snap_distance: u32,
+    pub keep_bottom_margin: bool,
     pub window_rect: RECT,
+    pub window_rects: BTreeMap<String, RECT>,
+    pub model_options: BTreeMap<String, BTreeMap<String, String>>,
+    pub profiles: BTreeMap<String, BTreeMap<String, String>>,
 }
 
 impl Config {
-    pub fn load() -> Self {
-        let conf = Ini::load_from_file("livesub.ini").unwrap_or_default();
+    pub fn load(args: &Args) -> Self {
+        let path = args
+            .config
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CONFIG_PATH.into());
+        let conf = Ini::load_from_file(&path).unwrap_or_default();
+
+        let window_rects: BTreeMap<String, RECT> = conf
+            .section(Some(SECTION_WINDOW_RECTS))
+            .map(|section| {
+                section
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.to_string(), parse_rect(value)?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let model_options = prefixed_sections(&conf, SECTION_MODEL_PREFIX);
+        let profiles = prefixed_sections(&conf, SECTION_PROFILE_PREFIX);
+        let window_rect = window_rects
+            .get(&utils::monitor_topology())
+            .copied()
+            .unwrap_or_else(|| {
+                RECT::new(
+                    conf.get_i32("window-x", 100),
+                    conf.get_i32("window-y", 100),
+                    conf.get_i32("window-width", 400),
+                    conf.get_i32("window-height", 200),
+                )
+            });
+        let theme = Theme::read(&conf);
+
         Self {
+            path,
+            data_dir: Some(conf.get_str("data-dir", ""))
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            data_dir_override: args.data_dir.clone(),
             model: conf.get_str("model", MODEL_SMALL_EN),
             latency: Duration::from_millis(conf.get_u32("latency", DELAY_LOW.as_millis() as _) as _),
-            opacity: conf.get_u32("opacity", 75) as f32 / 100.0,
-            font_name: conf.get_str("font-name", FONT_NAME_SEGOE_UI),
-            font_size: conf.get_u32("font-size", FONT_SIZE_SMALL),
-            bold: conf.get_bool("font-style-bold", false),
-            italic: conf.get_bool("font-style-italic", false),
-            outline: conf.get_bool("font-style-outline", false),
-            window_rect: RECT::new(
-                conf.get_i32("window-x", 100),
-                conf.get_i32("window-y", 100),
-                conf.get_i32("window-width", 400),
-                conf.get_i32("window-height", 200),
+            opacity: theme.opacity,
+            font_name: theme.font_name,
+            font_size: theme.font_size,
+            bold: theme.bold,
+            italic: theme.italic,
+            outline: theme.outline,
+            text_color: theme.text_color,
+            outline_color: theme.outline_color,
+            background_color: theme.background_color,
+            padding: theme.padding,
+            alignment: theme.alignment,
+            emphasis_color: conf.get_bool("emphasis-color", false),
+            emphasis_bold: conf.get_bool("emphasis-bold", false),
+            question_color: conf.get_str("question-color", "#80c8ff"),
+            exclamation_color: conf.get_str("exclamation-color", "#ffb060"),
+            transliteration: conf.get_str("transliteration", TRANSLITERATION_NONE),
+            language: conf.get_str("language", ""),
+            translate: conf.get_bool("translate", false),
+            language_filter: conf.get_str("language-filter", ""),
+            language_filter_ghost: conf.get_bool("language-filter-ghost", false),
+            simplify: conf.get_bool("simplify", false),
+            auto_casing: conf.get_bool("auto-casing", false),
+            punctuation: conf.get_bool("punctuation", false),
+            punctuation_model: conf.get_str("punctuation-model", "models/punctuation"),
+            hotwords: conf.get_str("hotwords", ""),
+            hotword_biasing: conf.get_bool("hotword-biasing", false),
+            replacements: conf.get_str("replacements", ""),
+            speech_stats: conf.get_bool("speech-stats", false),
+            plugins: conf.get_str("plugins", ""),
+            script: conf.get_str("script", ""),
+            silence_trim: Duration::from_millis(conf.get_u32("silence-trim", 0) as _),
+            minutes_pause: Duration::from_millis(conf.get_u32("minutes-pause", 10000) as _),
+            adaptive_vad: conf.get_bool("adaptive-vad", false),
+            vad_sensitivity: conf.get_str("vad-sensitivity", VAD_SENSITIVITY_MEDIUM),
+            vad_threshold: conf.get_i32("vad-threshold", -45),
+            vad_hangover: Duration::from_millis(conf.get_u32("vad-hangover", 600) as _),
+            audio_chain: conf.get_str("audio-chain", ""),
+            audio_chain_bypass: conf.get_bool("audio-chain-bypass", false),
+            pre_roll: Duration::from_millis(conf.get_u32("pre-roll", 300) as _).min(PRE_ROLL_MAX),
+            audio_source: conf.get_str("audio-source", ""),
+            monitor_device: conf.get_str("monitor-device", ""),
+            monitor_delay: Duration::from_millis(conf.get_u32("monitor-delay", 0) as _),
+            encoder_stride: conf.get_u32("encoder-stride", 0),
+            guardrails: conf.get_bool("guardrails", false),
+            fullscreen_pause: conf.get_bool("fullscreen-pause", false),
+            profile_switch: conf.get_bool("profile-switch", true),
+            fullscreen_process: conf.get_str("fullscreen-process", ""),
+            live_captions_hide: conf.get_bool("live-captions-hide", false),
+            ducking_tag: conf.get_bool("ducking-tag", false),
+            gamepad: conf.get_bool("gamepad", false),
+            no_activate: conf.get_bool("no-activate", false),
+            show_in_taskbar: conf.get_bool("show-in-taskbar", true),
+            gamepad_clear: conf.get_str("gamepad-clear", "back+rb"),
+            gamepad_toggle: conf.get_str("gamepad-toggle", "back+lb"),
+            midi: conf.get_bool("midi", false),
+            midi_clear: conf.get_str("midi-clear", "note 36"),
+            midi_pause: conf.get_str("midi-pause", "note 37"),
+            midi_model: conf.get_str("midi-model", "note 38"),
+            midi_profile: conf.get_str("midi-profile", "note 39"),
+            midi_toggle: conf.get_str("midi-toggle", "note 40"),
+            bookmark_hotkey: conf.get_str("bookmark-hotkey", "Ctrl+Alt+B"),
+            pause_hotkey: conf.get_str("pause-hotkey", ""),
+            clear_hotkey: conf.get_str("clear-hotkey", ""),
+            click_through_hotkey: conf.get_str("click-through-hotkey", ""),
+            toggle_hotkey: conf.get_str("toggle-hotkey", ""),
+            bookmark_note: conf.get_bool("bookmark-note", true),
+            line_max_chars: conf.get_u32("line-max-chars", 0),
+            cue_max_lines: conf.get_u32("cue-max-lines", 2),
+            hyphenate: conf.get_bool("hyphenate", false),
+            max_lines: conf.get_u32("max-lines", MAX_LINES_UNLIMITED),
+            line_wrap: conf.get_str("line-wrap", LINE_WRAP_WORD),
+            overflow: conf.get_str("overflow", OVERFLOW_CLIP),
+            granularity: conf.get_str("granularity", GRANULARITY_TOKEN),
+            privacy_mode: conf.get_bool("privacy-mode", false),
+            meeting_mode: conf.get_bool("meeting-mode", false),
+            redact: conf.get_str("redact", ""),
+            redact_patterns: conf.get_str("redact-patterns", ""),
+            redact_screen: conf.get_bool("redact-screen", false),
+            caption_log: conf.get_str("caption-log", ""),
+            caption_log_enabled: conf.get_bool("caption-log-enabled", true),
+            caption_log_timestamps: conf.get_bool("caption-log-timestamps", false),
+            caption_log_max_kb: conf.get_u32("caption-log-max-kb", 1024),
+            caption_log_format: conf.get_str("caption-log-format", FORMAT_PLAIN),
+            caption_log_passphrase: read_passphrase(&conf),
+            obs_file: conf.get_str("obs-file", ""),
+            obs_file_lines: conf.get_u32("obs-file-lines", 2),
+            obs_file_line_width: conf.get_u32("obs-file-line-width", 42),
+            obs_file_format: conf.get_str("obs-file-format", FORMAT_PLAIN),
+            subtitle_export: conf.get_str("subtitle-export", ""),
+            subtitle_max_duration: Duration::from_millis(
+                conf.get_u32("subtitle-max-duration", 7000) as _,
             ),
+            webhook_url: conf.get_str("webhook-url", ""),
+            webhook_template: conf.get_str("webhook-template", ""),
+            webhook_content_type: conf.get_str("webhook-content-type", ""),
+            webhook_format: conf.get_str("webhook-format", FORMAT_PLAIN),
+            watch_folder: conf.get_str("watch-folder", ""),
+            schedule: conf.get_str("schedule", ""),
+            soak_log: conf.get_str("soak-log", ""),
+            snap_distance: conf.get_u32("snap-distance", 16),
+            keep_bottom_margin: conf.get_bool("keep-bottom-margin", false),
+            window_rect: utils::clamp_to_work_area(window_rect),
+            window_rects,
+            model_options,
+            profiles,
         }
     }
 
     pub fn save(&self) {
         let mut conf = Ini::new();
         conf.with_general_section()
+            .set(
+                "data-dir",
+                self.data_dir
+                    .as_deref()
+                    .unwrap_or(Path::new(""))
+                    .to_string_lossy(),
+            )
             .set("model", &self.model)
             .set_u32("latency", self.latency.as_millis() as u32)
-            .set_u32("opacity", (100.0 * self.opacity) as _)
-            .set("font-name", &self.font_name)
-            .set_u32("font-size", self.font_size)
-            .set_bool("font-style-bold", self.bold)
-            .set_bool("font-style-italic", self.italic)
-            .set_bool("font-style-outline", self.outline)
+            .set_bool("emphasis-color", self.emphasis_color)
+            .set_bool("emphasis-bold", self.emphasis_bold)
+            .set("question-color", &self.question_color)
+            .set("exclamation-color", &self.exclamation_color)
+            .set("transliteration", &self.transliteration)
+            .set("language", &self.language)
+            .set_bool("translate", self.translate)
+            .set("language-filter", &self.language_filter)
+            .set_bool("language-filter-ghost", self.language_filter_ghost)
+            .set_bool("simplify", self.simplify)
+            .set_bool("auto-casing", self.auto_casing)
+            .set_bool("punctuation", self.punctuation)
+            .set("punctuation-model", &self.punctuation_model)
+            .set("hotwords", &self.hotwords)
+            .set_bool("hotword-biasing", self.hotword_biasing)
+            .set("replacements", &self.replacements)
+            .set_bool("speech-stats", self.speech_stats)
+            .set("plugins", &self.plugins)
+            .set("script", &self.script)
+            .set_u32("silence-trim", self.silence_trim.as_millis() as u32)
+            .set_u32("minutes-pause", self.minutes_pause.as_millis() as u32)
+            .set_u32("pre-roll", self.pre_roll.as_millis() as u32)
+            .set_bool("adaptive-vad", self.adaptive_vad)
+            .set("vad-sensitivity", &self.vad_sensitivity)
+            .set_i32("vad-threshold", self.vad_threshold)
+            .set_u32("vad-hangover", self.vad_hangover.as_millis() as u32)
+            .set("audio-chain", &self.audio_chain)
+            .set_bool("audio-chain-bypass", self.audio_chain_bypass)
+            .set("audio-source", &self.audio_source)
+            .set("monitor-device", &self.monitor_device)
+            .set_u32("monitor-delay", self.monitor_delay.as_millis() as u32)
+            .set_u32("encoder-stride", self.encoder_stride)
+            .set_bool("guardrails", self.guardrails)
+            .set_bool("fullscreen-pause", self.fullscreen_pause)
+            .set_bool("profile-switch", self.profile_switch)
+            .set("fullscreen-process", &self.fullscreen_process)
+            .set_bool("live-captions-hide", self.live_captions_hide)
+            .set_bool("ducking-tag", self.ducking_tag)
+            .set_bool("gamepad", self.gamepad)
+            .set_bool("no-activate", self.no_activate)
+            .set_bool("show-in-taskbar", self.show_in_taskbar)
+            .set("gamepad-clear", &self.gamepad_clear)
+            .set("gamepad-toggle", &self.gamepad_toggle)
+            .set_bool("midi", self.midi)
+            .set("midi-clear", &self.midi_clear)
+            .set("midi-pause", &self.midi_pause)
+            .set("midi-model", &self.midi_model)
+            .set("midi-profile", &self.midi_profile)
+            .set("midi-toggle", &self.midi_toggle)
+            .set("bookmark-hotkey", &self.bookmark_hotkey)
+            .set("pause-hotkey", &self.pause_hotkey)
+            .set("clear-hotkey", &self.clear_hotkey)
+            .set("click-through-hotkey", &self.click_through_hotkey)
+            .set("toggle-hotkey", &self.toggle_hotkey)
+            .set_bool("bookmark-note", self.bookmark_note)
+            .set_u32("line-max-chars", self.line_max_chars)
+            .set_u32("cue-max-lines", self.cue_max_lines)
+            .set_bool("hyphenate", self.hyphenate)
+            .set_u32("max-lines", self.max_lines)
+            .set("line-wrap", &self.line_wrap)
+            .set("overflow", &self.overflow)
+            .set("granularity", &self.granularity)
+            .set_bool("privacy-mode", self.privacy_mode)
+            .set_bool("meeting-mode", self.meeting_mode)
+            .set("redact", &self.redact)
+            .set("redact-patterns", &self.redact_patterns)
+            .set_bool("redact-screen", self.redact_screen)
+            .set("caption-log", &self.caption_log)
+            .set_bool("caption-log-enabled", self.caption_log_enabled)
+            .set_bool("caption-log-timestamps", self.caption_log_timestamps)
+            .set_u32("caption-log-max-kb", self.caption_log_max_kb)
+            .set("caption-log-format", &self.caption_log_format)
+            .set(
+                "caption-log-passphrase-protected",
+                protect_passphrase(&self.caption_log_passphrase),
+            )
+            .set("obs-file", &self.obs_file)
+            .set_u32("obs-file-lines", self.obs_file_lines)
+            .set_u32("obs-file-line-width", self.obs_file_line_width)
+            .set("obs-file-format", &self.obs_file_format)
+            .set("subtitle-export", &self.subtitle_export)
+            .set_u32(
+                "subtitle-max-duration",
+                self.subtitle_max_duration.as_millis() as _,
+            )
+            .set("webhook-url", &self.webhook_url)
+            .set("webhook-template", &self.webhook_template)
+            .set("webhook-content-type", &self.webhook_content_type)
+            .set("webhook-format", &self.webhook_format)
+            .set("watch-folder", &self.watch_folder)
+            .set("schedule", &self.schedule)
+            .set("soak-log", &self.soak_log)
+            .set_u32("snap-distance", self.snap_distance)
+            .set_bool("keep-bottom-margin", self.keep_bottom_margin)
             .set_i32("window-x", self.window_rect.x())
             .set_i32("window-y", self.window_rect.y())
             .set_i32("window-width", self.window_rect.width())
             .set_i32("window-height", self.window_rect.height());
+        Theme::from_config(self).write(&mut conf);
+
+        for (topology, rect) in &self.window_rects {
+            conf.with_section(Some(SECTION_WINDOW_RECTS)).set(
+                topology,
+                format!(
+                    "{},{},{},{}",
+                    rect.x(),
+                    rect.y(),
+                    rect.width(),
+                    rect.height()
+                ),
+            );
+        }
+
+        for (model, options) in &self.model_options {
+            let section = format!("{SECTION_MODEL_PREFIX}{model}");
+            for (key, value) in options {
+                conf.with_section(Some(section.as_str())).set(key, value);
+            }
+        }
+        for (profile, options) in &self.profiles {
+            let section = format!("{SECTION_PROFILE_PREFIX}{profile}");
+            for (key, value) in options {
+                conf.with_section(Some(section.as_str())).set(key, value);
+            }
+        }
+
+        _ = conf.write_to_file(&self.path);
+    }
+
+    /// Name used to allow one instance per config file. Paths are hashed, as configs in
+    /// different folders can share a file name and a mutex name can't hold backslashes.
+    pub fn instance_name(&self) -> String {
+        let path = std::path::absolute(&self.path).unwrap_or_else(|_| self.path.clone());
+        // FNV-1a, which unlike the std hasher is the same in every build.
+        let hash = path
+            .to_string_lossy()
+            .to_lowercase()
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, b| {
+                (hash ^ b as u64).wrapping_mul(0x100000001b3)
+            });
+        format!("livesub-{hash:016x}")
+    }
+
+    pub fn plugin_paths(&self) -> Vec<PathBuf> {
+        self.plugins
+            .split(';')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(|path| self.data_path(path))
+            .collect()
+    }
+
+    /// Terms from the hotwords file, one per line; empty if there is none. A file that
+    /// can't be read is an error, like the redaction patterns, so biasing isn't silently off.
+    pub fn hotwords(&self) -> Result<Vec<String>> {
+        self.read_list(&self.hotwords)
+    }
+
+    /// Built-in redaction rules, such as "card, email".
+    pub fn redact_rules(&self) -> Vec<String> {
+        self.redact
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    /// Regular expressions from the redaction patterns file, one per line. Unlike the
+    /// other lists, a file that can't be read is an error, so redaction isn't silently off.
+    pub fn redact_patterns(&self) -> Result<Vec<String>> {
+        self.read_list(&self.redact_patterns)
+    }
+
+    /// Rules from the replacements file, one per line. A file that can't be read is an
+    /// error, like the redaction patterns, so a mistyped path doesn't turn the filter off.
+    pub fn replacement_rules(&self) -> Result<Vec<String>> {
+        self.read_list(&self.replacements)
+    }
+
+    /// Lines of a file under the data folder, skipping blank lines and `#` comments.
+    fn read_list(&self, path: &str) -> Result<Vec<String>> {
+        if path.trim().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let path = self.data_path(path.trim());
+        let list = std::fs::read_to_string(&path)
+            .with_context(|| format!("can't read {}", path.display()))?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+
+        Ok(list)
+    }
+
+    /// Language codes captions are kept to, such as "en, ja"; empty keeps all.
+    pub fn filter_languages(&self) -> Vec<String> {
+        self.language_filter
+            .split(',')
+            .map(|language| language.trim().to_ascii_lowercase())
+            .filter(|language| !language.is_empty())
+            .collect()
+    }
+
+    /// Options of the current model, from its `[model:<repo id>]` section.
+    pub fn model_options(&self) -> BTreeMap<String, String> {
+        self.model_options
+            .get(&self.model)
+            .cloned()
+            .unwrap_or_default()
+    }
 
-        _ = conf.write_to_file("livesub.ini");
+    pub fn model_option(&self, key: &str) -> Option<&str> {
+        self.model_options
+            .get(&self.model)
+            .and_then(|options| options.get(key))
+            .map(String::as_str)
     }
+
+    pub fn set_model_option(&mut self, key: &str, value: &str) {
+        self.model_options
+            .entry(self.model.clone())
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+    }
+
+    /// Profiles from the `[profile:<name>]` sections.
+    pub fn profiles(&self) -> Vec<Profile> {
+        self.profiles
+            .iter()
+            .map(|(name, options)| Profile::parse(name, options))
+            .collect()
+    }
+
+    pub fn set_window_rect(&mut self, rect: RECT) {
+        self.window_rect = rect;
+        self.window_rects.insert(utils::monitor_topology(), rect);
+    }
+
+    fn data_dir(&self) -> Option<&Path> {
+        self.data_dir_override
+            .as_deref()
+            .or(self.data_dir.as_deref())
+    }
+
+    pub fn data_path(&self, name: &str) -> PathBuf {
+        self.data_dir().unwrap_or(Path::new(".")).join(name)
+    }
+
+    /// Where models are downloaded: `models` in the data directory if one is set, or the
+    /// Hugging Face cache shared with other apps otherwise.
+    pub fn model_dir(&self) -> PathBuf {
+        match self.data_dir() {
+            Some(dir) => dir.join("models"),
+            None => hf_hub::Cache::default().path().clone(),
+        }
+    }
+}
+
+/// Sections named `<prefix><name>`, by name.
+fn prefixed_sections(conf: &Ini, prefix: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    conf.iter()
+        .filter_map(|(section, props)| {
+            let name = section?.strip_prefix(prefix)?;
+            let options = props
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            Some((name.to_string(), options))
+        })
+        .collect()
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`.
+pub fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let hex = value.trim().strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut color = [1.0; 4];
+    for (i, level) in color.iter_mut().take(hex.len() / 2).enumerate() {
+        *level = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()? as f32 / 255.0;
+    }
+    Some(color)
+}
+
+/// The caption log passphrase, kept encrypted for the Windows user so it isn't lying
+/// next to the log in plain text. One typed in as `caption-log-passphrase` is taken as is
+/// and saved encrypted.
+fn read_passphrase(conf: &Ini) -> String {
+    let plain = conf.get_str("caption-log-passphrase", "");
+    if !plain.is_empty() {
+        return plain;
+    }
+
+    let protected = conf.get_str("caption-log-passphrase-protected", "");
+    let bytes: Option<Vec<u8>> = (0..protected.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(protected.get(i..i + 2)?, 16).ok())
+        .collect();
+    bytes
+        .and_then(|bytes| sink::unprotect_secret(&bytes).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or_default()
+}
+
+fn protect_passphrase(passphrase: &str) -> String {
+    if passphrase.is_empty() {
+        return String::new();
+    }
+
+    sink::protect_secret(passphrase.as_bytes())
+        .map(|bytes| bytes.iter().map(|b| format!("{b:02x}")).collect())
+        .unwrap_or_default()
+}
+
+fn parse_rect(value: &str) -> Option<RECT> {
+    let mut values = value.split(',').map(|v| i32::from_str(v.trim()).ok());
+    let mut next = || values.next().flatten();
+    Some(RECT::new(next()?, next()?, next()?, next()?))
 }
 
-trait IniSetter<'a> {
+pub(crate) trait IniSetter<'a> {
     fn set_bool(&'a mut self, key: &str, value: bool) -> &'a mut SectionSetter<'a>;
     fn set_i32(&'a mut self, key: &str, value: i32) -> &'a mut SectionSetter<'a>;
     fn set_u32(&'a mut self, key: &str, value: u32) -> &'a mut SectionSetter<'a>;
@@ -100,7 +702,7 @@ impl<'a> IniSetter<'a> for SectionSetter<'a> {
     }
 }
 
-trait IniGetter {
+pub(crate) trait IniGetter {
     fn get_bool(&self, key: &str, default: bool) -> bool;
     fn get_i32(&self, key: &str, default: i32) -> i32;
     fn get_u32(&self, key: &str, default: u32) -> u32;
@@ -5,10 +5,13 @@ use windows::{
         Foundation::{BOOL, FALSE, HWND},
         Graphics::{
             Direct2D::{
-                Common::{D2D1_COLOR_F, D2D_RECT_F},
+                Common::{
+                    D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F, D2D1_PIXEL_FORMAT, D2D_RECT_F,
+                },
                 D2D1CreateFactory, ID2D1DeviceContext, ID2D1Factory2, ID2D1SolidColorBrush,
-                D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_DEVICE_CONTEXT_OPTIONS_NONE,
-                D2D1_FACTORY_TYPE_SINGLE_THREADED,
+                D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+                D2D1_BITMAP_OPTIONS_TARGET, D2D1_BITMAP_PROPERTIES1,
+                D2D1_DEVICE_CONTEXT_OPTIONS_NONE, D2D1_FACTORY_TYPE_SINGLE_THREADED,
             },
             Direct3D::D3D_DRIVER_TYPE_HARDWARE,
             Direct3D11::{
@@ -37,7 +40,12 @@ use windows::{
         },
         System::WinRT::Composition::{ICompositorDesktopInterop, ICompositorInterop},
     },
-    UI::Composition::{CompositionStretch, Compositor, Desktop::DesktopWindowTarget},
+    UI::{
+        Color,
+        Composition::{
+            CompositionColorBrush, CompositionStretch, Compositor, Desktop::DesktopWindowTarget,
+        },
+    },
 };
 use windows_core::{implement, w, IUnknown, Interface as _, PCWSTR};
 
@@ -48,6 +56,8 @@ pub struct Context {
     pub context: ID2D1DeviceContext,
     pub dw_factory: IDWriteFactory,
     pub renderer: TextRenderer,
+    dpi: u32,
+    background: CompositionColorBrush,
     _compositor: Compositor,
     _window_targets: Vec<DesktopWindowTarget>,
 }
@@ -100,12 +110,20 @@ impl Context {
                 factory.CreateSwapChainForComposition(&device, &desc, None)?
             };
 
-            let (_compositor, _window_targets) = {
+            let (_compositor, _window_targets, background) = {
                 let compositor = Compositor::new()?;
                 let window_target = {
                     let interop: ICompositorDesktopInterop = compositor.cast()?;
                     interop.CreateDesktopWindowTarget(hwnd, true)?
                 };
+                let root = compositor.CreateContainerVisual()?;
+                root.SetRelativeSizeAdjustment(Vector2::one())?;
+
+                let background = compositor.CreateColorBrushWithColor(Color::default())?;
+                let backdrop = compositor.CreateSpriteVisual()?;
+                backdrop.SetRelativeSizeAdjustment(Vector2::one())?;
+                backdrop.SetBrush(&background)?;
+
                 let content = compositor.CreateSpriteVisual()?;
                 let surface = {
                     let interop: ICompositorInterop = compositor.cast()?;
@@ -116,31 +134,31 @@ impl Context {
 
                 content.SetRelativeSizeAdjustment(Vector2::one())?;
                 content.SetBrush(&brush)?;
-                window_target.SetRoot(&content)?;
 
-                (compositor, vec![window_target])
+                root.Children()?.InsertAtTop(&backdrop)?;
+                root.Children()?.InsertAtTop(&content)?;
+                window_target.SetRoot(&root)?;
+
+                (compositor, vec![window_target], background)
             };
 
             let d2d_factory: ID2D1Factory2 =
                 D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None)?;
 
+            let dpi = hwnd.dpi();
+
             let context = {
                 let device = d2d_factory.CreateDevice(&device)?;
                 let context: ID2D1DeviceContext = device
                     .CreateDeviceContext(D2D1_DEVICE_CONTEXT_OPTIONS_NONE)?
                     .cast()?;
 
-                let dpi = hwnd.dpi();
                 context.SetDpi(dpi as _, dpi as _);
 
                 context
             };
 
-            let target = {
-                let surface: IDXGISurface2 = swap_chain.GetBuffer(0)?;
-                context.CreateBitmapFromDxgiSurface(&surface, None)?
-            };
-            context.SetTarget(&target);
+            set_target(&context, &swap_chain, dpi)?;
 
             let dw_factory = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)?;
             let renderer = TextRenderer::new(d2d_factory, context.clone())?;
@@ -150,6 +168,8 @@ impl Context {
                 context,
                 dw_factory,
                 renderer,
+                dpi,
+                background,
                 _compositor,
                 _window_targets,
             })
@@ -180,6 +200,19 @@ impl Context {
         }
     }
 
+    pub fn set_background(&self, color: &[f32; 4]) -> Result<()> {
+        let level = |v: f32| (255.0 * v.clamp(0.0, 1.0)) as u8;
+
+        self.background
+            .SetColor(Color {
+                R: level(color[0]),
+                G: level(color[1]),
+                B: level(color[2]),
+                A: level(color[3]),
+            })
+            .map_err(E::msg)
+    }
+
     pub fn draw_text(&self, layout: &IDWriteTextLayout, x: f32, y: f32) -> Result<()> {
         unsafe {
             let context = Some(self.context.as_raw() as *const _);
@@ -207,6 +240,12 @@ impl Context {
         self.renderer.enable_outline(outline);
     }
 
+    pub fn set_text_colors(&mut self, fill: &[f32; 4], outline: &[f32; 4]) -> Result<()> {
+        self.renderer.fill_brush = self.create_solid_brush(fill)?;
+        self.renderer.outline_brush = self.create_solid_brush(outline)?;
+        Ok(())
+    }
+
     pub fn set_size(&mut self, width: u32, height: u32) -> Result<()> {
         unsafe {
             self.context.SetTarget(None);
@@ -218,14 +257,9 @@ impl Context {
                 DXGI_FORMAT_UNKNOWN,
                 DXGI_SWAP_CHAIN_FLAG(0),
             )?;
-
-            let target = {
-                let surface: IDXGISurface2 = self.swap_chain.GetBuffer(0)?;
-                self.context.CreateBitmapFromDxgiSurface(&surface, None)?
-            };
-            self.context.SetTarget(&target);
         }
-        Ok(())
+
+        set_target(&self.context, &self.swap_chain, self.dpi)
     }
 
     pub fn dpi(&self) -> f32 {
@@ -237,10 +271,31 @@ impl Context {
         }
     }
 
-    pub fn set_dpi(&mut self, dpi: u32) {
+    pub fn set_dpi(&mut self, dpi: u32) -> Result<()> {
+        self.dpi = dpi;
+
         unsafe {
+            self.context.SetTarget(None);
             self.context.SetDpi(dpi as _, dpi as _);
         }
+
+        set_target(&self.context, &self.swap_chain, dpi)
+    }
+
+    pub fn create_solid_brush(&self, color: &[f32; 4]) -> Result<ID2D1SolidColorBrush> {
+        unsafe {
+            self.context
+                .CreateSolidColorBrush(
+                    &D2D1_COLOR_F {
+                        r: color[0],
+                        g: color[1],
+                        b: color[2],
+                        a: color[3],
+                    },
+                    None,
+                )
+                .map_err(anyhow::Error::msg)
+        }
     }
 
     pub fn create_text_format(
@@ -294,6 +349,25 @@ impl Context {
     }
 }
 
+fn set_target(context: &ID2D1DeviceContext, swap_chain: &IDXGISwapChain1, dpi: u32) -> Result<()> {
+    unsafe {
+        let surface: IDXGISurface2 = swap_chain.GetBuffer(0)?;
+        let properties = D2D1_BITMAP_PROPERTIES1 {
+            pixelFormat: D2D1_PIXEL_FORMAT {
+                format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+            },
+            dpiX: dpi as _,
+            dpiY: dpi as _,
+            bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+            ..Default::default()
+        };
+        let target = context.CreateBitmapFromDxgiSurface(&surface, Some(&properties))?;
+        context.SetTarget(&target);
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 #[implement(IDWriteTextRenderer)]
 pub struct TextRenderer {
@@ -354,7 +428,7 @@ impl IDWriteTextRenderer_Impl for TextRenderer_Impl {
         _measuringmode: DWRITE_MEASURING_MODE,
         glyphrun: *const DWRITE_GLYPH_RUN,
         _glyphrundescription: *const DWRITE_GLYPH_RUN_DESCRIPTION,
-        _clientdrawingeffect: ::core::option::Option<&IUnknown>,
+        clientdrawingeffect: ::core::option::Option<&IUnknown>,
     ) -> ::windows::core::Result<()> {
         unsafe {
             let geometry = self.factory.CreatePathGeometry()?;
@@ -383,7 +457,13 @@ impl IDWriteTextRenderer_Impl for TextRenderer_Impl {
                     .DrawGeometry(&geometory, &self.outline_brush, 4.0, None);
             }
 
-            self.dc.FillGeometry(&geometory, &self.fill_brush, None);
+            let fill_brush =
+                clientdrawingeffect.and_then(|effect| effect.cast::<ID2D1SolidColorBrush>().ok());
+            self.dc.FillGeometry(
+                &geometory,
+                fill_brush.as_ref().unwrap_or(&self.fill_brush),
+                None,
+            );
         }
         Ok(())
     }
@@ -1,20 +1,71 @@
+use std::time::{Duration, Instant};
+
 use anyhow::Result;
 use windows::Win32::{
     Foundation::HWND,
     Graphics::{
         Direct2D::Common::D2D_RECT_F,
         DirectWrite::{
-            IDWriteTextFormat, IDWriteTextLayout, DWRITE_LINE_METRICS, DWRITE_TEXT_METRICS,
+            IDWriteTextFormat, IDWriteTextLayout, DWRITE_FONT_WEIGHT_BOLD, DWRITE_LINE_METRICS,
+            DWRITE_TEXT_ALIGNMENT, DWRITE_TEXT_ALIGNMENT_CENTER, DWRITE_TEXT_ALIGNMENT_LEADING,
+            DWRITE_TEXT_ALIGNMENT_TRAILING, DWRITE_TEXT_METRICS, DWRITE_TEXT_RANGE,
+            DWRITE_WORD_WRAPPING, DWRITE_WORD_WRAPPING_CHARACTER, DWRITE_WORD_WRAPPING_WHOLE_WORD,
+            DWRITE_WORD_WRAPPING_WRAP,
         },
     },
 };
 
-use crate::gui::utils::CStr;
+use crate::{
+    caption::{
+        emphasis::sentence_moods,
+        wrap::{line_breaks, needs_joiner, soft_breaks},
+        CaptionSegment, EmphasisStyle, SegmentRole,
+    },
+    config::{
+        ALIGNMENT_CENTER, ALIGNMENT_RIGHT, FONT_SIZE_AUTO, FONT_SIZE_SMALL, LINE_WRAP_CHARACTER,
+        LINE_WRAP_WHOLE_WORD, OVERFLOW_ELLIPSIS, OVERFLOW_SCROLL, OVERFLOW_SHRINK,
+    },
+};
 
 use super::context::Context;
 
+const WORD_JOINER: u16 = 0x2060;
+const ELLIPSIS: u16 = 0x2026;
+const MIN_SHRINK_FONT_SIZE: f32 = 8.0;
+const SCROLL_STEP: f32 = 0.25;
+const AUTO_FONT_LINES: u32 = 2;
+const AUTO_FONT_PROBE_SIZE: u32 = 100;
+const GRAB_HANDLE: &str = "\u{283F}";
+const GRAB_HANDLE_FONT: &str = "Segoe UI Symbol";
+const GRAB_HANDLE_SIZE: u32 = 16;
+const STATS_FONT: &str = "Segoe UI";
+const STATS_SIZE: u32 = 12;
+// One more dot of the listening indicator per step, up to three.
+const LISTENING_STEP: Duration = Duration::from_millis(400);
+
+const TENTATIVE_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const STATUS_COLOR: [f32; 4] = [1.0, 0.85, 0.4, 1.0];
+const LISTENING_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 0.5];
+const SPEAKER_COLORS: [[f32; 4]; 4] = [
+    [1.0, 1.0, 0.4, 1.0],
+    [0.4, 1.0, 1.0, 1.0],
+    [0.5, 1.0, 0.5, 1.0],
+    [1.0, 0.6, 1.0, 1.0],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Overflow {
+    Clip,
+    Shrink,
+    Scroll,
+    Ellipsis,
+}
+
 pub struct Renderer {
     text: Vec<u16>,
+    colors: Vec<(DWRITE_TEXT_RANGE, [f32; 4])>,
+    bolds: Vec<DWRITE_TEXT_RANGE>,
+    emphasis: EmphasisStyle,
     context: Context,
     format: Option<IDWriteTextFormat>,
     layout: Option<IDWriteTextLayout>,
@@ -23,7 +74,27 @@ pub struct Renderer {
     font_style_bold: bool,
     font_style_italic: bool,
     font_style_outline: bool,
-    opacity: f32,
+    background: [f32; 4],
+    padding: f32,
+    alignment: DWRITE_TEXT_ALIGNMENT,
+    line_max_chars: usize,
+    hyphenate: bool,
+    wrapping: DWRITE_WORD_WRAPPING,
+    /// Only the newest lines are laid out, or all if 0.
+    max_lines: usize,
+    overflow: Overflow,
+    scroll_y: f32,
+    grab_handle: Option<IDWriteTextLayout>,
+    stats: Option<IDWriteTextLayout>,
+    progress: Option<IDWriteTextLayout>,
+    badge: Option<IDWriteTextLayout>,
+    segments: Vec<CaptionSegment>,
+    listening: Option<Instant>,
+    listening_dots: usize,
+    size: (u32, u32),
+    /// The window less padding, where the overlays go.
+    bounds: D2D_RECT_F,
+    /// The part of `bounds` left for the captions.
     rect: D2D_RECT_F,
 }
 
@@ -42,59 +113,123 @@ impl Renderer {
         let rect = D2D_RECT_F::new(0.0, 0.0, width as _, height as _).inner(8.0, 8.0);
 
         let context = Context::new(hwnd)?;
-        let format = context.create_text_format(font_name, font_size, bold, italic)?;
+        let background = [0.0, 0.0, 0.0, opacity];
+        context.set_background(&background)?;
 
-        Ok(Self {
+        let mut renderer = Self {
             text: vec![],
+            colors: vec![],
+            bolds: vec![],
+            emphasis: EmphasisStyle::default(),
             context,
-            format: Some(format),
+            format: None,
             layout: None,
             font_name: font_name.into(),
             font_size,
             font_style_bold: bold,
             font_style_italic: italic,
             font_style_outline: outline,
-            opacity,
+            background,
+            padding: 8.0,
+            alignment: DWRITE_TEXT_ALIGNMENT_LEADING,
+            line_max_chars: 0,
+            hyphenate: false,
+            wrapping: DWRITE_WORD_WRAPPING_WRAP,
+            max_lines: 0,
+            overflow: Overflow::Clip,
+            scroll_y: 0.0,
+            grab_handle: None,
+            stats: None,
+            progress: None,
+            badge: None,
+            segments: vec![],
+            listening: None,
+            listening_dots: 0,
+            size: (width, height),
+            bounds: rect,
             rect,
-        })
+        };
+        renderer.setup_text_format();
+
+        Ok(renderer)
     }
 
     pub fn draw(&mut self) -> Result<()> {
         if let Some(layout) = &self.layout {
-            self.context.begin_draw(&[0.0, 0.0, 0.0, self.opacity]);
+            self.context.begin_draw(&[0.0, 0.0, 0.0, 0.0]);
             self.context.enable_outline(self.font_style_outline);
 
             let viewport_height = self.rect.height();
             let layout_height = layout.metrics()?.height;
-            let clip_and_offset = viewport_height < layout_height;
-
-            if clip_and_offset {
-                let mut clip_height = 0.0;
-                for metrics in layout.line_metrics()?.iter().rev() {
-                    if viewport_height < clip_height + metrics.baseline {
-                        break;
-                    }
-                    clip_height += metrics.height;
-                }
+            let overflowed = viewport_height < layout_height;
 
-                let clip_rect = D2D_RECT_F {
-                    left: self.rect.left - 1.0,
-                    top: self.rect.bottom - clip_height + 1.0,
-                    right: self.rect.right + 1.0,
-                    bottom: self.rect.bottom + 1.0,
-                };
-                self.context.clip(&clip_rect);
-            }
-
-            let y = if clip_and_offset {
+            let target_y = if overflowed {
                 self.rect.bottom - layout_height
             } else {
                 self.rect.y()
             };
-            self.context.draw_text(layout, self.rect.x(), y)?;
 
-            if clip_and_offset {
+            if self.overflow == Overflow::Scroll {
+                if self.scroll_y <= target_y || (self.scroll_y - target_y).abs() < 0.5 {
+                    self.scroll_y = target_y;
+                } else {
+                    self.scroll_y += (target_y - self.scroll_y) * SCROLL_STEP;
+                }
+
+                self.context.clip(&self.rect);
+                self.context
+                    .draw_text(layout, self.rect.x(), self.scroll_y)?;
                 self.context.pop_clip();
+            } else {
+                if overflowed {
+                    let mut clip_height = 0.0;
+                    for metrics in layout.line_metrics()?.iter().rev() {
+                        if viewport_height < clip_height + metrics.baseline {
+                            break;
+                        }
+                        clip_height += metrics.height;
+                    }
+
+                    let clip_rect = D2D_RECT_F {
+                        left: self.rect.left - 1.0,
+                        top: self.rect.bottom - clip_height + 1.0,
+                        right: self.rect.right + 1.0,
+                        bottom: self.rect.bottom + 1.0,
+                    };
+                    self.context.clip(&clip_rect);
+                }
+
+                self.context.draw_text(layout, self.rect.x(), target_y)?;
+
+                if overflowed {
+                    self.context.pop_clip();
+                }
+            }
+
+            if let Some(grab_handle) = &self.grab_handle {
+                let width = grab_handle.metrics()?.width;
+                self.context
+                    .draw_text(grab_handle, self.bounds.right - width, self.bounds.y())?;
+            }
+
+            if let Some(stats) = &self.stats {
+                self.context
+                    .draw_text(stats, self.bounds.x(), self.bounds.y())?;
+            }
+
+            if let Some(progress) = &self.progress {
+                let height = progress.metrics()?.height;
+                self.context
+                    .draw_text(progress, self.bounds.x(), self.bounds.bottom - height)?;
+            }
+
+            if let Some(badge) = &self.badge {
+                let metrics = badge.metrics()?;
+                self.context.draw_text(
+                    badge,
+                    self.bounds.right - metrics.width,
+                    self.bounds.bottom - metrics.height,
+                )?;
             }
 
             self.context.end_draw()?;
@@ -102,11 +237,158 @@ impl Renderer {
         Ok(())
     }
 
-    pub fn set_text(&mut self, text: &str) {
-        self.text = text.c_wstr();
+    pub fn set_segments(&mut self, segments: &[CaptionSegment]) {
+        self.segments = segments.to_vec();
+        self.update_text();
+        self.update_layout();
+    }
+
+    /// The captions on screen, without status messages.
+    pub fn caption_text(&self) -> String {
+        self.segments
+            .iter()
+            .filter(|segment| segment.role != SegmentRole::Status)
+            .map(|segment| segment.text.as_str())
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
+    pub fn set_line_max_chars(&mut self, line_max_chars: usize) {
+        self.line_max_chars = line_max_chars;
+        self.update_text();
+        self.update_layout();
+    }
+
+    pub fn set_hyphenate(&mut self, hyphenate: bool) {
+        self.hyphenate = hyphenate;
+        self.update_text();
+        self.update_layout();
+    }
+
+    /// How lines break: within a word only if it doesn't fit on a line by itself,
+    /// between words only, or after any character.
+    pub fn set_line_wrap(&mut self, line_wrap: &str) {
+        self.wrapping = match line_wrap {
+            LINE_WRAP_WHOLE_WORD => DWRITE_WORD_WRAPPING_WHOLE_WORD,
+            LINE_WRAP_CHARACTER => DWRITE_WORD_WRAPPING_CHARACTER,
+            _ => DWRITE_WORD_WRAPPING_WRAP,
+        };
+        self.update_format();
+    }
+
+    /// Shows only the newest `max_lines` lines, like broadcast captions, or all if 0.
+    /// An automatic font size is scaled to fit that many lines.
+    pub fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = max_lines;
+        self.update_size_dependents();
+    }
+
+    pub fn set_overflow(&mut self, overflow: &str) {
+        self.overflow = match overflow {
+            OVERFLOW_SHRINK => Overflow::Shrink,
+            OVERFLOW_SCROLL => Overflow::Scroll,
+            OVERFLOW_ELLIPSIS => Overflow::Ellipsis,
+            _ => Overflow::Clip,
+        };
+        self.update_layout();
+    }
+
+    pub fn set_emphasis(&mut self, emphasis: EmphasisStyle) {
+        self.emphasis = emphasis;
+        self.update_text();
         self.update_layout();
     }
 
+    pub fn set_grab_handle(&mut self, visible: bool) {
+        self.grab_handle = if visible {
+            let text: Vec<u16> = GRAB_HANDLE.encode_utf16().collect();
+            self.context
+                .create_text_format(GRAB_HANDLE_FONT, GRAB_HANDLE_SIZE, false, false)
+                .and_then(|format| {
+                    self.context
+                        .create_text_layout(&text, &format, f32::MAX, f32::MAX)
+                })
+                .ok()
+        } else {
+            None
+        };
+        _ = self.draw();
+    }
+
+    /// A line of figures in the top left corner, above the captions.
+    pub fn set_stats(&mut self, stats: Option<&str>) {
+        let shown = self.stats.is_some();
+        self.stats = stats.and_then(|stats| {
+            let text: Vec<u16> = stats.encode_utf16().collect();
+            self.context
+                .create_text_format(STATS_FONT, STATS_SIZE, false, false)
+                .and_then(|format| {
+                    self.context
+                        .create_text_layout(&text, &format, f32::MAX, f32::MAX)
+                })
+                .ok()
+        });
+        if self.stats.is_some() != shown {
+            self.update_rect();
+            self.update_size_dependents();
+        } else {
+            _ = self.draw();
+        }
+    }
+
+    /// A small list in the bottom corner, such as files being transcribed in the background.
+    pub fn set_progress(&mut self, progress: Option<&str>) {
+        self.progress = progress
+            .filter(|progress| !progress.is_empty())
+            .and_then(|progress| {
+                let text: Vec<u16> = progress.encode_utf16().collect();
+                self.context
+                    .create_text_format(STATS_FONT, STATS_SIZE, false, false)
+                    .and_then(|format| {
+                        self.context
+                            .create_text_layout(&text, &format, f32::MAX, f32::MAX)
+                    })
+                    .ok()
+            });
+        _ = self.draw();
+    }
+
+    /// A short label in the bottom right corner, such as a mode that is on, on a line
+    /// of its own below the captions.
+    pub fn set_badge(&mut self, badge: Option<&str>) {
+        self.badge = badge.and_then(|badge| {
+            let text: Vec<u16> = badge.encode_utf16().collect();
+            self.context
+                .create_text_format(STATS_FONT, STATS_SIZE, false, false)
+                .and_then(|format| {
+                    self.context
+                        .create_text_layout(&text, &format, f32::MAX, f32::MAX)
+                })
+                .ok()
+        });
+        self.update_rect();
+        self.update_size_dependents();
+    }
+
+    /// Shows animated dots after the captions while speech is heard but has no text yet.
+    pub fn set_listening(&mut self, listening: bool) {
+        if listening != self.listening.is_some() {
+            self.listening = listening.then(Instant::now);
+            self.update_text();
+            self.update_layout();
+        }
+    }
+
+    pub fn animate(&mut self) {
+        if self.listening.is_some() && self.current_listening_dots() != self.listening_dots {
+            self.update_text();
+            self.update_layout();
+        } else if self.overflow == Overflow::Scroll {
+            _ = self.draw();
+        }
+    }
+
     pub fn set_font_name(&mut self, font_name: &str) {
         self.font_name = font_name.into();
         self.update_format();
@@ -133,24 +415,158 @@ impl Renderer {
     }
 
     pub fn set_dpi(&mut self, dpi: u32) {
-        self.context.set_dpi(dpi);
-        _ = self.draw();
+        _ = self.context.set_dpi(dpi);
+        self.update_rect();
+        self.update_size_dependents();
     }
 
     pub fn set_opacity(&mut self, opacity: f32) {
-        self.opacity = opacity;
+        self.background[3] = opacity;
+        _ = self.context.set_background(&self.background);
+    }
+
+    /// The alpha of `color` is ignored in favor of the opacity.
+    pub fn set_background_color(&mut self, color: [f32; 4]) {
+        self.background = [color[0], color[1], color[2], self.background[3]];
+        _ = self.context.set_background(&self.background);
+    }
+
+    pub fn set_text_colors(&mut self, fill: [f32; 4], outline: [f32; 4]) {
+        _ = self.context.set_text_colors(&fill, &outline);
         _ = self.draw();
     }
 
+    pub fn set_padding(&mut self, padding: u32) {
+        self.padding = padding as f32;
+        self.update_rect();
+        self.update_size_dependents();
+    }
+
+    pub fn set_alignment(&mut self, alignment: &str) {
+        self.alignment = match alignment {
+            ALIGNMENT_CENTER => DWRITE_TEXT_ALIGNMENT_CENTER,
+            ALIGNMENT_RIGHT => DWRITE_TEXT_ALIGNMENT_TRAILING,
+            _ => DWRITE_TEXT_ALIGNMENT_LEADING,
+        };
+        self.update_format();
+    }
+
     pub fn set_size(&mut self, width: u32, height: u32) -> Result<()> {
         self.context.set_size(width, height)?;
+        self.size = (width, height);
+        self.update_rect();
+        self.update_size_dependents();
+        Ok(())
+    }
+
+    fn update_text(&mut self) {
+        self.text.clear();
+        self.colors.clear();
+        self.bolds.clear();
+
+        self.listening_dots = self.current_listening_dots();
+        let segments = with_listening(&self.segments, self.listening_dots);
+
+        let full: String = segments.iter().map(|s| s.text.as_str()).collect();
+        let mut break_before = vec![false; full.len() + 1];
+        let mut skip = vec![false; full.len()];
+        for range in line_breaks(&full, self.line_max_chars) {
+            break_before[range.start] = true;
+            skip[range].fill(true);
+        }
+
+        let mut hint_before = vec![None; full.len()];
+        if self.hyphenate {
+            for (pos, hint) in soft_breaks(&full) {
+                hint_before[pos] = Some(hint as u16);
+            }
+        }
 
+        // UTF-16 position of each byte of `full` in `self.text`.
+        let mut positions = vec![0; full.len() + 1];
+        let mut status = vec![];
+        let mut offset = 0;
+        let mut prev = '\n';
+        for segment in &segments {
+            let start = self.text.len() as u32;
+            for (i, c) in segment.text.char_indices() {
+                if break_before[offset + i] {
+                    self.text.push('\n' as u16);
+                    prev = '\n';
+                }
+                positions[offset + i] = self.text.len() as u32;
+                if !skip[offset + i] {
+                    if let Some(hint) = hint_before[offset + i] {
+                        self.text.push(hint);
+                    } else if needs_joiner(prev, c) {
+                        self.text.push(WORD_JOINER);
+                    }
+                    self.text.extend(c.encode_utf16(&mut [0; 2]).iter());
+                    prev = c;
+                }
+            }
+            if segment.role == SegmentRole::Status {
+                status.push(offset..offset + segment.text.len());
+            }
+            offset += segment.text.len();
+            let length = self.text.len() as u32 - start;
+
+            if let Some(color) = segment.color.or_else(|| role_color(segment.role)) {
+                let range = DWRITE_TEXT_RANGE {
+                    startPosition: start,
+                    length,
+                };
+                self.colors.push((range, color));
+            }
+        }
+        positions[full.len()] = self.text.len() as u32;
+
+        if self.emphasis.is_enabled() {
+            for (range, mood) in sentence_moods(&full) {
+                if status.iter().any(|status| status.contains(&range.start)) {
+                    continue;
+                }
+                let range = DWRITE_TEXT_RANGE {
+                    startPosition: positions[range.start],
+                    length: positions[range.end] - positions[range.start],
+                };
+                if let Some(color) = self.emphasis.color(mood) {
+                    self.colors.push((range, color));
+                }
+                if self.emphasis.bold {
+                    self.bolds.push(range);
+                }
+            }
+        }
+    }
+
+    fn current_listening_dots(&self) -> usize {
+        self.listening.map_or(0, |since| {
+            1 + (since.elapsed().as_millis() / LISTENING_STEP.as_millis()) as usize % 3
+        })
+    }
+
+    fn update_rect(&mut self) {
         let dpi = self.context.dpi();
-        let width = 96.0 * width as f32 / dpi;
-        let height = 96.0 * height as f32 / dpi;
-        self.rect = D2D_RECT_F::new(0.0, 0.0, width, height).inner(8.0, 4.0);
-        self.update_layout();
-        Ok(())
+        let width = 96.0 * self.size.0 as f32 / dpi;
+        let height = 96.0 * self.size.1 as f32 / dpi;
+        self.bounds =
+            D2D_RECT_F::new(0.0, 0.0, width, height).inner(self.padding, self.padding / 2.0);
+        self.rect = self.bounds;
+        if let Some(metrics) = self.stats.as_ref().and_then(|stats| stats.metrics().ok()) {
+            self.rect.top += metrics.height;
+        }
+        if let Some(metrics) = self.badge.as_ref().and_then(|badge| badge.metrics().ok()) {
+            self.rect.bottom -= metrics.height;
+        }
+    }
+
+    fn update_size_dependents(&mut self) {
+        if self.font_size == FONT_SIZE_AUTO {
+            self.update_format();
+        } else {
+            self.update_layout();
+        }
     }
 
     fn update_format(&mut self) {
@@ -172,21 +588,232 @@ impl Renderer {
             .context
             .create_text_format(
                 &self.font_name,
-                self.font_size,
+                self.effective_font_size(),
                 self.font_style_bold,
                 self.font_style_italic,
             )
+            .inspect(|format| unsafe {
+                _ = format.SetTextAlignment(self.alignment);
+                _ = format.SetWordWrapping(self.wrapping);
+            })
             .ok();
     }
 
-    fn setup_text_layout(&mut self) {
-        self.layout = if let Some(format) = &self.format {
-            self.context
-                .create_text_layout(&self.text, format, self.rect.width(), self.rect.height())
-                .ok()
+    fn effective_font_size(&self) -> u32 {
+        if self.font_size == FONT_SIZE_AUTO {
+            self.auto_font_size().unwrap_or(FONT_SIZE_SMALL)
         } else {
-            None
+            self.font_size
+        }
+    }
+
+    // Scales the font so that the maximum lines, or AUTO_FONT_LINES, fill the viewport.
+    fn auto_font_size(&self) -> Result<u32> {
+        let format = self.context.create_text_format(
+            &self.font_name,
+            AUTO_FONT_PROBE_SIZE,
+            self.font_style_bold,
+            self.font_style_italic,
+        )?;
+        let probe: Vec<u16> = "Ag".encode_utf16().collect();
+        let layout = self
+            .context
+            .create_text_layout(&probe, &format, f32::MAX, f32::MAX)?;
+        let line_height = layout.metrics()?.height / AUTO_FONT_PROBE_SIZE as f32;
+
+        let lines = match self.max_lines {
+            0 => AUTO_FONT_LINES as usize,
+            max_lines => max_lines,
+        };
+        let font_size = self.rect.height() / lines as f32 / line_height;
+        Ok((font_size as u32).max(MIN_SHRINK_FONT_SIZE as u32))
+    }
+
+    fn setup_text_layout(&mut self) {
+        self.layout = self.create_text_layout(0, false);
+        let skip = self.keep_last_lines().unwrap_or(0);
+
+        match self.overflow {
+            Overflow::Shrink => {
+                if let Some(layout) = &self.layout {
+                    _ = self.shrink_to_fit(layout);
+                }
+            }
+            Overflow::Ellipsis => {
+                _ = self.truncate_to_fit(skip);
+            }
+            _ => {}
+        }
+    }
+
+    // Creates a layout of the text without its first `skip` characters, replaced by an
+    // ellipsis if `ellipsis` is set.
+    fn create_text_layout(&self, skip: usize, ellipsis: bool) -> Option<IDWriteTextLayout> {
+        let format = self.format.as_ref()?;
+
+        let skip = skip.min(self.text.len());
+        let (text, shift) = match (skip, ellipsis) {
+            (0, _) => (self.text.clone(), 0),
+            (skip, true) => {
+                let mut text = vec![ELLIPSIS];
+                text.extend_from_slice(&self.text[skip..]);
+                (text, skip as i64 - 1)
+            }
+            (skip, false) => (self.text[skip..].to_vec(), skip as i64),
         };
+        let first = (skip > 0 && ellipsis) as i64;
+
+        let layout = self
+            .context
+            .create_text_layout(&text, format, self.rect.width(), self.rect.height())
+            .ok()?;
+
+        let shifted = |range: &DWRITE_TEXT_RANGE| {
+            let start = (range.startPosition as i64 - shift).max(first);
+            let end = range.startPosition as i64 + range.length as i64 - shift;
+            (start < end).then(|| DWRITE_TEXT_RANGE {
+                startPosition: start as _,
+                length: (end - start) as _,
+            })
+        };
+
+        for (range, color) in &self.colors {
+            let Some(range) = shifted(range) else {
+                continue;
+            };
+            if let Ok(brush) = self.context.create_solid_brush(color) {
+                _ = unsafe { layout.SetDrawingEffect(&brush, range) };
+            }
+        }
+
+        for range in self.bolds.iter().filter_map(shifted) {
+            _ = unsafe { layout.SetFontWeight(DWRITE_FONT_WEIGHT_BOLD, range) };
+        }
+
+        Some(layout)
+    }
+
+    fn shrink_to_fit(&self, layout: &IDWriteTextLayout) -> Result<()> {
+        let range = DWRITE_TEXT_RANGE {
+            startPosition: 0,
+            length: self.text.len() as _,
+        };
+
+        let mut font_size = self.effective_font_size() as f32;
+        while layout.metrics()?.height > self.rect.height() && font_size > MIN_SHRINK_FONT_SIZE {
+            font_size = (font_size * 0.9).max(MIN_SHRINK_FONT_SIZE);
+            unsafe { layout.SetFontSize(font_size, range)? };
+        }
+
+        Ok(())
+    }
+
+    /// Lays out the text again from the first of the newest `max_lines` lines,
+    /// returning how many characters were left out.
+    fn keep_last_lines(&mut self) -> Result<usize> {
+        let mut skip = 0;
+        if self.max_lines == 0 {
+            return Ok(skip);
+        }
+
+        // Lines that start where a wrapped line did can wrap differently on their own,
+        // so count again on the new layout.
+        for _ in 0..3 {
+            let Some(layout) = &self.layout else {
+                break;
+            };
+            let lines = layout.line_metrics()?;
+            if lines.len() <= self.max_lines {
+                break;
+            }
+
+            let hidden: u32 = lines[..lines.len() - self.max_lines]
+                .iter()
+                .map(|metrics| metrics.length)
+                .sum();
+            skip += hidden as usize;
+            self.layout = self.create_text_layout(skip, false);
+        }
+
+        Ok(skip)
+    }
+
+    // Starts from a layout already missing its first `skip` characters.
+    fn truncate_to_fit(&mut self, mut skip: usize) -> Result<()> {
+        let mut ellipsis = false;
+
+        // Inserting the ellipsis can rewrap the first line, so measure again after truncating.
+        for _ in 0..3 {
+            let Some(layout) = &self.layout else {
+                break;
+            };
+            if layout.metrics()?.height <= self.rect.height() {
+                break;
+            }
+
+            let lines = layout.line_metrics()?;
+            let mut visible_height = 0.0;
+            let mut visible = 0;
+            for metrics in lines.iter().rev() {
+                if visible_height + metrics.height > self.rect.height() {
+                    break;
+                }
+                visible_height += metrics.height;
+                visible += 1;
+            }
+
+            let hidden: u32 = lines[..lines.len() - visible.max(1)]
+                .iter()
+                .map(|metrics| metrics.length)
+                .sum();
+            if hidden == 0 {
+                break;
+            }
+
+            skip += hidden as usize - ellipsis as usize;
+            ellipsis = true;
+            self.layout = self.create_text_layout(skip, true);
+        }
+
+        Ok(())
+    }
+}
+
+/// Puts `dots` of the listening indicator after the captions, ahead of any status.
+fn with_listening(segments: &[CaptionSegment], dots: usize) -> Vec<CaptionSegment> {
+    let mut segments = segments.to_vec();
+    if dots == 0 {
+        return segments;
+    }
+
+    let pos = segments
+        .iter()
+        .rposition(|segment| segment.role != SegmentRole::Status)
+        .map_or(0, |i| i + 1);
+    let mut text = ".".repeat(dots);
+    if pos > 0 && !segments[pos - 1].text.ends_with(char::is_whitespace) {
+        text.insert(0, ' ');
+    }
+    if segments
+        .get(pos)
+        .is_some_and(|next| !next.text.starts_with(['\r', '\n']))
+    {
+        text.push_str("\r\n");
+    }
+    segments.insert(
+        pos,
+        CaptionSegment::tentative(text).with_color(LISTENING_COLOR),
+    );
+
+    segments
+}
+
+fn role_color(role: SegmentRole) -> Option<[f32; 4]> {
+    match role {
+        SegmentRole::Confirmed => None,
+        SegmentRole::Tentative => Some(TENTATIVE_COLOR),
+        SegmentRole::Status => Some(STATUS_COLOR),
+        SegmentRole::Speaker(n) => Some(SPEAKER_COLORS[n as usize % SPEAKER_COLORS.len()]),
     }
 }
 
@@ -1,5 +1,15 @@
 pub mod app;
+pub mod clipboard;
+pub mod ducking;
+pub mod gamepad;
+pub mod history;
+pub mod hotkey;
 mod menu;
+pub mod midi;
+pub mod note;
+mod slider;
+mod status;
+pub mod tray;
 pub mod utils;
 mod viewer;
 mod window;
@@ -21,7 +31,7 @@ use windows_core::{s, PCSTR};
 
 use crate::config::Config;
 
-pub fn run_app<T: app::App>() -> Result<()> {
+pub fn run_app<T: app::App>(config: Config) -> Result<()> {
     unsafe {
         RoInitialize(RO_INIT_MULTITHREADED)?;
 
@@ -34,7 +44,6 @@ pub fn run_app<T: app::App>() -> Result<()> {
 
         set_preferred_app_mode(PreferredAppMode::AllowDark)?;
 
-        let config = Config::load();
         let _viewer = Viewer::<T>::create(config)?;
 
         loop {
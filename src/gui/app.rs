@@ -11,16 +11,75 @@ pub trait App: Sized {
     fn on_paint(&mut self);
     fn on_timer(&mut self);
     fn on_dpi_changed(&mut self, dpi: u32);
+    fn on_display_changed(&mut self);
+    fn on_hover(&mut self, hover: bool);
+    fn on_tray_click(&mut self);
+    fn on_taskbar_created(&mut self);
+    /// Called before the menu is built, to refresh items that depend on the system.
+    fn on_menu_open(&mut self);
     fn on_menu(&mut self, id: u32, state: bool);
     fn menu_items(&self) -> Vec<MenuItem>;
+    fn on_slider(&mut self, id: u32, value: i32);
+    /// A hotkey the app registered for the window was pressed.
+    fn on_hotkey(&mut self, id: i32);
+    /// Ctrl+C copies the caption on screen, Ctrl+Shift+C the whole transcript with `all`.
+    fn on_copy(&mut self, all: bool);
+    /// A note entered in a [`NoteDialog`](super::note::NoteDialog), empty if dismissed.
+    fn on_note(&mut self, note: &str);
+    fn sliders(&self) -> Vec<Slider>;
+    /// Text of the status dialog, meant to be pasted into bug reports.
+    fn status_report(&self) -> String;
 }
 
+/// Menu id of [`MenuItem::Sliders`], handled by the viewer itself.
+pub const CMD_SLIDERS: u32 = 0xffff;
+/// Menu id of [`MenuItem::Status`], handled by the viewer itself.
+pub const CMD_STATUS: u32 = 0xfffe;
+
 pub enum MenuItem {
-    Action { id: u32, text: PCSTR },
-    CheckBox { id: u32, text: PCSTR, checked: bool },
-    Radio { id: u32, text: PCSTR, checked: bool },
+    Action {
+        id: u32,
+        text: PCSTR,
+    },
+    /// Opens a panel with the app's [`Slider`]s.
+    Sliders {
+        text: PCSTR,
+    },
+    /// Opens a dialog with the app's status report.
+    Status {
+        text: PCSTR,
+    },
+    CheckBox {
+        id: u32,
+        text: PCSTR,
+        checked: bool,
+    },
+    Radio {
+        id: u32,
+        text: PCSTR,
+        checked: bool,
+        /// Shown in a second, dimmed line under the text.
+        description: Option<PCSTR>,
+    },
     Separator,
-    SubMenu { text: PCSTR, items: Vec<MenuItem> },
+    SubMenu {
+        text: PCSTR,
+        items: Vec<MenuItem>,
+    },
+}
+
+pub struct Slider {
+    pub id: u32,
+    pub min: i32,
+    pub max: i32,
+    pub value: i32,
+    /// Presets the thumb snaps to when released, marked with ticks.
+    pub snaps: Vec<i32>,
+    /// Moves a released position off values the setting can't take.
+    pub clamp: fn(i32) -> i32,
+    /// Reports every position while dragging, rather than only where the thumb is released.
+    pub live: bool,
+    pub text: fn(i32) -> String,
 }
 
 #[macro_export]
@@ -51,6 +110,15 @@ macro_rules! radio {
             id: $id,
             text: ::windows::core::s!($text),
             checked: $checked,
+            description: None,
+        }
+    };
+    ($id:expr, $text:literal, $checked:expr, $description:expr $(,)?) => {
+        MenuItem::Radio {
+            id: $id,
+            text: ::windows::core::s!($text),
+            checked: $checked,
+            description: $description,
         }
     };
 }
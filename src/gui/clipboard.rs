@@ -0,0 +1,41 @@
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::{GlobalFree, HANDLE, HWND},
+    System::{
+        DataExchange::{CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData},
+        Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE},
+        Ole::CF_UNICODETEXT,
+    },
+};
+
+/// Puts `text` on the clipboard as Unicode text, replacing what was there.
+pub fn set_text(owner: HWND, text: &str) -> Result<()> {
+    let text: Vec<u16> = text.encode_utf16().chain([0]).collect();
+
+    unsafe {
+        OpenClipboard(owner)?;
+        let result = (|| -> Result<()> {
+            EmptyClipboard()?;
+
+            let memory = GlobalAlloc(GMEM_MOVEABLE, text.len() * std::mem::size_of::<u16>())?;
+            let dst = GlobalLock(memory) as *mut u16;
+            if dst.is_null() {
+                _ = GlobalFree(memory);
+                anyhow::bail!("can't lock clipboard memory");
+            }
+            std::ptr::copy_nonoverlapping(text.as_ptr(), dst, text.len());
+            _ = GlobalUnlock(memory);
+
+            // The clipboard owns the memory once it's set.
+            if let Err(e) = SetClipboardData(CF_UNICODETEXT.0 as _, HANDLE(memory.0)) {
+                _ = GlobalFree(memory);
+                return Err(e.into());
+            }
+
+            Ok(())
+        })();
+        _ = CloseClipboard();
+
+        result
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use windows::Win32::{
+    Media::Audio::*,
+    System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_ALL},
+};
+use windows_core::{implement, Interface as _, PCWSTR};
+
+use super::utils;
+
+/// Follows Windows lowering other audio for a call, and which app the call is in.
+pub struct DuckingWatcher {
+    manager: IAudioSessionManager2,
+    notification: IAudioVolumeDuckNotification,
+    // Sessions ducking other audio, each once, in the order they started.
+    sessions: Arc<Mutex<Vec<String>>>,
+    // The session last looked up and its app, since the lookup walks every audio session.
+    resolved: Option<(String, String)>,
+}
+
+impl DuckingWatcher {
+    pub fn new() -> Result<Self> {
+        unsafe {
+            let device_enumerator: IMMDeviceEnumerator =
+                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            // Ducking lowers the console device while a communications stream is open.
+            let device = device_enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+            let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None)?;
+
+            let sessions = Arc::new(Mutex::new(vec![]));
+            let notification: IAudioVolumeDuckNotification = DuckNotification {
+                sessions: sessions.clone(),
+            }
+            .into();
+            manager.RegisterDuckNotification(PCWSTR::null(), &notification)?;
+
+            Ok(Self {
+                manager,
+                notification,
+                sessions,
+                resolved: None,
+            })
+        }
+    }
+
+    /// Name of the app holding the latest call that ducks other audio, such as "Discord",
+    /// or "Call" if it can't be found.
+    pub fn app(&mut self) -> Option<&str> {
+        let session = self.sessions.lock().ok()?.last()?.clone();
+
+        if self.resolved.as_ref().is_none_or(|(id, _)| *id != session) {
+            let app = session_app(&session).unwrap_or_else(|| "Call".to_string());
+            self.resolved = Some((session, app));
+        }
+
+        self.resolved.as_ref().map(|(_, app)| app.as_str())
+    }
+}
+
+impl Drop for DuckingWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            _ = self.manager.UnregisterDuckNotification(&self.notification);
+        }
+    }
+}
+
+/// Records the sessions that start and end ducking, reported on a system thread.
+#[implement(IAudioVolumeDuckNotification)]
+struct DuckNotification {
+    sessions: Arc<Mutex<Vec<String>>>,
+}
+
+impl IAudioVolumeDuckNotification_Impl for DuckNotification_Impl {
+    fn OnVolumeDuckNotification(
+        &self,
+        session_id: &PCWSTR,
+        _count: u32,
+    ) -> windows_core::Result<()> {
+        let Ok(id) = (unsafe { session_id.to_string() }) else {
+            return Ok(());
+        };
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.retain(|session| *session != id);
+            sessions.push(id);
+        }
+        Ok(())
+    }
+
+    fn OnVolumeUnduckNotification(&self, session_id: &PCWSTR) -> windows_core::Result<()> {
+        let Ok(id) = (unsafe { session_id.to_string() }) else {
+            return Ok(());
+        };
+        if let Ok(mut sessions) = self.sessions.lock() {
+            sessions.retain(|session| *session != id);
+        }
+        Ok(())
+    }
+}
+
+/// Finds the app of a session on the communications devices, named after its executable.
+fn session_app(session_id: &str) -> Option<String> {
+    unsafe {
+        let device_enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).ok()?;
+
+        for flow in [eCapture, eRender] {
+            let Ok(device) = device_enumerator.GetDefaultAudioEndpoint(flow, eCommunications)
+            else {
+                continue;
+            };
+            let Ok(manager) = device.Activate::<IAudioSessionManager2>(CLSCTX_ALL, None) else {
+                continue;
+            };
+            let Ok(sessions) = manager.GetSessionEnumerator() else {
+                continue;
+            };
+
+            for i in 0..sessions.GetCount().unwrap_or(0) {
+                let Some(control) = sessions
+                    .GetSession(i)
+                    .and_then(|session| session.cast::<IAudioSessionControl2>())
+                    .ok()
+                else {
+                    continue;
+                };
+                let Ok(id) = control.GetSessionInstanceIdentifier() else {
+                    continue;
+                };
+                let matches = id.to_string().is_ok_and(|id| id == session_id);
+                CoTaskMemFree(Some(id.0 as _));
+
+                if matches {
+                    let name = utils::process_name(control.GetProcessId().ok()?)?;
+                    let app = match name.rsplit_once('.') {
+                        Some((app, _)) => app.to_string(),
+                        None => name,
+                    };
+                    return Some(app);
+                }
+            }
+        }
+
+        None
+    }
+}
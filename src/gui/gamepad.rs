@@ -0,0 +1,80 @@
+use std::time::{Duration, Instant};
+
+use windows::Win32::{
+    Foundation::ERROR_DEVICE_NOT_CONNECTED,
+    UI::Input::XboxController::{
+        XInputGetState, XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_BACK,
+        XINPUT_GAMEPAD_BUTTON_FLAGS, XINPUT_GAMEPAD_DPAD_DOWN, XINPUT_GAMEPAD_DPAD_LEFT,
+        XINPUT_GAMEPAD_DPAD_RIGHT, XINPUT_GAMEPAD_DPAD_UP, XINPUT_GAMEPAD_LEFT_SHOULDER,
+        XINPUT_GAMEPAD_LEFT_THUMB, XINPUT_GAMEPAD_RIGHT_SHOULDER, XINPUT_GAMEPAD_RIGHT_THUMB,
+        XINPUT_GAMEPAD_START, XINPUT_GAMEPAD_X, XINPUT_GAMEPAD_Y, XINPUT_STATE, XUSER_MAX_COUNT,
+    },
+};
+
+// Querying an empty slot is slow, so one found empty is left alone this long.
+const EMPTY_SLOT_RETRY: Duration = Duration::from_secs(3);
+
+#[derive(Default)]
+pub struct Gamepad {
+    buttons: u16,
+    /// When each empty slot is next checked for a controller.
+    retry_at: [Option<Instant>; XUSER_MAX_COUNT as usize],
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Polls all connected controllers and returns true for each chord that has just become fully pressed.
+    pub fn poll<const N: usize>(&mut self, chords: &[u16; N]) -> [bool; N] {
+        let now = Instant::now();
+        let mut buttons = 0;
+        for (user, retry_at) in self.retry_at.iter_mut().enumerate() {
+            if retry_at.is_some_and(|retry_at| now < retry_at) {
+                continue;
+            }
+
+            let mut state = XINPUT_STATE::default();
+            match unsafe { XInputGetState(user as _, &mut state) } {
+                0 => {
+                    buttons |= state.Gamepad.wButtons.0;
+                    *retry_at = None;
+                }
+                e if e == ERROR_DEVICE_NOT_CONNECTED.0 => *retry_at = Some(now + EMPTY_SLOT_RETRY),
+                _ => {}
+            }
+        }
+
+        let prev = std::mem::replace(&mut self.buttons, buttons);
+        chords.map(|chord| chord != 0 && buttons & chord == chord && prev & chord != chord)
+    }
+}
+
+/// Parses a chord such as "back+rb" into a button mask.
+pub fn parse_chord(chord: &str) -> u16 {
+    chord
+        .split('+')
+        .map(|name| button(name.trim()).0)
+        .fold(0, |mask, button| mask | button)
+}
+
+fn button(name: &str) -> XINPUT_GAMEPAD_BUTTON_FLAGS {
+    match name.to_ascii_lowercase().as_str() {
+        "a" => XINPUT_GAMEPAD_A,
+        "b" => XINPUT_GAMEPAD_B,
+        "x" => XINPUT_GAMEPAD_X,
+        "y" => XINPUT_GAMEPAD_Y,
+        "lb" => XINPUT_GAMEPAD_LEFT_SHOULDER,
+        "rb" => XINPUT_GAMEPAD_RIGHT_SHOULDER,
+        "ls" => XINPUT_GAMEPAD_LEFT_THUMB,
+        "rs" => XINPUT_GAMEPAD_RIGHT_THUMB,
+        "back" => XINPUT_GAMEPAD_BACK,
+        "start" => XINPUT_GAMEPAD_START,
+        "up" => XINPUT_GAMEPAD_DPAD_UP,
+        "down" => XINPUT_GAMEPAD_DPAD_DOWN,
+        "left" => XINPUT_GAMEPAD_DPAD_LEFT,
+        "right" => XINPUT_GAMEPAD_DPAD_RIGHT,
+        _ => XINPUT_GAMEPAD_BUTTON_FLAGS(0),
+    }
+}
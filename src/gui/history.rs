@@ -0,0 +1,553 @@
+use std::{collections::VecDeque, time::Duration};
+
+use anyhow::Result;
+use windows::{
+    core::{s, w, PCSTR, PCWSTR},
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{
+            BeginPaint, CreateSolidBrush, DeleteObject, EndPaint, FillRect, GetStockObject,
+            GetSysColorBrush, InvalidateRect, COLOR_BTNFACE, DEFAULT_GUI_FONT, HDC, PAINTSTRUCT,
+        },
+        UI::{
+            Controls::{EM_SETCUEBANNER, EM_SETSEL},
+            Input::KeyboardAndMouse::{
+                GetKeyState, SetFocus, VIRTUAL_KEY, VK_CONTROL, VK_ESCAPE, VK_F, VK_F3, VK_RETURN,
+                VK_SHIFT,
+            },
+            Shell::{DefSubclassProc, SetWindowSubclass},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+
+use super::{
+    utils::{self, CStr as _, Hwnd as _, Rect as _},
+    window::WindowClass,
+};
+use crate::{
+    caption::history::{format_offset, HistoryEntry, MAX_ENTRIES},
+    GET_X_LPARAM, GET_Y_LPARAM,
+};
+
+const CLASS_NAME: PCSTR = s!("livesub.history");
+const WIDTH: i32 = 480;
+const HEIGHT: i32 = 360;
+const MARGIN: i32 = 8;
+const TIMELINE_HEIGHT: i32 = 24;
+const SEARCH_HEIGHT: i32 = 22;
+const MATCHES_WIDTH: i32 = 90;
+
+const ID_SEARCH: usize = 1;
+const ID_FIND: usize = 2;
+const ID_FIND_NEXT: usize = 3;
+const ID_FIND_PREVIOUS: usize = 4;
+const ID_FIND_CLEAR: usize = 5;
+
+const SILENCE_COLOR: COLORREF = rgb(64, 64, 64);
+const SPEECH_COLOR: COLORREF = rgb(80, 200, 120);
+const MARKER_COLOR: COLORREF = rgb(255, 255, 255);
+const BOOKMARK_COLOR: COLORREF = rgb(255, 200, 0);
+
+/// The session's transcript under a timeline of speech and silence with a mark per caption.
+/// Clicking the timeline jumps to the caption spoken at that time. Ctrl+F searches the
+/// transcript, highlighting every match; Enter and F3 step through them, with Shift going back.
+pub struct HistoryWindow {
+    hwnd: HWND,
+}
+
+struct State {
+    list: HWND,
+    search: HWND,
+    matches_label: HWND,
+    entries: VecDeque<HistoryEntry>,
+    query: String,
+    matches: Vec<usize>,
+    current: usize,
+}
+
+impl HistoryWindow {
+    pub fn register() -> Result<()> {
+        WNDCLASSEXA::new()
+            .set_style(CS_VREDRAW | CS_HREDRAW)
+            .set_wndproc(wndproc)
+            .set_cursor(utils::load_cursor(Some(IDC_ARROW)))
+            .set_brush(unsafe { GetSysColorBrush(COLOR_BTNFACE) })
+            .set_name(CLASS_NAME)
+            .register()
+    }
+
+    pub fn show(owner: HWND, entries: &VecDeque<HistoryEntry>) -> Result<Self> {
+        let dpi = owner.dpi() as i32;
+        let scale = |value: i32| value * dpi / 96;
+
+        let (x, y) = utils::cursor_pos();
+        let rect = utils::clamp_to_work_area(RECT::new(x, y, scale(WIDTH), scale(HEIGHT)));
+        let hwnd = HWND::create(
+            WS_EX_TOOLWINDOW,
+            CLASS_NAME,
+            s!("livesub History"),
+            WS_POPUP | WS_CAPTION | WS_SYSMENU | WS_THICKFRAME,
+            rect.x(),
+            rect.y(),
+            rect.width(),
+            rect.height(),
+            owner,
+            None,
+            None,
+        )?;
+
+        // Created as a Unicode window so captions in any script survive.
+        let list = unsafe {
+            CreateWindowExW(
+                WS_EX_CLIENTEDGE,
+                w!("LISTBOX"),
+                PCWSTR::null(),
+                WS_CHILD
+                    | WS_VISIBLE
+                    | WS_VSCROLL
+                    | WS_TABSTOP
+                    | WINDOW_STYLE((LBS_NOTIFY | LBS_NOINTEGRALHEIGHT | LBS_EXTENDEDSEL) as _),
+                0,
+                0,
+                0,
+                0,
+                hwnd,
+                None,
+                None,
+                None,
+            )?
+        };
+        let search = unsafe {
+            CreateWindowExW(
+                WS_EX_CLIENTEDGE,
+                w!("EDIT"),
+                PCWSTR::null(),
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as _),
+                0,
+                0,
+                0,
+                0,
+                hwnd,
+                HMENU(ID_SEARCH as _),
+                None,
+                None,
+            )?
+        };
+        let cue = "Search (Ctrl+F)".c_wstr();
+        unsafe {
+            SendMessageW(
+                search,
+                EM_SETCUEBANNER,
+                WPARAM(1),
+                LPARAM(cue.as_ptr() as _),
+            );
+        }
+        let matches_label = unsafe {
+            CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                w!("STATIC"),
+                PCWSTR::null(),
+                WS_CHILD | WS_VISIBLE,
+                0,
+                0,
+                0,
+                0,
+                hwnd,
+                None,
+                None,
+                None,
+            )?
+        };
+
+        let font = WPARAM(unsafe { GetStockObject(DEFAULT_GUI_FONT) }.0 as _);
+        for control in [list, search, matches_label] {
+            control.send_message(WM_SETFONT, font, LPARAM(0));
+        }
+        for (id, control) in [list, search].into_iter().enumerate() {
+            unsafe { _ = SetWindowSubclass(control, Some(shortcut_proc), id, 0) };
+        }
+
+        let mut state = Box::new(State {
+            list,
+            search,
+            matches_label,
+            entries: VecDeque::new(),
+            query: String::new(),
+            matches: vec![],
+            current: 0,
+        });
+        for entry in entries {
+            state.append(entry);
+        }
+        state.layout(hwnd);
+
+        hwnd.set_user_data(Box::into_raw(state) as _);
+        hwnd.show(SW_SHOW);
+        hwnd.set_foreground();
+
+        Ok(Self { hwnd })
+    }
+
+    pub fn is_open(&self) -> bool {
+        unsafe { IsWindow(self.hwnd).as_bool() && self.hwnd.user_data() != 0 }
+    }
+
+    pub fn activate(&self) {
+        self.hwnd.show(SW_SHOW);
+        self.hwnd.set_foreground();
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) {
+        if !self.is_open() {
+            return;
+        }
+        if let Some(state) = unsafe { (self.hwnd.user_data() as *mut State).as_mut() } {
+            state.append(entry);
+            unsafe { _ = InvalidateRect(self.hwnd, None, FALSE) };
+        }
+    }
+}
+
+impl State {
+    fn append(&mut self, entry: &HistoryEntry) {
+        let text = match entry.bookmark {
+            true => format!("[{}] \u{2691} {}", format_offset(entry.start), entry.text),
+            false => format!("[{}] {}", format_offset(entry.start), entry.text),
+        }
+        .c_wstr();
+        unsafe {
+            SendMessageW(
+                self.list,
+                LB_ADDSTRING,
+                WPARAM(0),
+                LPARAM(text.as_ptr() as _),
+            );
+        }
+        self.entries.push_back(entry.clone());
+
+        let index = self.entries.len() - 1;
+        if !self.query.is_empty() && entry.text.to_lowercase().contains(&self.query) {
+            self.matches.push(index);
+            self.list
+                .send_message(LB_SETSEL, WPARAM(1), LPARAM(index as _));
+        }
+
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+            self.list
+                .send_message(LB_DELETESTRING, WPARAM(0), LPARAM(0));
+
+            if self.matches.first() == Some(&0) {
+                self.matches.remove(0);
+                self.current = self.current.saturating_sub(1);
+            }
+            for index in &mut self.matches {
+                *index -= 1;
+            }
+        }
+        self.update_matches_label();
+
+        // Follows the newest caption unless the user picked one.
+        if self
+            .list
+            .send_message(LB_GETSELCOUNT, WPARAM(0), LPARAM(0))
+            .0
+            == 0
+        {
+            self.list.send_message(
+                LB_SETTOPINDEX,
+                WPARAM(self.entries.len().saturating_sub(1)),
+                LPARAM(0),
+            );
+        }
+    }
+
+    fn layout(&self, hwnd: HWND) {
+        let dpi = hwnd.dpi() as i32;
+        let scale = |value: i32| value * dpi / 96;
+        let timeline = timeline_rect(hwnd);
+        let client = client_rect(hwnd);
+        let margin = scale(MARGIN);
+        let top = timeline.bottom + margin;
+        let search_top = client.height() - margin - scale(SEARCH_HEIGHT);
+        let matches_width = scale(MATCHES_WIDTH);
+
+        self.list.set_pos(
+            margin,
+            top,
+            client.width() - 2 * margin,
+            search_top - margin - top,
+        );
+        self.search.set_pos(
+            margin,
+            search_top,
+            client.width() - 3 * margin - matches_width,
+            scale(SEARCH_HEIGHT),
+        );
+        self.matches_label.set_pos(
+            client.width() - margin - matches_width,
+            search_top + scale(3),
+            matches_width,
+            scale(SEARCH_HEIGHT),
+        );
+    }
+
+    /// Highlights every caption containing the search text and moves to the first one
+    /// from the caret on.
+    fn on_search(&mut self) {
+        let len = unsafe { GetWindowTextLengthW(self.search) } as usize;
+        let mut text = vec![0; len + 1];
+        let len = unsafe { GetWindowTextW(self.search, &mut text) } as usize;
+        self.query = String::from_utf16_lossy(&text[..len]).trim().to_lowercase();
+
+        self.list.send_message(LB_SETSEL, WPARAM(0), LPARAM(-1));
+        self.matches.clear();
+        if !self.query.is_empty() {
+            for (i, entry) in self.entries.iter().enumerate() {
+                if entry.text.to_lowercase().contains(&self.query) {
+                    self.matches.push(i);
+                    self.list.send_message(LB_SETSEL, WPARAM(1), LPARAM(i as _));
+                }
+            }
+        }
+
+        let caret = self
+            .list
+            .send_message(LB_GETCARETINDEX, WPARAM(0), LPARAM(0))
+            .0
+            .max(0) as usize;
+        self.current = self.matches.iter().position(|&i| i >= caret).unwrap_or(0);
+        self.show_current();
+    }
+
+    fn step(&mut self, forward: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let n = self.matches.len();
+        self.current = if forward {
+            (self.current + 1) % n
+        } else {
+            (self.current + n - 1) % n
+        };
+        self.show_current();
+    }
+
+    fn show_current(&self) {
+        if let Some(&index) = self.matches.get(self.current) {
+            self.list
+                .send_message(LB_SETCARETINDEX, WPARAM(index), LPARAM(0));
+            self.list
+                .send_message(LB_SETTOPINDEX, WPARAM(index), LPARAM(0));
+        }
+        self.update_matches_label();
+    }
+
+    fn update_matches_label(&self) {
+        let text = match self.matches.len() {
+            _ if self.query.is_empty() => String::new(),
+            0 => "No matches".to_string(),
+            n => format!("{} of {n}", self.current.min(n - 1) + 1),
+        };
+        unsafe { _ = SetWindowTextW(self.matches_label, &windows::core::HSTRING::from(text)) };
+    }
+
+    fn length(&self) -> Duration {
+        self.entries
+            .back()
+            .map_or(Duration::ZERO, |entry| entry.end)
+            .max(Duration::from_secs(1))
+    }
+
+    fn paint(&self, hdc: HDC, rect: RECT) {
+        let length = self.length().as_secs_f64();
+        let x = |offset: Duration| {
+            rect.left + (rect.width() as f64 * offset.as_secs_f64() / length) as i32
+        };
+
+        fill(hdc, rect, SILENCE_COLOR);
+
+        for entry in self.entries.iter().filter(|entry| !entry.bookmark) {
+            let spans = if entry.spans.is_empty() {
+                &[(entry.start, entry.end)][..]
+            } else {
+                &entry.spans
+            };
+            for &(start, end) in spans {
+                let left = x(start);
+                let span = RECT {
+                    left,
+                    top: rect.top,
+                    right: x(end).max(left + 1),
+                    bottom: rect.bottom,
+                };
+                fill(hdc, span, SPEECH_COLOR);
+            }
+        }
+
+        for entry in &self.entries {
+            let left = x(entry.start);
+            let (width, height, color) = match entry.bookmark {
+                true => (2, rect.height(), BOOKMARK_COLOR),
+                false => (1, rect.height() / 3, MARKER_COLOR),
+            };
+            let marker = RECT {
+                left,
+                top: rect.top,
+                right: left + width,
+                bottom: rect.top + height,
+            };
+            fill(hdc, marker, color);
+        }
+    }
+
+    fn jump_to(&self, hwnd: HWND, x: i32) {
+        let rect = timeline_rect(hwnd);
+        let offset = self
+            .length()
+            .mul_f64(((x - rect.left) as f64 / rect.width().max(1) as f64).clamp(0.0, 1.0));
+        let index = self
+            .entries
+            .partition_point(|entry| entry.start <= offset)
+            .saturating_sub(1);
+        self.select(index);
+    }
+
+    fn select(&self, index: usize) {
+        let list = self.list;
+        list.send_message(LB_SETSEL, WPARAM(0), LPARAM(-1));
+        list.send_message(LB_SETSEL, WPARAM(1), LPARAM(index as _));
+        list.send_message(LB_SETCARETINDEX, WPARAM(index), LPARAM(0));
+        list.send_message(LB_SETTOPINDEX, WPARAM(index), LPARAM(0));
+    }
+}
+
+fn timeline_rect(hwnd: HWND) -> RECT {
+    let dpi = hwnd.dpi() as i32;
+    let scale = |value: i32| value * dpi / 96;
+    let client = client_rect(hwnd);
+
+    RECT {
+        left: scale(MARGIN),
+        top: scale(MARGIN),
+        right: client.right - scale(MARGIN),
+        bottom: scale(MARGIN + TIMELINE_HEIGHT),
+    }
+}
+
+fn client_rect(hwnd: HWND) -> RECT {
+    let mut rect = RECT::default();
+    unsafe { _ = GetClientRect(hwnd, &mut rect) };
+    rect
+}
+
+fn fill(hdc: HDC, rect: RECT, color: COLORREF) {
+    unsafe {
+        let brush = CreateSolidBrush(color);
+        FillRect(hdc, &rect, brush);
+        _ = DeleteObject(brush);
+    }
+}
+
+const fn rgb(r: u8, g: u8, b: u8) -> COLORREF {
+    COLORREF(r as u32 | (g as u32) << 8 | (b as u32) << 16)
+}
+
+unsafe extern "system" fn wndproc(hwnd: HWND, msg: u32, wp: WPARAM, lp: LPARAM) -> LRESULT {
+    let state = hwnd.user_data() as *mut State;
+
+    match msg {
+        WM_PAINT => {
+            let mut ps = PAINTSTRUCT::default();
+            let hdc = BeginPaint(hwnd, &mut ps);
+            if let Some(state) = state.as_ref() {
+                state.paint(hdc, timeline_rect(hwnd));
+            }
+            _ = EndPaint(hwnd, &ps);
+            LRESULT(0)
+        }
+        WM_SIZE => {
+            if let Some(state) = state.as_ref() {
+                state.layout(hwnd);
+            }
+            LRESULT(0)
+        }
+        WM_COMMAND => {
+            let Some(state) = state.as_mut() else {
+                return LRESULT(0);
+            };
+            match (wp.0 & 0xffff, (wp.0 >> 16) as u32) {
+                (ID_SEARCH, EN_CHANGE) => state.on_search(),
+                (ID_FIND, _) => {
+                    _ = SetFocus(state.search);
+                    state.search.send_message(EM_SETSEL, WPARAM(0), LPARAM(-1));
+                }
+                (ID_FIND_NEXT, _) => state.step(true),
+                (ID_FIND_PREVIOUS, _) => state.step(false),
+                (ID_FIND_CLEAR, _) => {
+                    state.search.set_text("");
+                    _ = SetFocus(state.list);
+                }
+                _ => {}
+            }
+            LRESULT(0)
+        }
+        WM_LBUTTONDOWN => {
+            let (x, y) = (GET_X_LPARAM!(lp), GET_Y_LPARAM!(lp));
+            if let Some(state) = state.as_ref() {
+                if timeline_rect(hwnd).is_in(x, y) {
+                    state.jump_to(hwnd, x);
+                }
+            }
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if !state.is_null() {
+                hwnd.set_user_data(0);
+                drop(Box::from_raw(state));
+            }
+            LRESULT(0)
+        }
+        _ => hwnd.def_proc(msg, wp, lp),
+    }
+}
+
+/// Turns the search shortcuts pressed in any control into commands for the window.
+unsafe extern "system" fn shortcut_proc(
+    hwnd: HWND,
+    msg: u32,
+    wp: WPARAM,
+    lp: LPARAM,
+    _id: usize,
+    _data: usize,
+) -> LRESULT {
+    let pressed = |key: VIRTUAL_KEY| GetKeyState(key.0 as _) < 0;
+    let in_search = GetDlgCtrlID(hwnd) == ID_SEARCH as i32;
+    let step = || match pressed(VK_SHIFT) {
+        true => ID_FIND_PREVIOUS,
+        false => ID_FIND_NEXT,
+    };
+
+    let command = match msg {
+        WM_KEYDOWN => match VIRTUAL_KEY(wp.0 as _) {
+            VK_F if pressed(VK_CONTROL) => Some(ID_FIND),
+            VK_F3 => Some(step()),
+            VK_RETURN if in_search => Some(step()),
+            VK_ESCAPE if in_search => Some(ID_FIND_CLEAR),
+            _ => None,
+        },
+        // Swallows the characters of handled keys, which the edit control would beep at.
+        WM_CHAR if in_search && matches!(wp.0, 0x06 | 0x0d | 0x1b) => return LRESULT(0),
+        _ => None,
+    };
+
+    match command {
+        Some(command) => {
+            hwnd.parent()
+                .post_message(WM_COMMAND, WPARAM(command), LPARAM(0));
+            LRESULT(0)
+        }
+        None => DefSubclassProc(hwnd, msg, wp, lp),
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use windows::Win32::{
+    Foundation::HWND,
+    UI::Input::KeyboardAndMouse::{
+        RegisterHotKey, UnregisterHotKey, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT,
+        MOD_SHIFT, MOD_WIN, VK_DELETE, VK_END, VK_F1, VK_HOME, VK_INSERT, VK_NEXT, VK_PAUSE,
+        VK_PRIOR, VK_SCROLL, VK_SPACE,
+    },
+};
+
+/// A system-wide shortcut, posting `WM_HOTKEY` with its id to the window it is registered for.
+pub struct Hotkey {
+    hwnd: HWND,
+    id: i32,
+}
+
+impl Hotkey {
+    /// Registers a shortcut such as "Ctrl+Alt+B", or returns `None` for an empty one.
+    pub fn register(hwnd: HWND, id: i32, hotkey: &str) -> Result<Option<Self>> {
+        if hotkey.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let (modifiers, key) =
+            parse_hotkey(hotkey).ok_or_else(|| anyhow!("invalid hotkey {hotkey:?}"))?;
+        unsafe { RegisterHotKey(hwnd, id, modifiers | MOD_NOREPEAT, key) }
+            .map_err(|e| anyhow!("{hotkey} is in use, {e}"))?;
+
+        Ok(Some(Self { hwnd, id }))
+    }
+}
+
+impl Drop for Hotkey {
+    fn drop(&mut self) {
+        unsafe { _ = UnregisterHotKey(self.hwnd, self.id) };
+    }
+}
+
+/// Parses modifiers and a key joined by "+", such as "Ctrl+Shift+F9".
+pub fn parse_hotkey(hotkey: &str) -> Option<(HOT_KEY_MODIFIERS, u32)> {
+    let mut modifiers = HOT_KEY_MODIFIERS(0);
+    let mut key = None;
+
+    for name in hotkey.split('+').map(str::trim) {
+        match name.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "shift" => modifiers |= MOD_SHIFT,
+            "win" => modifiers |= MOD_WIN,
+            name if key.is_none() => key = Some(virtual_key(name)?),
+            _ => return None,
+        }
+    }
+
+    key.map(|key| (modifiers, key))
+}
+
+fn virtual_key(name: &str) -> Option<u32> {
+    let key = match name {
+        "space" => VK_SPACE,
+        "pause" => VK_PAUSE,
+        "scrolllock" => VK_SCROLL,
+        "insert" => VK_INSERT,
+        "delete" => VK_DELETE,
+        "home" => VK_HOME,
+        "end" => VK_END,
+        "pageup" => VK_PRIOR,
+        "pagedown" => VK_NEXT,
+        name => {
+            if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u16>().ok()) {
+                return (1..=24).contains(&n).then(|| (VK_F1.0 + n - 1) as u32);
+            }
+
+            let mut chars = name.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase() as u32),
+                _ => None,
+            };
+        }
+    };
+
+    Some(key.0 as u32)
+}
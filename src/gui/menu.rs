@@ -6,14 +6,22 @@ use anyhow::Result;
 use windows::{
     core::{s, PCSTR, PSTR},
     Win32::{
-        Foundation::{FreeLibrary, BOOL, HWND},
+        Foundation::{FreeLibrary, BOOL, COLORREF, HWND, LRESULT, RECT},
+        Graphics::Gdi::*,
         System::LibraryLoader::{GetProcAddress, LoadLibraryExA, LOAD_LIBRARY_SEARCH_SYSTEM32},
-        UI::WindowsAndMessaging::*,
+        UI::{
+            Controls::{
+                DRAWITEMSTRUCT, MEASUREITEMSTRUCT, ODS_CHECKED, ODS_DISABLED, ODS_GRAYED,
+                ODS_NOACCEL, ODS_SELECTED, ODT_MENU,
+            },
+            HiDpi::SystemParametersInfoForDpi,
+            WindowsAndMessaging::*,
+        },
     },
 };
 
 use super::{
-    app::MenuItem,
+    app::{MenuItem, CMD_SLIDERS, CMD_STATUS},
     utils::{self, Hwnd},
 };
 
@@ -22,21 +30,55 @@ pub struct ContextMenu {
     menu: HMENU,
     checkboxes: Vec<u32>,
     radio_groups: Vec<Vec<u32>>,
+    painter: MenuPainter,
+}
+
+impl Drop for ContextMenu {
+    fn drop(&mut self) {
+        unsafe {
+            _ = DestroyMenu(self.menu);
+        }
+    }
 }
 
 impl ContextMenu {
     pub fn new(hwnd: HWND, items: &[MenuItem]) -> Result<Self> {
-        let (menu, checkboxes, radio_groups) = MenuBuilder::build(items)?;
+        let (menu, checkboxes, radio_groups, descriptions) = MenuBuilder::build(items)?;
+        let painter = MenuPainter::new(hwnd, menu, &descriptions)?;
         Ok(Self {
             hwnd,
             menu,
             checkboxes,
             radio_groups,
+            painter,
         })
     }
 
+    /// Handles `WM_MEASUREITEM` sent to the owner while the menu is open.
+    pub fn measure_item(&self, mis: &mut MEASUREITEMSTRUCT) -> bool {
+        mis.CtlType == ODT_MENU && self.painter.measure(self.hwnd, mis)
+    }
+
+    /// Handles `WM_DRAWITEM` sent to the owner while the menu is open.
+    pub fn draw_item(&self, dis: &DRAWITEMSTRUCT) -> bool {
+        dis.CtlType == ODT_MENU && self.painter.draw(dis)
+    }
+
+    /// Handles `WM_MENUCHAR`, which replaces the system's mnemonic lookup for owner-drawn items.
+    pub fn menu_char(&self, menu: HMENU, key: u16) -> Option<LRESULT> {
+        let pos = self.painter.find_mnemonic(menu, key)?;
+        Some(LRESULT(((MNC_EXECUTE as isize) << 16) | pos as isize))
+    }
+
     pub fn show(&self) -> Option<(u32, bool)> {
         let (x, y) = utils::cursor_pos();
+        self.show_at(x, y)
+    }
+
+    pub fn show_at(&self, x: i32, y: i32) -> Option<(u32, bool)> {
+        // The menu is only dismissed by clicking elsewhere if its owner is in the foreground,
+        // which a non-activating overlay never is on its own.
+        self.hwnd.set_foreground();
         let id = self.menu.popup(
             TPM_LEFTALIGN | TPM_TOPALIGN | TPM_RETURNCMD,
             x,
@@ -77,36 +119,424 @@ impl ContextMenu {
     }
 }
 
+const ITEM_PADDING: i32 = 6;
+const CHECK_WIDTH: i32 = 28;
+const ARROW_WIDTH: i32 = 24;
+const SEPARATOR_HEIGHT: i32 = 9;
+
+struct Palette {
+    background: COLORREF,
+    hover: COLORREF,
+    text: COLORREF,
+    disabled: COLORREF,
+    separator: COLORREF,
+}
+
+const DARK: Palette = Palette {
+    background: rgb(43, 43, 43),
+    hover: rgb(65, 65, 65),
+    text: rgb(255, 255, 255),
+    disabled: rgb(128, 128, 128),
+    separator: rgb(80, 80, 80),
+};
+
+const LIGHT: Palette = Palette {
+    background: rgb(249, 249, 249),
+    hover: rgb(230, 230, 230),
+    text: rgb(0, 0, 0),
+    disabled: rgb(150, 150, 150),
+    separator: rgb(215, 215, 215),
+};
+
+const fn rgb(r: u8, g: u8, b: u8) -> COLORREF {
+    COLORREF(r as u32 | (g as u32) << 8 | (b as u32) << 16)
+}
+
+struct Entry {
+    text: Vec<u16>,
+    description: Vec<u16>,
+    separator: bool,
+    radio: bool,
+    submenu: bool,
+}
+
+/// Owner-draws every item of a menu tree so it follows the system's dark mode.
+struct MenuPainter {
+    entries: Vec<Entry>,
+    palette: &'static Palette,
+    font: HFONT,
+    small_font: HFONT,
+    glyph_font: HFONT,
+    background: HBRUSH,
+    dpi: u32,
+}
+
+impl Drop for MenuPainter {
+    fn drop(&mut self) {
+        unsafe {
+            _ = DeleteObject(self.font);
+            _ = DeleteObject(self.small_font);
+            _ = DeleteObject(self.glyph_font);
+            _ = DeleteObject(self.background);
+        }
+    }
+}
+
+impl MenuPainter {
+    fn new(hwnd: HWND, menu: HMENU, descriptions: &HashMap<u32, PCSTR>) -> Result<Self> {
+        let dpi = hwnd.dpi();
+        let palette = if utils::is_dark_mode() { &DARK } else { &LIGHT };
+
+        let mut metrics = NONCLIENTMETRICSW {
+            cbSize: size_of::<NONCLIENTMETRICSW>() as _,
+            ..Default::default()
+        };
+        unsafe {
+            SystemParametersInfoForDpi(
+                SPI_GETNONCLIENTMETRICS.0,
+                metrics.cbSize,
+                Some(&mut metrics as *mut _ as _),
+                0,
+                dpi,
+            )?;
+        }
+
+        let font = unsafe { CreateFontIndirectW(&metrics.lfMenuFont) };
+        let mut small = metrics.lfMenuFont;
+        small.lfHeight = small.lfHeight * 5 / 6;
+        let small_font = unsafe { CreateFontIndirectW(&small) };
+        let mut glyph = metrics.lfMenuFont;
+        glyph.lfFaceName = [0; 32];
+        for (dst, src) in glyph
+            .lfFaceName
+            .iter_mut()
+            .zip("Segoe UI Symbol".encode_utf16())
+        {
+            *dst = src;
+        }
+        let glyph_font = unsafe { CreateFontIndirectW(&glyph) };
+        let background = unsafe { CreateSolidBrush(palette.background) };
+
+        let mut painter = Self {
+            entries: vec![],
+            palette,
+            font,
+            small_font,
+            glyph_font,
+            background,
+            dpi,
+        };
+        painter.attach(menu, descriptions)?;
+
+        Ok(painter)
+    }
+
+    fn scale(&self, value: i32) -> i32 {
+        value * self.dpi as i32 / 96
+    }
+
+    /// Turns every item of `menu` and its submenus owner-drawn, remembering what to draw.
+    fn attach(&mut self, menu: HMENU, descriptions: &HashMap<u32, PCSTR>) -> Result<()> {
+        unsafe {
+            let info = MENUINFO {
+                cbSize: size_of::<MENUINFO>() as _,
+                fMask: MIM_BACKGROUND,
+                hbrBack: self.background,
+                ..Default::default()
+            };
+            SetMenuInfo(menu, &info)?;
+
+            for pos in 0..GetMenuItemCount(menu).max(0) as u32 {
+                let mut mi = MENUITEMINFOW {
+                    cbSize: size_of::<MENUITEMINFOW>() as _,
+                    fMask: MIIM_FTYPE | MIIM_ID | MIIM_SUBMENU,
+                    ..Default::default()
+                };
+                GetMenuItemInfoW(menu, pos, true, &mut mi)?;
+
+                let mut text = vec![0u16; 256];
+                let len = GetMenuStringW(menu, pos, Some(&mut text), MF_BYPOSITION);
+                text.truncate(len.max(0) as usize);
+
+                let description = descriptions
+                    .get(&mi.wID)
+                    .filter(|_| mi.hSubMenu.is_invalid())
+                    .and_then(|description| description.to_string().ok())
+                    .map(|description| description.encode_utf16().collect())
+                    .unwrap_or_default();
+
+                self.entries.push(Entry {
+                    text,
+                    description,
+                    separator: mi.fType & MFT_SEPARATOR != MENU_ITEM_TYPE(0),
+                    radio: mi.fType & MFT_RADIOCHECK != MENU_ITEM_TYPE(0),
+                    submenu: !mi.hSubMenu.is_invalid(),
+                });
+
+                let owner_draw = MENUITEMINFOW {
+                    cbSize: size_of::<MENUITEMINFOW>() as _,
+                    fMask: MIIM_FTYPE | MIIM_DATA,
+                    fType: mi.fType | MFT_OWNERDRAW,
+                    dwItemData: self.entries.len() - 1,
+                    ..Default::default()
+                };
+                SetMenuItemInfoW(menu, pos, true, &owner_draw)?;
+
+                if !mi.hSubMenu.is_invalid() {
+                    self.attach(mi.hSubMenu, descriptions)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_mnemonic(&self, menu: HMENU, key: u16) -> Option<u32> {
+        let key = char::from_u32(key as _)?.to_lowercase().next()?;
+
+        (0..unsafe { GetMenuItemCount(menu) }.max(0) as u32).find(|&pos| {
+            let mut mi = MENUITEMINFOW {
+                cbSize: size_of::<MENUITEMINFOW>() as _,
+                fMask: MIIM_DATA,
+                ..Default::default()
+            };
+            if unsafe { GetMenuItemInfoW(menu, pos, true, &mut mi) }.is_err() {
+                return false;
+            }
+
+            self.entries.get(mi.dwItemData).is_some_and(|entry| {
+                let text = String::from_utf16_lossy(&entry.text);
+                let mut chars = text.chars();
+                while let Some(c) = chars.next() {
+                    if c == '&' {
+                        return chars
+                            .next()
+                            .is_some_and(|c| c.to_lowercase().next() == Some(key));
+                    }
+                }
+                false
+            })
+        })
+    }
+
+    fn measure(&self, hwnd: HWND, mis: &mut MEASUREITEMSTRUCT) -> bool {
+        let Some(entry) = self.entries.get(mis.itemData) else {
+            return false;
+        };
+
+        if entry.separator {
+            mis.itemWidth = 0;
+            mis.itemHeight = self.scale(SEPARATOR_HEIGHT) as _;
+            return true;
+        }
+
+        let mut rect = RECT::default();
+        let mut description = RECT::default();
+        unsafe {
+            let hdc = GetDC(hwnd);
+            let old = SelectObject(hdc, self.font);
+            DrawTextW(
+                hdc,
+                &mut entry.text.clone(),
+                &mut rect,
+                DT_CALCRECT | DT_SINGLELINE,
+            );
+            if !entry.description.is_empty() {
+                SelectObject(hdc, self.small_font);
+                DrawTextW(
+                    hdc,
+                    &mut entry.description.clone(),
+                    &mut description,
+                    DT_CALCRECT | DT_SINGLELINE,
+                );
+            }
+            SelectObject(hdc, old);
+            ReleaseDC(hwnd, hdc);
+        }
+        rect.right = rect.right.max(description.right);
+        rect.bottom += description.bottom;
+
+        mis.itemWidth = (self.scale(CHECK_WIDTH) + rect.right + self.scale(ARROW_WIDTH)) as _;
+        mis.itemHeight = (rect.bottom + 2 * self.scale(ITEM_PADDING)) as _;
+        true
+    }
+
+    fn draw(&self, dis: &DRAWITEMSTRUCT) -> bool {
+        let Some(entry) = self.entries.get(dis.itemData) else {
+            return false;
+        };
+
+        let hdc = dis.hDC;
+        let rect = dis.rcItem;
+        let selected = dis.itemState.0 & ODS_SELECTED.0 != 0;
+        let disabled = dis.itemState.0 & (ODS_DISABLED.0 | ODS_GRAYED.0) != 0;
+
+        unsafe {
+            let fill = |rect: &RECT, color: COLORREF| {
+                let brush = CreateSolidBrush(color);
+                FillRect(hdc, rect, brush);
+                _ = DeleteObject(brush);
+            };
+
+            let background = if selected && !disabled && !entry.separator {
+                self.palette.hover
+            } else {
+                self.palette.background
+            };
+            fill(&rect, background);
+
+            if entry.separator {
+                let y = (rect.top + rect.bottom) / 2;
+                let line = RECT {
+                    left: rect.left + self.scale(ITEM_PADDING),
+                    top: y,
+                    right: rect.right - self.scale(ITEM_PADDING),
+                    bottom: y + 1,
+                };
+                fill(&line, self.palette.separator);
+                return true;
+            }
+
+            SetBkMode(hdc, TRANSPARENT);
+            SetTextColor(
+                hdc,
+                if disabled {
+                    self.palette.disabled
+                } else {
+                    self.palette.text
+                },
+            );
+
+            let old = SelectObject(hdc, self.glyph_font);
+            if dis.itemState.0 & ODS_CHECKED.0 != 0 {
+                let mut glyph: Vec<u16> = if entry.radio { "\u{25CF}" } else { "\u{2713}" }
+                    .encode_utf16()
+                    .collect();
+                let mut check = RECT {
+                    right: rect.left + self.scale(CHECK_WIDTH),
+                    ..rect
+                };
+                DrawTextW(
+                    hdc,
+                    &mut glyph,
+                    &mut check,
+                    DT_CENTER | DT_VCENTER | DT_SINGLELINE,
+                );
+            }
+
+            SelectObject(hdc, self.font);
+            let mut format = DT_LEFT | DT_SINGLELINE;
+            if dis.itemState.0 & ODS_NOACCEL.0 != 0 {
+                format |= DT_HIDEPREFIX;
+            }
+            let mut text_rect = RECT {
+                left: rect.left + self.scale(CHECK_WIDTH),
+                top: rect.top + self.scale(ITEM_PADDING),
+                bottom: rect.bottom - self.scale(ITEM_PADDING),
+                ..rect
+            };
+            if entry.description.is_empty() {
+                DrawTextW(
+                    hdc,
+                    &mut entry.text.clone(),
+                    &mut text_rect,
+                    format | DT_VCENTER,
+                );
+            } else {
+                DrawTextW(
+                    hdc,
+                    &mut entry.text.clone(),
+                    &mut text_rect,
+                    format | DT_TOP,
+                );
+
+                SelectObject(hdc, self.small_font);
+                SetTextColor(hdc, self.palette.disabled);
+                DrawTextW(
+                    hdc,
+                    &mut entry.description.clone(),
+                    &mut text_rect,
+                    DT_LEFT | DT_BOTTOM | DT_SINGLELINE | DT_NOPREFIX,
+                );
+            }
+
+            if entry.submenu {
+                let mut arrow: Vec<u16> = "\u{203A}".encode_utf16().collect();
+                let mut arrow_rect = RECT {
+                    left: rect.right - self.scale(ARROW_WIDTH),
+                    ..rect
+                };
+                DrawTextW(
+                    hdc,
+                    &mut arrow,
+                    &mut arrow_rect,
+                    DT_CENTER | DT_VCENTER | DT_SINGLELINE,
+                );
+
+                // Keep the system from drawing its own, theme-unaware arrow over ours.
+                ExcludeClipRect(
+                    hdc,
+                    arrow_rect.left,
+                    arrow_rect.top,
+                    arrow_rect.right,
+                    arrow_rect.bottom,
+                );
+            }
+            SelectObject(hdc, old);
+        }
+
+        true
+    }
+}
+
 struct MenuBuilder {
     menu: HMENU,
     checkboxes: Vec<u32>,
     radio_groups: Vec<Vec<u32>>,
+    descriptions: HashMap<u32, PCSTR>,
 }
 
 impl MenuBuilder {
-    fn build(items: &[MenuItem]) -> Result<(HMENU, Vec<u32>, Vec<Vec<u32>>)> {
+    #[allow(clippy::type_complexity)]
+    fn build(items: &[MenuItem]) -> Result<(HMENU, Vec<u32>, Vec<Vec<u32>>, HashMap<u32, PCSTR>)> {
         let mut builder = Self {
             menu: HMENU::new_popup()?,
             checkboxes: Vec::new(),
             radio_groups: Vec::new(),
+            descriptions: HashMap::new(),
         };
 
         for item in items {
             builder.build_item(item, builder.menu)?;
         }
 
-        Ok((builder.menu, builder.checkboxes, builder.radio_groups))
+        Ok((
+            builder.menu,
+            builder.checkboxes,
+            builder.radio_groups,
+            builder.descriptions,
+        ))
     }
 
     fn build_item(&mut self, item: &MenuItem, menu: HMENU) -> Result<()> {
         match item {
             MenuItem::Action { id, text } => menu.append_action(*id, *text)?,
+            MenuItem::Sliders { text } => menu.append_action(CMD_SLIDERS, *text)?,
+            MenuItem::Status { text } => menu.append_action(CMD_STATUS, *text)?,
             MenuItem::CheckBox { id, text, checked } => {
                 menu.append_checkbox(*id, *text, *checked)?;
                 self.checkboxes.push(*id);
             }
-            MenuItem::Radio { id, text, checked } => {
+            MenuItem::Radio {
+                id,
+                text,
+                checked,
+                description,
+            } => {
                 menu.append_radio(*id, *text, *checked)?;
+                if let Some(description) = description {
+                    self.descriptions.insert(*id, *description);
+                }
 
                 if let Some(group) = self.radio_groups.last_mut() {
                     group.push(*id);
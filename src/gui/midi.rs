@@ -0,0 +1,109 @@
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+use windows::Win32::Media::{
+    Audio::{
+        midiInClose, midiInGetNumDevs, midiInOpen, midiInReset, midiInStart, midiInStop,
+        CALLBACK_FUNCTION, HMIDIIN,
+    },
+    MMSYSERR_NOERROR, MM_MIM_DATA,
+};
+
+/// A note or controller, by number, on any channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiEvent {
+    Note(u8),
+    Control(u8),
+}
+
+/// Listens to every MIDI input, such as a Stream Deck MIDI plugin or a pad controller,
+/// for notes pressed and controllers turned on.
+pub struct MidiInput {
+    inputs: Vec<HMIDIIN>,
+    // Boxed so the address handed to the driver callback stays put.
+    events: Box<Mutex<Vec<MidiEvent>>>,
+}
+
+impl MidiInput {
+    pub fn new() -> Result<Self> {
+        let events = Box::new(Mutex::new(vec![]));
+        let mut inputs = vec![];
+
+        for id in 0..unsafe { midiInGetNumDevs() } {
+            let mut input = HMIDIIN::default();
+            let result = unsafe {
+                midiInOpen(
+                    &mut input,
+                    id,
+                    on_midi as *const () as usize,
+                    &*events as *const Mutex<_> as usize,
+                    CALLBACK_FUNCTION,
+                )
+            };
+            // Skips devices another app holds.
+            if result != MMSYSERR_NOERROR {
+                continue;
+            }
+            unsafe { midiInStart(input) };
+            inputs.push(input);
+        }
+
+        if inputs.is_empty() {
+            bail!("no MIDI input is available");
+        }
+
+        Ok(Self { inputs, events })
+    }
+
+    /// Events received since the last poll.
+    pub fn poll(&self) -> Vec<MidiEvent> {
+        self.events
+            .lock()
+            .map(|mut events| std::mem::take(&mut *events))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for MidiInput {
+    fn drop(&mut self) {
+        for &input in &self.inputs {
+            unsafe {
+                midiInStop(input);
+                midiInReset(input);
+                midiInClose(input);
+            }
+        }
+    }
+}
+
+/// Parses a binding such as "note 36" or "cc 20".
+pub fn parse_binding(binding: &str) -> Option<MidiEvent> {
+    let (kind, number) = binding.trim().split_once(char::is_whitespace)?;
+    let number = number.trim().parse().ok().filter(|&n: &u8| n < 0x80)?;
+
+    match kind.to_ascii_lowercase().as_str() {
+        "note" => Some(MidiEvent::Note(number)),
+        "cc" => Some(MidiEvent::Control(number)),
+        _ => None,
+    }
+}
+
+/// Called by the driver on its own thread with short messages packed as status, data 1 and data 2.
+extern "system" fn on_midi(_input: HMIDIIN, msg: u32, instance: usize, param1: usize, _: usize) {
+    if msg != MM_MIM_DATA {
+        return;
+    }
+
+    let [status, number, value, _] = (param1 as u32).to_le_bytes();
+    // Note off is often sent as a note on with zero velocity, and buttons send 0 on release.
+    let event = match status & 0xf0 {
+        0x90 if value > 0 => MidiEvent::Note(number),
+        0xb0 if value > 0 => MidiEvent::Control(number),
+        _ => return,
+    };
+
+    let events = unsafe { &*(instance as *const Mutex<Vec<MidiEvent>>) };
+    if let Ok(mut events) = events.lock() {
+        events.push(event);
+    }
+}
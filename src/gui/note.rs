@@ -0,0 +1,206 @@
+use anyhow::Result;
+use windows::{
+    core::{s, w, PCSTR, PCWSTR},
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{GetStockObject, GetSysColorBrush, COLOR_BTNFACE, DEFAULT_GUI_FONT},
+        UI::{
+            Controls::EM_SETCUEBANNER,
+            Input::KeyboardAndMouse::{SetFocus, VK_ESCAPE, VK_RETURN},
+            Shell::{DefSubclassProc, SetWindowSubclass},
+            WindowsAndMessaging::*,
+        },
+    },
+};
+
+use super::{
+    utils::{self, CStr as _, Hwnd as _, Rect as _},
+    window::WindowClass,
+};
+
+/// Sent to the owner when the note is entered, with a `*const String` in lParam.
+/// An empty note means the dialog was dismissed.
+pub const WM_NOTE: u32 = WM_APP + 3;
+
+const CLASS_NAME: PCSTR = s!("livesub.note");
+const WIDTH: i32 = 360;
+const MARGIN: i32 = 10;
+const EDIT_HEIGHT: i32 = 22;
+
+/// A one-line prompt for a quick note, submitted with Enter and skipped with Escape.
+pub struct NoteDialog {
+    hwnd: HWND,
+}
+
+struct State {
+    owner: HWND,
+    edit: HWND,
+    sent: bool,
+}
+
+impl NoteDialog {
+    pub fn register() -> Result<()> {
+        WNDCLASSEXA::new()
+            .set_wndproc(wndproc)
+            .set_cursor(utils::load_cursor(Some(IDC_ARROW)))
+            .set_brush(unsafe { GetSysColorBrush(COLOR_BTNFACE) })
+            .set_name(CLASS_NAME)
+            .register()
+    }
+
+    /// Opens the prompt under the owner window.
+    pub fn show(owner: HWND, title: PCSTR, cue: &str) -> Result<Self> {
+        let dpi = owner.dpi() as i32;
+        let scale = |value: i32| value * dpi / 96;
+
+        let mut frame = RECT::new(0, 0, scale(WIDTH), scale(EDIT_HEIGHT + 2 * MARGIN));
+        unsafe {
+            AdjustWindowRectEx(
+                &mut frame,
+                WS_POPUP | WS_CAPTION | WS_SYSMENU,
+                FALSE,
+                WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+            )?
+        };
+        let owner_rect = owner.rect();
+        let rect = utils::clamp_to_work_area(RECT::new(
+            owner_rect.x(),
+            owner_rect.bottom,
+            frame.width(),
+            frame.height(),
+        ));
+
+        let hwnd = HWND::create(
+            WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+            CLASS_NAME,
+            title,
+            WS_POPUP | WS_CAPTION | WS_SYSMENU,
+            rect.x(),
+            rect.y(),
+            rect.width(),
+            rect.height(),
+            owner,
+            None,
+            None,
+        )?;
+
+        let edit = unsafe {
+            CreateWindowExW(
+                WS_EX_CLIENTEDGE,
+                w!("EDIT"),
+                PCWSTR::null(),
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as _),
+                scale(MARGIN),
+                scale(MARGIN),
+                scale(WIDTH - 2 * MARGIN),
+                scale(EDIT_HEIGHT),
+                hwnd,
+                None,
+                None,
+                None,
+            )?
+        };
+        let font = WPARAM(unsafe { GetStockObject(DEFAULT_GUI_FONT) }.0 as _);
+        edit.send_message(WM_SETFONT, font, LPARAM(0));
+        let cue = cue.c_wstr();
+        unsafe {
+            SendMessageW(edit, EM_SETCUEBANNER, WPARAM(1), LPARAM(cue.as_ptr() as _));
+            _ = SetWindowSubclass(edit, Some(edit_proc), 0, 0);
+        }
+
+        let state = Box::new(State {
+            owner,
+            edit,
+            sent: false,
+        });
+        hwnd.set_user_data(Box::into_raw(state) as _);
+        hwnd.show(SW_SHOW);
+        hwnd.set_foreground();
+        unsafe { _ = SetFocus(edit) };
+
+        Ok(Self { hwnd })
+    }
+
+    pub fn is_open(&self) -> bool {
+        unsafe { IsWindow(self.hwnd).as_bool() }
+    }
+
+    pub fn activate(&self) {
+        self.hwnd.set_foreground();
+    }
+}
+
+impl State {
+    /// Hands the note to the owner, once.
+    fn send(&mut self, note: &str) {
+        if self.sent {
+            return;
+        }
+        self.sent = true;
+
+        let note = note.trim().to_string();
+        self.owner
+            .send_message(WM_NOTE, WPARAM(0), LPARAM(&note as *const String as _));
+    }
+
+    fn text(&self) -> String {
+        let len = unsafe { GetWindowTextLengthW(self.edit) } as usize;
+        let mut text = vec![0; len + 1];
+        let len = unsafe { GetWindowTextW(self.edit, &mut text) } as usize;
+        String::from_utf16_lossy(&text[..len])
+    }
+}
+
+unsafe extern "system" fn wndproc(hwnd: HWND, msg: u32, wp: WPARAM, lp: LPARAM) -> LRESULT {
+    let state = hwnd.user_data() as *mut State;
+
+    match msg {
+        WM_COMMAND if lp.0 == 0 => {
+            if let Some(state) = state.as_mut() {
+                let note = match MESSAGEBOX_RESULT((wp.0 & 0xffff) as _) {
+                    IDOK => state.text(),
+                    _ => String::new(),
+                };
+                state.send(&note);
+            }
+            hwnd.destroy();
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if !state.is_null() {
+                (*state).send("");
+                hwnd.set_user_data(0);
+                drop(Box::from_raw(state));
+            }
+            LRESULT(0)
+        }
+        _ => hwnd.def_proc(msg, wp, lp),
+    }
+}
+
+/// Turns Enter and Escape in the edit into OK and Cancel.
+unsafe extern "system" fn edit_proc(
+    hwnd: HWND,
+    msg: u32,
+    wp: WPARAM,
+    lp: LPARAM,
+    _id: usize,
+    _data: usize,
+) -> LRESULT {
+    let command = match (msg, wp.0) {
+        (WM_KEYDOWN, key) if key == VK_RETURN.0 as usize => Some(IDOK),
+        (WM_KEYDOWN, key) if key == VK_ESCAPE.0 as usize => Some(IDCANCEL),
+        // Swallows the characters of those keys, which the edit control would beep at.
+        (WM_CHAR, 0x0d | 0x1b) => return LRESULT(0),
+        _ => None,
+    };
+
+    match command {
+        Some(command) => {
+            hwnd.parent()
+                .post_message(WM_COMMAND, WPARAM(command.0 as _), LPARAM(0));
+            LRESULT(0)
+        }
+        None => DefSubclassProc(hwnd, msg, wp, lp),
+    }
+}
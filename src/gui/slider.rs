@@ -0,0 +1,213 @@
+use anyhow::Result;
+use windows::{
+    core::{s, PCSTR},
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{GetStockObject, GetSysColorBrush, COLOR_BTNFACE, DEFAULT_GUI_FONT},
+        UI::{
+            Controls::{
+                InitCommonControlsEx, ICC_BAR_CLASSES, INITCOMMONCONTROLSEX, TBM_SETTIC,
+                TB_ENDTRACK, TB_THUMBTRACK, TRACKBAR_CLASSA,
+            },
+            WindowsAndMessaging::*,
+        },
+    },
+};
+
+use super::{
+    app::Slider,
+    utils::{self, Hwnd as _, Rect as _},
+    window::WindowClass,
+};
+
+/// Posted to the owner when a slider moves, with the slider id in `wparam` and the value in `lparam`.
+pub const WM_SLIDER: u32 = WM_APP + 2;
+
+const CLASS_NAME: PCSTR = s!("livesub.sliders");
+const WIDTH: i32 = 280;
+const MARGIN: i32 = 12;
+const LABEL_HEIGHT: i32 = 20;
+const TRACKBAR_HEIGHT: i32 = 32;
+// Positions within this share of the range snap to the nearest preset, or within half the
+// gap to its neighbor where presets are closer together.
+const SNAP_RATIO: f32 = 0.03;
+
+pub struct SliderPanel {
+    owner: HWND,
+    sliders: Vec<Slider>,
+    labels: Vec<HWND>,
+    trackbars: Vec<HWND>,
+}
+
+impl SliderPanel {
+    pub fn register() -> Result<()> {
+        unsafe {
+            _ = InitCommonControlsEx(&INITCOMMONCONTROLSEX {
+                dwSize: std::mem::size_of::<INITCOMMONCONTROLSEX>() as _,
+                dwICC: ICC_BAR_CLASSES,
+            });
+        }
+
+        WNDCLASSEXA::new()
+            .set_wndproc(wndproc)
+            .set_cursor(utils::load_cursor(Some(IDC_ARROW)))
+            .set_brush(unsafe { GetSysColorBrush(COLOR_BTNFACE) })
+            .set_name(CLASS_NAME)
+            .register()
+    }
+
+    /// Opens the panel at `(x, y)` in screen coordinates. It closes itself once it loses focus.
+    pub fn show(owner: HWND, x: i32, y: i32, sliders: Vec<Slider>) -> Result<()> {
+        let dpi = owner.dpi() as i32;
+        let scale = |value: i32| value * dpi / 96;
+
+        let row = LABEL_HEIGHT + TRACKBAR_HEIGHT;
+        let height = 2 * MARGIN + row * sliders.len() as i32;
+        let rect = utils::clamp_to_work_area(RECT::new(x, y, scale(WIDTH), scale(height)));
+
+        let hwnd = HWND::create(
+            WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+            CLASS_NAME,
+            s!("livesub"),
+            WS_POPUP | WS_BORDER,
+            rect.x(),
+            rect.y(),
+            rect.width(),
+            rect.height(),
+            owner,
+            None,
+            None,
+        )?;
+
+        let font = WPARAM(unsafe { GetStockObject(DEFAULT_GUI_FONT) }.0 as _);
+        let mut panel = Box::new(Self {
+            owner,
+            sliders,
+            labels: vec![],
+            trackbars: vec![],
+        });
+
+        for (i, slider) in panel.sliders.iter().enumerate() {
+            let top = MARGIN + row * i as i32;
+
+            let label = HWND::create(
+                WINDOW_EX_STYLE(0),
+                s!("STATIC"),
+                PCSTR::null(),
+                WS_CHILD | WS_VISIBLE,
+                scale(MARGIN),
+                scale(top),
+                scale(WIDTH - 2 * MARGIN),
+                scale(LABEL_HEIGHT),
+                hwnd,
+                None,
+                None,
+            )?;
+            label.send_message(WM_SETFONT, font, LPARAM(0));
+            label.set_text(&(slider.text)(slider.value));
+
+            let trackbar = HWND::create(
+                WINDOW_EX_STYLE(0),
+                TRACKBAR_CLASSA,
+                PCSTR::null(),
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP,
+                scale(MARGIN),
+                scale(top + LABEL_HEIGHT),
+                scale(WIDTH - 2 * MARGIN),
+                scale(TRACKBAR_HEIGHT),
+                hwnd,
+                None,
+                None,
+            )?;
+            trackbar.trackbar_set_min_max(slider.min, slider.max);
+            for &snap in &slider.snaps {
+                trackbar.send_message(TBM_SETTIC, WPARAM(0), LPARAM(snap as _));
+            }
+            trackbar.trackbar_set_pos(slider.value);
+
+            panel.labels.push(label);
+            panel.trackbars.push(trackbar);
+        }
+
+        hwnd.set_user_data(Box::into_raw(panel) as _);
+        hwnd.show(SW_SHOW);
+        hwnd.set_foreground();
+
+        Ok(())
+    }
+
+    /// Handles a trackbar notification with its scroll `code`. The thumb snaps and the owner
+    /// hears of the value once it's released, so dragging doesn't apply every step on the way.
+    fn on_scroll(&mut self, trackbar: HWND, code: u32) {
+        let Some(i) = self.trackbars.iter().position(|&hwnd| hwnd == trackbar) else {
+            return;
+        };
+        let slider = &mut self.sliders[i];
+
+        let mut value = trackbar.trackbar_pos();
+        let released = code == TB_ENDTRACK;
+        if released {
+            value = (slider.clamp)(snap(slider, value));
+            trackbar.trackbar_set_pos(value);
+        } else if code != TB_THUMBTRACK {
+            // Keys and clicks on the channel are followed by TB_ENDTRACK.
+            return;
+        }
+        self.labels[i].set_text(&(slider.text)(value));
+
+        if (released || slider.live) && value != slider.value {
+            slider.value = value;
+            self.owner
+                .post_message(WM_SLIDER, WPARAM(slider.id as _), LPARAM(value as _));
+        }
+    }
+}
+
+fn snap(slider: &Slider, value: i32) -> i32 {
+    let range = ((slider.max - slider.min) as f32 * SNAP_RATIO) as i32;
+    let Some(&nearest) = slider
+        .snaps
+        .iter()
+        .min_by_key(|&&snap| (snap - value).abs())
+    else {
+        return value;
+    };
+    let gap = slider
+        .snaps
+        .iter()
+        .filter(|&&snap| snap != nearest)
+        .map(|&snap| (snap - nearest).abs())
+        .min()
+        .unwrap_or(i32::MAX);
+
+    if (nearest - value).abs() <= range.min(gap / 2) {
+        nearest
+    } else {
+        value
+    }
+}
+
+unsafe extern "system" fn wndproc(hwnd: HWND, msg: u32, wp: WPARAM, lp: LPARAM) -> LRESULT {
+    let panel = hwnd.user_data() as *mut SliderPanel;
+
+    match msg {
+        WM_HSCROLL => {
+            if let Some(panel) = panel.as_mut() {
+                panel.on_scroll(HWND::from_lparam(lp), (wp.0 & 0xffff) as u32);
+            }
+            LRESULT(0)
+        }
+        WM_ACTIVATE if wp.0 & 0xffff == WA_INACTIVE as usize => {
+            hwnd.post_message(WM_CLOSE, WPARAM(0), LPARAM(0));
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if !panel.is_null() {
+                hwnd.set_user_data(0);
+                drop(Box::from_raw(panel));
+            }
+            LRESULT(0)
+        }
+        _ => hwnd.def_proc(msg, wp, lp),
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use windows::{
+    core::{s, HSTRING, PCSTR},
+    Win32::{
+        Foundation::*,
+        Graphics::Gdi::{GetStockObject, GetSysColorBrush, COLOR_BTNFACE, DEFAULT_GUI_FONT},
+        UI::{Controls::EM_SETSEL, WindowsAndMessaging::*},
+    },
+};
+
+use super::{
+    utils::{self, Hwnd as _, Rect as _},
+    window::WindowClass,
+};
+
+const CLASS_NAME: PCSTR = s!("livesub.status");
+const WIDTH: i32 = 420;
+const HEIGHT: i32 = 260;
+const MARGIN: i32 = 12;
+const BUTTON_WIDTH: i32 = 80;
+const BUTTON_HEIGHT: i32 = 26;
+
+const ID_COPY: usize = 1;
+const ID_CLOSE: usize = 2;
+
+/// A read-only report of the engine's state that can be copied into bug reports.
+pub struct StatusDialog {
+    edit: HWND,
+}
+
+impl StatusDialog {
+    pub fn register() -> Result<()> {
+        WNDCLASSEXA::new()
+            .set_wndproc(wndproc)
+            .set_cursor(utils::load_cursor(Some(IDC_ARROW)))
+            .set_brush(unsafe { GetSysColorBrush(COLOR_BTNFACE) })
+            .set_name(CLASS_NAME)
+            .register()
+    }
+
+    /// Opens the dialog at `(x, y)` in screen coordinates.
+    pub fn show(owner: HWND, x: i32, y: i32, report: &str) -> Result<()> {
+        let dpi = owner.dpi() as i32;
+        let scale = |value: i32| value * dpi / 96;
+
+        let rect = utils::clamp_to_work_area(RECT::new(x, y, scale(WIDTH), scale(HEIGHT)));
+        let hwnd = HWND::create(
+            WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+            CLASS_NAME,
+            s!("livesub Status"),
+            WS_POPUP | WS_CAPTION | WS_SYSMENU,
+            rect.x(),
+            rect.y(),
+            rect.width(),
+            rect.height(),
+            owner,
+            None,
+            None,
+        )?;
+
+        let mut client = RECT::default();
+        unsafe { GetClientRect(hwnd, &mut client)? };
+        let (width, height) = (client.width(), client.height());
+        let button_top = height - scale(MARGIN + BUTTON_HEIGHT);
+
+        let font = WPARAM(unsafe { GetStockObject(DEFAULT_GUI_FONT) }.0 as _);
+        let edit = HWND::create(
+            WS_EX_CLIENTEDGE,
+            s!("EDIT"),
+            PCSTR::null(),
+            WS_CHILD
+                | WS_VISIBLE
+                | WS_VSCROLL
+                | WINDOW_STYLE((ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL) as _),
+            scale(MARGIN),
+            scale(MARGIN),
+            width - 2 * scale(MARGIN),
+            button_top - 2 * scale(MARGIN),
+            hwnd,
+            None,
+            None,
+        )?;
+        edit.send_message(WM_SETFONT, font, LPARAM(0));
+        unsafe { SetWindowTextW(edit, &HSTRING::from(report.replace('\n', "\r\n")))? };
+
+        for (i, (id, text, style)) in [
+            (ID_COPY, s!("Copy"), BS_PUSHBUTTON),
+            (ID_CLOSE, s!("Close"), BS_DEFPUSHBUTTON),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let right =
+                width - scale(MARGIN) - (scale(BUTTON_WIDTH) + scale(MARGIN)) * (1 - i as i32);
+            let button = HWND::create(
+                WINDOW_EX_STYLE(0),
+                s!("BUTTON"),
+                text,
+                WS_CHILD | WS_VISIBLE | WS_TABSTOP | WINDOW_STYLE(style as _),
+                right - scale(BUTTON_WIDTH),
+                button_top,
+                scale(BUTTON_WIDTH),
+                scale(BUTTON_HEIGHT),
+                hwnd,
+                HMENU(id as _),
+                None,
+            )?;
+            button.send_message(WM_SETFONT, font, LPARAM(0));
+        }
+
+        hwnd.set_user_data(Box::into_raw(Box::new(Self { edit })) as _);
+        hwnd.show(SW_SHOW);
+        hwnd.set_foreground();
+
+        Ok(())
+    }
+
+    fn copy(&self) {
+        self.edit.send_message(EM_SETSEL, WPARAM(0), LPARAM(-1));
+        self.edit.send_message(WM_COPY, WPARAM(0), LPARAM(0));
+    }
+}
+
+unsafe extern "system" fn wndproc(hwnd: HWND, msg: u32, wp: WPARAM, lp: LPARAM) -> LRESULT {
+    let dialog = hwnd.user_data() as *mut StatusDialog;
+
+    match msg {
+        WM_COMMAND => {
+            match wp.0 & 0xffff {
+                ID_COPY => {
+                    if let Some(dialog) = dialog.as_ref() {
+                        dialog.copy();
+                    }
+                }
+                ID_CLOSE => hwnd.post_message(WM_CLOSE, WPARAM(0), LPARAM(0)),
+                _ => {}
+            }
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if !dialog.is_null() {
+                hwnd.set_user_data(0);
+                drop(Box::from_raw(dialog));
+            }
+            LRESULT(0)
+        }
+        _ => hwnd.def_proc(msg, wp, lp),
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::HWND,
+    UI::{
+        Shell::{
+            Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NOTIFYICONDATAW,
+        },
+        WindowsAndMessaging::WM_APP,
+    },
+};
+use windows_core::PCWSTR;
+
+use super::utils;
+
+pub const WM_TRAY: u32 = WM_APP + 1;
+
+const TRAY_ID: u32 = 1;
+
+pub struct TrayIcon {
+    hwnd: HWND,
+}
+
+impl TrayIcon {
+    pub fn new(hwnd: HWND) -> Result<Self> {
+        let tray = Self { hwnd };
+        tray.add()?;
+        Ok(tray)
+    }
+
+    /// Adds the icon again, e.g. after Explorer restarted and dropped it.
+    pub fn add(&self) -> Result<()> {
+        let mut data = self.data();
+        data.uFlags = NIF_MESSAGE | NIF_ICON | NIF_TIP;
+        data.uCallbackMessage = WM_TRAY;
+        data.hIcon = utils::load_icon(Some(PCWSTR(1 as _)));
+        for (dst, src) in data.szTip.iter_mut().zip("livesub".encode_utf16()) {
+            *dst = src;
+        }
+
+        unsafe { Shell_NotifyIconW(NIM_ADD, &data) }.ok()?;
+        Ok(())
+    }
+
+    fn data(&self) -> NOTIFYICONDATAW {
+        NOTIFYICONDATAW {
+            cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as _,
+            hWnd: self.hwnd,
+            uID: TRAY_ID,
+            ..Default::default()
+        }
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            _ = Shell_NotifyIconW(NIM_DELETE, &self.data());
+        }
+    }
+}
@@ -1,8 +1,10 @@
 #![allow(unused, non_snake_case, clippy::too_many_arguments)]
 
+use std::path::{Path, PathBuf};
+
 use anyhow::{Error as E, Result};
 use windows::{
-    core::{s, Param, PCSTR, PCWSTR},
+    core::{s, Param, PCSTR, PCWSTR, PWSTR},
     Win32::{
         Foundation::*,
         Graphics::{
@@ -11,14 +13,37 @@ use windows::{
                 DwmGetWindowAttribute, DwmSetWindowAttribute, DWMWA_CAPTION_BUTTON_BOUNDS,
                 DWMWINDOWATTRIBUTE, DWM_BB_ENABLE, DWM_BLURBEHIND,
             },
-            Gdi::{UpdateWindow, ValidateRect},
+            Gdi::{
+                EnumDisplayMonitors, GetMonitorInfoA, MonitorFromRect, MonitorFromWindow,
+                UpdateWindow, ValidateRect, HDC, HMONITOR, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+            },
         },
-        System::LibraryLoader::{
-            GetModuleHandleA, GetProcAddress, LoadLibraryExA, LOAD_LIBRARY_SEARCH_SYSTEM32,
+        System::{
+            LibraryLoader::{
+                GetModuleHandleA, GetProcAddress, LoadLibraryExA, LOAD_LIBRARY_SEARCH_SYSTEM32,
+            },
+            ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS_EX},
+            Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_DWORD},
+            SystemInformation::GetLocalTime,
+            Threading::{
+                CreateMutexW, GetCurrentProcess, OpenProcess, QueryFullProcessImageNameW,
+                PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+            },
         },
         UI::{
-            Controls::*,
+            Controls::{
+                Dialogs::{
+                    GetOpenFileNameW, GetSaveFileNameW, OFN_FILEMUSTEXIST, OFN_NOCHANGEDIR,
+                    OFN_OVERWRITEPROMPT, OFN_PATHMUSTEXIST, OPENFILENAMEW, OPEN_FILENAME_FLAGS,
+                },
+                *,
+            },
             HiDpi::{AdjustWindowRectExForDpi, GetDpiForWindow},
+            Input::{
+                KeyboardAndMouse::{TrackMouseEvent, TME_LEAVE, TME_NONCLIENT, TRACKMOUSEEVENT},
+                Pointer::GetPointerType,
+            },
+            Shell::{SHQueryUserNotificationState, QUNS_RUNNING_D3D_FULL_SCREEN},
             WindowsAndMessaging::*,
         },
     },
@@ -340,6 +365,12 @@ pub trait Hwnd: Copy + Into<HWND> {
         }
     }
 
+    fn set_text(self, text: &str) {
+        unsafe {
+            _ = SetWindowTextA(self.into(), PCSTR(text.c_str().as_ptr()));
+        }
+    }
+
     fn user_data(self) -> isize {
         unsafe { GetWindowLongPtrA(self.into(), GWLP_USERDATA) }
     }
@@ -395,6 +426,60 @@ pub trait Hwnd: Copy + Into<HWND> {
         unsafe { DefWindowProcA(self.into(), msg, wp, lp) }
     }
 
+    fn set_foreground(self) {
+        unsafe {
+            _ = SetForegroundWindow(self.into());
+        }
+    }
+
+    /// Moves the window just below any visible topmost window of the given classes
+    /// that overlaps it, staying in the topmost band. `yielded` holds the windows and rects
+    /// yielded to last time, so nothing is moved again until they change.
+    fn yield_to(self, classes: &[PCSTR], yielded: &mut Vec<(HWND, RECT)>) {
+        let rect = self.rect();
+        let mut targets = vec![];
+
+        for &class in classes {
+            let mut other = HWND::default();
+            while let Ok(next) = unsafe { FindWindowExA(None, other, class, PCSTR::null()) } {
+                if next.is_invalid() {
+                    break;
+                }
+                other = next;
+
+                let visible = unsafe { IsWindowVisible(other) }.as_bool();
+                let topmost = other.ex_style().contains(WS_EX_TOPMOST);
+                let r = other.rect();
+                let overlaps = r.left < rect.right
+                    && rect.left < r.right
+                    && r.top < rect.bottom
+                    && rect.top < r.bottom;
+
+                if visible && topmost && overlaps {
+                    targets.push((other, r));
+                }
+            }
+        }
+
+        if targets == *yielded {
+            return;
+        }
+        for &(other, _) in &targets {
+            unsafe {
+                _ = SetWindowPos(
+                    self.into(),
+                    other,
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+                );
+            }
+        }
+        *yielded = targets;
+    }
+
     fn destroy(self) {
         unsafe {
             _ = DestroyWindow(self.into());
@@ -423,6 +508,40 @@ pub trait Hwnd: Copy + Into<HWND> {
         unsafe { SetTimer(self.into(), id, elapse, None) }
     }
 
+    fn work_area(self) -> RECT {
+        unsafe {
+            let monitor = MonitorFromWindow(self.into(), MONITOR_DEFAULTTONEAREST);
+            let mut info = MONITORINFO {
+                cbSize: std::mem::size_of::<MONITORINFO>() as _,
+                ..Default::default()
+            };
+            _ = GetMonitorInfoA(monitor, &mut info);
+            info.rcWork
+        }
+    }
+
+    fn kill_timer(self, id: usize) {
+        unsafe {
+            _ = KillTimer(self.into(), id);
+        }
+    }
+
+    fn track_mouse_leave(self, nonclient: bool) {
+        let mut flags = TME_LEAVE;
+        if nonclient {
+            flags |= TME_NONCLIENT;
+        }
+
+        unsafe {
+            _ = TrackMouseEvent(&mut TRACKMOUSEEVENT {
+                cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as _,
+                dwFlags: flags,
+                hwndTrack: self.into(),
+                dwHoverTime: 0,
+            });
+        }
+    }
+
     fn scroll_info(
         self,
         bar: SCROLLBAR_CONSTANTS,
@@ -628,6 +747,274 @@ pub fn cursor_pos() -> (i32, i32) {
     }
 }
 
+pub struct InstanceGuard(HANDLE);
+
+impl Drop for InstanceGuard {
+    fn drop(&mut self) {
+        unsafe {
+            _ = CloseHandle(self.0);
+        }
+    }
+}
+
+/// Returns None if another instance holds the named mutex.
+pub fn single_instance(name: &str) -> Option<InstanceGuard> {
+    let name = format!("Local\\{name}").c_wstr();
+    unsafe {
+        let mutex = CreateMutexW(None, true, PCWSTR(name.as_ptr())).ok()?;
+        if GetLastError() == ERROR_ALREADY_EXISTS {
+            _ = CloseHandle(mutex);
+            None
+        } else {
+            Some(InstanceGuard(mutex))
+        }
+    }
+}
+
+/// Returns a stable key identifying the current set of monitor rectangles.
+pub fn monitor_topology() -> String {
+    unsafe extern "system" fn enum_proc(
+        _monitor: HMONITOR,
+        _hdc: HDC,
+        rect: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let rects = &mut *(data.0 as *mut Vec<RECT>);
+        rects.push(*rect);
+        TRUE
+    }
+
+    let mut rects: Vec<RECT> = vec![];
+    unsafe {
+        _ = EnumDisplayMonitors(
+            None,
+            None,
+            Some(enum_proc),
+            LPARAM(&mut rects as *mut _ as _),
+        );
+    }
+    rects.sort_by_key(|r| (r.left, r.top, r.right, r.bottom));
+
+    // FNV-1a, so that keys stay the same across builds.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for r in &rects {
+        for v in [r.left, r.top, r.right, r.bottom] {
+            for b in v.to_le_bytes() {
+                hash ^= b as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+
+    format!("{hash:016x}")
+}
+
+pub fn clamp_to_work_area(rect: RECT) -> RECT {
+    let work = unsafe {
+        let monitor = MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as _,
+            ..Default::default()
+        };
+        _ = GetMonitorInfoA(monitor, &mut info);
+        info.rcWork
+    };
+
+    let width = rect.width().min(work.width());
+    let height = rect.height().min(work.height());
+    let x = rect.x().clamp(work.left, work.right - width);
+    let y = rect.y().clamp(work.top, work.bottom - height);
+
+    RECT::new(x, y, width, height)
+}
+
+pub fn pointer_type(pointer_id: u32) -> POINTER_INPUT_TYPE {
+    let mut pointer_type = POINTER_INPUT_TYPE::default();
+    unsafe {
+        _ = GetPointerType(pointer_id, &mut pointer_type);
+    }
+    pointer_type
+}
+
+pub fn is_fullscreen_exclusive() -> bool {
+    unsafe { SHQueryUserNotificationState() == Ok(QUNS_RUNNING_D3D_FULL_SCREEN) }
+}
+
+/// Whether the Windows Live Captions window is open and visible.
+pub fn is_live_captions_visible() -> bool {
+    unsafe {
+        FindWindowA(s!("LiveCaptionsDesktopWindow"), PCSTR::null())
+            .is_ok_and(|hwnd| IsWindowVisible(hwnd).as_bool())
+    }
+}
+
+/// The foreground app's executable name, looked up again only when another window
+/// comes forward.
+#[derive(Default)]
+pub struct ForegroundProcess {
+    hwnd: HWND,
+    name: Option<String>,
+}
+
+impl ForegroundProcess {
+    pub fn name(&mut self) -> Option<String> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        if hwnd != self.hwnd {
+            let mut pid = 0;
+            unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+            self.hwnd = hwnd;
+            self.name = process_name(pid);
+        }
+        self.name.clone()
+    }
+}
+
+/// File name of the process's executable, such as "game.exe".
+pub fn process_name(pid: u32) -> Option<String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid).ok()?;
+        let mut buf = [0u16; MAX_PATH as usize];
+        let mut len = buf.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buf.as_mut_ptr()),
+            &mut len,
+        );
+        _ = CloseHandle(process);
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&buf[..len as usize]);
+        path.rsplit('\\').next().map(str::to_string)
+    }
+}
+
+/// Whether apps should use a dark theme, per the personalization settings.
+pub fn is_dark_mode() -> bool {
+    let mut value = 1u32;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let err = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            windows::core::w!("Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize"),
+            windows::core::w!("AppsUseLightTheme"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut value as *mut u32 as _),
+            Some(&mut size),
+        )
+    };
+
+    err.is_ok() && value == 0
+}
+
+/// Working set and private bytes of this process.
+pub fn process_memory() -> Option<(usize, usize)> {
+    let mut counters = PROCESS_MEMORY_COUNTERS_EX {
+        cb: std::mem::size_of::<PROCESS_MEMORY_COUNTERS_EX>() as _,
+        ..Default::default()
+    };
+    unsafe {
+        GetProcessMemoryInfo(
+            GetCurrentProcess(),
+            &mut counters as *mut _ as _,
+            counters.cb,
+        )
+        .ok()?;
+    }
+
+    Some((counters.WorkingSetSize, counters.PrivateUsage))
+}
+
+/// Local date and time as `YYYY-MM-DD HH:MM`.
+pub fn local_date_time() -> String {
+    let time = unsafe { GetLocalTime() };
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        time.wYear, time.wMonth, time.wDay, time.wHour, time.wMinute
+    )
+}
+
+/// Local date and time as `YYYY-MM-DD HH:MM:SS`.
+pub fn local_timestamp() -> String {
+    let time = unsafe { GetLocalTime() };
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+        time.wYear, time.wMonth, time.wDay, time.wHour, time.wMinute, time.wSecond
+    )
+}
+
+/// Day of the week, 0 being Sunday, and minutes since midnight in local time.
+pub fn local_time() -> (u32, u32) {
+    let time = unsafe { GetLocalTime() };
+    (
+        time.wDayOfWeek as u32,
+        time.wHour as u32 * 60 + time.wMinute as u32,
+    )
+}
+
+/// Asks for an existing file with `extension`, starting in `dir`.
+pub fn open_file_dialog(owner: HWND, extension: &str, dir: &Path) -> Option<PathBuf> {
+    file_dialog(
+        owner,
+        extension,
+        dir,
+        "",
+        OFN_FILEMUSTEXIST | OFN_PATHMUSTEXIST,
+        |ofn| unsafe { GetOpenFileNameW(ofn) },
+    )
+}
+
+/// Asks where to save a file with `extension`, suggesting `name` in `dir`.
+pub fn save_file_dialog(owner: HWND, extension: &str, dir: &Path, name: &str) -> Option<PathBuf> {
+    file_dialog(
+        owner,
+        extension,
+        dir,
+        name,
+        OFN_OVERWRITEPROMPT | OFN_PATHMUSTEXIST,
+        |ofn| unsafe { GetSaveFileNameW(ofn) },
+    )
+}
+
+fn file_dialog(
+    owner: HWND,
+    extension: &str,
+    dir: &Path,
+    name: &str,
+    flags: OPEN_FILENAME_FLAGS,
+    show: impl FnOnce(*mut OPENFILENAMEW) -> BOOL,
+) -> Option<PathBuf> {
+    let filter = format!("*.{extension}\0*.{extension}\0\0").c_wstr();
+    let dir = dir.to_string_lossy().into_owned().c_wstr();
+    let extension = extension.c_wstr();
+    let mut file = [0u16; MAX_PATH as usize];
+    for (dst, src) in file[..MAX_PATH as usize - 1]
+        .iter_mut()
+        .zip(name.encode_utf16())
+    {
+        *dst = src;
+    }
+
+    let mut ofn = OPENFILENAMEW {
+        lStructSize: std::mem::size_of::<OPENFILENAMEW>() as _,
+        hwndOwner: owner,
+        lpstrFilter: PCWSTR(filter.as_ptr()),
+        lpstrFile: PWSTR(file.as_mut_ptr()),
+        nMaxFile: file.len() as _,
+        lpstrInitialDir: PCWSTR(dir.as_ptr()),
+        lpstrDefExt: PCWSTR(extension.as_ptr()),
+        Flags: flags | OFN_NOCHANGEDIR,
+        ..Default::default()
+    };
+    if !show(&mut ofn).as_bool() {
+        return None;
+    }
+
+    let len = file.iter().position(|&c| c == 0).unwrap_or(file.len());
+    Some(PathBuf::from(String::from_utf16_lossy(&file[..len])))
+}
+
 pub fn system_metrics(index: SYSTEM_METRICS_INDEX) -> i32 {
     unsafe { GetSystemMetrics(index) }
 }
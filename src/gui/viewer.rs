@@ -3,7 +3,13 @@ use windows::{
     core::{s, PCSTR, PCWSTR},
     Win32::{
         Foundation::*,
-        UI::{Input::KeyboardAndMouse::VK_ESCAPE, WindowsAndMessaging::*},
+        UI::{
+            Controls::{DRAWITEMSTRUCT, MEASUREITEMSTRUCT, WM_MOUSELEAVE},
+            Input::KeyboardAndMouse::{
+                GetKeyState, VIRTUAL_KEY, VK_C, VK_CONTROL, VK_ESCAPE, VK_SHIFT,
+            },
+            WindowsAndMessaging::*,
+        },
     },
 };
 
@@ -13,15 +19,46 @@ use crate::{
         utils::{self, Hwnd as _, Rect as _},
         window::{self, WindowClass},
     },
+    GET_X_LPARAM, GET_Y_LPARAM,
 };
 
-use super::{app::App, menu::ContextMenu, utils::Word, window::Window};
+use super::{
+    app::{App, CMD_SLIDERS, CMD_STATUS},
+    history::HistoryWindow,
+    menu::ContextMenu,
+    note::{NoteDialog, WM_NOTE},
+    slider::{SliderPanel, WM_SLIDER},
+    status::StatusDialog,
+    tray::WM_TRAY,
+    utils::Word,
+    window::Window,
+};
+
+const LONG_PRESS_TIMER_ID: usize = 0x100;
+const LONG_PRESS_DURATION: u32 = 600;
+const LONG_PRESS_SLOP: i32 = 10;
+const TOUCH_BORDER_SCALE: i32 = 3;
+
+// IME candidate lists, the volume flyout and UAC credential prompts stay above the overlay.
+const YIELD_CLASSES: [PCSTR; 4] = [
+    s!("Microsoft.IME.UIManager.CandidateWindow.Host"),
+    s!("CiceroUIWndFrame"),
+    s!("NativeHWNDHost"),
+    s!("Credential Dialog Xaml Host"),
+];
 
 pub struct Viewer<T: App> {
     hwnd: HWND,
     app: Option<T>,
     menu: ContextMenu,
     show_menu: bool,
+    touch: bool,
+    long_press: Option<(i32, i32)>,
+    hover: bool,
+    snap_distance: i32,
+    bottom_margin: Option<i32>,
+    taskbar_created: u32,
+    yielded: Vec<(HWND, RECT)>,
 }
 
 impl<T: App> Viewer<T> {
@@ -34,9 +71,21 @@ impl<T: App> Viewer<T> {
             .set_icon(utils::load_icon(Some(PCWSTR(1 as _))))
             .set_name(CLASS_NAME)
             .register()?;
+        SliderPanel::register()?;
+        StatusDialog::register()?;
+        HistoryWindow::register()?;
+        NoteDialog::register()?;
+
+        let mut ex_style = WS_EX_TOPMOST | WS_EX_NOREDIRECTIONBITMAP;
+        if config.no_activate {
+            ex_style |= WS_EX_NOACTIVATE;
+        }
+        if !config.show_in_taskbar {
+            ex_style |= WS_EX_TOOLWINDOW;
+        }
 
         let hwnd = HWND::create(
-            WS_EX_TOPMOST | WS_EX_NOREDIRECTIONBITMAP,
+            ex_style,
             CLASS_NAME,
             s!("livesub"),
             WS_POPUP | WS_THICKFRAME | WS_MINIMIZEBOX | WS_MAXIMIZEBOX,
@@ -65,12 +114,22 @@ impl<T: App> Window for Viewer<T> {
         let config = unsafe { Box::from_raw(cs.lpCreateParams as *mut Config) };
         let app = T::new(config.as_ref().clone(), hwnd)?;
         let menu = ContextMenu::new(hwnd, &app.menu_items())?;
+        let bottom_margin = config
+            .keep_bottom_margin
+            .then(|| hwnd.work_area().bottom - config.window_rect.bottom);
 
         Ok(Box::new(Self {
             hwnd,
             app: Some(app),
             menu,
             show_menu: false,
+            touch: false,
+            long_press: None,
+            hover: false,
+            snap_distance: config.snap_distance as _,
+            bottom_margin,
+            taskbar_created: unsafe { RegisterWindowMessageA(s!("TaskbarCreated")) },
+            yielded: vec![],
         }))
     }
 
@@ -84,6 +143,12 @@ impl<T: App> Window for Viewer<T> {
                 hwnd.destroy();
                 Some(LRESULT(0))
             }
+            WM_KEYDOWN if wp.0 == VK_C.0 as usize && key_down(VK_CONTROL) => {
+                if let Some(app) = &mut self.app {
+                    app.on_copy(key_down(VK_SHIFT));
+                }
+                Some(LRESULT(0))
+            }
             WM_DESTROY => {
                 if let Some(mut app) = self.app.take() {
                     app.on_close();
@@ -114,12 +179,67 @@ impl<T: App> Window for Viewer<T> {
                 self.hwnd.validate_rect(None);
                 Some(LRESULT(0))
             }
+            WM_TIMER if wp.0 == LONG_PRESS_TIMER_ID => {
+                hwnd.kill_timer(LONG_PRESS_TIMER_ID);
+
+                if let Some((x, y)) = self.long_press.take() {
+                    // Leave the move loop started by the touch before showing the menu.
+                    hwnd.send_message(WM_CANCELMODE, WPARAM(0), LPARAM(0));
+                    self.show_menu_at(x, y);
+                }
+
+                Some(LRESULT(0))
+            }
             WM_TIMER => {
+                hwnd.yield_to(&YIELD_CLASSES, &mut self.yielded);
                 if let Some(app) = &mut self.app {
                     app.on_timer();
                 }
                 Some(LRESULT(0))
             }
+            WM_POINTERDOWN | WM_NCPOINTERDOWN => {
+                let pointer_type = utils::pointer_type(wp.lo());
+                self.touch = pointer_type == PT_TOUCH || pointer_type == PT_PEN;
+
+                if self.touch {
+                    self.long_press = Some((GET_X_LPARAM!(lp), GET_Y_LPARAM!(lp)));
+                    hwnd.set_timer(LONG_PRESS_TIMER_ID, LONG_PRESS_DURATION);
+                }
+
+                None
+            }
+            WM_POINTERUPDATE | WM_NCPOINTERUPDATE => {
+                if let Some((x, y)) = self.long_press {
+                    let slop = LONG_PRESS_SLOP * hwnd.dpi() as i32 / 96;
+                    if (GET_X_LPARAM!(lp) - x).abs() > slop || (GET_Y_LPARAM!(lp) - y).abs() > slop
+                    {
+                        self.cancel_long_press();
+                    }
+                }
+
+                None
+            }
+            WM_POINTERUP | WM_NCPOINTERUP | WM_POINTERCAPTURECHANGED => {
+                self.cancel_long_press();
+                None
+            }
+            WM_MOUSEMOVE | WM_NCMOUSEMOVE => {
+                if !self.hover {
+                    self.hover = true;
+                    hwnd.track_mouse_leave(msg == WM_NCMOUSEMOVE);
+                    if let Some(app) = &mut self.app {
+                        app.on_hover(true);
+                    }
+                }
+                None
+            }
+            WM_MOUSELEAVE | WM_NCMOUSELEAVE => {
+                self.hover = false;
+                if let Some(app) = &mut self.app {
+                    app.on_hover(false);
+                }
+                None
+            }
             WM_DPICHANGED => {
                 let dpi = wp.hi();
                 if let Some(app) = &mut self.app {
@@ -131,19 +251,92 @@ impl<T: App> Window for Viewer<T> {
 
                 Some(LRESULT(0))
             }
+            WM_DISPLAYCHANGE => {
+                if let Some(app) = &mut self.app {
+                    app.on_display_changed();
+                }
+                None
+            }
+            WM_MOVING => {
+                let rect = unsafe { (lp.0 as *mut RECT).as_mut().unwrap() };
+                let distance = self.snap_distance * hwnd.dpi() as i32 / 96;
+                snap(rect, &hwnd.work_area(), distance);
+                Some(LRESULT(1))
+            }
+            WM_EXITSIZEMOVE => {
+                if self.bottom_margin.is_some() {
+                    self.bottom_margin = Some(hwnd.work_area().bottom - hwnd.rect().bottom);
+                }
+                None
+            }
+            WM_SETTINGCHANGE if wp.0 == SPI_SETWORKAREA.0 as usize => {
+                if let Some(margin) = self.bottom_margin {
+                    let rect = hwnd.rect();
+                    let y = hwnd.work_area().bottom - margin - rect.height();
+                    hwnd.set_pos(rect.x(), y, rect.width(), rect.height());
+                }
+                None
+            }
             WM_RBUTTONDOWN | WM_NCRBUTTONDOWN => {
                 self.show_menu = true;
                 Some(LRESULT(0))
             }
-            WM_CONTEXTMENU => {
-                self.show_menu = false;
-
-                if let Some((id, state)) = self.menu.show() {
-                    if let Some(app) = &mut self.app {
-                        app.on_menu(id, state);
+            WM_MEASUREITEM => {
+                let mis = unsafe { (lp.0 as *mut MEASUREITEMSTRUCT).as_mut() }?;
+                self.menu.measure_item(mis).then_some(LRESULT(1))
+            }
+            WM_DRAWITEM => {
+                let dis = unsafe { (lp.0 as *const DRAWITEMSTRUCT).as_ref() }?;
+                self.menu.draw_item(dis).then_some(LRESULT(1))
+            }
+            WM_MENUCHAR => self
+                .menu
+                .menu_char(HMENU(lp.0 as _), wp.lo() as _)
+                .or(Some(LRESULT(0))),
+            WM_SLIDER => {
+                if let Some(app) = &mut self.app {
+                    app.on_slider(wp.0 as _, lp.0 as _);
+                }
+                Some(LRESULT(0))
+            }
+            WM_HOTKEY => {
+                if let Some(app) = &mut self.app {
+                    app.on_hotkey(wp.0 as _);
+                }
+                Some(LRESULT(0))
+            }
+            WM_NOTE => {
+                let note = unsafe { (lp.0 as *const String).as_ref() }?;
+                if let Some(app) = &mut self.app {
+                    app.on_note(note);
+                }
+                Some(LRESULT(0))
+            }
+            WM_TRAY => {
+                match lp.lo() {
+                    WM_LBUTTONUP => {
+                        if let Some(app) = &mut self.app {
+                            app.on_tray_click();
+                        }
+                    }
+                    WM_RBUTTONUP => {
+                        let (x, y) = utils::cursor_pos();
+                        self.show_menu_at(x, y);
                     }
+                    _ => {}
                 }
-
+                Some(LRESULT(0))
+            }
+            _ if msg == self.taskbar_created && msg != 0 => {
+                if let Some(app) = &mut self.app {
+                    app.on_taskbar_created();
+                }
+                None
+            }
+            WM_CONTEXTMENU => {
+                self.cancel_long_press();
+                let (x, y) = utils::cursor_pos();
+                self.show_menu_at(x, y);
                 Some(LRESULT(0))
             }
             WM_NCCALCSIZE => Some(LRESULT(0)),
@@ -151,7 +344,13 @@ impl<T: App> Window for Viewer<T> {
                 if self.show_menu {
                     Some(LRESULT(HTCLIENT as _))
                 } else {
-                    Some(nc_hit_test(hwnd, lp.lo() as _, lp.hi() as _))
+                    let scale = if self.touch { TOUCH_BORDER_SCALE } else { 1 };
+                    Some(nc_hit_test(
+                        hwnd,
+                        GET_X_LPARAM!(lp),
+                        GET_Y_LPARAM!(lp),
+                        scale,
+                    ))
                 }
             }
             _ => None,
@@ -159,7 +358,62 @@ impl<T: App> Window for Viewer<T> {
     }
 }
 
-fn nc_hit_test(hwnd: HWND, x: i32, y: i32) -> LRESULT {
+impl<T: App> Viewer<T> {
+    fn show_menu_at(&mut self, x: i32, y: i32) {
+        self.show_menu = false;
+
+        // Rebuild so items that depend on the current state, like model options, are up to date.
+        if let Some(app) = &mut self.app {
+            app.on_menu_open();
+            if let Ok(menu) = ContextMenu::new(self.hwnd, &app.menu_items()) {
+                self.menu = menu;
+            }
+        }
+
+        match self.menu.show_at(x, y) {
+            Some((CMD_SLIDERS, _)) => {
+                if let Some(app) = &self.app {
+                    _ = SliderPanel::show(self.hwnd, x, y, app.sliders());
+                }
+            }
+            Some((CMD_STATUS, _)) => {
+                if let Some(app) = &self.app {
+                    _ = StatusDialog::show(self.hwnd, x, y, &app.status_report());
+                }
+            }
+            Some((id, state)) => {
+                if let Some(app) = &mut self.app {
+                    app.on_menu(id, state);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn cancel_long_press(&mut self) {
+        if self.long_press.take().is_some() {
+            self.hwnd.kill_timer(LONG_PRESS_TIMER_ID);
+        }
+    }
+}
+
+fn snap(rect: &mut RECT, work: &RECT, distance: i32) {
+    let (width, height) = (rect.width(), rect.height());
+
+    if (rect.left - work.left).abs() < distance {
+        rect.set_x(work.left);
+    } else if (rect.right - work.right).abs() < distance {
+        rect.set_x(work.right - width);
+    }
+
+    if (rect.top - work.top).abs() < distance {
+        rect.set_y(work.top);
+    } else if (rect.bottom - work.bottom).abs() < distance {
+        rect.set_y(work.bottom - height);
+    }
+}
+
+fn nc_hit_test(hwnd: HWND, x: i32, y: i32, scale: i32) -> LRESULT {
     let RECT {
         left,
         top,
@@ -172,6 +426,7 @@ fn nc_hit_test(hwnd: HWND, x: i32, y: i32) -> LRESULT {
         bottom: by,
         ..
     } = *RECT::default().adjusted(WS_OVERLAPPEDWINDOW, FALSE, WINDOW_EX_STYLE(0), hwnd.dpi());
+    let (bx, by) = (bx * scale, by * scale);
 
     let col = if x < left + bx {
         0
@@ -202,3 +457,7 @@ fn nc_hit_test(hwnd: HWND, x: i32, y: i32) -> LRESULT {
         _ => unreachable!(),
     }
 }
+
+fn key_down(key: VIRTUAL_KEY) -> bool {
+    unsafe { GetKeyState(key.0 as _) < 0 }
+}
@@ -0,0 +1,116 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use windows::Win32::{
+    Foundation::BOOL,
+    System::Console::{AttachConsole, SetConsoleCtrlHandler, ATTACH_PARENT_PROCESS},
+};
+
+use crate::{
+    app,
+    caption::{self, SegmentRole},
+    config::Config,
+    script::{CaptionScript, ScriptAction},
+};
+
+static QUIT: AtomicBool = AtomicBool::new(false);
+
+/// Captions without a window, for servers or streams that only read the sinks, until
+/// Ctrl+C or the console closes. Status messages go to the console it was started from.
+pub fn run(config: Config) -> Result<()> {
+    unsafe {
+        // Built as a GUI app, so there's no console unless the parent's is borrowed.
+        _ = AttachConsole(ATTACH_PARENT_PROCESS);
+        SetConsoleCtrlHandler(Some(on_ctrl), true)?;
+    }
+
+    // Privacy mode keeps captions from every sink, which leaves nothing to do here.
+    if config.privacy_mode {
+        bail!("privacy mode is on; turn off privacy-mode to send captions to the sinks");
+    }
+    let mut warnings = vec![];
+    let mut sinks = app::create_sinks(&config, &mut warnings);
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+    if sinks.is_empty() {
+        bail!("no sink is configured; set obs-file, subtitle-export, webhook-url or caption-log");
+    }
+    let (redactor, skipped) = app::create_redactor(&config);
+    for e in skipped {
+        eprintln!("Skipped {e}");
+    }
+    sinks.set_redactor(redactor);
+    let mut script = app::load_script(&config).unwrap_or_else(|e| {
+        eprintln!("Can't load the script, {e}");
+        None
+    });
+    let mut warnings = vec![];
+    let mut s2t = app::create_speech_to_text(&config, &mut warnings)?;
+    for warning in warnings {
+        eprintln!("{warning}");
+    }
+
+    let interval = Duration::from_millis(app::timer_interval(config.latency) as _);
+    let mut status = String::new();
+
+    while !QUIT.load(Ordering::Relaxed) {
+        std::thread::sleep(interval);
+
+        for (text, timings, captured) in s2t.confirmed() {
+            let action = match &mut script {
+                Some(script) => script.on_confirmed(&text),
+                None => ScriptAction::Emit {
+                    text: text.clone(),
+                    sinks: None,
+                },
+            };
+
+            if let ScriptAction::Emit {
+                text: emitted,
+                sinks: names,
+            } = action
+            {
+                let timings = caption::retime(&timings, &emitted);
+                match names {
+                    None => sinks.confirmed(&emitted, &timings, captured),
+                    Some(names) => sinks.confirmed_to(&names, &emitted, &timings, captured),
+                }
+            }
+        }
+
+        for e in sinks.take_errors() {
+            eprintln!("{e}");
+        }
+        if let Some(e) = script.as_mut().and_then(CaptionScript::take_error) {
+            eprintln!("Script error, {e}");
+        }
+
+        if let Some(segments) = s2t.segments() {
+            sinks.update(&segments);
+
+            let text = segments
+                .iter()
+                .filter(|segment| segment.role == SegmentRole::Status)
+                .map(|segment| segment.text.as_str())
+                .collect::<String>();
+            if text != status {
+                if !text.is_empty() {
+                    eprintln!("{text}");
+                }
+                status = text;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Called on a system thread for Ctrl+C, Ctrl+Break and closing the console.
+unsafe extern "system" fn on_ctrl(_ctrl_type: u32) -> BOOL {
+    QUIT.store(true, Ordering::Relaxed);
+    true.into()
+}
@@ -0,0 +1,15 @@
+pub mod app;
+pub mod caption;
+pub mod cli;
+pub mod config;
+pub(crate) mod graphics;
+pub mod gui;
+pub mod headless;
+pub(crate) mod profile;
+pub(crate) mod schedule;
+pub(crate) mod script;
+pub(crate) mod sink;
+pub(crate) mod soak;
+pub mod speech_to_text;
+pub(crate) mod theme;
+pub(crate) mod watch;
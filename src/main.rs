@@ -1,13 +1,35 @@
 #![windows_subsystem = "windows"]
 
-pub mod app;
-pub mod config;
-pub mod graphics;
-pub mod gui;
-pub mod speech_to_text;
-
 use anyhow::Result;
+use livesub::{app, cli, config, gui, headless};
+use windows::{
+    core::{s, PCSTR},
+    Win32::UI::WindowsAndMessaging::{MessageBoxA, MB_ICONINFORMATION, MB_OK},
+};
 
 fn main() -> Result<()> {
-    gui::run_app::<app::App>()
+    let args = cli::Args::parse();
+    let config = config::Config::load(&args);
+
+    let Some(_guard) = gui::utils::single_instance(&config.instance_name()) else {
+        let text = format!(
+            "livesub is already running with {}.\nStart it with --config <file> to run another.\0",
+            config.path.display()
+        );
+        unsafe {
+            MessageBoxA(
+                None,
+                PCSTR(text.as_ptr()),
+                s!("livesub"),
+                MB_OK | MB_ICONINFORMATION,
+            );
+        }
+        return Ok(());
+    };
+
+    if args.headless {
+        return headless::run(config);
+    }
+
+    gui::run_app::<app::App>(config)
 }
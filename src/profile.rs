@@ -0,0 +1,166 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{config::Config, theme::Theme};
+
+/// How long an app has to stay in the foreground before its profile is applied,
+/// so switching windows past it doesn't flip the settings back and forth.
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+/// Settings applied while one of its apps is in the foreground, from a `[profile:<name>]`
+/// section such as `processes = mpc-hc.exe, vlc.exe`, `language = ja`, `translate = 1`
+/// and `theme = <theme name>`. Settings left out stay as they are.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    processes: Vec<String>,
+    pub language: Option<String>,
+    pub translate: Option<bool>,
+    pub theme: Option<String>,
+}
+
+impl Profile {
+    pub fn parse(name: &str, options: &BTreeMap<String, String>) -> Self {
+        Self {
+            name: name.to_string(),
+            processes: options
+                .get("processes")
+                .map(|processes| {
+                    processes
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|process| !process.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            language: options
+                .get("language")
+                .map(|language| language.trim().into()),
+            translate: options.get("translate").map(|translate| translate != "0"),
+            theme: options.get("theme").map(|theme| theme.trim().into()),
+        }
+    }
+
+    /// Whether `process`, an executable name such as "zoom.exe", is bound to this profile.
+    pub fn matches(&self, process: &str) -> bool {
+        self.processes
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(process))
+    }
+}
+
+/// The settings a profile may change, to put back when its app leaves the foreground.
+#[derive(Clone, Debug)]
+pub struct ProfileBase {
+    language: String,
+    translate: bool,
+    theme: Theme,
+}
+
+impl ProfileBase {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            language: config.language.clone(),
+            translate: config.translate,
+            theme: Theme::from_config(config),
+        }
+    }
+
+    /// Puts these settings back, except those changed since `applied`, the settings right
+    /// after the profile took effect, which the user chose while it was on.
+    pub fn restore(&self, applied: &Self, config: &mut Config) {
+        if config.language == applied.language {
+            config.language.clone_from(&self.language);
+        }
+        if config.translate == applied.translate {
+            config.translate = self.translate;
+        }
+        let current = Theme::from_config(config);
+        self.theme.merged(&applied.theme, &current).apply_to(config);
+    }
+}
+
+/// Follows the foreground app and picks its profile once it has settled.
+pub struct ProfileSwitcher {
+    profiles: Vec<Profile>,
+    active: Option<usize>,
+    pending: Option<(Option<usize>, Instant)>,
+    last_process: Option<String>,
+    // The app in front when a profile was picked by hand, kept until another comes forward.
+    held: Option<String>,
+}
+
+impl ProfileSwitcher {
+    pub fn new(profiles: Vec<Profile>) -> Self {
+        Self {
+            profiles,
+            active: None,
+            pending: None,
+            last_process: None,
+            held: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    pub fn active(&self) -> Option<&Profile> {
+        self.active.map(|i| &self.profiles[i])
+    }
+
+    /// Returns the profile to switch to, `Some(None)` for none, when the foreground app,
+    /// `process`, has kept a different one for long enough. `None` for `process` keeps the
+    /// current profile, for windows such as the app's own menu.
+    pub fn update(&mut self, process: Option<&str>, now: Instant) -> Option<Option<&Profile>> {
+        let Some(process) = process else {
+            self.pending = None;
+            return None;
+        };
+        if self.last_process.as_deref() != Some(process) {
+            self.last_process = Some(process.to_string());
+        }
+        if self.held.as_deref() == Some(process) {
+            return None;
+        }
+        self.held = None;
+
+        let wanted = self.profiles.iter().position(|p| p.matches(process));
+        if wanted == self.active {
+            self.pending = None;
+            return None;
+        }
+
+        match self.pending {
+            Some((pending, since)) if pending == wanted => {
+                if now.duration_since(since) < DEBOUNCE {
+                    return None;
+                }
+            }
+            _ => {
+                self.pending = Some((wanted, now));
+                return None;
+            }
+        }
+
+        self.pending = None;
+        self.active = wanted;
+        Some(self.active())
+    }
+
+    /// Steps to the next profile, or to none after the last, and keeps it
+    /// while the same app stays in front.
+    pub fn cycle(&mut self) -> Option<&Profile> {
+        self.active = match self.active {
+            None if !self.profiles.is_empty() => Some(0),
+            Some(i) if i + 1 < self.profiles.len() => Some(i + 1),
+            _ => None,
+        };
+        self.pending = None;
+        self.held.clone_from(&self.last_process);
+        self.active()
+    }
+}
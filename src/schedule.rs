@@ -0,0 +1,182 @@
+use anyhow::{bail, Context as _, Result};
+
+const DAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Weekly sessions parsed from `"tue 19:00-21:00; sat,sun 10:00-12:00; daily 08:30-09:00"`.
+/// A session ending at or before its start runs past midnight.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schedule {
+    sessions: Vec<Session>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Session {
+    day: u32,
+    start: u32,
+    end: u32,
+}
+
+impl Schedule {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut sessions = vec![];
+
+        for entry in text
+            .split(';')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            // Day names hold no digits, so the times start at the first one.
+            let (days, span) = entry
+                .find(|c: char| c.is_ascii_digit())
+                .map(|i| entry.split_at(i))
+                .with_context(|| format!("no times in schedule entry {entry:?}"))?;
+            let (start, end) = span
+                .split_once('-')
+                .and_then(|(start, end)| Some((parse_time(start)?, parse_time(end)?)))
+                .with_context(|| format!("invalid times in schedule entry {entry:?}"))?;
+            let days = parse_days(days)
+                .with_context(|| format!("invalid days in schedule entry {entry:?}"))?;
+            if days.is_empty() {
+                bail!("no days in schedule entry {entry:?}");
+            }
+            sessions.extend(days.into_iter().map(|day| Session { day, start, end }));
+        }
+
+        Ok(Self { sessions })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Whether a session covers `minute` past midnight on `day`, 0 being Sunday.
+    pub fn is_active(&self, day: u32, minute: u32) -> bool {
+        let now = day * MINUTES_PER_DAY + minute;
+        let week = 7 * MINUTES_PER_DAY;
+
+        self.sessions.iter().any(|session| {
+            let start = session.day * MINUTES_PER_DAY + session.start;
+            let mut length = (session.end + MINUTES_PER_DAY - session.start) % MINUTES_PER_DAY;
+            if length == 0 {
+                length = MINUTES_PER_DAY;
+            }
+            (now + week - start) % week < length
+        })
+    }
+}
+
+fn parse_time(text: &str) -> Option<u32> {
+    let (hour, minute) = text.trim().split_once(':')?;
+    let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+fn parse_days(text: &str) -> Option<Vec<u32>> {
+    let mut days = vec![];
+
+    for name in text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+    {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "daily" => days.extend(0..7),
+            "weekdays" => days.extend(1..6),
+            "weekends" => days.extend([0, 6]),
+            _ => days.push(DAYS.iter().position(|day| name.starts_with(day))? as u32),
+        }
+    }
+
+    Some(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUN: u32 = 0;
+    const MON: u32 = 1;
+    const TUE: u32 = 2;
+    const FRI: u32 = 5;
+    const SAT: u32 = 6;
+
+    fn at(hour: u32, minute: u32) -> u32 {
+        hour * 60 + minute
+    }
+
+    #[test]
+    fn spaced_day_list() {
+        let schedule = Schedule::parse("sat, sun 10:00-12:00").unwrap();
+        assert!(schedule.is_active(SAT, at(10, 0)));
+        assert!(schedule.is_active(SUN, at(11, 59)));
+        assert!(!schedule.is_active(SUN, at(12, 0)));
+        assert!(!schedule.is_active(SAT, at(9, 59)));
+        assert!(!schedule.is_active(MON, at(10, 30)));
+        assert_eq!(schedule, Schedule::parse("Sat,Sun 10:00-12:00").unwrap());
+    }
+
+    #[test]
+    fn day_groups() {
+        let schedule = Schedule::parse("weekdays 09:00-10:00; weekends 20:00-21:00").unwrap();
+        assert!((1..6).all(|day| schedule.is_active(day, at(9, 30))));
+        assert!(!schedule.is_active(SAT, at(9, 30)));
+        assert!(schedule.is_active(SUN, at(20, 30)));
+        assert!(!schedule.is_active(FRI, at(20, 30)));
+
+        let daily = Schedule::parse("daily 08:30-09:00").unwrap();
+        assert!((0..7).all(|day| daily.is_active(day, at(8, 45))));
+    }
+
+    #[test]
+    fn past_midnight() {
+        let schedule = Schedule::parse("fri 22:00-02:00").unwrap();
+        assert!(!schedule.is_active(FRI, at(21, 59)));
+        assert!(schedule.is_active(FRI, at(23, 0)));
+        assert!(schedule.is_active(SAT, at(1, 59)));
+        assert!(!schedule.is_active(SAT, at(2, 0)));
+        assert!(!schedule.is_active(FRI, at(1, 0)));
+    }
+
+    #[test]
+    fn week_wrap() {
+        let schedule = Schedule::parse("sat 23:00-01:00").unwrap();
+        assert!(schedule.is_active(SAT, at(23, 30)));
+        assert!(schedule.is_active(SUN, at(0, 30)));
+        assert!(!schedule.is_active(SUN, at(1, 0)));
+        assert!(!schedule.is_active(SUN, at(23, 30)));
+    }
+
+    #[test]
+    fn same_start_and_end_is_a_whole_day() {
+        let schedule = Schedule::parse("mon 08:00-08:00").unwrap();
+        assert!(!schedule.is_active(MON, at(7, 59)));
+        assert!(schedule.is_active(MON, at(8, 0)));
+        assert!(schedule.is_active(TUE, at(7, 59)));
+        assert!(!schedule.is_active(TUE, at(8, 0)));
+    }
+
+    #[test]
+    fn empty() {
+        let schedule = Schedule::parse(" ; ").unwrap();
+        assert!(schedule.is_empty());
+        assert!(!schedule.is_active(MON, at(12, 0)));
+    }
+
+    #[test]
+    fn rejected() {
+        for text in [
+            "mon",
+            "10:00-11:00",
+            "mon 10:00",
+            "mon 10:00-24:00",
+            "mon 10:60-11:00",
+            "mon 10-11",
+            "someday 10:00-11:00",
+            "mon, xyz 10:00-11:00",
+            "tue 19:00-21:00; fri",
+        ] {
+            assert!(Schedule::parse(text).is_err(), "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::Result;
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+pub enum ScriptAction {
+    // Only a script run by the engine can drop a caption.
+    #[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+    Drop,
+    Emit {
+        text: String,
+        sinks: Option<Vec<String>>,
+    },
+}
+
+/// Runs `on_caption(text)` from a user script on each confirmed caption.
+/// The function returns the new text, `()` to drop the caption, or
+/// `#{ text: "...", sinks: ["caption-log"] }` to route it to specific sinks.
+pub struct CaptionScript {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    checked: Instant,
+    engine: engine::ScriptEngine,
+    error: Option<String>,
+    // Reported once, so a script failing on every caption doesn't keep covering them.
+    last_error: Option<String>,
+}
+
+impl CaptionScript {
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let modified = modified_time(&path);
+        let engine = engine::ScriptEngine::compile(&std::fs::read_to_string(&path)?)?;
+
+        Ok(Self {
+            path,
+            modified,
+            checked: Instant::now(),
+            engine,
+            error: None,
+            last_error: None,
+        })
+    }
+
+    /// Passes the caption through unchanged if the script fails, keeping the error for `take_error`.
+    pub fn on_confirmed(&mut self, text: &str) -> ScriptAction {
+        self.reload_if_modified();
+
+        match self.engine.on_caption(text) {
+            Ok(action) => {
+                self.last_error = None;
+                action
+            }
+            Err(e) => {
+                self.set_error(format!("{e}"));
+                ScriptAction::Emit {
+                    text: text.to_string(),
+                    sinks: None,
+                }
+            }
+        }
+    }
+
+    /// The last error from running or reloading the script, once.
+    pub fn take_error(&mut self) -> Option<String> {
+        self.error.take()
+    }
+
+    fn set_error(&mut self, error: String) {
+        if self.last_error.as_ref() != Some(&error) {
+            self.last_error = Some(error.clone());
+            self.error = Some(error);
+        }
+    }
+
+    fn reload_if_modified(&mut self) {
+        if self.checked.elapsed() < RELOAD_INTERVAL {
+            return;
+        }
+        self.checked = Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        // Keep running the previous script if the new one doesn't compile.
+        let compiled = std::fs::read_to_string(&self.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| engine::ScriptEngine::compile(&source));
+        match compiled {
+            Ok(engine) => self.engine = engine,
+            Err(e) => self.set_error(format!("{e}")),
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(feature = "scripting")]
+mod engine {
+    use anyhow::{anyhow, bail, Result};
+    use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+
+    use super::ScriptAction;
+
+    pub struct ScriptEngine {
+        engine: Engine,
+        ast: AST,
+    }
+
+    impl ScriptEngine {
+        pub fn compile(source: &str) -> Result<Self> {
+            // Engine::new has no file or network access; only bound the work a script can do.
+            let mut engine = Engine::new();
+            engine.set_max_operations(1_000_000);
+            engine.set_max_call_levels(32);
+            engine.set_max_expr_depths(64, 32);
+            engine.set_max_string_size(64 * 1024);
+            engine.set_max_array_size(10_000);
+            engine.set_max_map_size(10_000);
+
+            let ast = engine.compile(source).map_err(|e| anyhow!("{e}"))?;
+            Ok(Self { engine, ast })
+        }
+
+        pub fn on_caption(&self, text: &str) -> Result<ScriptAction> {
+            let mut scope = Scope::new();
+            let result: Dynamic = self
+                .engine
+                .call_fn(&mut scope, &self.ast, "on_caption", (text.to_string(),))
+                .map_err(|e| anyhow!("{e}"))?;
+
+            if result.is_unit() {
+                return Ok(ScriptAction::Drop);
+            }
+
+            if result.is_string() {
+                let text = result.into_string().map_err(|e| anyhow!("{e}"))?;
+                return Ok(ScriptAction::Emit { text, sinks: None });
+            }
+
+            if let Some(map) = result.try_cast::<Map>() {
+                let text = map
+                    .get("text")
+                    .and_then(|v| v.clone().into_string().ok())
+                    .unwrap_or_else(|| text.to_string());
+                let sinks = map
+                    .get("sinks")
+                    .and_then(|v| v.clone().try_cast::<Array>())
+                    .map(|sinks| {
+                        sinks
+                            .into_iter()
+                            .filter_map(|v| v.into_string().ok())
+                            .collect()
+                    });
+                return Ok(ScriptAction::Emit { text, sinks });
+            }
+
+            bail!("on_caption must return a string, a map or ()")
+        }
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+mod engine {
+    use anyhow::{bail, Result};
+
+    use super::ScriptAction;
+
+    pub struct ScriptEngine;
+
+    impl ScriptEngine {
+        pub fn compile(_source: &str) -> Result<Self> {
+            bail!("livesub was built without the \"scripting\" feature")
+        }
+
+        pub fn on_caption(&self, _text: &str) -> Result<ScriptAction> {
+            bail!("livesub was built without the \"scripting\" feature")
+        }
+    }
+}
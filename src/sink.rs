@@ -0,0 +1,192 @@
+mod caption_log;
+mod cipher;
+mod format;
+mod obs_file;
+mod redact;
+mod subtitle;
+mod webhook;
+
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::caption::{CaptionSegment, TimedText};
+
+pub use caption_log::{decrypt as decrypt_caption_log, CaptionLog};
+pub use cipher::{protect as protect_secret, unprotect as unprotect_secret};
+pub use format::{Formatted, Formatter};
+pub use obs_file::ObsFile;
+pub use redact::Redactor;
+pub use subtitle::{SubtitleExport, SubtitleStyle};
+pub use webhook::Webhook;
+
+pub trait Sink {
+    /// A caption is final, its audio having started being captured at `captured`.
+    fn on_confirmed(&mut self, text: &str, captured: Instant) -> Result<()>;
+
+    fn on_update(&mut self, _segments: &[CaptionSegment]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Spoken spans of the caption about to be confirmed, when the model reports them.
+    fn on_timed(&mut self, _timings: &[TimedText]) -> Result<()> {
+        Ok(())
+    }
+
+    /// A moment flagged by the user, with an optional note.
+    fn on_bookmark(&mut self, _time: Instant, _note: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Drops what was queued but not yet delivered, as privacy mode starts.
+    fn discard_pending(&mut self) {}
+
+    /// A failure on a background thread since the last call, such as a request given up on.
+    fn take_error(&mut self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct Sinks {
+    sinks: Vec<(&'static str, Box<dyn Sink>)>,
+    private: bool,
+    redactor: Option<Redactor>,
+    errors: Vec<String>,
+}
+
+impl Sinks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &'static str, sink: Option<Box<dyn Sink>>) {
+        let pos = self.sinks.iter().position(|(n, _)| *n == name);
+
+        match (pos, sink) {
+            (Some(i), Some(sink)) => self.sinks[i].1 = sink,
+            (Some(i), None) => _ = self.sinks.remove(i),
+            (None, Some(sink)) => self.sinks.push((name, sink)),
+            (None, None) => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.sinks.iter().any(|(n, _)| *n == name)
+    }
+
+    /// Holds everything back from the sinks, which all write to disk or the network.
+    pub fn set_private(&mut self, private: bool) {
+        self.private = private;
+        if private {
+            for (_, sink) in &mut self.sinks {
+                sink.discard_pending();
+            }
+        }
+    }
+
+    /// Masks personal data in everything the sinks get.
+    pub fn set_redactor(&mut self, redactor: Option<Redactor>) {
+        self.redactor = redactor;
+    }
+
+    pub fn confirmed(&mut self, text: &str, timings: &[TimedText], captured: Instant) {
+        self.send_confirmed(None, text, timings, captured);
+    }
+
+    pub fn confirmed_to(
+        &mut self,
+        names: &[String],
+        text: &str,
+        timings: &[TimedText],
+        captured: Instant,
+    ) {
+        self.send_confirmed(Some(names), text, timings, captured);
+    }
+
+    fn send_confirmed(
+        &mut self,
+        names: Option<&[String]>,
+        text: &str,
+        timings: &[TimedText],
+        captured: Instant,
+    ) {
+        if self.private {
+            return;
+        }
+        let (text, timings) = match &self.redactor {
+            Some(redactor) => (redactor.redact(text), redactor.redact_timings(timings)),
+            None => (text.to_string(), timings.to_vec()),
+        };
+
+        for (name, sink) in &mut self.sinks {
+            if names.is_none_or(|names| names.iter().any(|n| n == name)) {
+                if let Err(e) = confirm(sink.as_mut(), &text, &timings, captured) {
+                    self.errors.push(format!("{name}: {e}"));
+                }
+            }
+        }
+    }
+
+    pub fn bookmark(&mut self, time: Instant, note: &str) {
+        if self.private {
+            return;
+        }
+        let note = match &self.redactor {
+            Some(redactor) => redactor.redact(note),
+            None => note.to_string(),
+        };
+
+        for (name, sink) in &mut self.sinks {
+            if let Err(e) = sink.on_bookmark(time, &note) {
+                self.errors.push(format!("{name}: {e}"));
+            }
+        }
+    }
+
+    /// Failures since the last call, each prefixed by the sink's name.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        let mut errors = std::mem::take(&mut self.errors);
+        for (name, sink) in &mut self.sinks {
+            if let Some(e) = sink.take_error() {
+                errors.push(format!("{name}: {e}"));
+            }
+        }
+        errors
+    }
+
+    pub fn update(&mut self, segments: &[CaptionSegment]) {
+        if self.private {
+            return;
+        }
+        let segments = match &self.redactor {
+            Some(redactor) => redactor.redact_segments(segments),
+            None => segments.to_vec(),
+        };
+
+        for (name, sink) in &mut self.sinks {
+            if let Err(e) = sink.on_update(&segments) {
+                self.errors.push(format!("{name}: {e}"));
+            }
+        }
+    }
+}
+
+fn confirm(
+    sink: &mut dyn Sink,
+    text: &str,
+    timings: &[TimedText],
+    captured: Instant,
+) -> Result<()> {
+    // A sink that fails one step still gets the caption.
+    let timed = match timings.is_empty() {
+        true => Ok(()),
+        false => sink.on_timed(timings),
+    };
+    let confirmed = sink.on_confirmed(text, captured);
+    timed.and(confirmed)
+}
@@ -0,0 +1,219 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read as _, Write as _},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{bail, Result};
+
+use crate::gui::utils;
+
+use super::{
+    cipher::{self, Cipher, SALT_LEN},
+    Sink,
+};
+
+/// Starts an encrypted log, followed by the salt and then one length-prefixed record per line.
+const ENCRYPTED_MAGIC: &[u8; 8] = b"LIVESUB\x01";
+// Far longer than any caption, so a damaged length isn't read as one.
+const MAX_RECORD_LEN: usize = 1 << 20;
+
+pub struct CaptionLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    passphrase: String,
+    cipher: Option<Cipher>,
+    timestamps: bool,
+}
+
+impl CaptionLog {
+    /// With a `passphrase` lines are encrypted with AES-GCM, readable with `decrypt`.
+    /// With `timestamps` each line starts with the local time it was written.
+    pub fn new(path: PathBuf, max_size: u64, passphrase: &str, timestamps: bool) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        let mut log = Self {
+            path,
+            file,
+            size,
+            max_size,
+            passphrase: passphrase.to_string(),
+            cipher: None,
+            timestamps,
+        };
+
+        // Lines are never mixed with records of the other kind, or of another passphrase.
+        // Rather than move the log aside, which a typo in the passphrase would make easy,
+        // it isn't opened at all.
+        let display = log.path.display();
+        match (read_header(&log.path)?, passphrase.is_empty()) {
+            _ if size == 0 => log.start_encrypted()?,
+            (None, true) => {}
+            (Some(salt), false) => {
+                let cipher = Cipher::new(passphrase, &salt)?;
+                if !check_first_record(&log.path, &cipher)? {
+                    bail!("{display} was encrypted with another passphrase, or is damaged");
+                }
+                log.cipher = Some(cipher);
+            }
+            (None, false) => bail!("{display} is not encrypted; move it away to encrypt the log"),
+            (Some(_), true) => bail!("{display} is encrypted; set caption-log-passphrase"),
+        }
+
+        Ok(log)
+    }
+
+    /// Moves the full log to the first free `.1`, `.2`, ... name and starts a new one.
+    fn rotate(&mut self) -> Result<()> {
+        let rotated = (1..)
+            .map(|n| {
+                let mut rotated = self.path.clone().into_os_string();
+                rotated.push(format!(".{n}"));
+                PathBuf::from(rotated)
+            })
+            .find(|rotated| !rotated.exists())
+            .unwrap_or_default();
+
+        std::fs::rename(&self.path, rotated)?;
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        self.start_encrypted()
+    }
+
+    /// Writes the header of an encrypted log to the empty file, if there is a passphrase.
+    fn start_encrypted(&mut self) -> Result<()> {
+        if self.passphrase.is_empty() {
+            self.cipher = None;
+            return Ok(());
+        }
+
+        let salt = cipher::random_bytes::<SALT_LEN>()?;
+        let cipher = Cipher::new(&self.passphrase, &salt)?;
+        // An empty first record lets the passphrase be checked before appending.
+        let check = seal_record(&cipher, b"")?;
+        self.file.write_all(ENCRYPTED_MAGIC)?;
+        self.file.write_all(&salt)?;
+        self.file.write_all(&check)?;
+        self.file.flush()?;
+        self.size = (ENCRYPTED_MAGIC.len() + SALT_LEN + check.len()) as u64;
+        self.cipher = Some(cipher);
+
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        let line = match self.timestamps {
+            true => format!("[{}] {line}", utils::local_timestamp()),
+            false => line.to_string(),
+        };
+        let len = match self.cipher {
+            Some(_) => 4 + cipher::SEALED_OVERHEAD + line.len(),
+            None => line.len() + 1,
+        };
+        if self.max_size > 0 && self.size + len as u64 > self.max_size {
+            self.rotate()?;
+        }
+
+        let record = match &self.cipher {
+            Some(cipher) => seal_record(cipher, line.as_bytes())?,
+            None => format!("{line}\n").into_bytes(),
+        };
+
+        self.file.write_all(&record)?;
+        self.file.flush()?;
+        self.size += record.len() as u64;
+
+        Ok(())
+    }
+}
+
+impl Sink for CaptionLog {
+    fn on_confirmed(&mut self, text: &str, _captured: Instant) -> Result<()> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        self.write_line(text)
+    }
+
+    fn on_bookmark(&mut self, _time: Instant, note: &str) -> Result<()> {
+        match note {
+            "" => self.write_line("[Bookmark]"),
+            note => self.write_line(&format!("[Bookmark] {note}")),
+        }
+    }
+}
+
+/// The lines of an encrypted caption log.
+pub fn decrypt(path: &Path, passphrase: &str) -> Result<String> {
+    let data = std::fs::read(path)?;
+    let Some(rest) = data.strip_prefix(ENCRYPTED_MAGIC) else {
+        bail!("{} is not an encrypted caption log", path.display());
+    };
+    if rest.len() < SALT_LEN {
+        bail!("{} is cut short", path.display());
+    }
+    let (salt, mut records) = rest.split_at(SALT_LEN);
+    let cipher = Cipher::new(passphrase, salt)?;
+
+    let mut text = String::new();
+    // A record cut off by a crash is left out.
+    while let Some((len, rest)) = records.split_first_chunk::<4>() {
+        let len = u32::from_le_bytes(*len) as usize;
+        let Some(sealed) = rest.get(..len) else {
+            break;
+        };
+        let line = cipher.open(sealed)?;
+        // The empty record written first only checks the passphrase.
+        if !line.is_empty() {
+            text += &String::from_utf8_lossy(&line);
+            text.push('\n');
+        }
+        records = &rest[len..];
+    }
+
+    Ok(text)
+}
+
+fn seal_record(cipher: &Cipher, line: &[u8]) -> Result<Vec<u8>> {
+    let sealed = cipher.seal(line)?;
+    Ok([&(sealed.len() as u32).to_le_bytes()[..], &sealed].concat())
+}
+
+/// Whether the first record of an encrypted log opens with `cipher`, reading only that
+/// record. A log without records yet has nothing to tell.
+fn check_first_record(path: &Path, cipher: &Cipher) -> Result<bool> {
+    let mut file = File::open(path)?;
+    let mut header = [0u8; ENCRYPTED_MAGIC.len() + SALT_LEN + 4];
+    if file.read_exact(&mut header).is_err() {
+        return Ok(true);
+    }
+
+    let len = u32::from_le_bytes(header[header.len() - 4..].try_into()?) as usize;
+    let mut sealed = vec![0u8; len];
+    if len > MAX_RECORD_LEN || file.read_exact(&mut sealed).is_err() {
+        return Ok(false);
+    }
+
+    Ok(cipher.open(&sealed).is_ok())
+}
+
+/// The salt of an encrypted log, or `None` for a plain or empty one.
+fn read_header(path: &Path) -> Result<Option<[u8; SALT_LEN]>> {
+    let mut header = [0u8; ENCRYPTED_MAGIC.len() + SALT_LEN];
+    let mut file = File::open(path)?;
+    if file.read_exact(&mut header).is_err() || !header.starts_with(ENCRYPTED_MAGIC) {
+        return Ok(None);
+    }
+
+    Ok(Some(header[ENCRYPTED_MAGIC.len()..].try_into()?))
+}
@@ -0,0 +1,214 @@
+use anyhow::{bail, Result};
+use windows::Win32::{
+    Foundation::{LocalFree, HLOCAL},
+    Security::Cryptography::*,
+};
+use windows_core::PCWSTR;
+
+pub const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const ITERATIONS: u64 = 200_000;
+/// How much longer a sealed record is than its plaintext.
+pub const SEALED_OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+/// AES-256-GCM with a key derived from a passphrase by PBKDF2-SHA256.
+pub struct Cipher {
+    aes: BCRYPT_ALG_HANDLE,
+    key: BCRYPT_KEY_HANDLE,
+}
+
+impl Cipher {
+    pub fn new(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        unsafe {
+            let mut sha256 = BCRYPT_ALG_HANDLE::default();
+            BCryptOpenAlgorithmProvider(
+                &mut sha256,
+                BCRYPT_SHA256_ALGORITHM,
+                None,
+                BCRYPT_ALG_HANDLE_HMAC_FLAG,
+            )
+            .ok()?;
+            let mut secret = [0u8; KEY_LEN];
+            let derived = BCryptDeriveKeyPBKDF2(
+                sha256,
+                Some(passphrase.as_bytes()),
+                Some(salt),
+                ITERATIONS,
+                &mut secret,
+                0,
+            );
+            _ = BCryptCloseAlgorithmProvider(sha256, 0);
+            derived.ok()?;
+
+            let mut aes = BCRYPT_ALG_HANDLE::default();
+            BCryptOpenAlgorithmProvider(
+                &mut aes,
+                BCRYPT_AES_ALGORITHM,
+                None,
+                BCRYPT_OPEN_ALGORITHM_PROVIDER_FLAGS(0),
+            )
+            .ok()?;
+            let mut cipher = Self {
+                aes,
+                key: BCRYPT_KEY_HANDLE::default(),
+            };
+
+            let mode: Vec<u8> = BCRYPT_CHAIN_MODE_GCM
+                .as_wide()
+                .iter()
+                .chain([&0])
+                .flat_map(|c| c.to_le_bytes())
+                .collect();
+            BCryptSetProperty(aes.into(), BCRYPT_CHAINING_MODE, &mode, 0).ok()?;
+            BCryptGenerateSymmetricKey(aes, &mut cipher.key, None, &secret, 0).ok()?;
+
+            Ok(cipher)
+        }
+    }
+
+    /// Encrypts `plaintext` under a fresh nonce, as the nonce, ciphertext and tag.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = random_bytes::<NONCE_LEN>()?;
+        let mut tag = [0u8; TAG_LEN];
+        let mut ciphertext = vec![0u8; plaintext.len()];
+        let info = auth_info(&mut nonce, &mut tag);
+
+        let mut len = 0;
+        unsafe {
+            BCryptEncrypt(
+                self.key,
+                Some(plaintext),
+                Some(&info as *const _ as _),
+                None,
+                Some(&mut ciphertext),
+                &mut len,
+                BCRYPT_FLAGS(0),
+            )
+            .ok()?;
+        }
+
+        Ok([&nonce[..], &ciphertext[..len as usize], &tag[..]].concat())
+    }
+
+    /// Decrypts what `seal` returned, failing if it was altered or the passphrase differs.
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < SEALED_OVERHEAD {
+            bail!("encrypted record is cut short");
+        }
+        let (nonce, rest) = sealed.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+        let mut nonce: [u8; NONCE_LEN] = nonce.try_into()?;
+        let mut tag: [u8; TAG_LEN] = tag.try_into()?;
+        let mut plaintext = vec![0u8; ciphertext.len()];
+        let info = auth_info(&mut nonce, &mut tag);
+
+        let mut len = 0;
+        let status = unsafe {
+            BCryptDecrypt(
+                self.key,
+                Some(ciphertext),
+                Some(&info as *const _ as _),
+                None,
+                Some(&mut plaintext),
+                &mut len,
+                BCRYPT_FLAGS(0),
+            )
+        };
+        if status.is_err() {
+            bail!("can't decrypt, wrong passphrase or damaged file");
+        }
+        plaintext.truncate(len as usize);
+
+        Ok(plaintext)
+    }
+}
+
+impl Drop for Cipher {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.key.is_invalid() {
+                _ = BCryptDestroyKey(self.key);
+            }
+            _ = BCryptCloseAlgorithmProvider(self.aes, 0);
+        }
+    }
+}
+
+pub fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    unsafe {
+        BCryptGenRandom(
+            BCRYPT_ALG_HANDLE::default(),
+            &mut bytes,
+            BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+        )
+        .ok()?
+    };
+    Ok(bytes)
+}
+
+/// Encrypts `data` with DPAPI so only the current Windows user can read it back.
+pub fn protect(data: &[u8]) -> Result<Vec<u8>> {
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as _,
+        pbData: data.as_ptr() as _,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptProtectData(
+            &input,
+            PCWSTR::null(),
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )?;
+        Ok(take_blob(output))
+    }
+}
+
+/// Decrypts what `protect` returned for the same user.
+pub fn unprotect(data: &[u8]) -> Result<Vec<u8>> {
+    let input = CRYPT_INTEGER_BLOB {
+        cbData: data.len() as _,
+        pbData: data.as_ptr() as _,
+    };
+    let mut output = CRYPT_INTEGER_BLOB::default();
+    unsafe {
+        CryptUnprotectData(
+            &input,
+            None,
+            None,
+            None,
+            None,
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )?;
+        Ok(take_blob(output))
+    }
+}
+
+/// Copies out and frees a blob DPAPI allocated.
+unsafe fn take_blob(blob: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    let data = std::slice::from_raw_parts(blob.pbData, blob.cbData as _).to_vec();
+    _ = LocalFree(HLOCAL(blob.pbData as _));
+    data
+}
+
+fn auth_info(
+    nonce: &mut [u8; NONCE_LEN],
+    tag: &mut [u8; TAG_LEN],
+) -> BCRYPT_AUTHENTICATED_CIPHER_MODE_INFO {
+    BCRYPT_AUTHENTICATED_CIPHER_MODE_INFO {
+        cbSize: std::mem::size_of::<BCRYPT_AUTHENTICATED_CIPHER_MODE_INFO>() as _,
+        dwInfoVersion: BCRYPT_AUTHENTICATED_CIPHER_MODE_INFO_VERSION,
+        pbNonce: nonce.as_mut_ptr(),
+        cbNonce: NONCE_LEN as _,
+        pbTag: tag.as_mut_ptr(),
+        cbTag: TAG_LEN as _,
+        ..Default::default()
+    }
+}
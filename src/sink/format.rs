@@ -0,0 +1,141 @@
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::{
+    caption::{CaptionSegment, SegmentRole, TimedText},
+    config::{FORMAT_HTML, FORMAT_MARKDOWN},
+};
+
+use super::Sink;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Formatter {
+    #[default]
+    Plain,
+    Markdown,
+    Html,
+}
+
+impl Formatter {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            FORMAT_MARKDOWN => Self::Markdown,
+            FORMAT_HTML => Self::Html,
+            _ => Self::Plain,
+        }
+    }
+
+    pub fn confirmed(&self, text: &str) -> String {
+        match self {
+            Self::Plain => text.to_string(),
+            Self::Markdown => escape_markdown(text),
+            Self::Html if text.trim().is_empty() => String::new(),
+            Self::Html => format!("<p class=\"caption\">{}</p>", escape_html(text.trim())),
+        }
+    }
+
+    pub fn segment(&self, segment: &CaptionSegment) -> CaptionSegment {
+        let text = match self {
+            Self::Plain => segment.text.clone(),
+            Self::Markdown => match segment.role {
+                SegmentRole::Tentative | SegmentRole::Status if !segment.text.trim().is_empty() => {
+                    format!("*{}*", escape_markdown(segment.text.trim()))
+                }
+                _ => escape_markdown(&segment.text),
+            },
+            Self::Html => format!(
+                "<span class=\"{}\">{}</span>",
+                role_class(segment.role),
+                escape_html(&segment.text)
+            ),
+        };
+
+        CaptionSegment {
+            text,
+            ..segment.clone()
+        }
+    }
+}
+
+pub struct Formatted<S> {
+    formatter: Formatter,
+    sink: S,
+}
+
+impl<S: Sink> Formatted<S> {
+    pub fn new(formatter: Formatter, sink: S) -> Self {
+        Self { formatter, sink }
+    }
+}
+
+impl<S: Sink> Sink for Formatted<S> {
+    fn on_confirmed(&mut self, text: &str, captured: Instant) -> Result<()> {
+        self.sink
+            .on_confirmed(&self.formatter.confirmed(text), captured)
+    }
+
+    fn on_bookmark(&mut self, time: Instant, note: &str) -> Result<()> {
+        self.sink.on_bookmark(time, note)
+    }
+
+    fn on_update(&mut self, segments: &[CaptionSegment]) -> Result<()> {
+        let segments: Vec<_> = segments
+            .iter()
+            .map(|segment| self.formatter.segment(segment))
+            .collect();
+        self.sink.on_update(&segments)
+    }
+
+    fn on_timed(&mut self, timings: &[TimedText]) -> Result<()> {
+        self.sink.on_timed(timings)
+    }
+
+    fn discard_pending(&mut self) {
+        self.sink.discard_pending();
+    }
+
+    fn take_error(&mut self) -> Option<String> {
+        self.sink.take_error()
+    }
+}
+
+fn role_class(role: SegmentRole) -> String {
+    match role {
+        SegmentRole::Confirmed => "confirmed".into(),
+        SegmentRole::Tentative => "tentative".into(),
+        SegmentRole::Status => "status".into(),
+        SegmentRole::Speaker(n) => format!("speaker speaker-{n}"),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '`' | '~' | '|' | '>' | '#' | '[' | ']'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\n' => escaped.push_str("<br>"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
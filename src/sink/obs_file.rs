@@ -0,0 +1,63 @@
+use std::{path::PathBuf, time::Instant};
+
+use anyhow::Result;
+
+use crate::caption::{wrap::wrap_lines, CaptionSegment, SegmentRole};
+
+use super::Sink;
+
+pub struct ObsFile {
+    path: PathBuf,
+    max_lines: usize,
+    line_width: usize,
+    contents: String,
+}
+
+impl ObsFile {
+    pub fn new(path: PathBuf, max_lines: usize, line_width: usize) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        Ok(Self {
+            path,
+            max_lines,
+            line_width,
+            contents: String::new(),
+        })
+    }
+
+    fn write(&mut self, contents: String) -> Result<()> {
+        if contents == self.contents {
+            return Ok(());
+        }
+
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+
+        std::fs::write(&temp, &contents)?;
+        std::fs::rename(&temp, &self.path)?;
+        self.contents = contents;
+
+        Ok(())
+    }
+}
+
+impl Sink for ObsFile {
+    fn on_confirmed(&mut self, _text: &str, _captured: Instant) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_update(&mut self, segments: &[CaptionSegment]) -> Result<()> {
+        let text: String = segments
+            .iter()
+            .filter(|segment| segment.role != SegmentRole::Status)
+            .map(|segment| segment.text.as_str())
+            .collect();
+
+        let lines = wrap_lines(&text, self.line_width);
+        let first = lines.len().saturating_sub(self.max_lines);
+
+        self.write(lines[first..].join("\n"))
+    }
+}
@@ -0,0 +1,122 @@
+use regex::{Captures, Regex};
+
+use crate::caption::{self, history::HistoryEntry, CaptionSegment, TimedText};
+
+const REDACT_CARD: &str = "card";
+const REDACT_PHONE: &str = "phone";
+const REDACT_EMAIL: &str = "email";
+
+const CARD_PATTERN: &str = r"\b(?:\d[ -]?){12,18}\d\b";
+const PHONE_PATTERN: &str =
+    r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?|\b\d{2,4}[ .-])\d{3,4}[ .-]\d{3,4}\b";
+const EMAIL_PATTERN: &str = r"\b[\w.+-]+@[\w-]+(?:\.[\w-]+)+\b";
+
+/// Masks personal data such as card numbers, phone numbers and email addresses,
+/// replacing each match with its kind in brackets.
+#[derive(Clone)]
+pub struct Redactor {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone)]
+struct Rule {
+    regex: Regex,
+    label: &'static str,
+    /// Long digit runs that fail the card checksum are more likely amounts or ids.
+    luhn: bool,
+}
+
+impl Redactor {
+    /// Built-in rules by name, `card`, `phone` or `email`, and custom regular expressions.
+    /// `None` if there are neither. A rule that can't be used is skipped and described in
+    /// the returned list, leaving the others in effect.
+    pub fn new(names: &[String], patterns: &[String]) -> (Option<Self>, Vec<String>) {
+        let mut rules = vec![];
+        let mut skipped = vec![];
+
+        for name in names {
+            let (pattern, label) = match name.as_str() {
+                REDACT_CARD => (CARD_PATTERN, "[card]"),
+                REDACT_PHONE => (PHONE_PATTERN, "[phone]"),
+                REDACT_EMAIL => (EMAIL_PATTERN, "[email]"),
+                name => {
+                    skipped.push(format!("unknown redaction rule {name:?}"));
+                    continue;
+                }
+            };
+            rules.push(Rule {
+                regex: Regex::new(pattern).expect("built-in pattern"),
+                label,
+                luhn: name == REDACT_CARD,
+            });
+        }
+        for pattern in patterns {
+            match Regex::new(pattern) {
+                Ok(regex) => rules.push(Rule {
+                    regex,
+                    label: "[redacted]",
+                    luhn: false,
+                }),
+                Err(e) => skipped.push(format!("invalid redaction pattern {pattern:?}, {e}")),
+            }
+        }
+
+        ((!rules.is_empty()).then_some(Self { rules }), skipped)
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+
+        for rule in &self.rules {
+            text = rule
+                .regex
+                .replace_all(&text, |caps: &Captures| match &caps[0] {
+                    found if rule.luhn && !luhn(found) => found.to_string(),
+                    _ => rule.label.to_string(),
+                })
+                .into_owned();
+        }
+
+        text
+    }
+
+    /// Redacts the spans as one text, so a number read out over several spans is still
+    /// caught, then spreads the result back over them.
+    pub fn redact_timings(&self, timings: &[TimedText]) -> Vec<TimedText> {
+        let text: String = timings.iter().map(|timed| timed.text.as_str()).collect();
+        caption::retime(timings, &self.redact(&text))
+    }
+
+    pub fn redact_segments(&self, segments: &[CaptionSegment]) -> Vec<CaptionSegment> {
+        segments
+            .iter()
+            .map(|segment| CaptionSegment {
+                text: self.redact(&segment.text),
+                ..segment.clone()
+            })
+            .collect()
+    }
+
+    pub fn redact_entry(&self, entry: &HistoryEntry) -> HistoryEntry {
+        HistoryEntry {
+            text: self.redact(&entry.text),
+            ..entry.clone()
+        }
+    }
+}
+
+fn luhn(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            0 => d,
+            _ if d * 2 > 9 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+
+    sum.is_multiple_of(10)
+}
@@ -0,0 +1,340 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{
+    caption::{CaptionSegment, SegmentRole, TimedText, WrapRules},
+    config::FONT_SIZE_AUTO,
+};
+
+use super::Sink;
+
+const ASS_PLAY_RES: (u32, u32) = (1920, 1080);
+const ASS_AUTO_FONT_SIZE: u32 = 54;
+const DEFAULT_LINE_CHARS: usize = 42;
+const BOOKMARK_DURATION: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub fn from_path(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match ext.as_str() {
+            "vtt" => Self::Vtt,
+            "ass" | "ssa" => Self::Ass,
+            _ => Self::Srt,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SubtitleStyle {
+    pub font_name: String,
+    pub font_size: u32,
+    pub bold: bool,
+    pub italic: bool,
+    pub outline: bool,
+    pub opacity: f32,
+}
+
+pub struct SubtitleExport {
+    file: File,
+    format: SubtitleFormat,
+    origin: Instant,
+    start: Option<Duration>,
+    count: usize,
+    rules: WrapRules,
+    max_duration: Duration,
+    timings: Vec<TimedText>,
+    // Bookmarks of an SRT export, in the OGM chapter format players and muxers read.
+    chapters_path: PathBuf,
+    chapters: usize,
+}
+
+impl SubtitleExport {
+    pub fn new(
+        path: PathBuf,
+        style: &SubtitleStyle,
+        rules: WrapRules,
+        max_duration: Duration,
+    ) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let format = SubtitleFormat::from_path(&path);
+        let mut file = File::create(&path)?;
+        let chapters_path = path.with_extension("chapters.txt");
+
+        match format {
+            SubtitleFormat::Srt => {}
+            SubtitleFormat::Vtt => write!(file, "WEBVTT\r\n\r\n")?,
+            SubtitleFormat::Ass => write!(file, "{}", ass_header(style))?,
+        }
+        file.flush()?;
+
+        Ok(Self {
+            file,
+            format,
+            origin: Instant::now(),
+            start: None,
+            count: 0,
+            rules,
+            max_duration,
+            timings: vec![],
+            chapters_path,
+            chapters: 0,
+        })
+    }
+
+    /// Adds a chapter to the file next to the subtitles, created with the first one.
+    fn write_chapter(&mut self, start: Duration, name: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(self.chapters > 0)
+            .truncate(self.chapters == 0)
+            .open(&self.chapters_path)?;
+        self.chapters += 1;
+        write!(
+            file,
+            "CHAPTER{n:02}={}\r\nCHAPTER{n:02}NAME={}\r\n",
+            srt_time(start, '.'),
+            name.replace(['\r', '\n'], " "),
+            n = self.chapters
+        )?;
+
+        Ok(())
+    }
+
+    /// Writes a caption spoken over `start..end` from the beginning of the recording,
+    /// for transcribing files rather than live audio.
+    pub fn write_at(&mut self, start: Duration, end: Duration, text: &str) -> Result<()> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        self.write_span(start, end, text)
+    }
+
+    fn split_cues(&self, text: &str) -> Vec<String> {
+        let mut rules = self.rules;
+        if rules.max_chars == 0 {
+            rules.max_chars = DEFAULT_LINE_CHARS;
+        }
+
+        rules.cues(text)
+    }
+
+    /// Splits `text` into cues over `start..end`, giving each a share proportional to its length.
+    fn write_span(&mut self, start: Duration, end: Duration, text: &str) -> Result<()> {
+        let end = end.max(start + Duration::from_millis(500));
+        let cues = self.split_cues(text);
+        let total = cues
+            .iter()
+            .map(|cue| cue.chars().count())
+            .sum::<usize>()
+            .max(1);
+
+        let mut cue_start = start;
+        let mut chars = 0;
+        for cue in &cues {
+            chars += cue.chars().count();
+            let cue_end = start + (end - start).mul_f64(chars as f64 / total as f64);
+            self.write_cue(cue_start, cue_end.min(cue_start + self.max_duration), cue)?;
+            cue_start = cue_end;
+        }
+
+        Ok(())
+    }
+
+    fn write_cue(&mut self, start: Duration, end: Duration, text: &str) -> Result<()> {
+        self.count += 1;
+
+        match self.format {
+            SubtitleFormat::Srt => write!(
+                self.file,
+                "{}\r\n{} --> {}\r\n{}\r\n\r\n",
+                self.count,
+                srt_time(start, ','),
+                srt_time(end, ','),
+                text.replace('\n', "\r\n")
+            )?,
+            SubtitleFormat::Vtt => write!(
+                self.file,
+                "{} --> {}\r\n{}\r\n\r\n",
+                srt_time(start, '.'),
+                srt_time(end, '.'),
+                text.replace('\n', "\r\n")
+            )?,
+            SubtitleFormat::Ass => write!(
+                self.file,
+                "Dialogue: 0,{},{},Default,,0,0,0,,{}\r\n",
+                ass_time(start),
+                ass_time(end),
+                ass_escape(text)
+            )?,
+        }
+        self.file.flush()?;
+
+        Ok(())
+    }
+}
+
+impl Sink for SubtitleExport {
+    fn on_confirmed(&mut self, text: &str, _captured: Instant) -> Result<()> {
+        let end = self.origin.elapsed();
+        let start = self.start.take().unwrap_or(end).min(end);
+
+        let timings = std::mem::take(&mut self.timings);
+        if !timings.is_empty() {
+            for timing in timings {
+                let start = timing.start.saturating_duration_since(self.origin);
+                let end = timing.end.saturating_duration_since(self.origin);
+                self.write_span(start, end, timing.text.trim())?;
+            }
+            return Ok(());
+        }
+
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        self.write_span(start, end, text)
+    }
+
+    fn on_timed(&mut self, timings: &[TimedText]) -> Result<()> {
+        self.timings = timings.to_vec();
+        Ok(())
+    }
+
+    /// SRT has no comments, so bookmarks go to a chapters file beside it, such as
+    /// `captions.chapters.txt`; VTT gets a `NOTE` block and ASS a `Comment` event.
+    fn on_bookmark(&mut self, time: Instant, note: &str) -> Result<()> {
+        let start = time.saturating_duration_since(self.origin);
+        let end = start + BOOKMARK_DURATION;
+        let text = match note {
+            "" => "Bookmark".to_string(),
+            note => format!("Bookmark: {note}"),
+        };
+
+        match self.format {
+            SubtitleFormat::Srt => return self.write_chapter(start, &text),
+            SubtitleFormat::Vtt => write!(
+                self.file,
+                "NOTE {} {}\r\n\r\n",
+                srt_time(start, '.'),
+                text.replace("-->", "->")
+            )?,
+            SubtitleFormat::Ass => write!(
+                self.file,
+                "Comment: 0,{},{},Default,,0,0,0,,{}\r\n",
+                ass_time(start),
+                ass_time(end),
+                ass_escape(&text)
+            )?,
+        }
+        self.file.flush()?;
+
+        Ok(())
+    }
+
+    fn on_update(&mut self, segments: &[CaptionSegment]) -> Result<()> {
+        let speaking = segments.iter().any(|segment| {
+            segment.role == SegmentRole::Tentative && !segment.text.trim().is_empty()
+        });
+
+        if speaking && self.start.is_none() {
+            self.start = Some(self.origin.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+fn srt_time(time: Duration, separator: char) -> String {
+    let ms = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn ass_time(time: Duration) -> String {
+    let cs = time.as_millis() / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        cs / 360_000,
+        cs / 6000 % 60,
+        cs / 100 % 60,
+        cs % 100
+    )
+}
+
+fn ass_escape(text: &str) -> String {
+    text.replace('{', "\\{")
+        .replace('}', "\\}")
+        .replace("\r\n", "\\N")
+        .replace('\n', "\\N")
+}
+
+fn ass_header(style: &SubtitleStyle) -> String {
+    // BorderStyle 3 draws an opaque box filled with OutlineColour.
+    let box_alpha = (255.0 * (1.0 - style.opacity.clamp(0.0, 1.0))) as u32;
+    let (border_style, outline) = match (style.opacity > 0.0, style.outline) {
+        (true, _) => (3, 4),
+        (false, true) => (1, 2),
+        (false, false) => (1, 0),
+    };
+    let font_size = if style.font_size == FONT_SIZE_AUTO {
+        ASS_AUTO_FONT_SIZE
+    } else {
+        style.font_size
+    };
+
+    format!(
+        "[Script Info]\r\n\
+         ScriptType: v4.00+\r\n\
+         PlayResX: {}\r\n\
+         PlayResY: {}\r\n\
+         WrapStyle: 0\r\n\
+         \r\n\
+         [V4+ Styles]\r\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, \
+         Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, \
+         Alignment, MarginL, MarginR, MarginV, Encoding\r\n\
+         Style: Default,{},{},&H00FFFFFF,&H00CCCCCC,&H{:02X}000000,&H80000000,{},{},0,0,100,100,0,0,{},{},0,1,16,16,16,1\r\n\
+         \r\n\
+         [Events]\r\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\r\n",
+        ASS_PLAY_RES.0,
+        ASS_PLAY_RES.1,
+        style.font_name,
+        font_size,
+        box_alpha,
+        if style.bold { -1 } else { 0 },
+        if style.italic { -1 } else { 0 },
+        border_style,
+        outline,
+    )
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+
+use super::Sink;
+
+const DEFAULT_TEMPLATE: &str = r#"{"event":"{event}","text":"{text}","time":{time},"captured":{captured},"emitted":{emitted}}"#;
+const MAX_ATTEMPTS: u32 = 4;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Requests waiting while the endpoint is slow or down; beyond this the oldest are dropped.
+const MAX_QUEUED: usize = 64;
+
+/// POSTs each caption and bookmark to a URL, with the body made from a template.
+///
+/// `{event}`, `{text}` and `{time}` in the template are replaced by "caption" or "bookmark",
+/// the text or note, and Unix time in seconds. `{captured}` and `{emitted}` are Unix times in
+/// milliseconds of when the caption's audio started being captured and when the caption was
+/// confirmed, so consumers can subtract the latency when aligning it to their own recording;
+/// a bookmark is captured at the moment it was made. Values are JSON-escaped when the content
+/// type is JSON. Requests are sent in order from a background thread, retrying failures with
+/// exponential backoff.
+pub struct Webhook {
+    template: String,
+    json: bool,
+    queue: Arc<Queue>,
+}
+
+#[derive(Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct QueueState {
+    bodies: VecDeque<String>,
+    // Counts discards, so a request being retried knows to give up.
+    generation: u64,
+    closed: bool,
+    error: Option<String>,
+}
+
+impl Webhook {
+    pub fn new(url: &str, template: &str, content_type: &str) -> Result<Self> {
+        let url = url.trim().to_string();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            anyhow::bail!("invalid webhook url {url:?}");
+        }
+
+        let content_type = match content_type.trim() {
+            "" => "application/json".to_string(),
+            content_type => content_type.to_string(),
+        };
+        let json = content_type.contains("json");
+        let template = match template.trim() {
+            "" => DEFAULT_TEMPLATE.to_string(),
+            template => template.to_string(),
+        };
+
+        let queue = Arc::new(Queue::default());
+        thread::Builder::new().name("webhook".into()).spawn({
+            let queue = queue.clone();
+            move || {
+                let agent = ureq::builder().timeout(TIMEOUT).build();
+                while let Some((body, generation)) = queue.next() {
+                    let discarded = || {
+                        queue
+                            .state
+                            .lock()
+                            .map_or(true, |state| state.generation != generation)
+                    };
+                    if let Err(e) = post(&agent, &url, &content_type, &body, discarded) {
+                        if let Ok(mut state) = queue.state.lock() {
+                            state.error = Some(e);
+                        }
+                    }
+                }
+            }
+        })?;
+
+        Ok(Self {
+            template,
+            json,
+            queue,
+        })
+    }
+
+    fn send(&self, event: &str, text: &str, captured: Instant) -> Result<()> {
+        let now = Instant::now();
+        let emitted = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let captured = emitted.saturating_sub(now.saturating_duration_since(captured));
+        let text = if self.json {
+            escape_json(text)
+        } else {
+            text.to_string()
+        };
+
+        let body = self
+            .template
+            .replace("{event}", event)
+            .replace("{time}", &emitted.as_secs().to_string())
+            .replace("{captured}", &captured.as_millis().to_string())
+            .replace("{emitted}", &emitted.as_millis().to_string())
+            .replace("{text}", &text);
+
+        if let Ok(mut state) = self.queue.state.lock() {
+            state.bodies.push_back(body);
+            if state.bodies.len() > MAX_QUEUED {
+                state.bodies.pop_front();
+                state.error = Some("the webhook isn't keeping up, dropped a request".to_string());
+            }
+        }
+        self.queue.ready.notify_one();
+
+        Ok(())
+    }
+}
+
+impl Queue {
+    /// Waits for the next body and the generation it was queued in, or `None` once closed.
+    fn next(&self) -> Option<(String, u64)> {
+        let mut state = self.state.lock().ok()?;
+        loop {
+            if let Some(body) = state.bodies.pop_front() {
+                return Some((body, state.generation));
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.ready.wait(state).ok()?;
+        }
+    }
+}
+
+impl Drop for Webhook {
+    /// Lets the thread finish what is queued and stop.
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.queue.state.lock() {
+            state.closed = true;
+        }
+        self.queue.ready.notify_one();
+    }
+}
+
+impl Sink for Webhook {
+    fn on_confirmed(&mut self, text: &str, captured: Instant) -> Result<()> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        self.send("caption", text, captured)
+    }
+
+    fn on_bookmark(&mut self, time: Instant, note: &str) -> Result<()> {
+        self.send("bookmark", note, time)
+    }
+
+    fn discard_pending(&mut self) {
+        if let Ok(mut state) = self.queue.state.lock() {
+            state.bodies.clear();
+            state.generation += 1;
+        }
+    }
+
+    fn take_error(&mut self) -> Option<String> {
+        self.queue.state.lock().ok()?.error.take()
+    }
+}
+
+/// Retries connection errors, rate limits and server errors, and gives up on the rest,
+/// or once the body is discarded. The error is why the request was given up on.
+fn post(
+    agent: &ureq::Agent,
+    url: &str,
+    content_type: &str,
+    body: &str,
+    discarded: impl Fn() -> bool,
+) -> Result<(), String> {
+    let mut delay = FIRST_RETRY_DELAY;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let (retry, e) = match agent
+            .post(url)
+            .set("Content-Type", content_type)
+            .send_string(body)
+        {
+            Ok(_) => return Ok(()),
+            Err(ureq::Error::Status(status, response)) => (
+                status == 429 || status >= 500,
+                format!("{status} {}", response.status_text()),
+            ),
+            Err(e @ ureq::Error::Transport(_)) => (true, e.to_string()),
+        };
+        if !retry || attempt == MAX_ATTEMPTS {
+            return Err(e);
+        }
+
+        thread::sleep(delay);
+        if discarded() {
+            return Ok(());
+        }
+        delay *= 2;
+    }
+
+    Ok(())
+}
+
+/// The contents of a JSON string literal, without the quotes.
+fn escape_json(text: &str) -> String {
+    let quoted = serde_json::Value::from(text).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::Write as _,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::gui::utils;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+// A day of samples.
+const MAX_SAMPLES: usize = 24 * 60;
+const MB: f64 = (1 << 20) as f64;
+
+/// Samples process memory once a minute to show growth over long sessions,
+/// optionally appending each sample to a CSV file.
+pub struct SoakMonitor {
+    started: Instant,
+    sampled: Option<Instant>,
+    samples: VecDeque<(Duration, usize, usize)>,
+    log: Option<File>,
+    log_paused: bool,
+}
+
+impl SoakMonitor {
+    pub fn new(log: Option<PathBuf>) -> Result<Self> {
+        let log = match log {
+            Some(path) => {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                // Later sessions append under the header of the first.
+                if file.metadata()?.len() == 0 {
+                    writeln!(file, "elapsed_s,working_set_mb,private_mb")?;
+                }
+                Some(file)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            started: Instant::now(),
+            sampled: None,
+            samples: VecDeque::new(),
+            log,
+            log_paused: false,
+        })
+    }
+
+    pub fn tick(&mut self) {
+        if self
+            .sampled
+            .is_some_and(|sampled| sampled.elapsed() < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        self.sampled = Some(Instant::now());
+
+        let Some((working_set, private)) = utils::process_memory() else {
+            return;
+        };
+        let elapsed = self.started.elapsed();
+
+        self.samples.push_back((elapsed, working_set, private));
+        if self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+
+        if let Some(log) = self.log.as_mut().filter(|_| !self.log_paused) {
+            _ = writeln!(
+                log,
+                "{},{:.1},{:.1}",
+                elapsed.as_secs(),
+                working_set as f64 / MB,
+                private as f64 / MB
+            );
+        }
+    }
+
+    /// Keeps sampling for the summary without appending to the CSV file.
+    pub fn set_log_paused(&mut self, paused: bool) {
+        self.log_paused = paused;
+    }
+
+    pub fn summary(&self) -> String {
+        let (Some(first), Some(last)) = (self.samples.front(), self.samples.back()) else {
+            return "Memory: not sampled yet".to_string();
+        };
+
+        let hours = (last.0 - first.0).as_secs_f64() / 3600.0;
+        let growth = if hours > 0.0 {
+            format!(
+                "{:+.1} MB/h over {hours:.1} h",
+                (last.2 as f64 - first.2 as f64) / MB / hours
+            )
+        } else {
+            "growth after the next sample".to_string()
+        };
+
+        format!(
+            "Memory: {:.0} MB working set, {:.0} MB private, {growth}",
+            last.1 as f64 / MB,
+            last.2 as f64 / MB
+        )
+    }
+}
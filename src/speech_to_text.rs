@@ -1,36 +1,109 @@
 use std::{
-    sync::mpsc::{Receiver, Sender},
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
     thread::JoinHandle,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
 use audio::Audio;
+pub use audio::{audio_devices, read_wav, AudioClock, AudioDevice};
 use candle_transformers::models::whisper::SAMPLE_RATE;
-use text::TextStream;
+use download::DownloadProgress;
+use guardrail::{Guardrail, Mitigation};
+pub use meeting::{MeetingEvent, MeetingOptions, MeetingTranscriber, Speaker};
+pub use models::{
+    description as model_description, repo_ids as model_repo_ids, settings as model_settings,
+    ModelSetting, LANGUAGES, SETTING_ENCODER_STRIDE,
+};
+use postprocess::{
+    Casing, DisfluencyFilter, Plugins, PostProcessChain, PostProcessor as _, Punctuation,
+    Replacements, Transliterator,
+};
+use preprocess::AudioChain;
+pub use stream::{MultiStreamTranscriber, StreamingTranscriber, TranscriptEvent};
+use text::{Granularity, TextStream};
 use transcribe::Transcriber;
+pub use vad::VadSensitivity;
+use vad::{SilenceTrimmer, SpeechActivity};
 use windows::Win32::{
     System::WinRT::{RoInitialize, RO_INIT_MULTITHREADED},
     UI::WindowsAndMessaging::{MessageBoxA, MB_OK},
 };
 use windows_core::{s, PCSTR};
 
+use crate::{
+    caption::{self, CaptionSegment, SegmentRole, TimedText},
+    config::{
+        DELAY_ADAPTIVE, DELAY_HIGH, DELAY_HIGHEST, DELAY_LOW, GRANULARITY_PHRASE,
+        GRANULARITY_SENTENCE, GRANULARITY_WORD, TRANSLITERATION_KANA, TRANSLITERATION_ROMAJI,
+    },
+};
+
+const REDUCED_ENCODER_STRIDE: usize = 100;
+// Adaptive latency aims for one decode per this many new tokens.
+const ADAPTIVE_TOKENS_PER_DECODE: f32 = 1.0;
+// Weight of the latest update in the smoothed real-time factor.
+const RTF_SMOOTHING: f32 = 0.1;
+
 mod audio;
+mod download;
+mod guardrail;
+mod meeting;
 mod mel;
+mod models;
+mod postprocess;
+mod preprocess;
+mod stream;
 mod text;
 mod transcribe;
+mod vad;
 
 pub struct SpeechToText {
     sender: Sender<Message>,
     handle: Option<JoinHandle<Result<()>>>,
     ts: TextStream,
+    /// Applied to confirmed text, which sinks receive too.
+    finalize: PostProcessChain,
+    /// Applied to all captions, confirmed or not, before they are shown or sent to sinks.
+    filter: PostProcessChain,
+    display: PostProcessChain,
+    status: Arc<Mutex<EngineStatus>>,
+}
+
+/// What the engine runs on and how fast, for bug reports.
+#[derive(Clone, Default)]
+pub struct EngineStatus {
+    /// Empty until a model has loaded.
+    pub model: String,
+    pub parameters: usize,
+    pub vram_bytes: usize,
+    pub cpu_decoder: bool,
+    /// Processing time over audio duration, smoothed; below 1 keeps up with real time.
+    pub real_time_factor: f32,
+    pub audio_device: String,
+    pub capture_format: String,
+    pub audio_clock: AudioClock,
+    /// Updates cut short by the token budget.
+    pub truncations: u32,
 }
 
 impl SpeechToText {
-    pub fn new(repo_id: &str, latency: Duration) -> Result<Self> {
+    pub fn new(repo_id: &str, latency: Duration, model_dir: &Path) -> Result<Self> {
         let ts = TextStream::new();
+        let status = Arc::new(Mutex::new(EngineStatus::default()));
         let (sender, receiver) = std::sync::mpsc::channel();
-        let mut ctx = SpeechToTextContext::new(latency, ts.clone(), receiver)?;
+        let mut ctx = SpeechToTextContext::new(
+            latency,
+            model_dir.to_path_buf(),
+            ts.clone(),
+            status.clone(),
+            receiver,
+        )?;
         let handle = Some(std::thread::spawn(move || -> Result<()> {
             unsafe { RoInitialize(RO_INIT_MULTITHREADED) }?;
             ctx.process()
@@ -38,21 +111,254 @@ impl SpeechToText {
 
         _ = sender.send(Message::Model(repo_id.to_string()));
 
-        Ok(Self { sender, handle, ts })
+        Ok(Self {
+            sender,
+            handle,
+            ts,
+            finalize: PostProcessChain::new(),
+            filter: PostProcessChain::new(),
+            display: PostProcessChain::new(),
+            status,
+        })
+    }
+
+    pub fn status(&self) -> EngineStatus {
+        self.status
+            .lock()
+            .map(|status| status.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn segments(&mut self) -> Option<Vec<CaptionSegment>> {
+        let mut segments = self.ts.get()?;
+
+        for segment in &mut segments {
+            if segment.role == SegmentRole::Confirmed {
+                segment.text = self.finalize.process(&segment.text);
+            }
+            if segment.role != SegmentRole::Status {
+                segment.text = self.filter.process(&segment.text);
+                segment.text = self.display.process(&segment.text);
+            }
+        }
+
+        Some(segments)
+    }
+
+    /// Speech is heard but no text for it has been decoded yet.
+    pub fn awaiting_text(&self) -> bool {
+        self.ts.awaiting_text()
+    }
+
+    /// Confirmed captions with their spoken spans, if the model reports them,
+    /// and when their audio started.
+    pub fn confirmed(&mut self) -> Vec<(String, Vec<TimedText>, Instant)> {
+        let mut confirmed = self.ts.take_confirmed();
+        for (text, timings, _) in &mut confirmed {
+            *text = self.finalize.process(text);
+            *text = self.filter.process(text);
+            *timings = caption::retime(timings, text);
+        }
+        confirmed
+    }
+
+    /// Runs text confirmed elsewhere, such as by the meeting transcriber, through the same
+    /// chains as `confirmed`.
+    pub fn process_confirmed(&mut self, text: &str) -> String {
+        let text = self.finalize.process(text);
+        self.filter.process(&text)
+    }
+
+    /// Prepares text for the screen: confirmed text from `process_confirmed` only needs the
+    /// display chain, partial text the filter chain as well.
+    pub fn process_display(&mut self, text: &str, partial: bool) -> String {
+        match partial {
+            true => {
+                let text = self.filter.process(text);
+                self.display.process(&text)
+            }
+            false => self.display.process(text),
+        }
+    }
+
+    /// Confirmed captions held back by the language filter, as `(language, text, timings)`.
+    pub fn filtered(&mut self) -> Vec<(String, String, Vec<TimedText>)> {
+        self.ts.take_filtered()
+    }
+
+    /// Keeps captions out unless the model detects one of `languages`, such as "en".
+    /// With `ghost` they are still shown, dimmed. Needs a multilingual model.
+    pub fn set_language_filter(&self, languages: Vec<String>, ghost: bool) {
+        _ = self
+            .sender
+            .send(Message::LanguageDetection(!languages.is_empty()));
+        self.ts.set_language_filter(languages, ghost);
+    }
+
+    /// Fixes the spoken language, such as "ja", or detects it if empty,
+    /// and with `translate` captions in English. Needs a multilingual model.
+    pub fn set_task(&self, language: &str, translate: bool) {
+        _ = self
+            .sender
+            .send(Message::Task(language.to_string(), translate));
+    }
+
+    /// Biases decoding towards `words`; empty turns it off.
+    pub fn set_hotwords(&self, words: Vec<String>) {
+        _ = self.sender.send(Message::Hotwords(words));
+    }
+
+    pub fn set_transliteration(&mut self, transliteration: &str) {
+        let transliterator = match transliteration {
+            TRANSLITERATION_ROMAJI => Some(Transliterator::Romaji),
+            TRANSLITERATION_KANA => Some(Transliterator::Hiragana),
+            _ => None,
+        };
+
+        self.display.set(
+            "transliteration",
+            transliterator.map(|t| Box::new(t) as Box<_>),
+        );
+        self.ts.refresh();
+    }
+
+    /// How often the caption in progress updates, by one of the `GRANULARITY_*` names.
+    pub fn set_granularity(&mut self, granularity: &str) {
+        self.ts.set_granularity(match granularity {
+            GRANULARITY_WORD => Granularity::Word,
+            GRANULARITY_PHRASE => Granularity::Phrase,
+            GRANULARITY_SENTENCE => Granularity::Sentence,
+            _ => Granularity::Token,
+        });
+    }
+
+    /// Only the displayed captions are simplified; confirmed text sent to sinks stays verbatim.
+    pub fn set_simplify(&mut self, enable: bool) {
+        self.display.set(
+            "disfluency",
+            enable.then(|| Box::new(DisfluencyFilter) as Box<_>),
+        );
+        self.ts.refresh();
+    }
+
+    /// Capitalizes sentence starts and `names` in confirmed text, for models that output lowercase.
+    pub fn set_casing(&mut self, enable: bool, names: &[String]) {
+        self.finalize.set(
+            "casing",
+            enable.then(|| Box::new(Casing::new(names)) as Box<_>),
+        );
+        self.ts.refresh();
+    }
+
+    /// Restores punctuation in confirmed text with the model in `dir`, for models that output none.
+    /// Text is left as it is, with the reason shown, if the model can't be loaded. The model
+    /// runs on the engine's thread, holding each caption back until it is done.
+    pub fn set_punctuation(&mut self, enable: bool, dir: &Path) {
+        _ = self
+            .sender
+            .send(Message::Punctuation(enable.then(|| dir.to_path_buf())));
+    }
+
+    /// Masks or replaces words by `rules`, see [`Replacements`], such as profanity or names
+    /// the model keeps getting wrong. A rule that doesn't parse is left out, with the reason shown.
+    pub fn set_replacements(&mut self, rules: &[String]) {
+        let (replacements, skipped) = Replacements::new(rules);
+        self.filter.set(
+            "replacements",
+            replacements.map(|replacements| Box::new(replacements) as Box<_>),
+        );
+        if !skipped.is_empty() {
+            let skipped: Vec<_> = skipped.iter().map(|e| format!("Skipped {e}")).collect();
+            self.ts.set_status(skipped.join("\n"));
+        }
+        self.ts.refresh();
     }
 
-    pub fn text(&mut self) -> Option<String> {
-        self.ts.get()
+    /// Runs confirmed and displayed text through the DLLs at `paths`, see [`Plugins`]. One
+    /// that can't be loaded is left out, with the reason shown.
+    pub fn set_plugins(&mut self, paths: &[PathBuf]) {
+        let (plugins, errors) = Plugins::load(paths);
+        self.filter.set(
+            "plugins",
+            (!plugins.is_empty()).then(|| Box::new(plugins) as Box<_>),
+        );
+        if !errors.is_empty() {
+            let errors: Vec<_> = errors.iter().map(|e| format!("{e:?}")).collect();
+            self.ts.set_status(errors.join("\n"));
+        }
+        self.ts.refresh();
     }
 
     pub fn set_model(&self, repo_id: &str) {
         _ = self.sender.send(Message::Model(repo_id.to_string()));
     }
 
+    /// Frees the model until the next `set_model`, for when another engine takes over.
+    pub fn unload(&self) {
+        _ = self.sender.send(Message::Unload);
+    }
+
+    pub fn set_model_options(&self, options: BTreeMap<String, String>) {
+        _ = self.sender.send(Message::ModelOptions(options));
+    }
+
     pub fn set_latency(&self, latency: Duration) {
         _ = self.sender.send(Message::Latency(latency.as_millis() as _));
     }
 
+    pub fn set_encoder_stride(&self, encoder_stride: u32) {
+        _ = self.sender.send(Message::EncoderStride(encoder_stride));
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        _ = self.sender.send(Message::Pause(paused));
+    }
+
+    pub fn set_monitor(&self, device_name: &str, delay: Duration) {
+        _ = self.sender.send(Message::Monitor(
+            device_name.to_string(),
+            delay.as_millis() as _,
+        ));
+    }
+
+    /// Captions the audio endpoint with the id `source`, or the default output device if it is empty.
+    pub fn set_audio_source(&self, source: &str) {
+        _ = self.sender.send(Message::Source(source.to_string()));
+    }
+
+    /// Sets the preprocessing between capture and resampling, as in `audio-chain`;
+    /// an empty chain only mixes the channels down.
+    pub fn set_audio_chain(&self, chain: &str) {
+        _ = self.sender.send(Message::AudioChain(chain.to_string()));
+    }
+
+    pub fn set_guardrails(&self, enable: bool) {
+        _ = self.sender.send(Message::Guardrails(enable));
+    }
+
+    pub fn set_silence_trim(&self, max_silence: Duration) {
+        _ = self
+            .sender
+            .send(Message::SilenceTrim(max_silence.as_millis() as _));
+    }
+
+    /// Audio kept from before a speech onset when silence is trimmed.
+    pub fn set_pre_roll(&self, pre_roll: Duration) {
+        _ = self
+            .sender
+            .send(Message::PreRoll(pre_roll.as_millis() as _));
+    }
+
+    /// Adapts the silence threshold to the background noise instead of a fixed level.
+    pub fn set_adaptive_vad(&self, enable: bool) {
+        _ = self.sender.send(Message::AdaptiveVad(enable));
+    }
+
+    /// Sets how readily audio counts as speech, for trimming silence and for the speaking indicator.
+    pub fn set_vad_sensitivity(&self, sensitivity: VadSensitivity) {
+        _ = self.sender.send(Message::VadSensitivity(sensitivity));
+    }
+
     pub fn clear(&self) {
         _ = self.sender.send(Message::Claer);
     }
@@ -76,23 +382,75 @@ impl Drop for SpeechToText {
 struct SpeechToTextContext {
     audio: Audio,
     transcriber: Option<Transcriber>,
+    pending_model: Option<String>,
+    repo_id: String,
+    trimmer: Option<SilenceTrimmer>,
+    activity: SpeechActivity,
+    max_silence: Duration,
+    pre_roll: Duration,
+    adaptive_vad: bool,
+    vad_sensitivity: VadSensitivity,
+    encoder_stride: usize,
+    model_options: BTreeMap<String, String>,
+    guardrail: Option<Guardrail>,
+    // The status the guardrails last showed, cleared by them alone.
+    guardrail_status: String,
+    language_detection: bool,
+    language: String,
+    translate: bool,
+    hotwords: Vec<String>,
+    punctuation: Option<Punctuation>,
     ts: TextStream,
+    status: Arc<Mutex<EngineStatus>>,
     latency: Duration,
+    model_dir: PathBuf,
     receiver: Receiver<Message>,
     keep_running: bool,
+    paused: bool,
 }
 
 impl SpeechToTextContext {
-    fn new(latency: Duration, ts: TextStream, receiver: Receiver<Message>) -> Result<Self> {
+    fn new(
+        latency: Duration,
+        model_dir: PathBuf,
+        ts: TextStream,
+        status: Arc<Mutex<EngineStatus>>,
+        receiver: Receiver<Message>,
+    ) -> Result<Self> {
         let audio = Audio::new(SAMPLE_RATE as _)?;
+        let activity = SpeechActivity::new(audio.sample_rate());
+        if let Ok(mut status) = status.lock() {
+            status.audio_device = audio.device_name();
+            status.capture_format = audio.capture_format();
+        }
 
         Ok(Self {
             audio,
             transcriber: None,
+            pending_model: None,
+            repo_id: String::new(),
+            trimmer: None,
+            activity,
+            max_silence: Duration::ZERO,
+            pre_roll: Duration::ZERO,
+            adaptive_vad: false,
+            vad_sensitivity: VadSensitivity::default(),
+            encoder_stride: 0,
+            model_options: BTreeMap::new(),
+            guardrail: None,
+            guardrail_status: String::new(),
+            language_detection: false,
+            language: String::new(),
+            translate: false,
+            hotwords: vec![],
+            punctuation: None,
             ts,
+            status,
             latency,
+            model_dir,
             receiver,
             keep_running: true,
+            paused: false,
         })
     }
 
@@ -102,44 +460,185 @@ impl SpeechToTextContext {
                 continue;
             }
 
-            if self.transcriber.is_some() {
-                self.transcribe()?;
+            // Loading waits for the queue to drain so the model's options are known beforehand.
+            if let Some(repo_id) = self.pending_model.take() {
+                self.load_model(repo_id);
+                continue;
+            }
+
+            // A failed update is reported and the next one tried, rather than ending captions.
+            if self.paused {
+                if let Err(e) = self.audio.capture() {
+                    self.ts.set_status(format!("{e:?}"));
+                }
+                self.audio.clear();
+                self.activity.clear();
+                self.ts.set_speaking(false);
+            } else if self.transcriber.is_some() {
+                if let Err(e) = self.transcribe() {
+                    self.ts.set_status(format!("{e:?}"));
+                }
+            }
+            if let Some(punctuation) = &mut self.punctuation {
+                self.ts.finalize(|text| punctuation.process(text));
+            }
+
+            if self.audio.take_device_change() {
+                self.on_device_changed();
+                self.ts
+                    .set_status(format!("Capturing {}", self.audio.device_name()));
+            }
+            if let Some(e) = self.audio.take_device_error() {
+                self.ts
+                    .set_status(format!("No audio device, waiting for one, {e}"));
             }
         }
 
         Ok(())
     }
 
+    fn on_device_changed(&mut self) {
+        if let Ok(mut status) = self.status.lock() {
+            status.audio_device = self.audio.device_name();
+            status.capture_format = self.audio.capture_format();
+            status.audio_clock = AudioClock::default();
+        }
+        if let Some(trimmer) = &mut self.trimmer {
+            trimmer.clear();
+        }
+        self.activity.clear();
+    }
+
     fn recieve_message(&mut self) -> Result<bool> {
-        if let Ok(message) = self.receiver.recv_timeout(self.latency) {
+        if let Ok(message) = self.receiver.recv_timeout(self.effective_latency()) {
             match message {
                 Message::Quit => {
                     self.keep_running = false;
                 }
+                Message::Pause(paused) => {
+                    self.paused = paused;
+                }
                 Message::Claer => {
                     if let Some(transcriber) = &mut self.transcriber {
                         transcriber.clear();
                     }
+                    if let Some(trimmer) = &mut self.trimmer {
+                        trimmer.clear();
+                    }
+                    self.activity.clear();
                     self.audio.clear();
                     self.ts.clear();
                 }
                 Message::Model(repo_id) => {
                     self.ts.clear();
-                    self.ts.set(format!("Loading {repo_id}\r\n"), true);
-
-                    match Transcriber::new(&repo_id) {
-                        Ok(transcriber) => {
-                            self.ts.clear();
-                            self.transcriber = Some(transcriber)
-                        }
+                    self.ts.set_status(format!("Loading {repo_id}"));
+                    self.pending_model = Some(repo_id);
+                }
+                Message::Unload => {
+                    self.pending_model = None;
+                    self.transcriber = None;
+                    self.ts.clear();
+                    if let Ok(mut status) = self.status.lock() {
+                        status.model.clear();
+                        status.parameters = 0;
+                        status.vram_bytes = 0;
+                        status.real_time_factor = 0.0;
+                    }
+                }
+                Message::Punctuation(dir) => {
+                    self.punctuation = match dir.map(|dir| Punctuation::load(&dir)).transpose() {
+                        Ok(punctuation) => punctuation,
                         Err(e) => {
-                            self.ts.set(format!("{e:?}"), true);
+                            self.ts.set_status(format!("{e:?}"));
+                            None
                         }
-                    }
+                    };
+                    self.ts.set_finalizing(self.punctuation.is_some());
                 }
                 Message::Latency(latency) => {
                     self.latency = Duration::from_millis(latency as _);
                 }
+                Message::EncoderStride(encoder_stride) => {
+                    self.encoder_stride = encoder_stride as _;
+                    self.update_encoder_stride();
+                }
+                Message::ModelOptions(options) => {
+                    self.model_options = options;
+                    self.apply_model_options();
+
+                    let cpu_decoder = self.cpu_decoder();
+                    if self
+                        .transcriber
+                        .as_ref()
+                        .is_some_and(|transcriber| transcriber.cpu_decoder() != cpu_decoder)
+                    {
+                        self.ts.set_status(format!("Loading {}", self.repo_id));
+                        self.pending_model = Some(self.repo_id.clone());
+                    }
+                }
+                Message::Monitor(device_name, delay) => {
+                    let delay = Duration::from_millis(delay as _);
+                    if let Err(e) = self.audio.set_monitor(&device_name, delay) {
+                        self.ts.set_status(format!("{e:?}"));
+                    }
+                }
+                Message::Source(source) => {
+                    match self.audio.set_source(&source) {
+                        Ok(()) => self.ts.set_status(String::new()),
+                        Err(e) => self.ts.set_status(format!("{e:?}")),
+                    }
+                    self.on_device_changed();
+                }
+                Message::AudioChain(chain) => match AudioChain::parse(&chain) {
+                    Ok(chain) => {
+                        self.audio.set_chain(chain);
+                        self.on_device_changed();
+                    }
+                    Err(e) => self.ts.set_status(format!("audio-chain: {e:?}")),
+                },
+                Message::Guardrails(enable) => {
+                    self.guardrail = enable.then(Guardrail::new);
+                    self.update_encoder_stride();
+                    self.ts
+                        .clear_status(&std::mem::take(&mut self.guardrail_status));
+                }
+                Message::LanguageDetection(enable) => {
+                    self.language_detection = enable;
+                    if let Some(transcriber) = &mut self.transcriber {
+                        transcriber.set_language_detection(enable);
+                    }
+                }
+                Message::Task(language, translate) => {
+                    if let Some(transcriber) = &mut self.transcriber {
+                        transcriber.set_task(&language, translate);
+                    }
+                    self.language = language;
+                    self.translate = translate;
+                }
+                Message::Hotwords(words) => {
+                    if let Some(transcriber) = &mut self.transcriber {
+                        transcriber.set_hotwords(&words);
+                    }
+                    self.hotwords = words;
+                }
+                Message::SilenceTrim(max_silence) => {
+                    self.max_silence = Duration::from_millis(max_silence as _);
+                    self.update_trimmer();
+                }
+                Message::PreRoll(pre_roll) => {
+                    self.pre_roll = Duration::from_millis(pre_roll as _);
+                    self.update_trimmer();
+                }
+                Message::AdaptiveVad(enable) => {
+                    self.adaptive_vad = enable;
+                    self.activity.set_adaptive(enable);
+                    self.update_trimmer();
+                }
+                Message::VadSensitivity(sensitivity) => {
+                    self.vad_sensitivity = sensitivity;
+                    self.activity.set_sensitivity(sensitivity);
+                    self.update_trimmer();
+                }
             }
             Ok(true)
         } else {
@@ -147,17 +646,233 @@ impl SpeechToTextContext {
         }
     }
 
+    fn load_model(&mut self, repo_id: String) {
+        // The sentence in progress is replayed into the new model instead of being dropped.
+        let snapshot = self
+            .transcriber
+            .take()
+            .map(|transcriber| transcriber.snapshot())
+            .filter(|_| models::sample_rate(&repo_id) == self.audio.sample_rate())
+            .unwrap_or_default();
+        if let Err(e) = self.audio.set_sample_rate(models::sample_rate(&repo_id)) {
+            self.ts.set_status(format!("{e:?}"));
+        }
+        self.update_trimmer();
+
+        let progress = (!download::is_cached(&repo_id, &self.model_dir))
+            .then(|| DownloadProgress::start(&repo_id, &self.model_dir, self.ts.clone()));
+        let transcriber = Transcriber::new(&repo_id, &self.model_dir, self.cpu_decoder());
+        drop(progress);
+
+        match transcriber {
+            Ok(mut transcriber) => {
+                self.ts.clear();
+                transcriber.set_encoder_stride(self.effective_encoder_stride());
+                self.transcriber = Some(transcriber);
+                self.apply_model_options();
+
+                if let Some(transcriber) =
+                    self.transcriber.as_mut().filter(|_| !snapshot.is_empty())
+                {
+                    match transcriber.transcribe(&snapshot) {
+                        Ok(Some((text, is_new_segment))) => {
+                            let timings = transcriber.timings().to_vec();
+                            let language = transcriber.language().map(str::to_string);
+                            let captured = transcriber.window_start();
+                            self.ts
+                                .set(text, is_new_segment, timings, language, captured);
+                        }
+                        Ok(None) => {}
+                        Err(e) => self.ts.set_status(format!("{e:?}")),
+                    }
+                }
+
+                if let Some(guardrail) = &mut self.guardrail {
+                    guardrail.reset();
+                }
+            }
+            Err(e) => {
+                self.ts.set_status(format!("{e:?}"));
+            }
+        }
+
+        if let Ok(mut status) = self.status.lock() {
+            let transcriber = self.transcriber.as_ref();
+            status.model = transcriber.map(|_| repo_id.clone()).unwrap_or_default();
+            status.parameters = transcriber.map_or(0, Transcriber::parameters);
+            status.vram_bytes = transcriber.map_or(0, Transcriber::vram_bytes);
+            status.cpu_decoder = transcriber.is_some_and(Transcriber::cpu_decoder);
+            status.capture_format = self.audio.capture_format();
+            status.real_time_factor = 0.0;
+        }
+
+        self.repo_id = repo_id;
+    }
+
+    fn cpu_decoder(&self) -> bool {
+        self.model_options
+            .get(models::SETTING_DECODER_DEVICE)
+            .is_some_and(|value| value == "cpu")
+    }
+
+    fn mitigation(&self) -> Mitigation {
+        self.guardrail
+            .as_ref()
+            .map(|guardrail| guardrail.level())
+            .unwrap_or(Mitigation::None)
+    }
+
+    fn effective_latency(&self) -> Duration {
+        let latency = if self.latency == DELAY_ADAPTIVE {
+            self.adaptive_latency()
+        } else {
+            self.latency
+        };
+
+        if self.mitigation() >= Mitigation::RaiseLatency {
+            (latency * 4).min(DELAY_HIGHEST)
+        } else {
+            latency
+        }
+    }
+
+    /// Fast speech is decoded often to keep up; slow or sparse speech waits, saving GPU time.
+    fn adaptive_latency(&self) -> Duration {
+        let rate = self
+            .transcriber
+            .as_ref()
+            .map_or(0.0, Transcriber::speech_rate);
+        if rate <= 0.0 {
+            return DELAY_HIGH;
+        }
+
+        Duration::from_secs_f32(ADAPTIVE_TOKENS_PER_DECODE / rate).clamp(DELAY_LOW, DELAY_HIGH)
+    }
+
+    fn effective_encoder_stride(&self) -> usize {
+        let encoder_stride = self
+            .model_options
+            .get(models::SETTING_ENCODER_STRIDE)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(self.encoder_stride);
+
+        if self.mitigation() >= Mitigation::ReduceDecodes {
+            encoder_stride.max(REDUCED_ENCODER_STRIDE)
+        } else {
+            encoder_stride
+        }
+    }
+
+    fn update_encoder_stride(&mut self) {
+        let encoder_stride = self.effective_encoder_stride();
+        if let Some(transcriber) = &mut self.transcriber {
+            transcriber.set_encoder_stride(encoder_stride);
+        }
+    }
+
+    fn apply_model_options(&mut self) {
+        self.update_encoder_stride();
+
+        let enabled = |key| {
+            self.model_options
+                .get(key)
+                .is_some_and(|value| value == "1")
+        };
+        let timestamps = enabled(models::SETTING_TIMESTAMPS);
+        let hallucination_guard = enabled(models::SETTING_HALLUCINATION_GUARD);
+        let token_budget = self
+            .model_options
+            .get(models::SETTING_TOKEN_BUDGET)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+
+        if let Some(transcriber) = &mut self.transcriber {
+            transcriber.set_timestamps(timestamps);
+            transcriber.set_hallucination_guard(hallucination_guard);
+            transcriber.set_token_budget(token_budget);
+            transcriber.set_language_detection(self.language_detection);
+            transcriber.set_task(&self.language, self.translate);
+            transcriber.set_hotwords(&self.hotwords);
+        }
+    }
+
+    fn update_trimmer(&mut self) {
+        self.trimmer = if self.max_silence.is_zero() {
+            None
+        } else {
+            Some(SilenceTrimmer::new(
+                self.audio.sample_rate(),
+                self.max_silence,
+                self.pre_roll,
+                self.adaptive_vad,
+                self.vad_sensitivity,
+            ))
+        };
+    }
+
     fn transcribe(&mut self) -> Result<()> {
-        let audio = self.audio.capture()?;
+        let start = Instant::now();
+        let sample_rate = self.audio.sample_rate();
+        self.audio.capture()?;
+        if let (Some(transcriber), Some(end)) = (&mut self.transcriber, self.audio.captured_until())
+        {
+            transcriber.set_audio_end(end);
+        }
+        let mut audio = self.audio.captured();
+        let duration = Duration::from_secs_f32(audio.len() as f32 / sample_rate as f32);
+        self.ts.set_speaking(self.activity.process(audio));
+
+        if let Some(trimmer) = &mut self.trimmer {
+            audio = trimmer.process(audio);
+        }
 
+        let mut truncations = None;
         let result = if let Some(transcruber) = &mut self.transcriber {
-            transcruber.transcribe(audio)?
+            let result = transcruber.transcribe(audio)?.map(|result| {
+                let language = transcruber.language().map(str::to_string);
+                let captured = transcruber.window_start();
+                (result, transcruber.timings().to_vec(), language, captured)
+            });
+            truncations = Some(transcruber.truncations());
+            result
         } else {
             None
         };
 
-        if let Some((text, is_new_segment)) = result {
-            self.ts.set(text, is_new_segment);
+        if let Some(((text, is_new_segment), timings, language, captured)) = result {
+            self.ts
+                .set(text, is_new_segment, timings, language, captured);
+        }
+
+        if !duration.is_zero() {
+            let rtf = start.elapsed().as_secs_f32() / duration.as_secs_f32();
+            if let Ok(mut status) = self.status.lock() {
+                status.audio_clock = self.audio.clock();
+                if let Some(truncations) = truncations {
+                    status.truncations = truncations;
+                }
+                status.real_time_factor = if status.real_time_factor == 0.0 {
+                    rtf
+                } else {
+                    status.real_time_factor + (rtf - status.real_time_factor) * RTF_SMOOTHING
+                };
+            }
+        }
+
+        let changed = self
+            .guardrail
+            .as_mut()
+            .and_then(|guardrail| guardrail.record(start.elapsed(), duration));
+        if let Some(mitigation) = changed {
+            self.update_encoder_stride();
+            let previous = std::mem::take(&mut self.guardrail_status);
+            match mitigation.message() {
+                Some(message) => {
+                    self.guardrail_status = message.to_string();
+                    self.ts.set_status(self.guardrail_status.clone());
+                }
+                None => self.ts.clear_status(&previous),
+            }
         }
 
         Ok(())
@@ -168,7 +883,23 @@ unsafe impl Send for SpeechToTextContext {}
 
 enum Message {
     Quit,
+    Pause(bool),
     Claer,
     Model(String),
+    Unload,
+    Punctuation(Option<PathBuf>),
     Latency(u32),
+    EncoderStride(u32),
+    ModelOptions(BTreeMap<String, String>),
+    Guardrails(bool),
+    LanguageDetection(bool),
+    Task(String, bool),
+    Hotwords(Vec<String>),
+    SilenceTrim(u32),
+    PreRoll(u32),
+    AdaptiveVad(bool),
+    VadSensitivity(VadSensitivity),
+    Monitor(String, u32),
+    Source(String),
+    AudioChain(String),
 }
@@ -1,33 +1,168 @@
-use anyhow::Result;
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context as _, Result};
+use byteorder::{ByteOrder as _, LittleEndian};
 use rubato::{Resampler as _, SincFixedOut, SincInterpolationParameters};
 use windows::Win32::{
+    Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
     Media::{Audio::*, Multimedia::WAVE_FORMAT_IEEE_FLOAT},
-    System::Com::*,
+    System::{
+        Com::*,
+        Performance::{QueryPerformanceCounter, QueryPerformanceFrequency},
+    },
+    UI::Shell::PropertiesSystem::PROPERTYKEY,
 };
+use windows_core::{implement, Interface as _, HSTRING, PCWSTR};
+
+use super::preprocess::{AudioChain, ChannelSelect};
+
+const WAVE_FORMAT_EXTENSIBLE: u32 = 0xfffe;
+// GetBuffer reports QPC positions in 100 ns units.
+const QPC_UNITS_PER_SEC: f64 = 1e7;
+// How often a lost device is tried again.
+const REOPEN_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct Audio {
     raw: Vec<f32>,
     resampled: Vec<f32>,
+    sample_rate: u32,
 
     capture: AudioCapture,
     resampler: Resampler,
+    chain: AudioChain,
+    monitor: Option<AudioMonitor>,
+    monitor_device: String,
+    monitor_delay: Duration,
+    device_changed: bool,
+    // Set while no device could be opened, to when to try again.
+    retry_at: Option<Instant>,
+    device_error: Option<String>,
+}
+
+/// An active audio endpoint, captured by loopback if it is an output device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AudioDevice {
+    pub id: String,
+    pub name: String,
+    pub input: bool,
 }
 
 impl Audio {
     pub fn new(sample_rate: u32) -> Result<Self> {
-        let capture = AudioCapture::new()?;
+        let capture = AudioCapture::new("")?;
         let resampler = Resampler::new(capture.sample_rate(), sample_rate)?;
 
         Ok(Self {
             raw: Vec::new(),
             resampled: Vec::new(),
+            sample_rate,
             capture,
             resampler,
+            chain: AudioChain::default(),
+            monitor: None,
+            monitor_device: String::new(),
+            monitor_delay: Duration::ZERO,
+            device_changed: false,
+            retry_at: None,
+            device_error: None,
         })
     }
 
+    /// Captures the endpoint with the id `source`, or what the default output device plays
+    /// if it is empty, following it when the default changes.
+    pub fn set_source(&mut self, source: &str) -> Result<()> {
+        if self.capture.source == source {
+            return Ok(());
+        }
+
+        self.open(source)
+    }
+
+    /// Whether the captured device changed since the last call, after the default output
+    /// device switched or the device went away.
+    pub fn take_device_change(&mut self) -> bool {
+        std::mem::take(&mut self.device_changed)
+    }
+
+    /// Why the device was lost, once per loss; capture resumes by itself when it's back.
+    pub fn take_device_error(&mut self) -> Option<String> {
+        self.device_error.take()
+    }
+
+    fn open(&mut self, source: &str) -> Result<()> {
+        self.capture = AudioCapture::new(source)?;
+        self.resampler = Resampler::new(self.capture.sample_rate(), self.sample_rate)?;
+        self.clear();
+
+        // The monitor is opened in the capture's format. It stays off if it would play
+        // into the newly captured device.
+        let device_name = std::mem::take(&mut self.monitor_device);
+        _ = self.set_monitor(&device_name, self.monitor_delay);
+
+        Ok(())
+    }
+
+    /// Plays the captured audio on another output device, delayed to line up with the captions.
+    pub fn set_monitor(&mut self, device_name: &str, delay: Duration) -> Result<()> {
+        self.monitor = None;
+        self.monitor_device = device_name.to_string();
+        self.monitor_delay = delay;
+
+        if !device_name.is_empty() {
+            self.monitor = Some(AudioMonitor::new(device_name, &self.capture, delay)?);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the stages between capture and resampling.
+    pub fn set_chain(&mut self, chain: AudioChain) {
+        self.chain = chain;
+        self.clear();
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) -> Result<()> {
+        if self.sample_rate != sample_rate {
+            self.resampler = Resampler::new(self.capture.sample_rate(), sample_rate)?;
+            self.sample_rate = sample_rate;
+            self.clear();
+        }
+        Ok(())
+    }
+
     pub fn capture(&mut self) -> Result<&[f32]> {
-        self.capture.capture(&mut self.raw)?;
+        let reopen = match self.retry_at {
+            Some(retry_at) => Instant::now() >= retry_at,
+            None => self.capture.default_changed(),
+        };
+        if reopen {
+            self.reopen();
+        }
+        if self.retry_at.is_some() {
+            self.resampled.clear();
+            return Ok(&self.resampled);
+        }
+
+        let start = self.raw.len();
+        match self
+            .capture
+            .capture(&mut self.raw, self.chain.channels, self.monitor.as_mut())
+        {
+            Err(e) if is_device_invalidated(&e) => self.reopen(),
+            result => result?,
+        }
+        // A reopened device starts over with an empty buffer.
+        let start = start.min(self.raw.len());
+        self.chain
+            .process(&mut self.raw, start, self.capture.sample_rate);
 
         self.resampled.clear();
         self.resampler
@@ -36,27 +171,151 @@ impl Audio {
         Ok(&self.resampled)
     }
 
+    /// The samples returned by the last `capture`.
+    pub fn captured(&self) -> &[f32] {
+        &self.resampled
+    }
+
     pub fn clear(&mut self) {
         self.resampled.clear();
         self.raw.clear();
+        self.chain.clear();
+    }
+
+    /// Opens the source again; a chosen device that went away falls back to the default.
+    /// With no device at all, capture stops until a later try succeeds.
+    fn reopen(&mut self) {
+        let source = self.capture.source.clone();
+        match self.open(&source).or_else(|_| self.open("")) {
+            Ok(()) => {
+                self.retry_at = None;
+                self.device_changed = true;
+            }
+            Err(e) => {
+                if self.retry_at.is_none() {
+                    self.device_error = Some(e.to_string());
+                }
+                self.retry_at = Some(Instant::now() + REOPEN_INTERVAL);
+            }
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn device_name(&self) -> String {
+        device_name(&self.capture.audio_device).unwrap_or_default()
+    }
+
+    pub fn capture_format(&self) -> String {
+        format!(
+            "{} Hz, {} ch, 32-bit float {}, {}, resampled to {} Hz mono",
+            self.capture.sample_rate,
+            self.capture.n_ch,
+            if self.capture.loopback {
+                "loopback"
+            } else {
+                "input"
+            },
+            self.chain,
+            self.sample_rate
+        )
+    }
+
+    /// When the last sample returned by `capture` was recorded, by the audio clock rather than
+    /// by when it was read, so processing jitter doesn't shift timestamps.
+    pub fn captured_until(&self) -> Option<Instant> {
+        let end = self.capture.clock.end?;
+        let age = (qpc_now()? - end as f64) / QPC_UNITS_PER_SEC
+            + (self.raw.len() + self.chain.pending()) as f64 / self.capture.sample_rate as f64;
+        Instant::now().checked_sub(Duration::from_secs_f64(age.max(0.0)))
+    }
+
+    pub fn clock(&self) -> AudioClock {
+        self.capture.clock
     }
 }
 
+/// How far the device's sample clock strays from the system clock.
+#[derive(Clone, Copy, Default)]
+pub struct AudioClock {
+    start: Option<u64>,
+    end: Option<u64>,
+    frames: u64,
+    /// Span of continuous audio measured so far.
+    pub span: Duration,
+    /// Audio time minus system time over `span`; positive when the device runs fast.
+    pub drift: f64,
+    pub discontinuities: u32,
+}
+
+impl AudioClock {
+    fn packet(&mut self, qpc: u64, n_frames: u32, sample_rate: u32, discontinuity: bool) {
+        if discontinuity {
+            self.discontinuities += 1;
+            self.start = None;
+        }
+
+        let start = *self.start.get_or_insert_with(|| {
+            self.frames = 0;
+            qpc
+        });
+        let span = qpc.saturating_sub(start) as f64 / QPC_UNITS_PER_SEC;
+        self.span = Duration::from_secs_f64(span);
+        self.drift = self.frames as f64 / sample_rate as f64 - span;
+
+        self.frames += n_frames as u64;
+        self.end = Some(qpc + n_frames as u64 * QPC_UNITS_PER_SEC as u64 / sample_rate as u64);
+    }
+}
+
+fn is_device_invalidated(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<windows_core::Error>()
+        .is_some_and(|e| e.code() == AUDCLNT_E_DEVICE_INVALIDATED)
+}
+
+fn qpc_now() -> Option<f64> {
+    let (mut count, mut frequency) = (0, 0);
+    unsafe {
+        QueryPerformanceCounter(&mut count).ok()?;
+        QueryPerformanceFrequency(&mut frequency).ok()?;
+    }
+    Some(count as f64 * QPC_UNITS_PER_SEC / frequency as f64)
+}
+
 struct AudioCapture {
-    _audio_device: IMMDevice,
+    source: String,
+    loopback: bool,
+    audio_device: IMMDevice,
     _audio_client: IAudioClient,
     capture: IAudioCaptureClient,
     sample_rate: u32,
     n_ch: u32,
+    clock: AudioClock,
+    device_enumerator: IMMDeviceEnumerator,
+    /// Registered while following the default output device.
+    watcher: Option<(IMMNotificationClient, Arc<AtomicBool>)>,
 }
 
 impl AudioCapture {
-    pub fn new() -> Result<Self> {
+    pub fn new(source: &str) -> Result<Self> {
         unsafe {
             let device_enumerator: IMMDeviceEnumerator =
                 CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
-            let audio_device: IMMDevice =
-                device_enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+            let audio_device = if source.is_empty() {
+                device_enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?
+            } else {
+                device_enumerator
+                    .GetDevice(&HSTRING::from(source))
+                    .with_context(|| format!("audio device {source} not found"))?
+            };
+            let loopback = audio_device.cast::<IMMEndpoint>()?.GetDataFlow()? == eRender;
+            let flags = if loopback {
+                AUDCLNT_STREAMFLAGS_LOOPBACK
+            } else {
+                AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY
+            };
             let audio_client: IAudioClient = audio_device.Activate(CLSCTX_ALL, None)?;
 
             let (n_ch, sample_rate) = {
@@ -77,30 +336,51 @@ impl AudioCapture {
             };
 
             let duration = 1000 * 1000 * 10;
-            audio_client.Initialize(
-                AUDCLNT_SHAREMODE_SHARED,
-                AUDCLNT_STREAMFLAGS_LOOPBACK,
-                duration,
-                0,
-                &wfx,
-                None,
-            )?;
+            audio_client.Initialize(AUDCLNT_SHAREMODE_SHARED, flags, duration, 0, &wfx, None)?;
 
             let capture = audio_client.GetService()?;
 
             audio_client.Start()?;
 
+            let watcher = if source.is_empty() {
+                let changed = Arc::new(AtomicBool::new(false));
+                let client: IMMNotificationClient = DeviceWatcher {
+                    changed: changed.clone(),
+                }
+                .into();
+                device_enumerator.RegisterEndpointNotificationCallback(&client)?;
+                Some((client, changed))
+            } else {
+                None
+            };
+
             Ok(Self {
-                _audio_device: audio_device,
+                source: source.to_string(),
+                loopback,
+                audio_device,
                 _audio_client: audio_client,
                 capture,
                 sample_rate,
                 n_ch,
+                clock: AudioClock::default(),
+                device_enumerator,
+                watcher,
             })
         }
     }
 
-    pub fn capture(&mut self, buf: &mut Vec<f32>) -> Result<()> {
+    fn default_changed(&self) -> bool {
+        self.watcher
+            .as_ref()
+            .is_some_and(|(_, changed)| changed.load(Ordering::Relaxed))
+    }
+
+    pub fn capture(
+        &mut self,
+        buf: &mut Vec<f32>,
+        channels: ChannelSelect,
+        mut monitor: Option<&mut AudioMonitor>,
+    ) -> Result<()> {
         unsafe {
             loop {
                 if self.capture.GetNextPacketSize()? == 0 {
@@ -110,24 +390,40 @@ impl AudioCapture {
                 let mut frames: *mut f32 = std::ptr::null_mut();
                 let mut n_frames = 0;
                 let mut flags = 0;
+                let mut qpc = 0;
                 self.capture.GetBuffer(
                     &mut frames as *mut _ as _,
                     &mut n_frames,
                     &mut flags,
                     None,
-                    None,
+                    Some(&mut qpc),
                 )?;
+                self.clock.packet(
+                    qpc,
+                    n_frames,
+                    self.sample_rate,
+                    flags & AUDCLNT_BUFFERFLAGS_DATA_DISCONTINUITY.0 as u32 != 0,
+                );
+
+                let samples = std::slice::from_raw_parts(frames, (self.n_ch * n_frames) as _);
+                if let Some(monitor) = &mut monitor {
+                    monitor.push(samples);
+                }
 
                 buf.extend(
-                    std::slice::from_raw_parts(frames, (self.n_ch * n_frames) as _)
+                    samples
                         .chunks(self.n_ch as _)
-                        .map(|frame| frame.iter().sum::<f32>()),
+                        .map(|frame| channels.select(frame)),
                 );
 
                 self.capture.ReleaseBuffer(n_frames)?;
             }
         }
 
+        if let Some(monitor) = monitor {
+            monitor.render()?;
+        }
+
         Ok(())
     }
 
@@ -136,6 +432,305 @@ impl AudioCapture {
     }
 }
 
+impl Drop for AudioCapture {
+    fn drop(&mut self) {
+        if let Some((client, _)) = &self.watcher {
+            unsafe {
+                _ = self
+                    .device_enumerator
+                    .UnregisterEndpointNotificationCallback(client);
+            }
+        }
+    }
+}
+
+/// Flags a change of the default output device, reported on a system thread.
+#[implement(IMMNotificationClient)]
+struct DeviceWatcher {
+    changed: Arc<AtomicBool>,
+}
+
+impl IMMNotificationClient_Impl for DeviceWatcher_Impl {
+    fn OnDeviceStateChanged(&self, _id: &PCWSTR, _state: DEVICE_STATE) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnDeviceAdded(&self, _id: &PCWSTR) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnDeviceRemoved(&self, _id: &PCWSTR) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnDefaultDeviceChanged(
+        &self,
+        flow: EDataFlow,
+        role: ERole,
+        _id: &PCWSTR,
+    ) -> windows_core::Result<()> {
+        if flow == eRender && role == eConsole {
+            self.changed.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn OnPropertyValueChanged(&self, _id: &PCWSTR, _key: &PROPERTYKEY) -> windows_core::Result<()> {
+        Ok(())
+    }
+}
+
+struct AudioMonitor {
+    audio_client: IAudioClient,
+    render: IAudioRenderClient,
+    buffer_frames: u32,
+    n_ch: usize,
+    queue: VecDeque<f32>,
+    max_queued: usize,
+}
+
+impl AudioMonitor {
+    fn new(device_name: &str, capture: &AudioCapture, delay: Duration) -> Result<Self> {
+        unsafe {
+            let device_enumerator: IMMDeviceEnumerator =
+                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            let audio_device = find_device(&device_enumerator, eRender, device_name)?
+                .with_context(|| format!("audio device \"{device_name}\" not found"))?;
+
+            // Rendering to the captured device would feed the monitor back into itself.
+            if device_id(&audio_device)? == device_id(&capture.audio_device)? {
+                bail!("the monitor device must differ from the captured device");
+            }
+
+            let audio_client: IAudioClient = audio_device.Activate(CLSCTX_ALL, None)?;
+
+            let (n_ch, sample_rate) = (capture.n_ch, capture.sample_rate);
+            let wfx = WAVEFORMATEX {
+                wFormatTag: WAVE_FORMAT_IEEE_FLOAT as _,
+                nChannels: n_ch as _,
+                nSamplesPerSec: sample_rate,
+                nAvgBytesPerSec: n_ch * 32 * sample_rate / 8,
+                nBlockAlign: n_ch as u16 * 32 / 8,
+                wBitsPerSample: 32,
+                cbSize: 0,
+            };
+
+            let duration = 1000 * 1000 * 10;
+            audio_client.Initialize(
+                AUDCLNT_SHAREMODE_SHARED,
+                AUDCLNT_STREAMFLAGS_AUTOCONVERTPCM | AUDCLNT_STREAMFLAGS_SRC_DEFAULT_QUALITY,
+                duration,
+                0,
+                &wfx,
+                None,
+            )?;
+
+            let render = audio_client.GetService()?;
+            let buffer_frames = audio_client.GetBufferSize()?;
+
+            audio_client.Start()?;
+
+            let delay_samples = (delay.as_secs_f64() * sample_rate as f64) as usize * n_ch as usize;
+            let max_queued = delay_samples + (sample_rate * n_ch) as usize;
+
+            Ok(Self {
+                audio_client,
+                render,
+                buffer_frames,
+                n_ch: n_ch as _,
+                queue: VecDeque::from(vec![0.0; delay_samples]),
+                max_queued,
+            })
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        self.queue.extend(samples);
+
+        // Drop the oldest audio if the output device falls behind the capture clock.
+        if self.queue.len() > self.max_queued {
+            let excess = self.queue.len() - self.max_queued;
+            self.queue.drain(..excess - excess % self.n_ch);
+        }
+    }
+
+    fn render(&mut self) -> Result<()> {
+        unsafe {
+            let padding = self.audio_client.GetCurrentPadding()?;
+            let n_frames =
+                ((self.buffer_frames - padding) as usize).min(self.queue.len() / self.n_ch);
+            if n_frames == 0 {
+                return Ok(());
+            }
+
+            let data = self.render.GetBuffer(n_frames as _)? as *mut f32;
+            let out = std::slice::from_raw_parts_mut(data, n_frames * self.n_ch);
+            for (dst, src) in out.iter_mut().zip(self.queue.drain(..n_frames * self.n_ch)) {
+                *dst = src;
+            }
+
+            self.render.ReleaseBuffer(n_frames as _, 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Active output devices, then microphones and other inputs, each sorted by name.
+pub fn audio_devices() -> Result<Vec<AudioDevice>> {
+    let mut list = vec![];
+
+    unsafe {
+        let device_enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        for (flow, input) in [(eRender, false), (eCapture, true)] {
+            let devices = device_enumerator.EnumAudioEndpoints(flow, DEVICE_STATE_ACTIVE)?;
+            let mut found = (0..devices.GetCount()?)
+                .map(|i| {
+                    let device = devices.Item(i)?;
+                    Ok(AudioDevice {
+                        id: device_id(&device)?,
+                        name: device_name(&device)?,
+                        input,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            found.sort_by(|a, b| a.name.cmp(&b.name));
+            list.extend(found);
+        }
+    }
+
+    Ok(list)
+}
+
+/// Id of the default microphone, to pass as a source.
+pub fn default_input_id() -> Result<String> {
+    unsafe {
+        let device_enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = device_enumerator
+            .GetDefaultAudioEndpoint(eCapture, eConsole)
+            .context("no microphone found")?;
+        device_id(&device)
+    }
+}
+
+/// Finds an active endpoint whose friendly name contains `name`, ignoring case.
+fn find_device(
+    device_enumerator: &IMMDeviceEnumerator,
+    flow: EDataFlow,
+    name: &str,
+) -> Result<Option<IMMDevice>> {
+    let name = name.to_lowercase();
+
+    unsafe {
+        let devices = device_enumerator.EnumAudioEndpoints(flow, DEVICE_STATE_ACTIVE)?;
+        for i in 0..devices.GetCount()? {
+            let device = devices.Item(i)?;
+            if device_name(&device)?.to_lowercase().contains(&name) {
+                return Ok(Some(device));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn device_name(device: &IMMDevice) -> Result<String> {
+    unsafe {
+        let store = device.OpenPropertyStore(STGM_READ)?;
+        Ok(store.GetValue(&PKEY_Device_FriendlyName)?.to_string())
+    }
+}
+
+fn device_id(device: &IMMDevice) -> Result<String> {
+    unsafe {
+        let id = device.GetId()?;
+        let result = id.to_string();
+        CoTaskMemFree(Some(id.0 as _));
+        Ok(result?)
+    }
+}
+
+/// Reads a PCM or float WAV file as mono samples at `sample_rate`.
+pub fn read_wav(path: &Path, sample_rate: u32) -> Result<Vec<f32>> {
+    let bytes = std::fs::read(path)?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        bail!("{} is not a WAV file", path.display());
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let len = LittleEndian::read_u32(&bytes[pos + 4..pos + 8]) as usize;
+        let body = &bytes[pos + 8..(pos + 8 + len).min(bytes.len())];
+
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let mut tag = LittleEndian::read_u16(&body[0..2]) as u32;
+                // WAVE_FORMAT_EXTENSIBLE keeps the actual format in its sub-format GUID.
+                if tag == WAVE_FORMAT_EXTENSIBLE && body.len() >= 26 {
+                    tag = LittleEndian::read_u16(&body[24..26]) as u32;
+                }
+                let n_ch = LittleEndian::read_u16(&body[2..4]) as usize;
+                let rate = LittleEndian::read_u32(&body[4..8]);
+                let bits = LittleEndian::read_u16(&body[14..16]);
+                format = Some((tag, n_ch, rate, bits));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are padded to an even length.
+        pos += 8 + len + (len & 1);
+    }
+
+    let (Some((tag, n_ch, rate, bits)), Some(data)) = (format, data) else {
+        bail!("{} has no audio data", path.display());
+    };
+    if n_ch == 0 || rate == 0 {
+        bail!("{} has {n_ch} channels at {rate} Hz", path.display());
+    }
+
+    let samples: Vec<f32> = match (tag, bits) {
+        (WAVE_FORMAT_PCM, 8) => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        (WAVE_FORMAT_PCM, 16) => data
+            .chunks_exact(2)
+            .map(|b| LittleEndian::read_i16(b) as f32 / 32768.0)
+            .collect(),
+        (WAVE_FORMAT_PCM, 24) => data
+            .chunks_exact(3)
+            .map(|b| LittleEndian::read_i24(b) as f32 / 8388608.0)
+            .collect(),
+        (WAVE_FORMAT_PCM, 32) => data
+            .chunks_exact(4)
+            .map(|b| LittleEndian::read_i32(b) as f32 / 2147483648.0)
+            .collect(),
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => data.chunks_exact(4).map(LittleEndian::read_f32).collect(),
+        _ => bail!("unsupported WAV format {tag} with {bits} bits"),
+    };
+
+    let mut mono: Vec<f32> = samples
+        .chunks_exact(n_ch)
+        .map(|frame| frame.iter().sum::<f32>() / n_ch as f32)
+        .collect();
+    if rate == sample_rate {
+        return Ok(mono);
+    }
+
+    let expected = (mono.len() as u64 * sample_rate as u64 / rate as u64) as usize;
+    let mut resampler = Resampler::new(rate, sample_rate)?;
+    // The resampler only takes whole blocks, so the tail is flushed with silence.
+    mono.resize(mono.len() + resampler.resampler.input_frames_max(), 0.0);
+    let mut output = Vec::with_capacity(expected);
+    resampler.resample(&mut mono, &mut output)?;
+    output.truncate(expected);
+
+    Ok(output)
+}
+
 struct Resampler {
     resampler: SincFixedOut<f32>,
 }
@@ -0,0 +1,77 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use hf_hub::{Cache, Repo, RepoType};
+
+use super::text::TextStream;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const MODEL_FILES: &[&str] = &["model.safetensors", "config.json", "tokenizer.json"];
+
+/// Whether the files of a model are already in the cache, so loading it needs no download.
+pub fn is_cached(repo_id: &str, model_dir: &Path) -> bool {
+    let repo = Cache::new(model_dir.to_path_buf()).repo(Repo::new(repo_id.into(), RepoType::Model));
+    MODEL_FILES.iter().all(|file| repo.get(file).is_some())
+}
+
+/// Shows how much of a model has been downloaded as the caption status, until dropped.
+///
+/// hf-hub only draws progress to a console, so this watches the files it downloads into.
+pub struct DownloadProgress {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DownloadProgress {
+    pub fn start(repo_id: &str, model_dir: &Path, ts: TextStream) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let temp_dir = model_dir.join("tmp");
+        let repo_id = repo_id.to_string();
+
+        let handle = std::thread::spawn({
+            let running = running.clone();
+            move || {
+                while running.load(Ordering::Relaxed) {
+                    let mb = downloaded_bytes(&temp_dir) as f32 / 1e6;
+                    ts.set_status(format!("Downloading {repo_id}... {mb:.0} MB"));
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+            }
+        });
+
+        Self {
+            running,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for DownloadProgress {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            _ = handle.join();
+        }
+    }
+}
+
+/// Size of the partial files in hf-hub's temporary folder. Leftovers of an interrupted
+/// download count too, which only makes the figure start higher.
+fn downloaded_bytes(temp_dir: &Path) -> u64 {
+    std::fs::read_dir(temp_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+const WINDOW: Duration = Duration::from_secs(5);
+const RTF_HIGH: f32 = 0.9;
+const RTF_LOW: f32 = 0.4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mitigation {
+    None,
+    RaiseLatency,
+    ReduceDecodes,
+    SuggestSmallerModel,
+}
+
+impl Mitigation {
+    fn next(self) -> Self {
+        match self {
+            Self::None => Self::RaiseLatency,
+            Self::RaiseLatency => Self::ReduceDecodes,
+            _ => Self::SuggestSmallerModel,
+        }
+    }
+
+    fn prev(self) -> Self {
+        match self {
+            Self::SuggestSmallerModel => Self::ReduceDecodes,
+            Self::ReduceDecodes => Self::RaiseLatency,
+            _ => Self::None,
+        }
+    }
+
+    pub fn message(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::RaiseLatency => Some("Transcription is falling behind; latency raised."),
+            Self::ReduceDecodes => {
+                Some("Transcription is falling behind; latency raised and updates reduced.")
+            }
+            Self::SuggestSmallerModel => {
+                Some("Transcription is falling behind; try a smaller model.")
+            }
+        }
+    }
+}
+
+pub struct Guardrail {
+    level: Mitigation,
+    busy: Duration,
+    audio: Duration,
+}
+
+impl Guardrail {
+    pub fn new() -> Self {
+        Self {
+            level: Mitigation::None,
+            busy: Duration::ZERO,
+            audio: Duration::ZERO,
+        }
+    }
+
+    pub fn level(&self) -> Mitigation {
+        self.level
+    }
+
+    pub fn record(&mut self, busy: Duration, audio: Duration) -> Option<Mitigation> {
+        self.busy += busy;
+        self.audio += audio;
+
+        if self.audio < WINDOW {
+            return None;
+        }
+
+        let rtf = self.busy.as_secs_f32() / self.audio.as_secs_f32();
+        self.busy = Duration::ZERO;
+        self.audio = Duration::ZERO;
+
+        let level = if rtf > RTF_HIGH {
+            self.level.next()
+        } else if rtf < RTF_LOW {
+            self.level.prev()
+        } else {
+            self.level
+        };
+
+        if level != self.level {
+            self.level = level;
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
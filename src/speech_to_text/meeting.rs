@@ -0,0 +1,162 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::Result;
+use windows::Win32::System::WinRT::{RoInitialize, RO_INIT_MULTITHREADED};
+
+use super::{
+    audio::{self, Audio},
+    stream::{MultiStreamTranscriber, TranscriptEvent},
+    vad::SpeechActivity,
+};
+
+/// Who a line of a meeting transcript came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speaker {
+    /// The default microphone.
+    Me,
+    /// What the default output device plays.
+    Them,
+}
+
+impl Speaker {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Me => "Me",
+            Self::Them => "Them",
+        }
+    }
+}
+
+pub enum MeetingEvent {
+    Text(Speaker, TranscriptEvent),
+    Status(String),
+}
+
+/// Decoding settings shared with the main captions.
+#[derive(Clone, Debug, Default)]
+pub struct MeetingOptions {
+    pub language: String,
+    pub translate: bool,
+    pub hotwords: Vec<String>,
+}
+
+/// Transcribes the microphone and the desktop audio side by side on one model, so both
+/// sides of a call end up in one transcript.
+pub struct MeetingTranscriber {
+    receiver: Receiver<MeetingEvent>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MeetingTranscriber {
+    /// Loads `repo_id` and starts capturing on a background thread, updating every `latency`.
+    pub fn new(
+        repo_id: &str,
+        model_dir: &Path,
+        latency: Duration,
+        options: MeetingOptions,
+    ) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let repo_id = repo_id.to_string();
+        let model_dir = model_dir.to_path_buf();
+
+        let handle = std::thread::Builder::new().name("meeting".into()).spawn({
+            let stop = stop.clone();
+            move || {
+                if let Err(e) = run(&repo_id, &model_dir, latency, &options, &sender, &stop) {
+                    _ = sender.send(MeetingEvent::Status(format!("{e:?}")));
+                }
+            }
+        })?;
+
+        Ok(Self {
+            receiver,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Takes the events produced since the last call.
+    pub fn poll(&self) -> Vec<MeetingEvent> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for MeetingTranscriber {
+    /// Leaves the thread to stop on its own, as a model still loading or downloading
+    /// can't be interrupted and waiting would freeze the window.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.take();
+    }
+}
+
+fn run(
+    repo_id: &str,
+    model_dir: &Path,
+    latency: Duration,
+    options: &MeetingOptions,
+    sender: &Sender<MeetingEvent>,
+    stop: &AtomicBool,
+) -> Result<()> {
+    unsafe { RoInitialize(RO_INIT_MULTITHREADED) }?;
+
+    _ = sender.send(MeetingEvent::Status(format!("Loading {repo_id}")));
+    let speakers = [Speaker::Me, Speaker::Them];
+    let mut transcriber = MultiStreamTranscriber::new(repo_id, model_dir, speakers.len())?;
+    if stop.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    transcriber.set_task(&options.language, options.translate);
+    transcriber.set_hotwords(&options.hotwords);
+    let sample_rate = transcriber.sample_rate();
+
+    let mut mic = Audio::new(sample_rate)?;
+    mic.set_source(&audio::default_input_id()?)?;
+    let mut sources = [mic, Audio::new(sample_rate)?];
+    let mut activity = speakers.map(|_| SpeechActivity::new(sample_rate));
+    let mut speaking = speakers.map(|_| false);
+    _ = sender.send(MeetingEvent::Status(String::new()));
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(latency);
+
+        let mut captured = vec![];
+        for (source, activity) in sources.iter_mut().zip(&mut activity) {
+            let samples = source.capture()?.to_vec();
+            // A side that is quiet isn't decoded, so silence can't turn into made-up text.
+            captured.push(match activity.process(&samples) {
+                true => samples,
+                false => vec![],
+            });
+        }
+        let samples: Vec<&[f32]> = captured.iter().map(Vec::as_slice).collect();
+        transcriber.feed(&samples)?;
+
+        for (i, &speaker) in speakers.iter().enumerate() {
+            let active = activity[i].is_active();
+            if speaking[i] && !active {
+                transcriber.flush(i);
+            }
+            speaking[i] = active;
+
+            for event in transcriber.poll(i) {
+                if sender.send(MeetingEvent::Text(speaker, event)).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,19 +1,130 @@
-use std::{f32::consts::PI, sync::Arc};
+use std::{f32::consts::PI, sync::Arc, time::Duration};
 
-use anyhow::Result;
-use candle_transformers::models::whisper::{HOP_LENGTH as N_HOP, N_FFT, N_FRAMES};
+use anyhow::{bail, Result};
+use candle_transformers::models::whisper::{HOP_LENGTH, N_FFT, N_FRAMES, SAMPLE_RATE};
 use rustfft::{num_complex::Complex32 as Complex, Fft, FftPlanner};
+use serde_json::Value;
 
-const N_FILTER: usize = (N_FFT / 2) + 1;
 const MEL_ZERO: f32 = (-10.0 + 4.0) / 4.0;
 
+/// How frequencies map to mel bands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MelScale {
+    /// Linear below 1 kHz and logarithmic above, as librosa and Whisper use.
+    Slaney,
+    Htk,
+}
+
+/// The window applied to each frame before the FFT.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowFunc {
+    Hann,
+    Hamming,
+    /// Kaldi's default, a Hann window raised to 0.85, as k2 and icefall models use.
+    Povey,
+}
+
+impl WindowFunc {
+    fn coefficients(self, len: usize) -> Vec<f32> {
+        let cos = |i: usize| ((2.0 * PI * i as f32) / (len - 1) as f32).cos();
+        (0..len)
+            .map(|i| match self {
+                Self::Hann => 0.5 * (1. - cos(i)),
+                Self::Hamming => 0.54 - 0.46 * cos(i),
+                Self::Povey => (0.5 * (1. - cos(i))).powf(0.85),
+            })
+            .collect()
+    }
+}
+
+/// Feature extraction settings of a model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MelConfig {
+    pub n_mels: usize,
+    pub n_fft: usize,
+    pub hop_length: usize,
+    pub sample_rate: usize,
+    pub mel_scale: MelScale,
+    pub window: WindowFunc,
+    /// Scale of the Gaussian noise added to the samples, matching features trained with it.
+    pub dither: f32,
+}
+
+impl MelConfig {
+    /// Whisper's own settings.
+    pub fn whisper(n_mels: usize) -> Self {
+        Self {
+            n_mels,
+            n_fft: N_FFT,
+            hop_length: HOP_LENGTH,
+            sample_rate: SAMPLE_RATE,
+            mel_scale: MelScale::Slaney,
+            window: WindowFunc::Hann,
+            dither: 0.0,
+        }
+    }
+
+    /// Reads a `preprocessor_config.json` as Hugging Face writes it, with Whisper's settings
+    /// for the keys it leaves out.
+    pub fn from_json(json: &str, n_mels: usize) -> Result<Self> {
+        let json: Value = serde_json::from_str(json)?;
+        let default = Self::whisper(n_mels);
+        let get = |key: &str, default: usize| {
+            json.get(key)
+                .and_then(Value::as_u64)
+                .map_or(default, |value| value as usize)
+        };
+
+        let config = Self {
+            n_mels: get("feature_size", default.n_mels),
+            n_fft: get("n_fft", default.n_fft),
+            hop_length: get("hop_length", default.hop_length),
+            sample_rate: get("sampling_rate", default.sample_rate),
+            mel_scale: match json.get("mel_scale").and_then(Value::as_str) {
+                None | Some("slaney") => MelScale::Slaney,
+                Some("htk") => MelScale::Htk,
+                Some(scale) => bail!("unknown mel_scale {scale:?}"),
+            },
+            window: match json.get("window").and_then(Value::as_str) {
+                None | Some("hann") | Some("hanning") => WindowFunc::Hann,
+                Some("hamming") => WindowFunc::Hamming,
+                Some("povey") => WindowFunc::Povey,
+                Some(window) => bail!("unknown window {window:?}"),
+            },
+            dither: json
+                .get("dither")
+                .and_then(Value::as_f64)
+                .map_or(default.dither, |dither| dither as f32),
+        };
+        if config.n_fft == 0 || config.hop_length == 0 || config.hop_length > config.n_fft {
+            bail!(
+                "invalid n_fft {} and hop_length {}",
+                config.n_fft,
+                config.hop_length
+            );
+        }
+
+        Ok(config)
+    }
+
+    /// Audio covered by `n_frames` frames.
+    pub fn duration(&self, n_frames: usize) -> Duration {
+        Duration::from_secs_f32((n_frames * self.hop_length) as f32 / self.sample_rate as f32)
+    }
+}
+
 pub struct MelSpectrogram {
+    config: MelConfig,
     samples: Vec<f32>,
     mel: Vec<f32>,
     i_frame: usize,
     n_bins: usize,
+    n_fft: usize,
+    n_hop: usize,
+    n_filter: usize,
 
     window: Vec<f32>,
+    noise: Noise,
     fft: Arc<dyn Fft<f32>>,
     fft_io: Vec<Complex>,
     fft_scratch: Vec<Complex>,
@@ -22,45 +133,66 @@ pub struct MelSpectrogram {
 }
 
 impl MelSpectrogram {
-    pub fn new(num_mel_bins: usize) -> Result<Self> {
-        let window = (0..N_FFT)
-            .map(|i| 0.5 * (1. - ((2.0 * PI * i as f32) / (N_FFT - 1) as f32).cos()))
-            .collect();
-
-        let fft = FftPlanner::new().plan_fft_forward(N_FFT);
-        let n_scratch = fft.get_inplace_scratch_len();
+    pub fn new(config: &MelConfig) -> Result<Self> {
+        let (n_mels, n_fft) = (config.n_mels, config.n_fft);
+        if n_mels == 0 {
+            bail!("unexpected num_mel_bins 0");
+        }
+        let n_filter = n_fft / 2 + 1;
 
-        let filter = {
-            let mel_bytes = match num_mel_bins {
-                80 => include_bytes!("melfilters.bytes").as_slice(),
-                128 => include_bytes!("melfilters128.bytes").as_slice(),
-                n => anyhow::bail!("unexpected num_mel_bins {n}"),
-            };
+        let window = config.window.coefficients(n_fft);
 
-            let mut filter = vec![0f32; mel_bytes.len() / 4];
-            <byteorder::LittleEndian as byteorder::ByteOrder>::read_f32_into(
-                mel_bytes,
-                &mut filter,
-            );
+        let fft = FftPlanner::new().plan_fft_forward(n_fft);
+        let n_scratch = fft.get_inplace_scratch_len();
 
-            filter
+        // Whisper's filters are bundled, others are computed the same way librosa does.
+        let whisper = MelConfig::whisper(n_mels);
+        let bundled = match n_mels {
+            _ if (config.n_fft, config.sample_rate, config.mel_scale)
+                != (whisper.n_fft, whisper.sample_rate, whisper.mel_scale) =>
+            {
+                None
+            }
+            80 => Some(include_bytes!("melfilters.bytes").as_slice()),
+            128 => Some(include_bytes!("melfilters128.bytes").as_slice()),
+            _ => None,
+        };
+        let filter = match bundled {
+            Some(mel_bytes) => {
+                let mut filter = vec![0f32; mel_bytes.len() / 4];
+                <byteorder::LittleEndian as byteorder::ByteOrder>::read_f32_into(
+                    mel_bytes,
+                    &mut filter,
+                );
+                filter
+            }
+            None => mel_filters(config),
         };
 
         Ok(Self {
+            config: config.clone(),
             samples: vec![],
-            mel: vec![0.0; num_mel_bins * N_FRAMES],
+            mel: vec![0.0; n_mels * N_FRAMES],
             i_frame: 0,
-            n_bins: num_mel_bins,
+            n_bins: n_mels,
+            n_fft,
+            n_hop: config.hop_length,
+            n_filter,
             window,
+            noise: Noise::default(),
             fft,
-            fft_io: vec![Complex::default(); N_FFT],
+            fft_io: vec![Complex::default(); n_fft],
             fft_scratch: vec![Complex::default(); n_scratch],
-            magnitude: vec![0f32; N_FILTER],
+            magnitude: vec![0f32; n_filter],
             filter,
         })
     }
 
-    pub fn decode(&mut self, samples: &[f32]) -> Option<(&[f32], bool)> {
+    pub fn config(&self) -> &MelConfig {
+        &self.config
+    }
+
+    pub fn decode(&mut self, samples: &[f32]) -> Option<(&[f32], bool, usize)> {
         self.samples.extend_from_slice(samples);
 
         let is_new_segment = self.i_frame == 0;
@@ -70,7 +202,7 @@ impl MelSpectrogram {
 
         let n_frames = {
             let n_samples = self.samples.len();
-            let n_frames = n_samples.saturating_sub(N_FFT - N_HOP) / N_HOP;
+            let n_frames = n_samples.saturating_sub(self.n_fft - self.n_hop) / self.n_hop;
             n_frames.min(N_FRAMES) - self.i_frame
         };
 
@@ -82,22 +214,32 @@ impl MelSpectrogram {
             self.pcm_to_mel();
             self.i_frame += 1;
         }
+        let n_valid = self.i_frame;
 
         if self.i_frame >= N_FRAMES {
-            _ = self.samples.drain(..self.i_frame * N_HOP);
+            _ = self.samples.drain(..self.i_frame * self.n_hop);
+            // A burst of audio after a stall would otherwise keep its capacity for the session.
+            self.samples.shrink_to(2 * N_FRAMES * self.n_hop);
             self.i_frame = 0;
         }
 
-        Some((&self.mel, is_new_segment))
+        Some((&self.mel, is_new_segment, n_valid))
     }
 
     fn pcm_to_mel(&mut self) {
         let i_frame = self.i_frame;
         let mel = &mut self.mel;
-        let samples = &self.samples[i_frame * N_HOP..i_frame * N_HOP + N_FFT];
+        let start = i_frame * self.n_hop;
+        let samples = &self.samples[start..start + self.n_fft];
 
+        let dither = self.config.dither;
         for (i, io) in self.fft_io.iter_mut().enumerate() {
-            io.re = self.window[i] * samples[i];
+            let sample = if dither > 0.0 {
+                samples[i] + dither * self.noise.next_gaussian()
+            } else {
+                samples[i]
+            };
+            io.re = self.window[i] * sample;
             io.im = 0.0;
         }
 
@@ -113,8 +255,8 @@ impl MelSpectrogram {
         for i in 0..self.n_bins {
             let mut m = 0.0;
 
-            for j in 0..N_FILTER {
-                m += self.filter[i * N_FILTER + j] * self.magnitude[j];
+            for j in 0..self.n_filter {
+                m += self.filter[i * self.n_filter + j] * self.magnitude[j];
             }
 
             m = m.max(1e-10).log10();
@@ -132,9 +274,93 @@ impl MelSpectrogram {
         }
     }
 
+    /// Audio of the current 30-second window.
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
     pub fn clear(&mut self) {
         self.samples.clear();
         self.mel.fill(MEL_ZERO);
         self.i_frame = 0;
     }
 }
+
+/// Triangular filters over the FFT bins, spaced evenly in mel with Slaney area normalization.
+fn mel_filters(config: &MelConfig) -> Vec<f32> {
+    let n_filter = config.n_fft / 2 + 1;
+    let nyquist = config.sample_rate as f32 / 2.0;
+    let (min_mel, max_mel) = (0.0, hz_to_mel(nyquist, config.mel_scale));
+
+    let hz: Vec<f32> = (0..config.n_mels + 2)
+        .map(|i| min_mel + (max_mel - min_mel) * i as f32 / (config.n_mels + 1) as f32)
+        .map(|mel| mel_to_hz(mel, config.mel_scale))
+        .collect();
+
+    let mut filter = vec![0f32; config.n_mels * n_filter];
+    for i in 0..config.n_mels {
+        let (lower, center, upper) = (hz[i], hz[i + 1], hz[i + 2]);
+        let norm = 2.0 / (upper - lower);
+
+        for j in 0..n_filter {
+            let f = nyquist * j as f32 / (n_filter - 1) as f32;
+            let rise = (f - lower) / (center - lower);
+            let fall = (upper - f) / (upper - center);
+            filter[i * n_filter + j] = rise.min(fall).max(0.0) * norm;
+        }
+    }
+
+    filter
+}
+
+// Slaney's scale is linear up to 1 kHz, 200/3 Hz per mel, and logarithmic above.
+const SLANEY_HZ_PER_MEL: f32 = 200.0 / 3.0;
+const SLANEY_MIN_LOG_HZ: f32 = 1000.0;
+const SLANEY_MIN_LOG_MEL: f32 = SLANEY_MIN_LOG_HZ / SLANEY_HZ_PER_MEL;
+
+fn slaney_log_step() -> f32 {
+    6.4f32.ln() / 27.0
+}
+
+fn hz_to_mel(hz: f32, scale: MelScale) -> f32 {
+    match scale {
+        MelScale::Htk => 2595.0 * (1.0 + hz / 700.0).log10(),
+        MelScale::Slaney if hz < SLANEY_MIN_LOG_HZ => hz / SLANEY_HZ_PER_MEL,
+        MelScale::Slaney => SLANEY_MIN_LOG_MEL + (hz / SLANEY_MIN_LOG_HZ).ln() / slaney_log_step(),
+    }
+}
+
+fn mel_to_hz(mel: f32, scale: MelScale) -> f32 {
+    match scale {
+        MelScale::Htk => 700.0 * (10f32.powf(mel / 2595.0) - 1.0),
+        MelScale::Slaney if mel < SLANEY_MIN_LOG_MEL => mel * SLANEY_HZ_PER_MEL,
+        MelScale::Slaney => {
+            SLANEY_MIN_LOG_HZ * ((mel - SLANEY_MIN_LOG_MEL) * slaney_log_step()).exp()
+        }
+    }
+}
+
+/// A small xorshift generator for dithering, which needs no more than plausible noise.
+struct Noise(u64);
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl Noise {
+    fn next_uniform(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        // The top 24 bits, mapped into (0, 1] so the logarithm below stays finite.
+        ((self.0 >> 40) + 1) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Box-Muller transform of two uniform samples.
+    fn next_gaussian(&mut self) -> f32 {
+        let (u, v) = (self.next_uniform(), self.next_uniform());
+        (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+    }
+}
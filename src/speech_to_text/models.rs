@@ -0,0 +1,146 @@
+use candle_transformers::models::whisper::SAMPLE_RATE;
+use windows_core::{s, PCSTR};
+
+use crate::config::{MODEL_LARGE_V3, MODEL_LARGE_V3_TURBO, MODEL_MEDIUM_EN, MODEL_SMALL_EN};
+
+pub struct ModelInfo {
+    pub repo_id: &'static str,
+    /// Language, size, memory and speed at a glance.
+    pub description: PCSTR,
+    pub sample_rate: u32,
+    pub settings: &'static [ModelSetting],
+}
+
+/// An option the engine reads from the model's own config section.
+pub struct ModelSetting {
+    pub key: &'static str,
+    pub label: PCSTR,
+    pub default: &'static str,
+    pub choices: &'static [(&'static str, PCSTR)],
+}
+
+pub const SETTING_ENCODER_STRIDE: &str = "encoder-stride";
+pub const SETTING_TIMESTAMPS: &str = "timestamps";
+pub const SETTING_HALLUCINATION_GUARD: &str = "hallucination-guard";
+pub const SETTING_TOKEN_BUDGET: &str = "token-budget";
+pub const SETTING_DECODER_DEVICE: &str = "decoder-device";
+
+const WHISPER_SETTINGS: &[ModelSetting] = &[
+    ModelSetting {
+        key: SETTING_ENCODER_STRIDE,
+        label: s!("Re-encode Interval"),
+        default: "0",
+        choices: &[
+            ("0", s!("Every Update")),
+            ("50", s!("0.5 s")),
+            ("100", s!("1 s")),
+            ("200", s!("2 s")),
+        ],
+    },
+    ModelSetting {
+        key: SETTING_TIMESTAMPS,
+        label: s!("Segment Timing"),
+        default: "0",
+        choices: &[("0", s!("Off")), ("1", s!("Timestamp Tokens"))],
+    },
+    ModelSetting {
+        key: SETTING_HALLUCINATION_GUARD,
+        label: s!("Hallucination Guard"),
+        default: "0",
+        choices: &[("0", s!("Off")), ("1", s!("On"))],
+    },
+    ModelSetting {
+        key: SETTING_TOKEN_BUDGET,
+        label: s!("Tokens per Update"),
+        default: "0",
+        choices: &[
+            ("0", s!("Unlimited")),
+            ("32", s!("32")),
+            ("64", s!("64")),
+            ("128", s!("128")),
+        ],
+    },
+    ModelSetting {
+        key: SETTING_DECODER_DEVICE,
+        label: s!("Decoder Device"),
+        default: "gpu",
+        choices: &[("gpu", s!("GPU")), ("cpu", s!("CPU (Encoder on GPU)"))],
+    },
+];
+
+/// Languages offered for a multilingual model, by Whisper's code.
+pub const LANGUAGES: &[(&str, PCSTR)] = &[
+    ("en", s!("English")),
+    ("zh", s!("Chinese")),
+    ("de", s!("German")),
+    ("es", s!("Spanish")),
+    ("ru", s!("Russian")),
+    ("ko", s!("Korean")),
+    ("fr", s!("French")),
+    ("ja", s!("Japanese")),
+    ("pt", s!("Portuguese")),
+    ("tr", s!("Turkish")),
+    ("pl", s!("Polish")),
+    ("it", s!("Italian")),
+    ("nl", s!("Dutch")),
+    ("ar", s!("Arabic")),
+    ("hi", s!("Hindi")),
+    ("vi", s!("Vietnamese")),
+    ("id", s!("Indonesian")),
+    ("uk", s!("Ukrainian")),
+];
+
+pub const MODELS: &[ModelInfo] = &[
+    ModelInfo {
+        repo_id: MODEL_SMALL_EN,
+        description: s!("English, 166M params, ~0.7 GB VRAM, fastest"),
+        sample_rate: SAMPLE_RATE as _,
+        settings: WHISPER_SETTINGS,
+    },
+    ModelInfo {
+        repo_id: MODEL_MEDIUM_EN,
+        description: s!("English, 394M params, ~1.6 GB VRAM, fast"),
+        sample_rate: SAMPLE_RATE as _,
+        settings: WHISPER_SETTINGS,
+    },
+    ModelInfo {
+        repo_id: MODEL_LARGE_V3,
+        description: s!("English, 756M params, ~3 GB VRAM, moderate"),
+        sample_rate: SAMPLE_RATE as _,
+        settings: WHISPER_SETTINGS,
+    },
+    ModelInfo {
+        repo_id: MODEL_LARGE_V3_TURBO,
+        description: s!("Multilingual, 809M params, ~3.2 GB VRAM, moderate"),
+        sample_rate: SAMPLE_RATE as _,
+        settings: WHISPER_SETTINGS,
+    },
+];
+
+pub fn sample_rate(repo_id: &str) -> u32 {
+    MODELS
+        .iter()
+        .find(|model| model.repo_id == repo_id)
+        .map(|model| model.sample_rate)
+        .unwrap_or(SAMPLE_RATE as _)
+}
+
+/// Repo ids of the models, in menu order.
+pub fn repo_ids() -> Vec<&'static str> {
+    MODELS.iter().map(|model| model.repo_id).collect()
+}
+
+pub fn description(repo_id: &str) -> Option<PCSTR> {
+    MODELS
+        .iter()
+        .find(|model| model.repo_id == repo_id)
+        .map(|model| model.description)
+}
+
+pub fn settings(repo_id: &str) -> &'static [ModelSetting] {
+    MODELS
+        .iter()
+        .find(|model| model.repo_id == repo_id)
+        .map(|model| model.settings)
+        .unwrap_or_default()
+}
@@ -0,0 +1,47 @@
+mod casing;
+mod disfluency;
+mod plugin;
+mod punctuation;
+mod replace;
+mod transliterate;
+
+pub use casing::Casing;
+pub use disfluency::DisfluencyFilter;
+pub use plugin::Plugins;
+pub use punctuation::Punctuation;
+pub use replace::Replacements;
+pub use transliterate::Transliterator;
+
+pub trait PostProcessor: Send {
+    fn process(&mut self, text: &str) -> String;
+}
+
+#[derive(Default)]
+pub struct PostProcessChain {
+    processors: Vec<(&'static str, Box<dyn PostProcessor>)>,
+}
+
+impl PostProcessChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, name: &'static str, processor: Option<Box<dyn PostProcessor>>) {
+        let pos = self.processors.iter().position(|(n, _)| *n == name);
+
+        match (pos, processor) {
+            (Some(i), Some(processor)) => self.processors[i].1 = processor,
+            (Some(i), None) => _ = self.processors.remove(i),
+            (None, Some(processor)) => self.processors.push((name, processor)),
+            (None, None) => {}
+        }
+    }
+
+    pub fn process(&mut self, text: &str) -> String {
+        self.processors
+            .iter_mut()
+            .fold(text.to_string(), |text, (_, processor)| {
+                processor.process(&text)
+            })
+    }
+}
@@ -0,0 +1,78 @@
+use super::PostProcessor;
+
+/// Capitalizes the first letter of each sentence and restores the casing of known names,
+/// for models that output lowercase text.
+pub struct Casing {
+    names: Vec<String>,
+}
+
+impl Casing {
+    /// Only names with an uppercase letter are kept, since the rest carry no casing.
+    pub fn new(names: &[String]) -> Self {
+        let mut names: Vec<_> = names
+            .iter()
+            .map(|name| name.trim().to_string())
+            .filter(|name| name.chars().any(char::is_uppercase))
+            .collect();
+        // Longest first so "New York City" wins over "New York".
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+
+        Self { names }
+    }
+}
+
+impl PostProcessor for Casing {
+    fn process(&mut self, text: &str) -> String {
+        let mut text = capitalize_sentences(text);
+        for name in &self.names {
+            text = restore_name(&text, name);
+        }
+        text
+    }
+}
+
+fn capitalize_sentences(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut at_start = true;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if at_start && c.is_alphabetic() {
+            result.extend(c.to_uppercase());
+            at_start = false;
+            continue;
+        }
+        result.push(c);
+
+        if c.is_alphanumeric() {
+            at_start = false;
+        } else if matches!(c, '.' | '!' | '?') {
+            at_start = chars.peek().is_none_or(|next| next.is_whitespace());
+        }
+    }
+
+    result
+}
+
+/// Replaces whole-word, ASCII case-insensitive matches of `name` with its own casing.
+fn restore_name(text: &str, name: &str) -> String {
+    let lower = text.to_ascii_lowercase();
+    let key = name.to_ascii_lowercase();
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find(&key) {
+        let start = pos + found;
+        let end = start + key.len();
+        let bounded =
+            !is_word(text[..start].chars().next_back()) && !is_word(text[end..].chars().next());
+
+        result.push_str(&text[pos..start]);
+        result.push_str(if bounded { name } else { &text[start..end] });
+        pos = end;
+    }
+    result.push_str(&text[pos..]);
+
+    result
+}
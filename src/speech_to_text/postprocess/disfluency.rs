@@ -0,0 +1,187 @@
+use super::PostProcessor;
+
+const FILLERS: &[&str] = &["um", "umm", "uh", "uhh", "uhm", "erm", "hmm", "mhm"];
+// Also words, as in "5 mm" or "the ER", so only dropped when set off by a pause.
+const PAUSED_FILLERS: &[&str] = &["er", "mm"];
+// Hyphenated prefixes that would otherwise look like a stutter, as in "re-read".
+const PREFIXES: &[&str] = &[
+    "anti", "co", "de", "ex", "mid", "non", "pre", "pro", "re", "self", "semi", "sub", "un",
+];
+// Longest first so "えーっと" isn't left as "っと" by "えー".
+const JA_FILLERS: &[&str] = &[
+    "えーっと",
+    "えーと",
+    "えっと",
+    "えー",
+    "あのー",
+    "あの〜",
+    "うーん",
+    "んー",
+];
+const MAX_REPEAT_WORDS: usize = 3;
+
+/// Strips filler words, stutters and repeated false starts.
+pub struct DisfluencyFilter;
+
+impl PostProcessor for DisfluencyFilter {
+    fn process(&mut self, text: &str) -> String {
+        let text = remove_ja_fillers(text);
+
+        let mut words: Vec<String> = vec![];
+        // Whether a filler followed each word, which makes a repeat after it a false start.
+        let mut paused: Vec<bool> = vec![];
+        let mut capitalize = false;
+
+        for word in text.split_whitespace() {
+            if is_filler(word, words.last().map(String::as_str)) {
+                capitalize |= starts_uppercase(word);
+                if let Some(prev) = words.last_mut() {
+                    merge_punctuation(prev, word);
+                }
+                if let Some(paused) = paused.last_mut() {
+                    *paused = true;
+                }
+                continue;
+            }
+
+            let mut word = destutter(word);
+            if std::mem::take(&mut capitalize) {
+                word = capitalized(&word);
+            }
+            words.push(word);
+            paused.push(false);
+        }
+
+        remove_repeats(&mut words, &mut paused);
+        words.join(" ")
+    }
+}
+
+fn is_filler(word: &str, prev: Option<&str>) -> bool {
+    let key = key(word);
+    if FILLERS.contains(&key.as_str()) {
+        return true;
+    }
+
+    PAUSED_FILLERS.contains(&key.as_str())
+        && !word.chars().any(char::is_uppercase)
+        && ends_with_pause(word)
+        && !prev.is_some_and(|prev| prev.ends_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Drops the first copy of a phrase repeated after a pause, e.g. "I was, I was going" or
+/// "the, uh, the", leaving intended repeats such as "had had" or "bye bye".
+fn remove_repeats(words: &mut Vec<String>, paused: &mut Vec<bool>) {
+    let mut i = 0;
+
+    while i < words.len() {
+        let repeat = (1..=MAX_REPEAT_WORDS).rev().find(|&n| {
+            i + 2 * n <= words.len()
+                && !key(&words[i]).is_empty()
+                && (paused[i + n - 1] || ends_with_pause(&words[i + n - 1]))
+                && (0..n).all(|k| key(&words[i + k]) == key(&words[i + n + k]))
+        });
+
+        match repeat {
+            Some(n) => {
+                let capitalize = starts_uppercase(&words[i]);
+                words.drain(i..i + n);
+                paused.drain(i..i + n);
+                if capitalize {
+                    words[i] = capitalized(&words[i]);
+                }
+            }
+            None => i += 1,
+        }
+    }
+}
+
+fn ends_with_pause(word: &str) -> bool {
+    word.ends_with([',', '-', '—', '…']) || word.ends_with("...")
+}
+
+/// Japanese fillers standing alone between punctuation, spaces or the ends of the text, so
+/// words that merely contain one are left alone.
+fn remove_ja_fillers(text: &str) -> String {
+    let is_edge = |c: Option<char>| {
+        c.is_none_or(|c| {
+            c.is_whitespace()
+                || matches!(c, '、' | '。' | '，' | '！' | '？' | ',' | '.' | '!' | '?')
+        })
+    };
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let filler = JA_FILLERS.iter().find(|filler| {
+            rest.starts_with(*filler) && is_edge(rest[filler.len()..].chars().next())
+        });
+        match filler.filter(|_| is_edge(result.chars().next_back())) {
+            Some(filler) => {
+                rest = &rest[filler.len()..];
+                rest = rest.strip_prefix('、').unwrap_or(rest);
+            }
+            None => {
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    result
+}
+
+/// Keeps sentence punctuation of a dropped filler: "is, uh, fine" -> "is fine", "it, um." -> "it.".
+fn merge_punctuation(prev: &mut String, filler: &str) {
+    let Some(punct) = filler.chars().last().filter(|c| !c.is_alphanumeric()) else {
+        return;
+    };
+
+    if prev.ends_with(',') {
+        prev.pop();
+    }
+    if punct != ',' && !prev.ends_with(punct) {
+        prev.push(punct);
+    }
+}
+
+/// "w-w-what" -> "what", "Th-the" -> "The".
+fn destutter(word: &str) -> String {
+    let parts: Vec<&str> = word.split('-').collect();
+    let Some((last, heads)) = parts.split_last() else {
+        return word.to_string();
+    };
+
+    let last_key = last.to_lowercase();
+    let is_prefix = heads.len() == 1 && PREFIXES.contains(&heads[0].to_lowercase().as_str());
+    let is_stutter = !heads.is_empty()
+        && !is_prefix
+        && heads.iter().all(|head| {
+            !head.is_empty()
+                && head.len() < last.len()
+                && last_key.starts_with(&head.to_lowercase())
+        });
+
+    match (is_stutter, starts_uppercase(word)) {
+        (true, true) => capitalized(last),
+        (true, false) => last.to_string(),
+        (false, _) => word.to_string(),
+    }
+}
+
+fn key(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+fn starts_uppercase(word: &str) -> bool {
+    word.chars().next().is_some_and(char::is_uppercase)
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|c| c.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,119 @@
+//! Caption processors loaded from DLLs.
+//!
+//! A plugin exports the following C functions:
+//!
+//! ```c
+//! // Returns the modified text as a NUL-terminated UTF-8 string, or NULL to keep the input.
+//! char *livesub_process(const char *text);
+//! // Frees a string returned by livesub_process.
+//! void livesub_free(char *text);
+//! ```
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    path::Path,
+};
+
+use anyhow::{bail, Error, Result};
+use windows::{
+    core::{s, PCWSTR},
+    Win32::{
+        Foundation::{FreeLibrary, HMODULE},
+        System::LibraryLoader::{GetProcAddress, LoadLibraryW},
+    },
+};
+
+use super::PostProcessor;
+use crate::gui::utils::CStr as _;
+
+type ProcessFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+pub struct DllPlugin {
+    module: HMODULE,
+    process: ProcessFn,
+    free: FreeFn,
+}
+
+impl DllPlugin {
+    pub fn load(path: &Path) -> Result<Self> {
+        unsafe {
+            let name = path.to_string_lossy().to_string().c_wstr();
+            let module = LoadLibraryW(PCWSTR(name.as_ptr()))?;
+
+            let process = GetProcAddress(module, s!("livesub_process"));
+            let free = GetProcAddress(module, s!("livesub_free"));
+            let (Some(process), Some(free)) = (process, free) else {
+                _ = FreeLibrary(module);
+                bail!("{} is not a livesub plugin", path.display());
+            };
+
+            Ok(Self {
+                module,
+                process: std::mem::transmute::<unsafe extern "system" fn() -> isize, ProcessFn>(
+                    process,
+                ),
+                free: std::mem::transmute::<unsafe extern "system" fn() -> isize, FreeFn>(free),
+            })
+        }
+    }
+}
+
+impl PostProcessor for DllPlugin {
+    fn process(&mut self, text: &str) -> String {
+        let Ok(input) = CString::new(text) else {
+            return text.to_string();
+        };
+
+        unsafe {
+            let output = (self.process)(input.as_ptr());
+            if output.is_null() {
+                return text.to_string();
+            }
+
+            let result = CStr::from_ptr(output).to_string_lossy().into_owned();
+            (self.free)(output);
+            result
+        }
+    }
+}
+
+impl Drop for DllPlugin {
+    fn drop(&mut self) {
+        unsafe {
+            _ = FreeLibrary(self.module);
+        }
+    }
+}
+
+unsafe impl Send for DllPlugin {}
+
+#[derive(Default)]
+pub struct Plugins(Vec<DllPlugin>);
+
+impl Plugins {
+    /// Loads the plugins at `paths`, skipping any that fail with the reason why.
+    pub fn load(paths: &[impl AsRef<Path>]) -> (Self, Vec<Error>) {
+        let mut plugins = vec![];
+        let mut errors = vec![];
+        for path in paths {
+            match DllPlugin::load(path.as_ref()) {
+                Ok(plugin) => plugins.push(plugin),
+                Err(e) => errors.push(e),
+            }
+        }
+        (Self(plugins), errors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl PostProcessor for Plugins {
+    fn process(&mut self, text: &str) -> String {
+        self.0
+            .iter_mut()
+            .fold(text.to_string(), |text, plugin| plugin.process(&text))
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use candle::{DType, Device, Tensor, D};
+use candle_nn::{Linear, Module as _, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config};
+use tokenizers::Tokenizer;
+
+use super::PostProcessor;
+
+// Words per model call, well within the 512 tokens BERT takes.
+const CHUNK_WORDS: usize = 128;
+const CACHE_SIZE: usize = 256;
+
+/// Restores punctuation and casing with a token classification model that labels each word
+/// with the mark following it and whether it starts uppercase, such as a fine-tuned BERT.
+/// Labels are two characters: the mark or `O` for none, then `U` for uppercase or `O`;
+/// a model with any other label is rejected.
+pub struct Punctuation {
+    model: BertModel,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+    labels: Vec<(Option<char>, bool)>,
+    // Confirmed text is processed again on every update, so results are kept.
+    cache: HashMap<String, String>,
+}
+
+impl Punctuation {
+    /// Loads `config.json`, `tokenizer.json` and `model.safetensors` from `dir`, running on the CPU
+    /// to leave the GPU to speech recognition.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let config = std::fs::read_to_string(dir.join("config.json"))
+            .with_context(|| format!("punctuation model not found in {}", dir.display()))?;
+        let json: serde_json::Value = serde_json::from_str(&config)?;
+        let labels = parse_labels(&json)?;
+        let hidden_size = json["hidden_size"]
+            .as_u64()
+            .ok_or_else(|| anyhow!("punctuation model has no hidden size"))?;
+        let config: Config = serde_json::from_str(&config)?;
+
+        let tokenizer =
+            Tokenizer::from_file(dir.join("tokenizer.json")).map_err(anyhow::Error::msg)?;
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(
+                &[dir.join("model.safetensors")],
+                DType::F32,
+                &Device::Cpu,
+            )?
+        };
+        let model = BertModel::load(vb.clone(), &config)?;
+        let classifier = candle_nn::linear(hidden_size as _, labels.len(), vb.pp("classifier"))?;
+
+        Ok(Self {
+            model,
+            classifier,
+            tokenizer,
+            labels,
+            cache: HashMap::new(),
+        })
+    }
+
+    fn restore(&self, text: &str) -> Result<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut restored = Vec::with_capacity(words.len());
+
+        for chunk in words.chunks(CHUNK_WORDS) {
+            let labels = self.classify(chunk)?;
+            for (word, (mark, upper)) in chunk.iter().zip(labels) {
+                let mut word = match upper {
+                    true => capitalize(word),
+                    false => word.to_string(),
+                };
+                if let Some(mark) =
+                    mark.filter(|_| !word.ends_with(|c: char| c.is_ascii_punctuation()))
+                {
+                    word.push(mark);
+                }
+                restored.push(word);
+            }
+        }
+
+        Ok(restored.join(" "))
+    }
+
+    /// The label of the first token of each word.
+    fn classify(&self, words: &[&str]) -> Result<Vec<(Option<char>, bool)>> {
+        // Marks already in the text would hide the word from a model trained without them.
+        let bare: Vec<&str> = words
+            .iter()
+            .map(
+                |word| match word.trim_end_matches(|c: char| c.is_ascii_punctuation()) {
+                    "" => *word,
+                    bare => bare,
+                },
+            )
+            .collect();
+        let encoding = self
+            .tokenizer
+            .encode(bare, true)
+            .map_err(anyhow::Error::msg)?;
+
+        let device = &self.model.device;
+        let input_ids = Tensor::new(encoding.get_ids(), device)?.unsqueeze(0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+        let hidden = self.model.forward(&input_ids, &token_type_ids, None)?;
+        let predictions = self
+            .classifier
+            .forward(&hidden)?
+            .argmax(D::Minus1)?
+            .squeeze(0)?
+            .to_vec1::<u32>()?;
+
+        let mut labels = vec![(None, false); words.len()];
+        let mut last_word = None;
+        for (word, prediction) in encoding.get_word_ids().iter().zip(predictions) {
+            if let Some(word) = word.filter(|&word| Some(word) != last_word) {
+                if let Some(label) = labels.get_mut(word as usize) {
+                    *label = self.labels[prediction as usize];
+                }
+                last_word = Some(word);
+            }
+        }
+
+        Ok(labels)
+    }
+}
+
+impl PostProcessor for Punctuation {
+    fn process(&mut self, text: &str) -> String {
+        if let Some(restored) = self.cache.get(text) {
+            return restored.clone();
+        }
+
+        // Left as it is if the model fails, rather than dropping the caption.
+        let restored = self.restore(text).unwrap_or_else(|_| text.to_string());
+        if self.cache.len() >= CACHE_SIZE {
+            self.cache.clear();
+        }
+        self.cache.insert(text.to_string(), restored.clone());
+
+        restored
+    }
+}
+
+fn parse_labels(config: &serde_json::Value) -> Result<Vec<(Option<char>, bool)>> {
+    let id2label = config["id2label"]
+        .as_object()
+        .ok_or_else(|| anyhow!("punctuation model has no labels"))?;
+
+    let mut labels = vec![(None, false); id2label.len()];
+    for (id, label) in id2label {
+        let chars: Vec<char> = label.as_str().unwrap_or_default().chars().collect();
+        let (mark, upper) = match chars[..] {
+            ['O', case @ ('O' | 'U')] => (None, case == 'U'),
+            [mark, case @ ('O' | 'U')] if is_mark(mark) => (Some(mark), case == 'U'),
+            _ => bail!("unknown punctuation label {label}"),
+        };
+        *labels
+            .get_mut(id.parse::<usize>()?)
+            .ok_or_else(|| anyhow!("punctuation label {id} out of range"))? = (mark, upper);
+    }
+
+    Ok(labels)
+}
+
+fn is_mark(c: char) -> bool {
+    c.is_ascii_punctuation() || matches!(c, '、' | '。' | '，' | '！' | '？' | '…')
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
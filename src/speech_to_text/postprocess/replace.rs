@@ -0,0 +1,115 @@
+use regex::{Captures, Regex};
+
+use super::PostProcessor;
+use crate::caption::wrap::is_cjk;
+
+/// Rewrites captions by rules, one per line: `word => replacement` matches whole words
+/// ignoring case, `/regex/ => replacement` can refer to groups as `$1`, and a rule without
+/// `=>` masks its matches with asterisks after the first letter.
+pub struct Replacements {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    regex: Regex,
+    replacement: Replacement,
+    // Words run together in Chinese and Japanese, where `\b` would only match at the ends
+    // of a whole sentence, so word rules check their edges against spaced scripts instead.
+    whole_words: bool,
+}
+
+enum Replacement {
+    /// Inserted as is, `$` included.
+    Literal(String),
+    /// Expands `$1` and `${name}` to the groups of a regex rule.
+    Template(String),
+    Mask,
+}
+
+impl Replacements {
+    /// The rules from `lines`, along with the lines that had to be skipped.
+    pub fn new(lines: &[String]) -> (Option<Self>, Vec<String>) {
+        let mut rules = vec![];
+        let mut skipped = vec![];
+
+        for line in lines {
+            let (pattern, replacement) = match line.split_once("=>") {
+                Some((pattern, replacement)) => (pattern.trim(), Some(replacement.trim())),
+                None => (line.trim(), None),
+            };
+            let regex = pattern
+                .strip_prefix('/')
+                .and_then(|pattern| pattern.strip_suffix('/'));
+            let whole_words = regex.is_none();
+            let replacement = match replacement {
+                Some(replacement) if whole_words => Replacement::Literal(replacement.to_string()),
+                Some(replacement) => Replacement::Template(replacement.to_string()),
+                None => Replacement::Mask,
+            };
+            let regex = match regex {
+                Some(pattern) => Regex::new(pattern),
+                None => Regex::new(&format!("(?i){}", regex::escape(pattern))),
+            };
+            match regex {
+                Ok(regex) => rules.push(Rule {
+                    regex,
+                    replacement,
+                    whole_words,
+                }),
+                Err(e) => skipped.push(format!("invalid replacement rule {line:?}, {e}")),
+            }
+        }
+
+        ((!rules.is_empty()).then_some(Self { rules }), skipped)
+    }
+}
+
+impl PostProcessor for Replacements {
+    fn process(&mut self, text: &str) -> String {
+        let mut text = text.to_string();
+
+        for rule in &self.rules {
+            let replaced = rule.regex.replace_all(&text, |caps: &Captures| {
+                let m = &caps[0];
+                let start = caps.get(0).map_or(0, |m| m.start());
+                if rule.whole_words && !is_whole_word(&text, start, start + m.len()) {
+                    return m.to_string();
+                }
+
+                match &rule.replacement {
+                    Replacement::Literal(replacement) => replacement.clone(),
+                    Replacement::Template(template) => {
+                        let mut replacement = String::new();
+                        caps.expand(template, &mut replacement);
+                        replacement
+                    }
+                    Replacement::Mask => mask(m),
+                }
+            });
+            text = replaced.into_owned();
+        }
+
+        text
+    }
+}
+
+/// Whether `text[start..end]` isn't part of a longer word, going by letters of spaced
+/// scripts; Chinese and Japanese characters never join it to its neighbors.
+fn is_whole_word(text: &str, start: usize, end: usize) -> bool {
+    let is_word = |c: char| (c.is_alphanumeric() || c == '_') && !is_cjk(c);
+    let matched = &text[start..end];
+
+    let joins_before = matched.chars().next().is_some_and(is_word)
+        && text[..start].chars().next_back().is_some_and(is_word);
+    let joins_after = matched.chars().next_back().is_some_and(is_word)
+        && text[end..].chars().next().is_some_and(is_word);
+
+    !joins_before && !joins_after
+}
+
+fn mask(word: &str) -> String {
+    word.chars()
+        .enumerate()
+        .map(|(i, c)| if i == 0 || c.is_whitespace() { c } else { '*' })
+        .collect()
+}
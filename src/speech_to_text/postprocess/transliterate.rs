@@ -0,0 +1,207 @@
+use super::PostProcessor;
+
+/// Rewrites kana, leaving kanji as they are, as their readings depend on context a
+/// character table can't give.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transliterator {
+    Romaji,
+    Hiragana,
+}
+
+impl PostProcessor for Transliterator {
+    fn process(&mut self, text: &str) -> String {
+        match self {
+            Self::Romaji => to_romaji(text),
+            Self::Hiragana => text.chars().map(to_hiragana).collect(),
+        }
+    }
+}
+
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn to_romaji(text: &str) -> String {
+    let chars: Vec<char> = text.chars().map(to_hiragana).collect();
+    let mut out = String::with_capacity(text.len());
+    let mut sokuon = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let (mut romaji, n) = match (kana(c), next) {
+            (Some(base), Some(small @ ('ゃ' | 'ゅ' | 'ょ'))) if base.ends_with('i') => {
+                (youon(base, small), 2)
+            }
+            (Some(base), Some(small @ ('ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ'))) if base.len() > 1 =>
+            {
+                let consonant = &base[..base.len() - 1];
+                (format!("{consonant}{}", kana(small).unwrap_or_default()), 2)
+            }
+            (Some("u"), Some(small @ ('ぃ' | 'ぇ' | 'ぉ'))) => {
+                (format!("w{}", kana(small).unwrap_or_default()), 2)
+            }
+            (Some(base), _) => (base.to_string(), 1),
+            (None, _) => {
+                match c {
+                    'っ' => {
+                        sokuon = true;
+                        i += 1;
+                        continue;
+                    }
+                    'ー' => {
+                        if let Some(vowel) = out.chars().last().filter(|c| "aiueo".contains(*c)) {
+                            out.push(vowel);
+                        }
+                    }
+                    'ん' => {
+                        out.push('n');
+                        if matches!(next.and_then(kana), Some(r) if r.starts_with(['a', 'i', 'u', 'e', 'o', 'y']))
+                        {
+                            out.push('\'');
+                        }
+                    }
+                    _ => out.push_str(&punctuation(c)),
+                }
+                i += 1;
+                continue;
+            }
+        };
+
+        if std::mem::take(&mut sokuon) {
+            if romaji.starts_with("ch") {
+                romaji.insert(0, 't');
+            } else if let Some(consonant) = romaji.chars().next().filter(|c| !"aiueo".contains(*c))
+            {
+                romaji.insert(0, consonant);
+            }
+        }
+
+        out.push_str(&romaji);
+        i += n;
+    }
+
+    out
+}
+
+fn youon(base: &str, small: char) -> String {
+    let stem = &base[..base.len() - 1];
+    let vowel = match small {
+        'ゃ' => 'a',
+        'ゅ' => 'u',
+        _ => 'o',
+    };
+
+    if stem.ends_with("sh") || stem.ends_with("ch") || stem.ends_with('j') {
+        format!("{stem}{vowel}")
+    } else {
+        format!("{stem}y{vowel}")
+    }
+}
+
+fn punctuation(c: char) -> String {
+    match c {
+        '。' => ". ".into(),
+        '、' => ", ".into(),
+        '？' => "?".into(),
+        '！' => "!".into(),
+        '「' | '」' | '『' | '』' => "\"".into(),
+        '・' => " ".into(),
+        '\u{3000}' => " ".into(),
+        c => c.to_string(),
+    }
+}
+
+fn kana(c: char) -> Option<&'static str> {
+    let romaji = match c {
+        'あ' => "a",
+        'い' => "i",
+        'う' => "u",
+        'え' => "e",
+        'お' => "o",
+        'か' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' => "ji",
+        'ず' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'ぢ' => "ji",
+        'づ' => "zu",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' => "ya",
+        'ゆ' => "yu",
+        'よ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' => "wa",
+        'ゐ' => "wi",
+        'ゑ' => "we",
+        'を' => "wo",
+        'ゔ' => "vu",
+        'ぁ' => "a",
+        'ぃ' => "i",
+        'ぅ' => "u",
+        'ぇ' => "e",
+        'ぉ' => "o",
+        'ゃ' => "ya",
+        'ゅ' => "yu",
+        'ょ' => "yo",
+        'ゎ' => "wa",
+        _ => return None,
+    };
+    Some(romaji)
+}
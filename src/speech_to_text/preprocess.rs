@@ -0,0 +1,252 @@
+use std::fmt;
+
+use anyhow::{bail, Context as _, Result};
+
+// Gain and gate levels are measured over blocks this long.
+const BLOCK_MS: u32 = 10;
+const AGC_TARGET_DB: f32 = -20.0;
+const AGC_MAX_GAIN_DB: f32 = 24.0;
+// Blocks quieter than this leave the gain alone, so pauses aren't pumped up to speech level.
+const AGC_GATE_DB: f32 = -60.0;
+// The gain falls quickly when the level jumps and recovers slowly.
+const AGC_ATTACK: f32 = 0.5;
+const AGC_RELEASE: f32 = 0.02;
+const DENOISE_THRESHOLD_DB: f32 = -50.0;
+const DENOISE_REDUCTION_DB: f32 = 20.0;
+const DENOISE_SMOOTHING: f32 = 0.3;
+
+/// Which channels of the captured frames make up the mono signal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelSelect {
+    /// The sum of all channels.
+    #[default]
+    Mix,
+    /// One channel, counted from 0; a missing one falls back to the mix.
+    Channel(usize),
+}
+
+impl ChannelSelect {
+    fn parse(param: &str) -> Result<Self> {
+        Ok(match param.to_ascii_lowercase().as_str() {
+            "" | "mix" => Self::Mix,
+            "left" => Self::Channel(0),
+            "right" => Self::Channel(1),
+            n => match n.parse::<usize>() {
+                Ok(n) if n >= 1 => Self::Channel(n - 1),
+                _ => bail!("unknown channel {param:?}"),
+            },
+        })
+    }
+
+    /// Reduces one interleaved frame to a sample.
+    pub fn select(self, frame: &[f32]) -> f32 {
+        match self {
+            Self::Channel(ch) if ch < frame.len() => frame[ch],
+            _ => frame.iter().sum(),
+        }
+    }
+}
+
+/// Brings speech to a steady level, so a quiet microphone or a soft voice in a video
+/// reaches the model as loud as anything else.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Agc {
+    target_db: f32,
+    max_gain_db: f32,
+    gain_db: f32,
+}
+
+impl Agc {
+    fn process(&mut self, block: &mut [f32]) {
+        let db = level_db(block);
+        if db > AGC_GATE_DB {
+            let wanted = (self.target_db - db).min(self.max_gain_db);
+            let rate = if wanted < self.gain_db {
+                AGC_ATTACK
+            } else {
+                AGC_RELEASE
+            };
+            self.gain_db += (wanted - self.gain_db) * rate;
+        }
+
+        let gain = db_to_amplitude(self.gain_db);
+        for x in block {
+            *x = (*x * gain).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+/// A noise gate: blocks below the threshold are turned down, taking hum and hiss out of
+/// the pauses, where the model is most likely to make up words.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Denoise {
+    threshold_db: f32,
+    reduction_db: f32,
+    gain: f32,
+}
+
+impl Denoise {
+    fn process(&mut self, block: &mut [f32]) {
+        let wanted = match level_db(block) < self.threshold_db {
+            true => db_to_amplitude(-self.reduction_db),
+            false => 1.0,
+        };
+        let start = self.gain;
+        self.gain += (wanted - self.gain) * DENOISE_SMOOTHING;
+
+        // Ramped over the block so the gate doesn't click.
+        let step = (self.gain - start) / block.len().max(1) as f32;
+        for (i, x) in block.iter_mut().enumerate() {
+            *x *= start + step * (i + 1) as f32;
+        }
+    }
+}
+
+/// The stages audio passes through between capture and resampling, from `audio-chain`,
+/// such as `capture, channel left, agc target=-18, denoise threshold=-55, resample`.
+/// Stages run in that order; one left out is bypassed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AudioChain {
+    pub channels: ChannelSelect,
+    agc: Option<Agc>,
+    denoise: Option<Denoise>,
+    block: Vec<f32>,
+}
+
+impl AudioChain {
+    pub fn parse(chain: &str) -> Result<Self> {
+        let mut this = Self::default();
+        // Stages in the order they run, so a chain listing them otherwise is rejected.
+        let order = ["capture", "channel", "agc", "denoise", "resample"];
+        let mut last = None;
+
+        for node in chain
+            .split(',')
+            .map(str::trim)
+            .filter(|node| !node.is_empty())
+        {
+            let (name, params) = node.split_once(char::is_whitespace).unwrap_or((node, ""));
+            let name = name.to_ascii_lowercase();
+            let Some(rank) = order.iter().position(|&stage| stage == name) else {
+                bail!("unknown audio stage {name:?}");
+            };
+            if last.is_some_and(|last| rank <= last) {
+                bail!("audio stages must run in the order {}", order.join(", "));
+            }
+            last = Some(rank);
+
+            match name.as_str() {
+                "channel" => this.channels = ChannelSelect::parse(params.trim())?,
+                "agc" => {
+                    let mut agc = Agc {
+                        target_db: AGC_TARGET_DB,
+                        max_gain_db: AGC_MAX_GAIN_DB,
+                        gain_db: 0.0,
+                    };
+                    for (key, value) in parse_params(params)? {
+                        match key {
+                            "target" => agc.target_db = value,
+                            "max-gain" => agc.max_gain_db = value,
+                            _ => bail!("unknown agc parameter {key:?}"),
+                        }
+                    }
+                    this.agc = Some(agc);
+                }
+                "denoise" => {
+                    let mut denoise = Denoise {
+                        threshold_db: DENOISE_THRESHOLD_DB,
+                        reduction_db: DENOISE_REDUCTION_DB,
+                        gain: 1.0,
+                    };
+                    for (key, value) in parse_params(params)? {
+                        match key {
+                            "threshold" => denoise.threshold_db = value,
+                            "reduction" => denoise.reduction_db = value.max(0.0),
+                            _ => bail!("unknown denoise parameter {key:?}"),
+                        }
+                    }
+                    this.denoise = Some(denoise);
+                }
+                _ if !params.trim().is_empty() => bail!("{name} takes no parameters"),
+                _ => {}
+            }
+        }
+
+        Ok(this)
+    }
+
+    /// Runs the mono stages over `samples` captured at `sample_rate`, in place. A partial
+    /// block is held back until the next call, so levels are always measured over whole blocks.
+    pub fn process(&mut self, samples: &mut Vec<f32>, start: usize, sample_rate: u32) {
+        if self.agc.is_none() && self.denoise.is_none() {
+            return;
+        }
+
+        let block_len = (sample_rate * BLOCK_MS / 1000).max(1) as usize;
+        let mut pending = std::mem::take(&mut self.block);
+        pending.extend(samples.drain(start..));
+
+        let whole = pending.len() - pending.len() % block_len;
+        for block in pending[..whole].chunks_mut(block_len) {
+            if let Some(agc) = &mut self.agc {
+                agc.process(block);
+            }
+            if let Some(denoise) = &mut self.denoise {
+                denoise.process(block);
+            }
+        }
+
+        self.block = pending.split_off(whole);
+        samples.extend(pending);
+    }
+
+    /// Drops the held back partial block.
+    pub fn clear(&mut self) {
+        self.block.clear();
+    }
+
+    /// Samples held back by `process`.
+    pub fn pending(&self) -> usize {
+        self.block.len()
+    }
+}
+
+impl fmt::Display for AudioChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.channels {
+            ChannelSelect::Mix => write!(f, "mixed")?,
+            ChannelSelect::Channel(ch) => write!(f, "channel {}", ch + 1)?,
+        }
+        if let Some(agc) = &self.agc {
+            write!(f, ", AGC to {} dB", agc.target_db)?;
+        }
+        if let Some(denoise) = &self.denoise {
+            write!(f, ", gated below {} dB", denoise.threshold_db)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_params(params: &str) -> Result<Vec<(&str, f32)>> {
+    params
+        .split_whitespace()
+        .map(|param| {
+            let (key, value) = param
+                .split_once('=')
+                .with_context(|| format!("expected key=value, found {param:?}"))?;
+            let value = value
+                .parse()
+                .with_context(|| format!("invalid value for {key}: {value:?}"))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+fn level_db(block: &[f32]) -> f32 {
+    let power = block.iter().map(|x| x * x).sum::<f32>() / block.len().max(1) as f32;
+    10.0 * power.max(1e-10).log10()
+}
+
+fn db_to_amplitude(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::{models, transcribe::Transcriber};
+
+// Events kept for a caller that doesn't poll, oldest dropped first.
+const MAX_EVENTS: usize = 256;
+
+/// An event produced by [`StreamingTranscriber::poll`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptEvent {
+    /// The current hypothesis for the segment in progress. It may still change.
+    Partial(String),
+    /// The final text of a segment that has ended.
+    Final(String),
+}
+
+/// Transcription engine driven by the caller, without the built-in audio capture.
+///
+/// Feed mono samples at [`StreamingTranscriber::sample_rate`] and poll for events.
+pub struct StreamingTranscriber {
+    transcriber: Transcriber,
+    sample_rate: u32,
+    events: Events,
+}
+
+impl StreamingTranscriber {
+    /// Loads `repo_id` from the Hugging Face hub, caching it in `model_dir`.
+    pub fn new(repo_id: &str, model_dir: &Path) -> Result<Self> {
+        Ok(Self {
+            transcriber: Transcriber::new(repo_id, model_dir, false)?,
+            sample_rate: models::sample_rate(repo_id),
+            events: Events::default(),
+        })
+    }
+
+    /// The sample rate the fed audio must be in.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Transcribes newly captured samples.
+    pub fn feed(&mut self, samples: &[f32]) -> Result<()> {
+        let result = self.transcriber.transcribe(samples)?;
+        self.events.push(result);
+        Ok(())
+    }
+
+    /// Ends the segment in progress, emitting it as final.
+    pub fn flush(&mut self) {
+        self.events.flush();
+        self.transcriber.clear();
+    }
+
+    /// Takes the events produced since the last call.
+    pub fn poll(&mut self) -> Vec<TranscriptEvent> {
+        self.events.poll()
+    }
+}
+
+/// Several audio sources, such as a microphone and the desktop, transcribed by one model.
+///
+/// Encoder passes are batched across streams; each stream keeps its own decoder state.
+pub struct MultiStreamTranscriber {
+    streams: Vec<Transcriber>,
+    sample_rate: u32,
+    events: Vec<Events>,
+}
+
+impl MultiStreamTranscriber {
+    /// Loads `repo_id` once and sets up `count` streams on it.
+    pub fn new(repo_id: &str, model_dir: &Path, count: usize) -> Result<Self> {
+        let first = Transcriber::new(repo_id, model_dir, false)?;
+        let mut streams = Vec::with_capacity(count);
+        for _ in 1..count {
+            streams.push(first.fork()?);
+        }
+        streams.insert(0, first);
+
+        Ok(Self {
+            streams,
+            sample_rate: models::sample_rate(repo_id),
+            events: (0..count).map(|_| Events::default()).collect(),
+        })
+    }
+
+    /// The sample rate the fed audio must be in.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Transcribes newly captured samples, one slice per stream.
+    pub fn feed(&mut self, samples: &[&[f32]]) -> Result<()> {
+        let results = Transcriber::transcribe_batch(&mut self.streams, samples)?;
+        for (events, result) in self.events.iter_mut().zip(results) {
+            events.push(result);
+        }
+
+        Ok(())
+    }
+
+    /// Sets the language and task of every stream, as in [`super::SpeechToText::set_task`].
+    pub fn set_task(&mut self, language: &str, translate: bool) {
+        for stream in &mut self.streams {
+            stream.set_task(language, translate);
+        }
+    }
+
+    /// Favors `words` on every stream.
+    pub fn set_hotwords(&mut self, words: &[String]) {
+        for stream in &mut self.streams {
+            stream.set_hotwords(words);
+        }
+    }
+
+    /// Ends the segment in progress on `stream`, emitting it as final.
+    pub fn flush(&mut self, stream: usize) {
+        self.events[stream].flush();
+        self.streams[stream].clear();
+    }
+
+    /// Takes the events `stream` produced since the last call.
+    pub fn poll(&mut self, stream: usize) -> Vec<TranscriptEvent> {
+        self.events[stream].poll()
+    }
+}
+
+#[derive(Default)]
+struct Events {
+    current: String,
+    events: Vec<TranscriptEvent>,
+}
+
+impl Events {
+    fn push(&mut self, result: Option<(String, bool)>) {
+        let Some((text, is_new_segment)) = result else {
+            return;
+        };
+
+        if is_new_segment && !self.current.is_empty() {
+            let text = std::mem::take(&mut self.current);
+            self.events.push(TranscriptEvent::Final(text));
+        }
+
+        if text != self.current {
+            self.current = text.clone();
+            self.events.push(TranscriptEvent::Partial(text));
+        }
+
+        if self.events.len() > MAX_EVENTS {
+            // Partials are superseded anyway, so they go before any final text.
+            let excess = self.events.len() - MAX_EVENTS;
+            let mut dropped = 0;
+            self.events.retain(|event| {
+                let drop = dropped < excess && matches!(event, TranscriptEvent::Partial(_));
+                dropped += drop as usize;
+                !drop
+            });
+            let excess = self.events.len().saturating_sub(MAX_EVENTS);
+            self.events.drain(..excess);
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.current.is_empty() {
+            let text = std::mem::take(&mut self.current);
+            self.events.push(TranscriptEvent::Final(text));
+        }
+    }
+
+    fn poll(&mut self) -> Vec<TranscriptEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
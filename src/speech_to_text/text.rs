@@ -1,4 +1,13 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::caption::{wrap::is_cjk, CaptionSegment, TimedText};
+
+// Confirmed captions kept for a reader that stopped taking them, oldest dropped first.
+const MAX_CONFIRMED: usize = 256;
+const GHOST_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.5];
 
 #[derive(Clone)]
 pub struct TextStream(Arc<Mutex<TextStreamInner>>);
@@ -8,13 +17,122 @@ impl TextStream {
         Self(Arc::new(Mutex::new(TextStreamInner::new())))
     }
 
-    pub fn set(&self, text: String, is_new_segment: bool) {
+    pub fn set(
+        &self,
+        text: String,
+        is_new_segment: bool,
+        timings: Vec<TimedText>,
+        language: Option<String>,
+        captured: Instant,
+    ) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.set(text, is_new_segment, timings, language, captured);
+        }
+    }
+
+    /// Keeps captions in other languages out of the confirmed text, showing them dimmed
+    /// if `ghost` is set. Empty `languages` lets everything through.
+    pub fn set_language_filter(&self, languages: Vec<String>, ghost: bool) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.filter = LanguageFilter { languages, ghost };
+            inner.dirty = true;
+        }
+    }
+
+    pub fn set_granularity(&self, granularity: Granularity) {
         if let Ok(mut inner) = self.0.lock() {
-            inner.set(text, is_new_segment);
+            inner.granularity = granularity;
+            inner.dirty = true;
         }
     }
 
-    pub fn get(&self) -> Option<String> {
+    /// Confirmed captions the language filter held back, with their language.
+    pub fn take_filtered(&self) -> Vec<(String, String, Vec<TimedText>)> {
+        if let Ok(mut inner) = self.0.lock() {
+            std::mem::take(&mut inner.filtered)
+        } else {
+            vec![]
+        }
+    }
+
+    /// Whether the engine hears speech, so captions awaiting text can be hinted at.
+    pub fn set_speaking(&self, speaking: bool) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.speaking = speaking;
+        }
+    }
+
+    /// Speech is heard but none of its text is shown yet.
+    pub fn awaiting_text(&self) -> bool {
+        self.0
+            .lock()
+            .is_ok_and(|inner| inner.speaking && inner.granularity.visible(&inner.cur).is_empty())
+    }
+
+    pub fn set_status(&self, status: String) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.set_status(status);
+        }
+    }
+
+    /// Clears the status if it is still `status`, leaving one set since in place.
+    pub fn clear_status(&self, status: &str) {
+        if let Ok(mut inner) = self.0.lock() {
+            if !status.is_empty() && inner.status == status {
+                inner.set_status(String::new());
+            }
+        }
+    }
+
+    /// Holds confirmed captions back until `finalize` has run over them, for processing too
+    /// slow for the window's thread.
+    pub fn set_finalizing(&self, finalizing: bool) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.finalizing = finalizing;
+            if !finalizing {
+                let unfinalized = std::mem::take(&mut inner.unfinalized);
+                inner.push_confirmed(unfinalized);
+            }
+        }
+    }
+
+    /// Rewrites the captions held back since the last call and releases them. The lock is
+    /// released meanwhile, so readers aren't kept waiting.
+    pub fn finalize(&self, mut process: impl FnMut(&str) -> String) {
+        let unfinalized = match self.0.lock() {
+            Ok(mut inner) => std::mem::take(&mut inner.unfinalized),
+            Err(_) => return,
+        };
+        if unfinalized.is_empty() {
+            return;
+        }
+
+        let finalized: Vec<_> = unfinalized
+            .into_iter()
+            .map(|(text, timings, captured)| (process(&text), text, timings, captured))
+            .collect();
+
+        if let Ok(mut inner) = self.0.lock() {
+            for (finalized, text, timings, captured) in finalized {
+                if inner.prev == text {
+                    inner.prev = finalized.clone();
+                    inner.dirty = true;
+                }
+                inner.push_confirmed(vec![(finalized, timings, captured)]);
+            }
+        }
+    }
+
+    /// Confirmed captions with their timings and when their audio started.
+    pub fn take_confirmed(&self) -> Vec<(String, Vec<TimedText>, Instant)> {
+        if let Ok(mut inner) = self.0.lock() {
+            std::mem::take(&mut inner.confirmed)
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn get(&self) -> Option<Vec<CaptionSegment>> {
         if let Ok(mut inner) = self.0.lock() {
             inner.get()
         } else {
@@ -27,11 +145,83 @@ impl TextStream {
             inner.clear();
         }
     }
+
+    pub fn refresh(&self) {
+        if let Ok(mut inner) = self.0.lock() {
+            inner.dirty = true;
+        }
+    }
+}
+
+/// How much of the caption in progress is shown: every token as it is decoded, or only
+/// up to the last complete word, phrase or sentence. Finished segments are always shown whole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Granularity {
+    #[default]
+    Token,
+    Word,
+    Phrase,
+    Sentence,
+}
+
+impl Granularity {
+    fn visible(self, text: &str) -> &str {
+        let end = match self {
+            Self::Token => return text,
+            // Scripts written without spaces show every character.
+            Self::Word if text.chars().any(is_cjk) => return text,
+            Self::Word => text.rfind(char::is_whitespace),
+            Self::Phrase => last_end(text, |c| {
+                matches!(c, ',' | ';' | ':' | '、' | '，' | '；' | '：') || is_terminal(c)
+            }),
+            Self::Sentence => last_end(text, is_terminal),
+        };
+
+        text[..end.unwrap_or(0)].trim_end()
+    }
+}
+
+fn is_terminal(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '。' | '！' | '？')
+}
+
+/// Byte position just past the last character matching `pred`.
+fn last_end(text: &str, pred: impl Fn(char) -> bool) -> Option<usize> {
+    text.char_indices()
+        .rev()
+        .find(|&(_, c)| pred(c))
+        .map(|(i, c)| i + c.len_utf8())
+}
+
+#[derive(Default)]
+struct LanguageFilter {
+    languages: Vec<String>,
+    ghost: bool,
+}
+
+impl LanguageFilter {
+    /// Captions of unknown language pass, as with English-only models.
+    fn rejects(&self, language: Option<&str>) -> bool {
+        !self.languages.is_empty()
+            && language.is_some_and(|language| !self.languages.iter().any(|l| l == language))
+    }
 }
 
 struct TextStreamInner {
     prev: String,
+    prev_language: Option<String>,
     cur: String,
+    cur_language: Option<String>,
+    cur_timings: Vec<TimedText>,
+    cur_captured: Instant,
+    status: String,
+    confirmed: Vec<(String, Vec<TimedText>, Instant)>,
+    finalizing: bool,
+    unfinalized: Vec<(String, Vec<TimedText>, Instant)>,
+    filtered: Vec<(String, String, Vec<TimedText>)>,
+    filter: LanguageFilter,
+    granularity: Granularity,
+    speaking: bool,
     dirty: bool,
 }
 
@@ -39,28 +229,122 @@ impl TextStreamInner {
     fn new() -> Self {
         Self {
             prev: String::new(),
+            prev_language: None,
             cur: String::new(),
+            cur_language: None,
+            cur_timings: vec![],
+            cur_captured: Instant::now(),
+            status: String::new(),
+            confirmed: vec![],
+            finalizing: false,
+            unfinalized: vec![],
+            filtered: vec![],
+            filter: LanguageFilter::default(),
+            granularity: Granularity::default(),
+            speaking: false,
             dirty: false,
         }
     }
 
-    fn set(&mut self, text: String, is_new_segment: bool) {
+    fn set(
+        &mut self,
+        text: String,
+        is_new_segment: bool,
+        timings: Vec<TimedText>,
+        language: Option<String>,
+        captured: Instant,
+    ) {
         if is_new_segment {
+            if !self.cur.is_empty() {
+                let timings = std::mem::take(&mut self.cur_timings);
+                match &self.cur_language {
+                    Some(language) if self.filter.rejects(Some(language)) => {
+                        self.filtered
+                            .push((language.clone(), self.cur.clone(), timings));
+                        if self.filtered.len() > MAX_CONFIRMED {
+                            self.filtered.remove(0);
+                        }
+                    }
+                    _ if self.finalizing => {
+                        self.unfinalized
+                            .push((self.cur.clone(), timings, self.cur_captured));
+                    }
+                    _ => {
+                        let confirmed = vec![(self.cur.clone(), timings, self.cur_captured)];
+                        self.push_confirmed(confirmed);
+                    }
+                }
+            }
             self.prev = self.cur.clone();
+            self.prev_language = self.cur_language.take();
             self.cur.clear();
             self.dirty = true;
         }
 
+        if self.cur_language != language {
+            self.cur_language = language;
+            self.dirty = true;
+        }
+
         if self.cur != text {
+            let granularity = self.granularity;
+            if granularity.visible(&self.cur) != granularity.visible(&text) {
+                self.dirty = true;
+            }
             self.cur = text;
-            self.dirty = true;
         }
+        self.cur_timings = timings;
+        self.cur_captured = captured;
     }
 
-    fn get(&mut self) -> Option<String> {
+    fn push_confirmed(&mut self, confirmed: Vec<(String, Vec<TimedText>, Instant)>) {
+        self.confirmed.extend(confirmed);
+        if self.confirmed.len() > MAX_CONFIRMED {
+            let excess = self.confirmed.len() - MAX_CONFIRMED;
+            self.confirmed.drain(..excess);
+        }
+    }
+
+    fn set_status(&mut self, status: String) {
+        self.status = status;
+        self.dirty = true;
+    }
+
+    fn get(&mut self) -> Option<Vec<CaptionSegment>> {
         if self.dirty {
             self.dirty = false;
-            Some(self.prev.clone() + &self.cur)
+
+            let mut segments: Vec<_> = [
+                (
+                    CaptionSegment::confirmed(self.prev.clone()),
+                    &self.prev_language,
+                ),
+                (
+                    CaptionSegment::tentative(self.granularity.visible(&self.cur)),
+                    &self.cur_language,
+                ),
+            ]
+            .into_iter()
+            .filter(|(segment, _)| !segment.text.is_empty())
+            .filter_map(
+                |(segment, language)| match self.filter.rejects(language.as_deref()) {
+                    false => Some(segment),
+                    true if self.filter.ghost => Some(segment.with_color(GHOST_COLOR)),
+                    true => None,
+                },
+            )
+            .collect();
+
+            if !self.status.is_empty() {
+                let status = if segments.is_empty() {
+                    self.status.clone()
+                } else {
+                    format!("\r\n{}", self.status)
+                };
+                segments.push(CaptionSegment::status(status));
+            }
+
+            Some(segments)
         } else {
             None
         }
@@ -68,7 +352,12 @@ impl TextStreamInner {
 
     pub fn clear(&mut self) {
         self.prev.clear();
+        self.prev_language = None;
         self.cur.clear();
+        self.cur_language = None;
+        self.cur_timings.clear();
+        self.status.clear();
+        self.speaking = false;
         self.dirty = true;
     }
 }
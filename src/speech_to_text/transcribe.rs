@@ -1,33 +1,98 @@
-use anyhow::Result;
-use candle::{Device, IndexOp, Tensor};
-use candle_nn::VarBuilder;
-use candle_transformers::models::whisper::{self as m, model::Whisper, Config};
-use hf_hub::{api::sync::Api, Repo};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use anyhow::{anyhow, bail, Result};
+use candle::{safetensors::MmapedSafetensors, DType, Device, IndexOp, Shape, Tensor};
+use candle_nn::{var_builder::SimpleBackend, Init, VarBuilder};
+use candle_transformers::models::whisper::{
+    self as m, model::Whisper, Config, N_FRAMES, SAMPLE_RATE,
+};
+use hf_hub::{api::sync::ApiBuilder, Repo};
 use tokenizers::Tokenizer;
 
-use super::mel::MelSpectrogram;
+use crate::caption::TimedText;
+
+use super::mel::{MelConfig, MelSpectrogram};
+
+const TIMESTAMP_BEGIN_TOKEN: &str = "<|0.00|>";
+// Timestamp tokens step by one encoder frame, which spans two mel frames.
+const MEL_FRAMES_PER_TIMESTAMP: usize = 2;
+const MAX_INITIAL_TIMESTAMP: u32 = 50;
+const MAX_REPEAT_NGRAM: usize = 4;
+const MAX_REPEATS: usize = 3;
+// English-only models have a smaller vocabulary and no trained language tokens.
+const MULTILINGUAL_VOCAB_SIZE: usize = 51865;
+const LARGE_V3_VOCAB_SIZE: usize = 51866;
+const LARGE_V3_MEL_BINS: usize = 128;
+// Tensor names listed per kind of problem before the rest are counted.
+const MAX_LISTED_TENSORS: usize = 3;
+// Added to the logit of the token continuing a hotword; starting one gets half.
+const HOTWORD_BOOST: f32 = 3.0;
 
 pub struct Transcriber {
     device: Device,
+    decoder_device: Device,
     config: Config,
 
     model: Whisper,
+    parameters: usize,
+    vram_bytes: usize,
     suppress_tokens: Tensor,
+    // Whether the encoder has run since the last clear.
+    encoded: bool,
+    encoded_frames: usize,
+    encoder_stride: usize,
 
     tokenizer: Tokenizer,
     tokens: Vec<u32>,
     initial_tokens: Vec<u32>,
     interrupt_tokens: Vec<u32>,
+    sot_token: u32,
+    transcribe_token: u32,
+    translate_token: Option<u32>,
+    no_timestamps_token: u32,
+    timestamp_begin: Option<u32>,
+    timestamps: bool,
+    no_speech_tokens: Vec<u32>,
+    language_tokens: Vec<(u32, String)>,
+    language_detection: bool,
+    language: Option<String>,
+    forced_language: Option<(u32, String)>,
+    translate: bool,
+    hallucination_guard: bool,
+    token_budget: usize,
+    truncations: u32,
+    /// Token sequences of the hotwords, with and without a leading space, and whether the
+    /// sequence may start one; only the spaced form begins a word.
+    hotwords: Vec<(Vec<u32>, bool)>,
+    window_start: Instant,
+    audio_end: Option<Instant>,
+    timings: Vec<TimedText>,
 
     melspec: MelSpectrogram,
 }
 
 impl Transcriber {
-    pub fn new(repo_id: &str) -> Result<Self> {
+    /// `cpu_decoder` keeps the decoder and the token loop on the CPU while the encoder runs on CUDA,
+    /// which avoids thousands of tiny kernel launches per update on small GPUs.
+    pub fn new(repo_id: &str, model_dir: &Path, cpu_decoder: bool) -> Result<Self> {
         let device = Device::new_cuda(0)?;
+        let decoder_device = if cpu_decoder {
+            Device::Cpu
+        } else {
+            device.clone()
+        };
 
-        let (model, config, tokenizer) = {
-            let api = Api::new()?;
+        let (model, config, tokenizer, mel_config) = {
+            let api = ApiBuilder::new()
+                .with_cache_dir(model_dir.to_path_buf())
+                // There is no console to draw it; download progress is shown as the status.
+                .with_progress(false)
+                .build()?;
             let repo = api.repo(Repo::new(repo_id.to_owned(), hf_hub::RepoType::Model));
 
             let (model, config, tokenizer) = (
@@ -37,14 +102,54 @@ impl Transcriber {
             );
 
             let config: Config = serde_json::from_str(&std::fs::read_to_string(config)?)?;
-            let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[model], m::DTYPE, &device)? };
+            check_weights(&model, &config)?;
+
+            // Checkpoints without one use Whisper's feature settings.
+            let mel_config = match repo.get("preprocessor_config.json") {
+                Ok(path) => {
+                    MelConfig::from_json(&std::fs::read_to_string(path)?, config.num_mel_bins)?
+                }
+                Err(_) => MelConfig::whisper(config.num_mel_bins),
+            };
+            if mel_config.n_mels != config.num_mel_bins {
+                bail!(
+                    "preprocessor_config.json has {} mel bins, but the model takes {}",
+                    mel_config.n_mels,
+                    config.num_mel_bins
+                );
+            }
+            if mel_config.sample_rate != SAMPLE_RATE {
+                bail!(
+                    "{repo_id} expects {} Hz audio, but Whisper models are fed {SAMPLE_RATE} Hz",
+                    mel_config.sample_rate
+                );
+            }
+            let (parameters, vram_bytes) = weight_sizes(&model, cpu_decoder)?;
+            let model = if cpu_decoder {
+                // Whisper::load creates the decoder mask and the encoder positional embedding on
+                // the VarBuilder's device, so each half is taken from a load on its own device.
+                let tensors = PlacedTensors::load(&model, &device, &decoder_device)?;
+                let encoder = Whisper::load(&tensors.var_builder(&device), config.clone())?;
+                let decoder = Whisper::load(&tensors.var_builder(&decoder_device), config.clone())?;
+                Whisper {
+                    encoder: encoder.encoder,
+                    decoder: decoder.decoder,
+                    config: config.clone(),
+                }
+            } else {
+                let vb =
+                    unsafe { VarBuilder::from_mmaped_safetensors(&[model], m::DTYPE, &device)? };
+                Whisper::load(&vb, config.clone())?
+            };
 
             (
-                m::model::Whisper::load(&vb, config.clone())?,
+                (model, parameters, vram_bytes),
                 config,
                 Tokenizer::from_file(tokenizer).map_err(anyhow::Error::msg)?,
+                mel_config,
             )
         };
+        let (model, parameters, vram_bytes) = model;
 
         let suppress_tokens = {
             let suppress_tokens: Vec<f32> = (0..config.vocab_size as u32)
@@ -57,62 +162,242 @@ impl Transcriber {
                 })
                 .collect();
 
-            Tensor::new(suppress_tokens, &device)?
+            Tensor::new(suppress_tokens, &decoder_device)?
         };
 
-        let initial_tokens = vec![
-            tokenizer.token_to_id(m::SOT_TOKEN).unwrap(),
-            tokenizer.token_to_id(m::TRANSCRIBE_TOKEN).unwrap(),
-            tokenizer.token_to_id(m::NO_TIMESTAMPS_TOKEN).unwrap(),
-        ];
+        let sot_token = tokenizer.token_to_id(m::SOT_TOKEN).unwrap();
+        let transcribe_token = tokenizer.token_to_id(m::TRANSCRIBE_TOKEN).unwrap();
+        let no_timestamps_token = tokenizer.token_to_id(m::NO_TIMESTAMPS_TOKEN).unwrap();
+        let initial_tokens = vec![sot_token, transcribe_token, no_timestamps_token];
+        let timestamp_begin = tokenizer.token_to_id(TIMESTAMP_BEGIN_TOKEN);
 
+        let no_speech_tokens: Vec<u32> = m::NO_SPEECH_TOKENS
+            .iter()
+            .filter_map(|token| tokenizer.token_to_id(token))
+            .collect();
         let mut interrupt_tokens = vec![tokenizer.token_to_id(m::EOT_TOKEN).unwrap()];
-        if let Some(token) = tokenizer.token_to_id(m::NO_SPEECH_TOKENS[0]) {
-            interrupt_tokens.push(token);
-        }
-        if let Some(token) = tokenizer.token_to_id(m::NO_SPEECH_TOKENS[1]) {
-            interrupt_tokens.push(token);
-        }
+        interrupt_tokens.extend(&no_speech_tokens);
+        let (language_tokens, translate_token) = if config.vocab_size >= MULTILINGUAL_VOCAB_SIZE {
+            (
+                language_tokens(&tokenizer),
+                tokenizer.token_to_id(m::TRANSLATE_TOKEN),
+            )
+        } else {
+            (vec![], None)
+        };
 
-        let melspec = MelSpectrogram::new(config.num_mel_bins)?;
+        let melspec = MelSpectrogram::new(&mel_config)?;
 
         Ok(Self {
             device,
+            decoder_device,
             config,
             model,
+            parameters,
+            vram_bytes,
             suppress_tokens,
+            encoded: false,
+            encoded_frames: 0,
+            encoder_stride: 0,
             tokenizer,
             tokens: vec![],
             initial_tokens,
             interrupt_tokens,
+            sot_token,
+            transcribe_token,
+            translate_token,
+            no_timestamps_token,
+            timestamp_begin,
+            timestamps: false,
+            no_speech_tokens,
+            language_tokens,
+            language_detection: false,
+            language: None,
+            forced_language: None,
+            translate: false,
+            hallucination_guard: false,
+            token_budget: 0,
+            truncations: 0,
+            hotwords: vec![],
+            window_start: Instant::now(),
+            audio_end: None,
+            timings: vec![],
             melspec,
         })
     }
 
     pub fn transcribe(&mut self, audio: &[f32]) -> Result<Option<(String, bool)>> {
-        let (features, is_new_segment) = if let Some((mel, is_new_segment)) =
-            self.melspec.decode(audio)
-        {
-            let mel_len = mel.len();
-            let num_mel_bins = self.config.num_mel_bins;
-            let mel =
-                Tensor::from_slice(mel, (1, num_mel_bins, mel_len / num_mel_bins), &self.device)?;
-            let features = self.model.encoder.forward(&mel, is_new_segment)?;
-            (features, is_new_segment)
-        } else {
+        let Some((mel, is_new_segment)) = self.prepare(audio)? else {
             return Ok(None);
         };
 
+        let features = self.model.encoder.forward(&mel, is_new_segment)?;
+        self.decode(features, is_new_segment).map(Some)
+    }
+
+    /// When the audio passed to the next `transcribe` ended, by the capture clock.
+    /// Without it timings are anchored to when the audio arrives.
+    pub fn set_audio_end(&mut self, end: Instant) {
+        self.audio_end = Some(end);
+    }
+
+    /// When the audio of the segment in progress started, by the capture clock.
+    pub fn window_start(&self) -> Instant {
+        self.window_start
+    }
+
+    /// Raw audio of the segment in progress, for replaying into another model.
+    pub fn snapshot(&self) -> Vec<f32> {
+        self.melspec.samples().to_vec()
+    }
+
+    /// Creates another stream on the same weights, with its own audio and decoder state.
+    pub fn fork(&self) -> Result<Self> {
+        let mut model = self.model.clone();
+        model.reset_kv_cache();
+
+        Ok(Self {
+            device: self.device.clone(),
+            decoder_device: self.decoder_device.clone(),
+            config: self.config.clone(),
+            model,
+            parameters: self.parameters,
+            vram_bytes: self.vram_bytes,
+            suppress_tokens: self.suppress_tokens.clone(),
+            encoded: false,
+            encoded_frames: 0,
+            encoder_stride: self.encoder_stride,
+            tokenizer: self.tokenizer.clone(),
+            tokens: vec![],
+            initial_tokens: self.initial_tokens.clone(),
+            interrupt_tokens: self.interrupt_tokens.clone(),
+            sot_token: self.sot_token,
+            transcribe_token: self.transcribe_token,
+            translate_token: self.translate_token,
+            no_timestamps_token: self.no_timestamps_token,
+            timestamp_begin: self.timestamp_begin,
+            timestamps: self.timestamps,
+            no_speech_tokens: self.no_speech_tokens.clone(),
+            language_tokens: self.language_tokens.clone(),
+            language_detection: self.language_detection,
+            language: None,
+            forced_language: self.forced_language.clone(),
+            translate: self.translate,
+            hallucination_guard: self.hallucination_guard,
+            token_budget: self.token_budget,
+            truncations: 0,
+            hotwords: self.hotwords.clone(),
+            window_start: Instant::now(),
+            audio_end: None,
+            timings: vec![],
+            melspec: MelSpectrogram::new(self.melspec.config())?,
+        })
+    }
+
+    /// Transcribes one chunk of audio per stream. Streams due for re-encoding share a single
+    /// batched encoder pass; shorter spectrograms are padded with silence, as Whisper expects.
+    /// All streams must be forks of the same transcriber.
+    pub fn transcribe_batch(
+        streams: &mut [Transcriber],
+        audio: &[&[f32]],
+    ) -> Result<Vec<Option<(String, bool)>>> {
+        let mut prepared = vec![];
+        for (i, (stream, audio)) in streams.iter_mut().zip(audio).enumerate() {
+            if let Some((mel, is_new_segment)) = stream.prepare(audio)? {
+                prepared.push((i, mel, is_new_segment));
+            }
+        }
+
+        let mut results = vec![None; streams.len()];
+        if prepared.is_empty() {
+            return Ok(results);
+        }
+
+        let mut n_frames = 0;
+        for (_, mel, _) in &prepared {
+            n_frames = n_frames.max(mel.dim(2)?);
+        }
+
+        let mut mels = vec![];
+        for (_, mel, _) in &prepared {
+            let (_, num_mel_bins, len) = mel.dims3()?;
+            if len == n_frames {
+                mels.push(mel.clone());
+                continue;
+            }
+
+            // The quietest bin of the log-mel is what silence maps to after normalization.
+            let silence = mel.flatten_all()?.min(0)?.to_scalar::<f32>()?;
+            let padding = Tensor::full(silence, (1, num_mel_bins, n_frames - len), mel.device())?;
+            mels.push(Tensor::cat(&[mel, &padding], 2)?);
+        }
+
+        let features = streams[prepared[0].0]
+            .model
+            .encoder
+            .forward(&Tensor::cat(&mels, 0)?, true)?;
+
+        for (b, (i, mel, is_new_segment)) in prepared.into_iter().enumerate() {
+            let len = mel.dim(2)?.div_ceil(2);
+            let features = features.i(b..b + 1)?.narrow(1, 0, len)?;
+            results[i] = Some(streams[i].decode(features, is_new_segment)?);
+        }
+
+        Ok(results)
+    }
+
+    /// Turns newly captured audio into a spectrogram, if the encoder output is due for an update.
+    fn prepare(&mut self, audio: &[f32]) -> Result<Option<(Tensor, bool)>> {
+        let Some((mel, is_new_segment, n_frames)) = self.melspec.decode(audio) else {
+            return Ok(None);
+        };
+
+        let is_stale = is_new_segment
+            || !self.encoded
+            || n_frames >= self.encoded_frames + self.encoder_stride
+            || n_frames == N_FRAMES;
+
+        if !is_stale {
+            return Ok(None);
+        }
+
+        let mel_len = mel.len();
+        let num_mel_bins = self.config.num_mel_bins;
+        let mel = Tensor::from_slice(mel, (1, num_mel_bins, mel_len / num_mel_bins), &self.device)?;
+
+        self.encoded_frames = n_frames;
+
+        self.window_start = self.audio_end() - self.melspec.config().duration(n_frames);
+
+        Ok(Some((mel, is_new_segment)))
+    }
+
+    fn decode(&mut self, features: Tensor, is_new_segment: bool) -> Result<(String, bool)> {
+        let features = features.to_device(&self.decoder_device)?;
+        self.encoded = true;
+
         if is_new_segment || self.tokens.is_empty() {
             self.init_tokens();
         } else {
             self.forget_tokens(4);
         }
 
+        let n_prefix = self.tokens.len();
+        let mut no_speech_prob = 0.0;
+        let mut sum_logprob = 0.0;
+
         for i in 0.. {
-            let tokens_t = Tensor::new(self.tokens.as_slice(), &self.device)?.unsqueeze(0)?;
+            let tokens_t =
+                Tensor::new(self.tokens.as_slice(), &self.decoder_device)?.unsqueeze(0)?;
             let ys = self.model.decoder.forward(&tokens_t, &features, i == 0)?;
 
+            if i == 0 && self.hallucination_guard {
+                no_speech_prob = self.no_speech_prob(&ys)?;
+            }
+            if i == 0 && self.language_detection {
+                self.language = self.detect_language(&ys)?;
+            }
+
             let (_, seq_len, _) = ys.dims3()?;
             let logits = self
                 .model
@@ -122,8 +407,13 @@ impl Transcriber {
                 .i(0)?
                 .broadcast_add(&self.suppress_tokens)?;
 
+            let mut logits = logits.to_vec1::<f32>()?;
+            if let Some(timestamp_begin) = self.timestamp_begin.filter(|_| self.timestamps) {
+                self.apply_timestamp_rules(&mut logits, timestamp_begin);
+            }
+            self.apply_hotword_boost(&mut logits);
+
             let next_token = logits
-                .to_vec1::<f32>()?
                 .iter()
                 .enumerate()
                 .max_by(|(_, u), (_, v)| u.total_cmp(v))
@@ -134,24 +424,369 @@ impl Transcriber {
                 break;
             }
 
+            if self.hallucination_guard {
+                sum_logprob += logits[next_token as usize] - log_sum_exp(&logits);
+            }
+
             self.tokens.push(next_token);
 
             if self.tokens.len() > self.config.max_target_positions {
                 break;
             }
+
+            if self.token_budget > 0 && i + 1 >= self.token_budget {
+                self.truncations += 1;
+                break;
+            }
+        }
+
+        if self.hallucination_guard {
+            self.suppress_hallucination(n_prefix, no_speech_prob, sum_logprob);
+        }
+
+        let text = self.decode_text(&self.tokens)?;
+        self.timings = self.decode_timings()?;
+
+        Ok((text, is_new_segment))
+    }
+
+    /// Spans of the latest decode when timestamp decoding is on, empty otherwise.
+    pub fn timings(&self) -> &[TimedText] {
+        &self.timings
+    }
+
+    /// Language of the latest decode, such as "en", while detection is on with a multilingual model.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn set_language_detection(&mut self, enable: bool) {
+        self.language_detection = enable;
+        if !enable {
+            self.language = None;
+        }
+    }
+
+    /// Decodes as `language`, such as "ja", instead of letting the model pick one, and with
+    /// `translate` outputs English. Both need a multilingual model; an unknown or empty
+    /// language is detected as before.
+    pub fn set_task(&mut self, language: &str, translate: bool) {
+        let forced_language = self
+            .language_tokens
+            .iter()
+            .find(|(_, name)| name == language)
+            .cloned();
+        let translate = translate && self.translate_token.is_some();
+        if self.forced_language == forced_language && self.translate == translate {
+            return;
         }
 
-        let text = self
-            .tokenizer
-            .decode(&self.tokens, true)
-            .map_err(anyhow::Error::msg)?;
+        self.forced_language = forced_language;
+        self.translate = translate;
+        self.update_initial_tokens();
+    }
 
-        Ok(Some((text, is_new_segment)))
+    /// Drops decodes of silence, music or applause that the model is unsure of,
+    /// and cuts off tokens stuck in a repetition loop.
+    pub fn set_hallucination_guard(&mut self, enable: bool) {
+        self.hallucination_guard = enable;
+    }
+
+    /// Text tokens per second of audio in the segment in progress.
+    pub fn speech_rate(&self) -> f32 {
+        let seconds =
+            self.melspec.samples().len() as f32 / self.melspec.config().sample_rate as f32;
+        if seconds <= 0.0 {
+            return 0.0;
+        }
+
+        let n_text = self.tokens[self.initial_tokens.len().min(self.tokens.len())..]
+            .iter()
+            .filter(|&&token| self.timestamp_begin.is_none_or(|begin| token < begin))
+            .count();
+        n_text as f32 / seconds
+    }
+
+    pub fn cpu_decoder(&self) -> bool {
+        self.decoder_device.is_cpu()
+    }
+
+    pub fn parameters(&self) -> usize {
+        self.parameters
+    }
+
+    /// Size of the weights placed on the GPU; activations and the KV cache come on top.
+    pub fn vram_bytes(&self) -> usize {
+        self.vram_bytes
+    }
+
+    /// Caps the tokens decoded per update so pathological audio can't stall the loop; 0 is unlimited.
+    /// The rest of the text is picked up by later updates.
+    pub fn set_token_budget(&mut self, token_budget: usize) {
+        self.token_budget = token_budget;
+    }
+
+    /// Makes `words`, such as names and jargon, more likely to be decoded by raising the
+    /// tokens that start or continue them; empty turns biasing off.
+    pub fn set_hotwords(&mut self, words: &[String]) {
+        self.hotwords = words
+            .iter()
+            .flat_map(|word| {
+                [
+                    (format!(" {}", word.trim()), true),
+                    (word.trim().to_string(), false),
+                ]
+            })
+            .filter_map(|(word, starts)| {
+                let encoding = self.tokenizer.encode(word, false).ok()?;
+                Some((encoding.get_ids().to_vec(), starts))
+            })
+            .filter(|(tokens, _)| !tokens.is_empty())
+            .collect();
+    }
+
+    /// How many updates were cut short by the token budget.
+    pub fn truncations(&self) -> u32 {
+        self.truncations
+    }
+
+    /// Lets the model emit timestamp tokens instead of forcing `<|notimestamps|>`.
+    pub fn set_timestamps(&mut self, enable: bool) {
+        let enable = enable && self.timestamp_begin.is_some();
+        if self.timestamps == enable {
+            return;
+        }
+
+        self.timestamps = enable;
+        self.update_initial_tokens();
+    }
+
+    /// `<|startoftranscript|>`, the language if fixed, the task and `<|notimestamps|>` if needed.
+    fn update_initial_tokens(&mut self) {
+        self.initial_tokens = [self.sot_token]
+            .into_iter()
+            .chain(self.forced_language.as_ref().map(|(token, _)| *token))
+            .chain([match self.translate_token.filter(|_| self.translate) {
+                Some(translate_token) => translate_token,
+                None => self.transcribe_token,
+            }])
+            .chain((!self.timestamps).then_some(self.no_timestamps_token))
+            .collect();
+        self.tokens.clear();
+        self.timings.clear();
+    }
+
+    pub fn set_encoder_stride(&mut self, encoder_stride: usize) {
+        self.encoder_stride = encoder_stride;
     }
 
     pub fn clear(&mut self) {
         self.tokens.clear();
+        self.timings.clear();
         self.melspec.clear();
+        self.encoded = false;
+        self.encoded_frames = 0;
+        self.language = None;
+    }
+
+    /// Probability of the no-speech token predicted right after `<|startoftranscript|>`.
+    fn no_speech_prob(&self, ys: &Tensor) -> Result<f32> {
+        let logits = self
+            .model
+            .decoder
+            .final_linear(&ys.i((..1, ..1))?)?
+            .i(0)?
+            .i(0)?
+            .to_vec1::<f32>()?;
+        let log_total = log_sum_exp(&logits);
+
+        Ok(self
+            .no_speech_tokens
+            .iter()
+            .map(|&token| (logits[token as usize] - log_total).exp())
+            .sum())
+    }
+
+    /// The language token Whisper predicts right after `<|startoftranscript|>`.
+    fn detect_language(&self, ys: &Tensor) -> Result<Option<String>> {
+        if let Some((_, language)) = &self.forced_language {
+            return Ok(Some(language.clone()));
+        }
+        if self.language_tokens.is_empty() {
+            return Ok(None);
+        }
+
+        let logits = self
+            .model
+            .decoder
+            .final_linear(&ys.i((..1, ..1))?)?
+            .i(0)?
+            .i(0)?
+            .to_vec1::<f32>()?;
+
+        Ok(self
+            .language_tokens
+            .iter()
+            .max_by(|(u, _), (v, _)| logits[*u as usize].total_cmp(&logits[*v as usize]))
+            .map(|(_, language)| language.clone()))
+    }
+
+    fn suppress_hallucination(&mut self, n_prefix: usize, no_speech_prob: f32, sum_logprob: f32) {
+        let n_decoded = self.tokens.len() - n_prefix;
+        let avg_logprob = sum_logprob / n_decoded.max(1) as f32;
+
+        // The same rule Whisper uses to skip silent windows.
+        if no_speech_prob > m::NO_SPEECH_THRESHOLD as f32
+            && avg_logprob < m::LOGPROB_THRESHOLD as f32
+        {
+            self.tokens.truncate(self.initial_tokens.len());
+            return;
+        }
+
+        let sampled = &self.tokens[self.initial_tokens.len()..];
+        if let Some(start) = repetition_start(sampled) {
+            self.tokens.truncate(self.initial_tokens.len() + start);
+        }
+    }
+
+    /// Whisper's timestamp rules: timestamps come in pairs, never decrease,
+    /// and the first token is a timestamp near the start of the window.
+    fn apply_timestamp_rules(&self, logits: &mut [f32], timestamp_begin: u32) {
+        let begin = timestamp_begin as usize;
+        let sampled = &self.tokens[self.initial_tokens.len()..];
+        let is_timestamp = |t: &u32| *t >= timestamp_begin;
+
+        let last_was_timestamp = sampled.last().is_some_and(is_timestamp);
+        let penultimate_was_timestamp =
+            sampled.len() < 2 || is_timestamp(&sampled[sampled.len() - 2]);
+
+        if sampled.is_empty() {
+            logits[..begin].fill(f32::NEG_INFINITY);
+            let max_initial = (begin + MAX_INITIAL_TIMESTAMP as usize + 1).min(logits.len());
+            logits[max_initial..].fill(f32::NEG_INFINITY);
+        } else if last_was_timestamp {
+            if penultimate_was_timestamp {
+                logits[begin..].fill(f32::NEG_INFINITY);
+            } else {
+                for (i, logit) in logits[..begin].iter_mut().enumerate() {
+                    if !self.interrupt_tokens.contains(&(i as u32)) {
+                        *logit = f32::NEG_INFINITY;
+                    }
+                }
+            }
+        }
+
+        if let Some(&last) = sampled.iter().rev().find(|t| is_timestamp(t)) {
+            let floor = if last_was_timestamp && !penultimate_was_timestamp {
+                last
+            } else {
+                last + 1
+            };
+            let floor = (floor as usize).min(logits.len());
+            logits[begin..floor].fill(f32::NEG_INFINITY);
+        }
+    }
+
+    /// Shallow fusion: each hotword boosts the token after the longest part of it
+    /// that ends the text decoded so far, or the first token of its spaced form if none does.
+    fn apply_hotword_boost(&self, logits: &mut [f32]) {
+        if self.hotwords.is_empty() {
+            return;
+        }
+
+        // Timestamps between words would hide a hotword the text is partway through.
+        let text = &self.tokens[self.initial_tokens.len().min(self.tokens.len())..];
+        let text: Vec<u32> = match self.timestamp_begin {
+            Some(begin) => text.iter().copied().filter(|&t| t < begin).collect(),
+            None => text.to_vec(),
+        };
+        let mut boosts = HashMap::new();
+        for (hotword, starts) in &self.hotwords {
+            let matched = (1..hotword.len())
+                .rev()
+                .find(|&n| text.ends_with(&hotword[..n]))
+                .unwrap_or(0);
+            if matched == 0 && !starts {
+                continue;
+            }
+            let boost = match matched {
+                0 => HOTWORD_BOOST / 2.0,
+                _ => HOTWORD_BOOST,
+            };
+            let entry = boosts.entry(hotword[matched]).or_insert(boost);
+            *entry = f32::max(*entry, boost);
+        }
+
+        for (token, boost) in boosts {
+            if let Some(logit) = logits.get_mut(token as usize) {
+                *logit += boost;
+            }
+        }
+    }
+
+    fn decode_text(&self, tokens: &[u32]) -> Result<String> {
+        let tokens: Vec<u32> = match self.timestamp_begin {
+            Some(begin) => tokens.iter().copied().filter(|&t| t < begin).collect(),
+            None => tokens.to_vec(),
+        };
+
+        self.tokenizer
+            .decode(&tokens, true)
+            .map_err(anyhow::Error::msg)
+    }
+
+    fn audio_end(&self) -> Instant {
+        self.audio_end.unwrap_or_else(Instant::now)
+    }
+
+    fn decode_timings(&self) -> Result<Vec<TimedText>> {
+        let Some(timestamp_begin) = self.timestamp_begin.filter(|_| self.timestamps) else {
+            return Ok(vec![]);
+        };
+
+        let time = |token: u32| {
+            let n_frames = (token - timestamp_begin) as usize * MEL_FRAMES_PER_TIMESTAMP;
+            self.window_start + self.melspec.config().duration(n_frames)
+        };
+
+        let mut timings = vec![];
+        let mut start = None;
+        let mut text_tokens = vec![];
+
+        for &token in &self.tokens[self.initial_tokens.len()..] {
+            if token < timestamp_begin {
+                text_tokens.push(token);
+                continue;
+            }
+
+            match start.take() {
+                None => start = Some(time(token)),
+                Some(start) => {
+                    let text = self.decode_text(&std::mem::take(&mut text_tokens))?;
+                    if !text.trim().is_empty() {
+                        timings.push(TimedText {
+                            start,
+                            end: time(token),
+                            text,
+                        });
+                    }
+                }
+            }
+        }
+
+        // A span still being spoken lasts until now.
+        if let Some(start) = start {
+            let text = self.decode_text(&text_tokens)?;
+            if !text.trim().is_empty() {
+                timings.push(TimedText {
+                    start,
+                    end: self.audio_end(),
+                    text,
+                });
+            }
+        }
+
+        Ok(timings)
     }
 
     fn init_tokens(&mut self) {
@@ -164,3 +799,249 @@ impl Transcriber {
         self.tokens.truncate(len);
     }
 }
+
+fn log_sum_exp(logits: &[f32]) -> f32 {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    max + logits.iter().map(|l| (l - max).exp()).sum::<f32>().ln()
+}
+
+/// Where an n-gram starts repeating more than `MAX_REPEATS` times in a row; the first copy is kept.
+fn repetition_start(tokens: &[u32]) -> Option<usize> {
+    for start in 0..tokens.len() {
+        for n in 1..=MAX_REPEAT_NGRAM {
+            let Some(ngram) = tokens.get(start..start + n) else {
+                break;
+            };
+            let repeats = tokens[start..]
+                .chunks_exact(n)
+                .take_while(|chunk| *chunk == ngram)
+                .count();
+
+            if repeats > MAX_REPEATS {
+                return Some(start + n);
+            }
+        }
+    }
+
+    None
+}
+
+/// Language tokens sit between `<|startoftranscript|>` and `<|translate|>`, as in `<|en|>`.
+fn language_tokens(tokenizer: &Tokenizer) -> Vec<(u32, String)> {
+    let (Some(sot), Some(translate)) = (
+        tokenizer.token_to_id(m::SOT_TOKEN),
+        tokenizer.token_to_id(m::TRANSLATE_TOKEN),
+    ) else {
+        return vec![];
+    };
+
+    (sot + 1..translate)
+        .filter_map(|id| {
+            let token = tokenizer.id_to_token(id)?;
+            let language = token.strip_prefix("<|")?.strip_suffix("|>")?;
+            Some((id, language.to_string()))
+        })
+        .collect()
+}
+
+/// Parameter count of the model and the bytes its weights take on the GPU.
+fn weight_sizes(path: &Path, cpu_decoder: bool) -> Result<(usize, usize)> {
+    let safetensors = unsafe { MmapedSafetensors::new(path)? };
+    let mut parameters = 0;
+    let mut vram_bytes = 0;
+
+    for (name, view) in safetensors.tensors() {
+        let count = view.shape().iter().product::<usize>();
+        parameters += count;
+        if !(cpu_decoder && name.starts_with("model.decoder.")) {
+            vram_bytes += count * m::DTYPE.size_in_bytes();
+        }
+    }
+
+    Ok((parameters, vram_bytes))
+}
+
+/// Checks the checkpoint holds every tensor the config calls for, in the right shape,
+/// so a mismatched download names all the tensors at fault instead of the first one hit.
+fn check_weights(path: &Path, config: &Config) -> Result<()> {
+    let probe = TensorProbe::default();
+    let vb = VarBuilder::from_backend(Box::new(probe.clone()), m::DTYPE, Device::Cpu);
+    Whisper::load(&vb, config.clone())?;
+
+    let safetensors = unsafe { MmapedSafetensors::new(path)? };
+    let found: HashMap<String, Vec<usize>> = safetensors
+        .tensors()
+        .into_iter()
+        .map(|(name, view)| (name, view.shape().to_vec()))
+        .collect();
+
+    let mut missing = vec![];
+    let mut mismatched = vec![];
+    for (name, shape) in probe.0.lock().map_err(|e| anyhow!("{e}"))?.iter() {
+        match found.get(name) {
+            None => missing.push(name.clone()),
+            Some(dims) if dims != shape.dims() => {
+                mismatched.push(format!("{name} is {dims:?}, expected {:?}", shape.dims()))
+            }
+            _ => {}
+        }
+    }
+    if missing.is_empty() && mismatched.is_empty() {
+        return Ok(());
+    }
+
+    let mut message = "model.safetensors doesn't match config.json".to_string();
+    if !missing.is_empty() {
+        message += &format!("\nMissing: {}", list_tensors(&missing));
+    }
+    if !mismatched.is_empty() {
+        message += &format!("\nWrong shape: {}", list_tensors(&mismatched));
+    }
+    message += "\n";
+    message += &version_hint(&found, config).unwrap_or_else(|| {
+        "The download may be incomplete; delete the model folder to fetch it again.".into()
+    });
+
+    Err(anyhow!(message))
+}
+
+fn list_tensors(names: &[String]) -> String {
+    let listed = names[..names.len().min(MAX_LISTED_TENSORS)].join(", ");
+    match names.len().saturating_sub(MAX_LISTED_TENSORS) {
+        0 => listed,
+        rest => format!("{listed} and {rest} more"),
+    }
+}
+
+/// Which Whisper version the weights look like, when it differs from the config.
+fn version_hint(found: &HashMap<String, Vec<usize>>, config: &Config) -> Option<String> {
+    let dim = |name: &str, i: usize| found.get(name).and_then(|dims| dims.get(i)).copied();
+    let mel_version = |n_mels| match n_mels {
+        LARGE_V3_MEL_BINS => "large-v3",
+        _ => "v1/v2",
+    };
+    let vocab_version = |vocab_size| match vocab_size {
+        LARGE_V3_VOCAB_SIZE => "large-v3",
+        MULTILINGUAL_VOCAB_SIZE => "a multilingual v1/v2 model",
+        _ => "an English-only model",
+    };
+
+    if let Some(n_mels) = dim("model.encoder.conv1.weight", 1).filter(|&n| n != config.num_mel_bins)
+    {
+        return Some(format!(
+            "The weights take {n_mels} mel bins as in {}, but the config is for {}.",
+            mel_version(n_mels),
+            mel_version(config.num_mel_bins)
+        ));
+    }
+
+    if let Some(vocab_size) =
+        dim("model.decoder.embed_tokens.weight", 0).filter(|&n| n != config.vocab_size)
+    {
+        return Some(format!(
+            "The weights are for {}, but the config is for {}.",
+            vocab_version(vocab_size),
+            vocab_version(config.vocab_size)
+        ));
+    }
+
+    let decoder_layers = found
+        .keys()
+        .filter_map(|name| {
+            name.strip_prefix("model.decoder.layers.")?
+                .split('.')
+                .next()
+        })
+        .collect::<HashSet<_>>()
+        .len();
+    (decoder_layers != config.decoder_layers).then(|| {
+        format!(
+            "The weights have {decoder_layers} decoder layers, but the config has {}; \
+             turbo and distil checkpoints have fewer than the model they come from.",
+            config.decoder_layers
+        )
+    })
+}
+
+/// Records the tensors a model asks for, handing out zeros that take no memory.
+#[derive(Clone, Default)]
+struct TensorProbe(Arc<Mutex<Vec<(String, Shape)>>>);
+
+impl SimpleBackend for TensorProbe {
+    fn get(
+        &self,
+        s: Shape,
+        name: &str,
+        _: Init,
+        dtype: DType,
+        dev: &Device,
+    ) -> candle::Result<Tensor> {
+        if let Ok(mut requested) = self.0.lock() {
+            requested.push((name.to_string(), s.clone()));
+        }
+
+        Tensor::zeros((), dtype, dev)?.broadcast_as(s)
+    }
+
+    fn contains_tensor(&self, _: &str) -> bool {
+        true
+    }
+}
+
+/// Model weights with the decoder placed on its own device. Both VarBuilders share the tensors,
+/// so nothing is loaded twice.
+struct PlacedTensors(HashMap<String, Tensor>);
+
+impl PlacedTensors {
+    fn load(path: &Path, device: &Device, decoder_device: &Device) -> Result<Self> {
+        let safetensors = unsafe { MmapedSafetensors::new(path)? };
+        let mut tensors = HashMap::new();
+
+        for (name, _) in safetensors.tensors() {
+            let device = if name.starts_with("model.decoder.") {
+                decoder_device
+            } else {
+                device
+            };
+            let tensor = safetensors.load(&name, device)?;
+            tensors.insert(name, tensor);
+        }
+
+        Ok(Self(tensors))
+    }
+
+    fn var_builder(&self, device: &Device) -> VarBuilder<'static> {
+        VarBuilder::from_backend(Box::new(Self(self.0.clone())), m::DTYPE, device.clone())
+    }
+}
+
+impl SimpleBackend for PlacedTensors {
+    fn get(
+        &self,
+        s: Shape,
+        name: &str,
+        _: Init,
+        dtype: DType,
+        _: &Device,
+    ) -> candle::Result<Tensor> {
+        let tensor = self
+            .0
+            .get(name)
+            .ok_or_else(|| candle::Error::CannotFindTensor {
+                path: name.to_string(),
+            })?;
+        if tensor.shape() != &s {
+            return Err(candle::Error::UnexpectedShape {
+                msg: format!("shape mismatch for {name}"),
+                expected: s,
+                got: tensor.shape().clone(),
+            });
+        }
+
+        tensor.to_dtype(dtype)
+    }
+
+    fn contains_tensor(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+}
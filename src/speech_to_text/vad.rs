@@ -0,0 +1,243 @@
+use std::{collections::VecDeque, time::Duration};
+
+const FRAME_MS: u32 = 30;
+const THRESHOLD_DB: f32 = -45.0;
+const SLOPE_DB: f32 = 3.0;
+// Adaptive mode puts the threshold this far above the noise floor, within the range below.
+const FLOOR_MARGIN_DB: f32 = 12.0;
+const MIN_THRESHOLD_DB: f32 = -60.0;
+const MAX_THRESHOLD_DB: f32 = -25.0;
+// The floor is a low percentile of the levels over a few seconds of every frame, speech
+// or not, so it follows noise that comes and goes while speech, being louder, doesn't lift it.
+const FLOOR_WINDOW: Duration = Duration::from_secs(5);
+const FLOOR_PERCENTILE: f32 = 0.1;
+// Floors mapped to a noise level of 0 and 1.
+const QUIET_FLOOR_DB: f32 = -70.0;
+const LOUD_FLOOR_DB: f32 = -35.0;
+// Speech activity holds over pauses shorter than this.
+const ACTIVITY_HANGOVER: Duration = Duration::from_millis(600);
+
+/// How readily audio counts as speech.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VadSensitivity {
+    /// The level at which a frame is as likely speech as not. Adaptive mode moves its
+    /// margin above the noise floor by as much as this differs from the default.
+    pub threshold_db: f32,
+    /// How long speech activity holds over a pause.
+    pub hangover: Duration,
+}
+
+impl Default for VadSensitivity {
+    fn default() -> Self {
+        Self {
+            threshold_db: THRESHOLD_DB,
+            hangover: ACTIVITY_HANGOVER,
+        }
+    }
+}
+
+pub struct Vad {
+    frame_len: usize,
+    base_threshold_db: f32,
+    threshold_db: f32,
+    adaptive: bool,
+    noise_floor_db: Option<f32>,
+    levels: VecDeque<f32>,
+}
+
+impl Vad {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            frame_len: (sample_rate * FRAME_MS / 1000) as usize,
+            base_threshold_db: THRESHOLD_DB,
+            threshold_db: THRESHOLD_DB,
+            adaptive: false,
+            noise_floor_db: None,
+            levels: VecDeque::new(),
+        }
+    }
+
+    /// Tracks the ambient noise floor and sets the threshold relative to it,
+    /// instead of using a fixed level.
+    pub fn set_adaptive(&mut self, adaptive: bool) {
+        self.adaptive = adaptive;
+        self.threshold_db = self.base_threshold_db;
+        self.noise_floor_db = None;
+        self.levels.clear();
+    }
+
+    pub fn set_threshold(&mut self, threshold_db: f32) {
+        self.base_threshold_db = threshold_db;
+        self.set_adaptive(self.adaptive);
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    pub fn speech_prob(&mut self, frame: &[f32]) -> f32 {
+        let power = frame.iter().map(|x| x * x).sum::<f32>() / frame.len().max(1) as f32;
+        let db = 10.0 * power.max(1e-10).log10();
+        let prob = 1.0 / (1.0 + (-(db - self.threshold_db) / SLOPE_DB).exp());
+
+        if self.adaptive {
+            let window = (FLOOR_WINDOW.as_millis() as u32 / FRAME_MS) as usize;
+            if self.levels.len() == window {
+                self.levels.pop_front();
+            }
+            self.levels.push_back(db);
+
+            let mut levels: Vec<f32> = self.levels.iter().copied().collect();
+            levels.sort_by(f32::total_cmp);
+            let floor = levels[(levels.len() as f32 * FLOOR_PERCENTILE) as usize];
+            self.noise_floor_db = Some(floor);
+            let margin = FLOOR_MARGIN_DB + self.base_threshold_db - THRESHOLD_DB;
+            self.threshold_db = (floor + margin).clamp(MIN_THRESHOLD_DB, MAX_THRESHOLD_DB);
+        }
+
+        prob
+    }
+
+    /// How loud the background is, from 0 for silence to 1 for loud game audio or music.
+    pub fn noise_level(&self) -> f32 {
+        self.noise_floor_db.map_or(0.0, |floor| {
+            ((floor - QUIET_FLOOR_DB) / (LOUD_FLOOR_DB - QUIET_FLOOR_DB)).clamp(0.0, 1.0)
+        })
+    }
+}
+
+pub struct SilenceTrimmer {
+    vad: Vad,
+    pending: Vec<f32>,
+    output: Vec<f32>,
+    max_silence: usize,
+    silence: usize,
+    pre_roll: usize,
+    trimmed: VecDeque<f32>,
+}
+
+impl SilenceTrimmer {
+    /// The last `pre_roll` of trimmed audio is put back when speech resumes,
+    /// so quiet onsets below the VAD threshold aren't cut off.
+    /// With `adaptive`, the threshold follows the noise floor and silence is held longer
+    /// over a noisy background, where pauses within speech are easily mistaken for silence.
+    pub fn new(
+        sample_rate: u32,
+        max_silence: Duration,
+        pre_roll: Duration,
+        adaptive: bool,
+        sensitivity: VadSensitivity,
+    ) -> Self {
+        let mut vad = Vad::new(sample_rate);
+        vad.set_threshold(sensitivity.threshold_db);
+        vad.set_adaptive(adaptive);
+
+        Self {
+            vad,
+            pending: vec![],
+            output: vec![],
+            max_silence: (sample_rate as f32 * max_silence.as_secs_f32()) as usize,
+            silence: 0,
+            pre_roll: (sample_rate as f32 * pre_roll.as_secs_f32()) as usize,
+            trimmed: VecDeque::new(),
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> &[f32] {
+        self.pending.extend_from_slice(samples);
+        self.output.clear();
+
+        let frame_len = self.vad.frame_len();
+        let n_samples = self.pending.len() / frame_len * frame_len;
+
+        for frame in self.pending[..n_samples].chunks(frame_len) {
+            if self.vad.speech_prob(frame) < 0.5 {
+                self.silence += frame_len;
+            } else {
+                self.silence = 0;
+                self.output.extend(self.trimmed.drain(..));
+            }
+
+            let max_silence = (self.max_silence as f32 * (1.0 + self.vad.noise_level())) as usize;
+            if self.silence <= max_silence {
+                self.output.extend_from_slice(frame);
+            } else if self.pre_roll > 0 {
+                self.trimmed.extend(frame);
+                let excess = self.trimmed.len().saturating_sub(self.pre_roll);
+                self.trimmed.drain(..excess);
+            }
+        }
+
+        _ = self.pending.drain(..n_samples);
+
+        &self.output
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.output.clear();
+        self.silence = 0;
+        self.trimmed.clear();
+    }
+}
+
+/// Whether someone is speaking right now, held over short pauses between words.
+pub struct SpeechActivity {
+    vad: Vad,
+    pending: Vec<f32>,
+    sample_rate: u32,
+    hangover: usize,
+    silence: usize,
+}
+
+impl SpeechActivity {
+    pub fn new(sample_rate: u32) -> Self {
+        let hangover = (sample_rate as f32 * ACTIVITY_HANGOVER.as_secs_f32()) as usize;
+
+        Self {
+            vad: Vad::new(sample_rate),
+            pending: vec![],
+            sample_rate,
+            hangover,
+            silence: hangover + 1,
+        }
+    }
+
+    pub fn set_adaptive(&mut self, adaptive: bool) {
+        self.vad.set_adaptive(adaptive);
+    }
+
+    pub fn set_sensitivity(&mut self, sensitivity: VadSensitivity) {
+        self.vad.set_threshold(sensitivity.threshold_db);
+        self.hangover = (self.sample_rate as f32 * sensitivity.hangover.as_secs_f32()) as usize;
+        self.clear();
+    }
+
+    pub fn process(&mut self, samples: &[f32]) -> bool {
+        self.pending.extend_from_slice(samples);
+
+        let frame_len = self.vad.frame_len();
+        let n_samples = self.pending.len() / frame_len * frame_len;
+
+        for frame in self.pending[..n_samples].chunks(frame_len) {
+            if self.vad.speech_prob(frame) < 0.5 {
+                self.silence = self.silence.saturating_add(frame_len);
+            } else {
+                self.silence = 0;
+            }
+        }
+
+        _ = self.pending.drain(..n_samples);
+
+        self.is_active()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.silence <= self.hangover
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.silence = self.hangover + 1;
+    }
+}
@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use anyhow::Result;
+use ini::Ini;
+
+use crate::config::{
+    Config, IniGetter as _, IniSetter as _, ALIGNMENT_CENTER, ALIGNMENT_LEFT, FONT_NAME_ARIAL,
+    FONT_NAME_SEGOE_UI, FONT_NAME_VERDANA, FONT_SIZE_MEDIUM, FONT_SIZE_SMALL,
+};
+
+pub const THEME_EXTENSION: &str = "theme";
+
+/// The appearance settings, saved on their own so they can be shared.
+/// Theme files use the same keys as the config file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub font_name: String,
+    pub font_size: u32,
+    pub bold: bool,
+    pub italic: bool,
+    pub outline: bool,
+    pub text_color: String,
+    pub outline_color: String,
+    pub background_color: String,
+    pub opacity: f32,
+    pub padding: u32,
+    pub alignment: String,
+}
+
+impl Theme {
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self::read(&Ini::load_from_file(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut conf = Ini::new();
+        self.write(&mut conf);
+        conf.write_to_file(path)?;
+        Ok(())
+    }
+
+    /// Themes in `dir`, named after their files and sorted by name.
+    pub fn installed(dir: &Path) -> Vec<(String, Theme)> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+
+        let mut themes: Vec<_> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(THEME_EXTENSION))
+            })
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_string_lossy().into_owned();
+                Some((name, Self::load(&path).ok()?))
+            })
+            .collect();
+        themes.sort_by_key(|(name, _)| name.to_lowercase());
+
+        themes
+    }
+
+    /// Themes shipped with the app, listed before the installed ones.
+    pub fn builtin() -> Vec<(String, Theme)> {
+        let theme = |font_name: &str, text_color: &str, background_color: &str, opacity| Theme {
+            font_name: font_name.into(),
+            font_size: FONT_SIZE_SMALL,
+            bold: false,
+            italic: false,
+            outline: false,
+            text_color: text_color.into(),
+            outline_color: "#000000".into(),
+            background_color: background_color.into(),
+            opacity,
+            padding: 8,
+            alignment: ALIGNMENT_CENTER.into(),
+        };
+
+        vec![
+            (
+                "YouTube".into(),
+                theme(FONT_NAME_ARIAL, "#ffffff", "#080808", 0.75),
+            ),
+            (
+                "Netflix".into(),
+                Theme {
+                    bold: true,
+                    outline: true,
+                    padding: 16,
+                    ..theme(FONT_NAME_SEGOE_UI, "#ffffff", "#000000", 0.0)
+                },
+            ),
+            (
+                "High Contrast".into(),
+                Theme {
+                    font_size: FONT_SIZE_MEDIUM,
+                    bold: true,
+                    padding: 12,
+                    alignment: ALIGNMENT_LEFT.into(),
+                    ..theme(FONT_NAME_VERDANA, "#ffff00", "#000000", 1.0)
+                },
+            ),
+            (
+                "Minimal".into(),
+                Theme {
+                    outline: true,
+                    padding: 4,
+                    alignment: ALIGNMENT_LEFT.into(),
+                    ..theme(FONT_NAME_SEGOE_UI, "#f0f0f0", "#000000", 0.0)
+                },
+            ),
+        ]
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            font_name: config.font_name.clone(),
+            font_size: config.font_size,
+            bold: config.bold,
+            italic: config.italic,
+            outline: config.outline,
+            text_color: config.text_color.clone(),
+            outline_color: config.outline_color.clone(),
+            background_color: config.background_color.clone(),
+            opacity: config.opacity,
+            padding: config.padding,
+            alignment: config.alignment.clone(),
+        }
+    }
+
+    /// This theme with the settings that differ between `applied` and `current` taken
+    /// from `current`, keeping changes made since `applied` was put in place.
+    pub fn merged(&self, applied: &Self, current: &Self) -> Self {
+        fn pick<T: Clone + PartialEq>(base: &T, applied: &T, current: &T) -> T {
+            match current == applied {
+                true => base.clone(),
+                false => current.clone(),
+            }
+        }
+
+        Self {
+            font_name: pick(&self.font_name, &applied.font_name, &current.font_name),
+            font_size: pick(&self.font_size, &applied.font_size, &current.font_size),
+            bold: pick(&self.bold, &applied.bold, &current.bold),
+            italic: pick(&self.italic, &applied.italic, &current.italic),
+            outline: pick(&self.outline, &applied.outline, &current.outline),
+            text_color: pick(&self.text_color, &applied.text_color, &current.text_color),
+            outline_color: pick(
+                &self.outline_color,
+                &applied.outline_color,
+                &current.outline_color,
+            ),
+            background_color: pick(
+                &self.background_color,
+                &applied.background_color,
+                &current.background_color,
+            ),
+            opacity: pick(&self.opacity, &applied.opacity, &current.opacity),
+            padding: pick(&self.padding, &applied.padding, &current.padding),
+            alignment: pick(&self.alignment, &applied.alignment, &current.alignment),
+        }
+    }
+
+    pub fn apply_to(&self, config: &mut Config) {
+        config.font_name.clone_from(&self.font_name);
+        config.font_size = self.font_size;
+        config.bold = self.bold;
+        config.italic = self.italic;
+        config.outline = self.outline;
+        config.text_color.clone_from(&self.text_color);
+        config.outline_color.clone_from(&self.outline_color);
+        config.background_color.clone_from(&self.background_color);
+        config.opacity = self.opacity;
+        config.padding = self.padding;
+        config.alignment.clone_from(&self.alignment);
+    }
+
+    pub(crate) fn read(conf: &Ini) -> Self {
+        Self {
+            font_name: conf.get_str("font-name", FONT_NAME_SEGOE_UI),
+            font_size: conf.get_u32("font-size", FONT_SIZE_SMALL),
+            bold: conf.get_bool("font-style-bold", false),
+            italic: conf.get_bool("font-style-italic", false),
+            outline: conf.get_bool("font-style-outline", false),
+            text_color: conf.get_str("text-color", "#ffffff"),
+            outline_color: conf.get_str("outline-color", "#030303"),
+            background_color: conf.get_str("background-color", "#000000"),
+            opacity: conf.get_u32("opacity", 75) as f32 / 100.0,
+            padding: conf.get_u32("padding", 8),
+            alignment: conf.get_str("alignment", ALIGNMENT_LEFT),
+        }
+    }
+
+    pub(crate) fn write(&self, conf: &mut Ini) {
+        conf.with_general_section()
+            .set("font-name", &self.font_name)
+            .set_u32("font-size", self.font_size)
+            .set_bool("font-style-bold", self.bold)
+            .set_bool("font-style-italic", self.italic)
+            .set_bool("font-style-outline", self.outline)
+            .set("text-color", &self.text_color)
+            .set("outline-color", &self.outline_color)
+            .set("background-color", &self.background_color)
+            .set_u32("opacity", (100.0 * self.opacity) as _)
+            .set_u32("padding", self.padding)
+            .set("alignment", &self.alignment);
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::{
+    caption::WrapRules,
+    sink::{Redactor, SubtitleExport, SubtitleStyle},
+    speech_to_text::{self, StreamingTranscriber, TranscriptEvent},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const CHUNK: Duration = Duration::from_secs(1);
+const MAX_LISTED: usize = 5;
+
+/// Transcribes WAV files dropped into a folder, writing an SRT file next to each.
+///
+/// A file is picked up once its size stops changing, and skipped if its SRT already exists.
+/// The model is loaded on the first file and released when the queue runs empty.
+pub struct FolderWatcher {
+    progress: Arc<Mutex<Progress>>,
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+pub struct WatchOptions {
+    pub repo_id: String,
+    pub model_dir: PathBuf,
+    pub style: SubtitleStyle,
+    pub rules: WrapRules,
+    pub max_duration: Duration,
+    pub redactor: Option<Redactor>,
+}
+
+#[derive(Default)]
+struct Progress {
+    jobs: VecDeque<(String, String)>,
+    dirty: bool,
+}
+
+impl Progress {
+    fn set(&mut self, name: &str, state: String) {
+        match self.jobs.iter_mut().find(|(n, _)| n == name) {
+            Some((_, s)) => *s = state,
+            None => {
+                self.jobs.push_back((name.to_string(), state));
+                if self.jobs.len() > MAX_LISTED {
+                    self.jobs.pop_front();
+                }
+            }
+        }
+        self.dirty = true;
+    }
+}
+
+impl FolderWatcher {
+    pub fn new(folder: PathBuf, options: WatchOptions) -> Result<Self> {
+        std::fs::create_dir_all(&folder)?;
+
+        let progress = Arc::new(Mutex::new(Progress::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(false));
+        let mut worker = Worker {
+            folder,
+            options,
+            progress: progress.clone(),
+            stop: stop.clone(),
+            paused: paused.clone(),
+            sizes: HashMap::new(),
+            finished: HashSet::new(),
+            transcriber: None,
+        };
+        let handle = Some(std::thread::spawn(move || worker.run()));
+
+        Ok(Self {
+            progress,
+            stop,
+            paused,
+            handle,
+        })
+    }
+
+    /// While paused nothing is written: the file in progress is dropped and picked up again
+    /// on resuming.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// One line per recent file, or `None` if nothing changed since the last call.
+    pub fn progress(&self) -> Option<String> {
+        let mut progress = self.progress.lock().ok()?;
+        if !progress.dirty {
+            return None;
+        }
+        progress.dirty = false;
+
+        Some(
+            progress
+                .jobs
+                .iter()
+                .map(|(name, state)| format!("{name}: {state}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+impl Drop for FolderWatcher {
+    /// Leaves the thread to stop on its own, as it may be loading the model and waiting
+    /// would freeze the window.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.handle.take();
+    }
+}
+
+struct Worker {
+    folder: PathBuf,
+    options: WatchOptions,
+    progress: Arc<Mutex<Progress>>,
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    sizes: HashMap<PathBuf, u64>,
+    finished: HashSet<PathBuf>,
+    transcriber: Option<StreamingTranscriber>,
+}
+
+impl Worker {
+    fn run(&mut self) {
+        while !self.stop.load(Ordering::Relaxed) {
+            let ready = if self.paused.load(Ordering::Relaxed) {
+                vec![]
+            } else {
+                self.scan()
+            };
+            if ready.is_empty() {
+                self.transcriber = None;
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+
+            for path in ready {
+                if self.stop.load(Ordering::Relaxed) || self.paused.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                let name = file_name(&path);
+                let state = match self.transcribe(&path, &name) {
+                    Ok(true) => "done".to_string(),
+                    Ok(false) => {
+                        self.set_progress(&name, "paused".to_string());
+                        break;
+                    }
+                    Err(e) => format!("failed, {e}"),
+                };
+                self.set_progress(&name, state);
+                self.finished.insert(path);
+            }
+        }
+    }
+
+    /// Files whose size held still since the previous scan.
+    fn scan(&mut self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.folder) else {
+            return vec![];
+        };
+
+        let mut sizes = HashMap::new();
+        let mut ready = vec![];
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_wav = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+            if !is_wav || self.finished.contains(&path) || srt_path(&path).exists() {
+                continue;
+            }
+
+            let Ok(size) = entry.metadata().map(|metadata| metadata.len()) else {
+                continue;
+            };
+            if self.sizes.get(&path) == Some(&size) {
+                ready.push(path.clone());
+            } else {
+                self.set_progress(&file_name(&path), "waiting".to_string());
+            }
+            sizes.insert(path, size);
+        }
+        self.sizes = sizes;
+
+        ready
+    }
+
+    /// Whether the file was finished, rather than stopped or paused part way.
+    fn transcribe(&mut self, path: &Path, name: &str) -> Result<bool> {
+        if self.transcriber.is_none() {
+            self.set_progress(name, "loading model".to_string());
+            self.transcriber = Some(StreamingTranscriber::new(
+                &self.options.repo_id,
+                &self.options.model_dir,
+            )?);
+        }
+        let Some(transcriber) = &mut self.transcriber else {
+            return Ok(false);
+        };
+
+        let sample_rate = transcriber.sample_rate();
+        let samples = speech_to_text::read_wav(path, sample_rate)?;
+        let time = |pos: usize| Duration::from_secs_f64(pos as f64 / sample_rate as f64);
+
+        // Written under a temporary name so an interrupted file is picked up again.
+        let srt = srt_path(path);
+        let part = srt.with_extension("srt.part");
+        let mut export = SubtitleExport::new(
+            part.clone(),
+            &self.options.style,
+            self.options.rules,
+            self.options.max_duration,
+        )?;
+
+        let redact = |text: String| match &self.options.redactor {
+            Some(redactor) => redactor.redact(&text),
+            None => text,
+        };
+        let mut write = || -> Result<bool> {
+            let chunk = (CHUNK.as_secs_f64() * sample_rate as f64) as usize;
+            let mut start = 0;
+            for (i, block) in samples.chunks(chunk).enumerate() {
+                if self.stop.load(Ordering::Relaxed) || self.paused.load(Ordering::Relaxed) {
+                    return Ok(false);
+                }
+
+                let pos = i * chunk;
+                transcriber.feed(block)?;
+                for event in transcriber.poll() {
+                    if let TranscriptEvent::Final(text) = event {
+                        export.write_at(time(start), time(pos), &redact(text))?;
+                        start = pos;
+                    }
+                }
+
+                let percent = 100 * (pos + block.len()) / samples.len();
+                if let Ok(mut progress) = self.progress.lock() {
+                    progress.set(name, format!("{percent}%"));
+                }
+            }
+
+            transcriber.flush();
+            for event in transcriber.poll() {
+                if let TranscriptEvent::Final(text) = event {
+                    export.write_at(time(start), time(samples.len()), &redact(text))?;
+                }
+            }
+            Ok(true)
+        };
+        let result = write();
+        drop(export);
+
+        match result {
+            Ok(true) => std::fs::rename(part, srt)?,
+            _ => _ = std::fs::remove_file(&part),
+        }
+        result
+    }
+
+    fn set_progress(&self, name: &str, state: String) {
+        if let Ok(mut progress) = self.progress.lock() {
+            progress.set(name, state);
+        }
+    }
+}
+
+fn srt_path(path: &Path) -> PathBuf {
+    path.with_extension("srt")
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}